@@ -0,0 +1,222 @@
+//! Contracts written with `#[alkali::contract]`, whose methods declare their
+//! opcode with `#[opcode(n)]` and their inputs and outputs in their real
+//! signatures.
+//! Shared by the `alkali-abi` helper and the `alkali-macros` expansion so
+//! both derive the same ABI.
+
+use serde_json::{json, Value};
+use syn::spanned::Spanned;
+use syn::{
+    Attribute, Expr, FnArg, GenericArgument, Ident, ImplItemFn, Lit, Pat, PathArguments,
+    ReturnType, Type,
+};
+
+/// An `#[opcode(n)]` method of a contract impl.
+pub struct OpcodeMethod<'a> {
+    pub opcode: u64,
+    pub ident: &'a Ident,
+    pub inputs: Vec<(&'a Ident, &'a Type)>,
+    /// `T` of a `Result<T>` return type, or `None` for methods building their
+    /// own `CallResponse`.
+    pub output: Option<&'a Type>,
+    pub doc: Option<String>,
+}
+
+impl<'a> OpcodeMethod<'a> {
+    pub fn abi(&self) -> syn::Result<Value> {
+        let mut inputs = Vec::new();
+        for (name, ty) in &self.inputs {
+            inputs.push(json!({ "name": name.to_string(), "type": abi_type(ty)? }));
+        }
+        let mut outputs = Vec::new();
+        for (index, ty) in self.output_types().into_iter().enumerate() {
+            outputs.push(json!({ "name": format!("output{index}"), "type": abi_type(ty)? }));
+        }
+        let mut method = json!({
+            "opcode": self.opcode,
+            "name": self.ident.to_string(),
+            "inputs": inputs,
+            "outputs": outputs,
+        });
+        if let Some(doc) = &self.doc {
+            method["doc"] = json!(doc);
+        }
+        Ok(method)
+    }
+
+    /// The values written to the response data, in order: a tuple return
+    /// type lists one per element, and `()` none.
+    pub fn output_types(&self) -> Vec<&'a Type> {
+        match self.output {
+            None => Vec::new(),
+            Some(Type::Tuple(tuple)) => tuple.elems.iter().collect(),
+            Some(ty) => vec![ty],
+        }
+    }
+}
+
+/// Whether `attr` is `#[contract]` / `#[alkali::contract]`.
+pub fn is_contract_attribute(attr: &Attribute) -> bool {
+    attr.path()
+        .segments
+        .last()
+        .is_some_and(|segment| segment.ident == "contract")
+}
+
+pub fn is_opcode_attribute(attr: &Attribute) -> bool {
+    attr.path().is_ident("opcode")
+}
+
+/// Reads an `#[opcode(n)] fn name(&self, input: Type, ...)` method, or
+/// `None` for methods without an opcode.
+pub fn opcode_method(method: &ImplItemFn) -> syn::Result<Option<OpcodeMethod<'_>>> {
+    let Some(attr) = method.attrs.iter().find(|attr| is_opcode_attribute(attr)) else {
+        return Ok(None);
+    };
+    let opcode = attr.parse_args::<syn::LitInt>()?.base10_parse::<u64>()?;
+
+    match method.sig.receiver() {
+        Some(receiver) if receiver.reference.is_some() && receiver.mutability.is_none() => {}
+        _ => {
+            return Err(syn::Error::new(
+                method.sig.span(),
+                "opcode methods must take `&self`",
+            ))
+        }
+    }
+
+    let mut inputs = Vec::new();
+    for arg in method.sig.inputs.iter().skip(1) {
+        let FnArg::Typed(arg) = arg else {
+            continue;
+        };
+        match &*arg.pat {
+            Pat::Ident(pat) if pat.subpat.is_none() => inputs.push((&pat.ident, &*arg.ty)),
+            pat => {
+                return Err(syn::Error::new(
+                    pat.span(),
+                    "opcode method inputs must be bound to plain names",
+                ))
+            }
+        }
+    }
+
+    Ok(Some(OpcodeMethod {
+        opcode,
+        ident: &method.sig.ident,
+        inputs,
+        output: result_output(&method.sig.output)?,
+        doc: doc_comment(&method.attrs),
+    }))
+}
+
+/// `T` of a `-> Result<T>` method, or `None` for `Result<CallResponse>`.
+fn result_output(output: &ReturnType) -> syn::Result<Option<&Type>> {
+    let invalid = || {
+        syn::Error::new(
+            output.span(),
+            "opcode methods must return `Result<CallResponse>` or `Result<T>`",
+        )
+    };
+
+    let ReturnType::Type(_, ty) = output else {
+        return Err(invalid());
+    };
+    let Type::Path(path) = &**ty else {
+        return Err(invalid());
+    };
+    let segment = path.path.segments.last().ok_or_else(invalid)?;
+    if segment.ident != "Result" {
+        return Err(invalid());
+    }
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return Err(invalid());
+    };
+    let Some(GenericArgument::Type(ty)) = args.args.first() else {
+        return Err(invalid());
+    };
+
+    match ty {
+        Type::Path(path)
+            if path
+                .path
+                .segments
+                .last()
+                .is_some_and(|segment| segment.ident == "CallResponse") =>
+        {
+            Ok(None)
+        }
+        ty => Ok(Some(ty)),
+    }
+}
+
+/// `///` lines of an item, joined with newlines.
+pub fn doc_comment(attrs: &[Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta.require_name_value().ok()?.value {
+            Expr::Lit(syn::ExprLit {
+                lit: Lit::Str(doc), ..
+            }) => Some(doc.value().trim().to_string()),
+            _ => None,
+        })
+        .collect();
+    let doc = lines.join("\n").trim().to_string();
+    (!doc.is_empty()).then_some(doc)
+}
+
+/// The `AlkanesType` JSON for a Rust input or output type.
+pub fn abi_type(ty: &Type) -> syn::Result<Value> {
+    let unsupported = || {
+        syn::Error::new(
+            ty.span(),
+            "unsupported type; use integers, bool, String, Vec, arrays or tuples",
+        )
+    };
+
+    match ty {
+        Type::Paren(paren) => abi_type(&paren.elem),
+        Type::Group(group) => abi_type(&group.elem),
+        Type::Array(array) => {
+            let length = match &array.len {
+                Expr::Lit(syn::ExprLit {
+                    lit: Lit::Int(length),
+                    ..
+                }) => length.base10_parse::<u64>()?,
+                len => {
+                    return Err(syn::Error::new(
+                        len.span(),
+                        "array lengths must be integer literals",
+                    ))
+                }
+            };
+            Ok(json!({ "array": { "type": abi_type(&array.elem)?, "length": length } }))
+        }
+        Type::Tuple(tuple) if !tuple.elems.is_empty() => Ok(json!({
+            "tuple": tuple.elems.iter().map(abi_type).collect::<syn::Result<Vec<_>>>()?
+        })),
+        Type::Path(path) if path.qself.is_none() => {
+            let segment = path.path.segments.last().ok_or_else(unsupported)?;
+            let name = segment.ident.to_string();
+            match name.as_str() {
+                "u8" | "u16" | "u32" | "u64" | "u128" | "i8" | "i16" | "i32" | "i64" | "i128"
+                | "bool" | "String" => Ok(json!(name)),
+                "Vec" => {
+                    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+                        return Err(unsupported());
+                    };
+                    let Some(GenericArgument::Type(item)) = args.args.first() else {
+                        return Err(unsupported());
+                    };
+                    match abi_type(item)? {
+                        Value::String(item) if item == "u8" => Ok(json!("Vec<u8>")),
+                        item => Ok(json!({ "vec": { "type": item } })),
+                    }
+                }
+                _ => Err(unsupported()),
+            }
+        }
+        _ => Err(unsupported()),
+    }
+}
@@ -0,0 +1,613 @@
+//! Extracts the ABI of an Alkanes contract from its Rust syntax tree.
+//!
+//! The contract is the type implementing `AlkaneResponder`; its methods are
+//! the arms of the `match shift_or_err(&mut inputs)?` dispatch in `execute`,
+//! each described by the comments written above the arm. Contracts written
+//! with `#[alkali::contract]` are read from their `#[opcode(n)]` methods.
+//! Storage is described by how the contract uses its `StoragePointer`s.
+//! A contract split over files is read from its crate root and the module
+//! files it declares.
+
+pub mod attributes;
+mod modules;
+mod storage;
+
+pub use modules::{load_crate, Module};
+use proc_macro2::{LineColumn, Span};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+pub use storage::Storage;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+use syn::{Expr, ImplItem, Item, ItemImpl, Lit, Pat};
+
+/// 1-based position in the contract source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl From<LineColumn> for Location {
+    fn from(lc: LineColumn) -> Self {
+        Location {
+            line: lc.line,
+            column: lc.column + 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExtractError {
+    pub message: String,
+    /// The file the location is in, for contracts read from files
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(flatten)]
+    pub location: Location,
+}
+
+impl ExtractError {
+    fn at(span: Span, message: impl Into<String>) -> Self {
+        ExtractError {
+            message: message.into(),
+            file: None,
+            location: span.start().into(),
+        }
+    }
+
+    /// Places the error in `path`, unless it already is in a file.
+    fn in_file(mut self, path: Option<&Path>) -> Self {
+        if self.file.is_none() {
+            self.file = path.map(|path| path.display().to_string());
+        }
+        self
+    }
+}
+
+impl fmt::Display for ExtractError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{file}:")?;
+        }
+        write!(
+            f,
+            "{}:{}: {}",
+            self.location.line, self.location.column, self.message
+        )
+    }
+}
+
+impl std::error::Error for ExtractError {}
+
+/// One arm of the opcode dispatch.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Method {
+    pub opcode: u64,
+    /// Comments above the arm, stripped of their delimiters, in order
+    pub comments: Vec<String>,
+    /// Names bound by `let name = shift_or_err(&mut inputs)?` in the arm, in
+    /// order
+    pub bindings: Vec<String>,
+    /// The full ABI entry, when the signature is known from Rust itself
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub abi: Option<Value>,
+    #[serde(flatten)]
+    pub location: Location,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Contract {
+    pub name: String,
+    /// The file the methods are read from, for contracts read from files
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    pub methods: Vec<Method>,
+    pub storage: Vec<Storage>,
+}
+
+pub fn extract(source: &str) -> Result<Contract, ExtractError> {
+    extract_modules(&[Module::parse(None, source.to_string())?])
+}
+
+/// Extracts the contract of the crate rooted at `root`, following its
+/// module declarations.
+pub fn extract_crate(root: &Path) -> Result<Contract, ExtractError> {
+    extract_modules(&load_crate(root)?)
+}
+
+fn extract_modules(modules: &[Module]) -> Result<Contract, ExtractError> {
+    let files: Vec<&syn::File> = modules.iter().map(|module| &module.file).collect();
+    let responders: Vec<(&Module, &ItemImpl)> = modules
+        .iter()
+        .flat_map(|module| {
+            module.file.items.iter().filter_map(move |item| match item {
+                Item::Impl(item) if implements(item, "AlkaneResponder") => Some((module, item)),
+                _ => None,
+            })
+        })
+        .collect();
+    let (module, responder) = match responders.as_slice() {
+        [responder] => *responder,
+        [] => {
+            return extract_attribute_contract(modules, &files).unwrap_or_else(|| {
+                Err(ExtractError::at(
+                    Span::call_site(),
+                    "no `impl AlkaneResponder for ...` block found",
+                ))
+            })
+        }
+        [_, (module, second), ..] => {
+            return Err(ExtractError::at(
+                second.span(),
+                "only one `AlkaneResponder` implementation per contract is supported",
+            )
+            .in_file(module.path.as_deref()))
+        }
+    };
+    let path = module.path.as_deref();
+    let source = module.source.as_str();
+
+    let name = type_name(&responder.self_ty).ok_or_else(|| {
+        ExtractError::at(responder.self_ty.span(), "cannot name the contract type").in_file(path)
+    })?;
+
+    let execute = responder
+        .items
+        .iter()
+        .find_map(|item| match item {
+            ImplItem::Fn(f) if f.sig.ident == "execute" => Some(f),
+            _ => None,
+        })
+        .ok_or_else(|| {
+            ExtractError::at(responder.impl_token.span, "`execute` is not implemented")
+                .in_file(path)
+        })?;
+
+    let mut finder = DispatchFinder::default();
+    finder.visit_block(&execute.block);
+    let dispatch = finder.dispatch.ok_or_else(|| {
+        ExtractError::at(
+            execute.sig.ident.span(),
+            "`execute` has no `match shift_or_err(&mut inputs)?` dispatch",
+        )
+        .in_file(path)
+    })?;
+
+    let constants = collect_constants(&files);
+    let lines = LineIndex::new(source);
+    let mut methods = Vec::new();
+    let mut previous_end = dispatch.brace_token.span.open().end();
+    for arm in &dispatch.arms {
+        // `///` comments are attributes of the arm, so scan up to the pattern
+        let start = arm.pat.span().start();
+        let comments = comments_between(&lines, source, previous_end, start);
+        previous_end = match &arm.comma {
+            Some(comma) => comma.span.end(),
+            None => arm.body.span().end(),
+        };
+
+        let mut bindings = BindingFinder::default();
+        bindings.visit_expr(&arm.body);
+
+        for opcode in opcodes(&arm.pat, &constants).map_err(|e| e.in_file(path))? {
+            methods.push(Method {
+                opcode,
+                comments: comments.clone(),
+                bindings: bindings.names.clone(),
+                abi: None,
+                location: arm.pat.span().start().into(),
+            });
+        }
+    }
+
+    Ok(Contract {
+        name,
+        file: path.map(|path| path.display().to_string()),
+        methods,
+        storage: storage::storage_layout(&files),
+    })
+}
+
+/// Reads an `#[alkali::contract] impl Name { ... }` block, if there is one.
+fn extract_attribute_contract(
+    modules: &[Module],
+    files: &[&syn::File],
+) -> Option<Result<Contract, ExtractError>> {
+    let (module, imp) = modules.iter().find_map(|module| {
+        module.file.items.iter().find_map(|item| match item {
+            Item::Impl(imp) if imp.attrs.iter().any(attributes::is_contract_attribute) => {
+                Some((module, imp))
+            }
+            _ => None,
+        })
+    })?;
+    let path = module.path.as_deref();
+    Some(
+        attribute_contract(files, imp)
+            .map(|contract| Contract {
+                file: path.map(|path| path.display().to_string()),
+                ..contract
+            })
+            .map_err(|e| ExtractError::at(e.span(), e.to_string()).in_file(path)),
+    )
+}
+
+fn attribute_contract(files: &[&syn::File], imp: &ItemImpl) -> syn::Result<Contract> {
+    let name = type_name(&imp.self_ty)
+        .ok_or_else(|| syn::Error::new(imp.self_ty.span(), "cannot name the contract type"))?;
+
+    let mut methods = Vec::new();
+    for item in &imp.items {
+        let ImplItem::Fn(f) = item else {
+            continue;
+        };
+        if let Some(method) = attributes::opcode_method(f)? {
+            methods.push(Method {
+                opcode: method.opcode,
+                comments: Vec::new(),
+                bindings: Vec::new(),
+                abi: Some(method.abi()?),
+                location: f.sig.ident.span().start().into(),
+            });
+        }
+    }
+
+    Ok(Contract {
+        name,
+        file: None,
+        methods,
+        storage: storage::storage_layout(files),
+    })
+}
+
+fn type_name(ty: &syn::Type) -> Option<String> {
+    match ty {
+        syn::Type::Path(ty) => ty.path.segments.last().map(|s| s.ident.to_string()),
+        _ => None,
+    }
+}
+
+fn implements(item: &ItemImpl, trait_name: &str) -> bool {
+    item.trait_
+        .as_ref()
+        .and_then(|(_, path, _)| path.segments.last())
+        .is_some_and(|segment| segment.ident == trait_name)
+}
+
+/// Opcodes matched by a dispatch arm; the `_` fallback matches none.
+fn opcodes(pat: &Pat, constants: &HashMap<String, u64>) -> Result<Vec<u64>, ExtractError> {
+    match pat {
+        Pat::Wild(_) => Ok(vec![]),
+        Pat::Lit(lit) => match &lit.lit {
+            Lit::Int(int) => int
+                .base10_parse::<u64>()
+                .map(|opcode| vec![opcode])
+                .map_err(|e| ExtractError::at(int.span(), e.to_string())),
+            _ => Err(ExtractError::at(lit.span(), "opcodes must be integers")),
+        },
+        Pat::Ident(ident) if ident.subpat.is_none() => resolve(&ident.ident, constants),
+        Pat::Path(path) => match path.path.segments.last() {
+            Some(segment) => resolve(&segment.ident, constants),
+            None => Err(ExtractError::at(path.span(), "empty opcode path")),
+        },
+        Pat::Or(or) => {
+            let mut all = Vec::new();
+            for case in &or.cases {
+                all.extend(opcodes(case, constants)?);
+            }
+            Ok(all)
+        }
+        Pat::Paren(paren) => opcodes(&paren.pat, constants),
+        _ => Err(ExtractError::at(
+            pat.span(),
+            "unsupported opcode pattern; use an integer literal or a constant",
+        )),
+    }
+}
+
+fn resolve(ident: &syn::Ident, constants: &HashMap<String, u64>) -> Result<Vec<u64>, ExtractError> {
+    constants
+        .get(&ident.to_string())
+        .map(|opcode| vec![*opcode])
+        .ok_or_else(|| {
+            ExtractError::at(
+                ident.span(),
+                format!("cannot resolve opcode `{ident}` to an integer constant"),
+            )
+        })
+}
+
+/// Integer constants declared at module level or in impl blocks.
+fn collect_constants(files: &[&syn::File]) -> HashMap<String, u64> {
+    let mut constants = HashMap::new();
+    let mut add = |ident: &syn::Ident, expr: &Expr| {
+        if let Some(value) = integer(expr) {
+            constants.insert(ident.to_string(), value);
+        }
+    };
+    for item in files.iter().flat_map(|file| &file.items) {
+        match item {
+            Item::Const(c) => add(&c.ident, &c.expr),
+            Item::Impl(imp) => {
+                for item in &imp.items {
+                    if let ImplItem::Const(c) = item {
+                        add(&c.ident, &c.expr);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    constants
+}
+
+fn integer(expr: &Expr) -> Option<u64> {
+    match expr {
+        Expr::Lit(lit) => match &lit.lit {
+            Lit::Int(int) => int.base10_parse().ok(),
+            _ => None,
+        },
+        Expr::Cast(cast) => integer(&cast.expr),
+        Expr::Paren(paren) => integer(&paren.expr),
+        _ => None,
+    }
+}
+
+/// Finds the outermost `match` on a `shift_or_err(..)` call.
+#[derive(Default)]
+struct DispatchFinder<'ast> {
+    dispatch: Option<&'ast syn::ExprMatch>,
+}
+
+impl<'ast> Visit<'ast> for DispatchFinder<'ast> {
+    fn visit_expr_match(&mut self, node: &'ast syn::ExprMatch) {
+        if self.dispatch.is_some() {
+            return;
+        }
+        if calls_shift_or_err(&node.expr) {
+            self.dispatch = Some(node);
+        } else {
+            visit::visit_expr_match(self, node);
+        }
+    }
+}
+
+fn calls_shift_or_err(expr: &Expr) -> bool {
+    match expr {
+        Expr::Try(e) => calls_shift_or_err(&e.expr),
+        Expr::Paren(e) => calls_shift_or_err(&e.expr),
+        Expr::Call(call) => match &*call.func {
+            Expr::Path(path) => path
+                .path
+                .segments
+                .last()
+                .is_some_and(|segment| segment.ident == "shift_or_err"),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Collects the names of `let` bindings initialised from `shift_or_err`.
+#[derive(Default)]
+struct BindingFinder {
+    names: Vec<String>,
+}
+
+impl<'ast> Visit<'ast> for BindingFinder {
+    fn visit_local(&mut self, node: &'ast syn::Local) {
+        let shifted = node
+            .init
+            .as_ref()
+            .is_some_and(|init| shifts_input(&init.expr));
+        if shifted {
+            let pat = match &node.pat {
+                Pat::Type(typed) => &*typed.pat,
+                pat => pat,
+            };
+            if let Pat::Ident(ident) = pat {
+                self.names.push(ident.ident.to_string());
+            }
+        }
+        visit::visit_local(self, node);
+    }
+}
+
+/// Whether `expr` is a `shift_or_err` call, possibly converted afterwards
+/// (`shift_or_err(&mut inputs)? as u64`, `.try_into()?`, ...).
+fn shifts_input(expr: &Expr) -> bool {
+    match expr {
+        Expr::Cast(e) => shifts_input(&e.expr),
+        Expr::MethodCall(e) => shifts_input(&e.receiver),
+        Expr::Try(e) => shifts_input(&e.expr),
+        Expr::Paren(e) => shifts_input(&e.expr),
+        expr => calls_shift_or_err(expr),
+    }
+}
+
+/// Maps proc-macro2 line/column positions back to byte offsets.
+struct LineIndex {
+    starts: Vec<usize>,
+}
+
+impl LineIndex {
+    fn new(source: &str) -> Self {
+        let mut starts = vec![0];
+        starts.extend(source.match_indices('\n').map(|(i, _)| i + 1));
+        LineIndex { starts }
+    }
+
+    fn offset(&self, source: &str, lc: LineColumn) -> usize {
+        let start = self
+            .starts
+            .get(lc.line - 1)
+            .copied()
+            .unwrap_or(source.len());
+        source[start..]
+            .char_indices()
+            .nth(lc.column)
+            .map_or(source.len(), |(i, _)| start + i)
+    }
+}
+
+/// Text of the `//` and `/* */` comments between two positions, without
+/// their delimiters or leading `*` decoration.
+fn comments_between(
+    lines: &LineIndex,
+    source: &str,
+    from: LineColumn,
+    to: LineColumn,
+) -> Vec<String> {
+    let text = &source[lines.offset(source, from)..lines.offset(source, to)];
+    let mut comments = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('/') {
+        rest = &rest[start..];
+        if let Some(line) = rest.strip_prefix("//") {
+            let end = line.find('\n').unwrap_or(line.len());
+            let body = line[..end].trim_start_matches(['/', '!']).trim();
+            if !body.is_empty() {
+                comments.push(body.to_string());
+            }
+            rest = &line[end..];
+        } else if let Some(block) = rest.strip_prefix("/*") {
+            let end = block.find("*/").unwrap_or(block.len());
+            let body = block[..end]
+                .trim_start_matches(['*', '!'])
+                .lines()
+                .map(|line| line.trim().trim_start_matches('*').trim())
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n");
+            if !body.is_empty() {
+                comments.push(body);
+            }
+            rest = &block[(end + 2).min(block.len())..];
+        } else {
+            rest = &rest[1..];
+        }
+    }
+    comments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contract(dispatch: &str) -> String {
+        format!(
+            "pub struct Token(());\n\
+             impl AlkaneResponder for Token {{\n\
+             fn execute(&self) -> Result<CallResponse> {{\n\
+             let mut inputs = self.context()?.inputs.clone();\n\
+             {dispatch}\n\
+             }}\n\
+             }}\n"
+        )
+    }
+
+    #[test]
+    fn extracts_arms_with_their_comments() {
+        let source = contract(
+            "match shift_or_err(&mut inputs)? {\n\
+             /* initialize(u128) */\n\
+             0 => Ok(response),\n\
+             // Mints tokens\n\
+             /// mint(u128)\n\
+             77 => {\n\
+                 let amount = shift_or_err(&mut inputs)?;\n\
+                 let to: u64 = shift_or_err(&mut inputs)?.try_into()?;\n\
+                 let _ = shift_or_err(&mut inputs)?;\n\
+                 Ok(response)\n\
+             }\n\
+             _ => Err(anyhow!(\"unrecognized opcode\")),\n\
+             }",
+        );
+
+        let abi = extract(&source).unwrap();
+        assert_eq!(abi.name, "Token");
+        assert_eq!(abi.methods.len(), 2);
+        assert_eq!(abi.methods[0].comments, ["initialize(u128)"]);
+        assert_eq!(abi.methods[1].opcode, 77);
+        assert_eq!(abi.methods[1].comments, ["Mints tokens", "mint(u128)"]);
+        assert_eq!(abi.methods[1].bindings, ["amount", "to"]);
+        assert!(abi.methods[0].bindings.is_empty());
+        assert_eq!(
+            abi.methods[1].location,
+            Location {
+                line: 10,
+                column: 1
+            }
+        );
+    }
+
+    #[test]
+    fn resolves_constant_opcodes() {
+        let source = format!(
+            "const MINT: u128 = 77;\n{}",
+            contract("match shift_or_err(&mut inputs)? { /* mint() */ MINT | 78 => Ok(response), _ => unreachable!() }")
+        );
+
+        let opcodes: Vec<u64> = extract(&source)
+            .unwrap()
+            .methods
+            .iter()
+            .map(|method| method.opcode)
+            .collect();
+        assert_eq!(opcodes, [77, 78]);
+    }
+
+    #[test]
+    fn reports_unresolvable_opcodes_with_their_location() {
+        let source = contract("match shift_or_err(&mut inputs)? {\nBURN => Ok(response),\n}");
+
+        let error = extract(&source).unwrap_err();
+        assert_eq!(error.location, Location { line: 6, column: 1 });
+        assert!(error.message.contains("`BURN`"));
+    }
+
+    #[test]
+    fn reports_syntax_errors_with_their_location() {
+        let error = extract(
+            "pub struct Token(());\nimpl AlkaneResponder for Token {\n  fn execute(&self) {",
+        )
+        .unwrap_err();
+        assert_eq!(error.location.line, 3);
+    }
+
+    #[test]
+    fn extracts_contracts_split_over_files() {
+        let root = std::env::temp_dir().join(format!("alkali-abi-crate-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(
+            root.join("lib.rs"),
+            "mod token;\nconst MINT: u128 = 77;\nfn supply_pointer() -> StoragePointer { StoragePointer::from_keyword(\"/supply\") }\n",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("token.rs"),
+            contract("match shift_or_err(&mut inputs)? {\n/* mint(u128) */\nMINT => { let supply: u128 = supply_pointer().get_value(); Ok(response) }\nBURN => Ok(response),\n}"),
+        )
+        .unwrap();
+
+        let error = extract_crate(&root.join("lib.rs")).unwrap_err();
+        let token = root.join("token.rs").display().to_string();
+        assert_eq!(
+            error.to_string(),
+            format!("{token}:8:1: cannot resolve opcode `BURN` to an integer constant")
+        );
+
+        std::fs::write(root.join("lib.rs"), "mod token;\nconst MINT: u128 = 77;\nconst BURN: u128 = 88;\nfn supply_pointer() -> StoragePointer { StoragePointer::from_keyword(\"/supply\") }\n").unwrap();
+        let abi = extract_crate(&root.join("lib.rs")).unwrap();
+        assert_eq!(abi.file, Some(token));
+        assert_eq!(abi.methods[0].comments, ["mint(u128)"]);
+        assert_eq!(abi.methods[0].location, Location { line: 7, column: 1 });
+        assert_eq!(abi.storage[0].key, "/supply");
+        assert_eq!(abi.storage[0].ty, serde_json::json!("u128"));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,35 @@
+//! `alkali-abi [FILE]`: prints the ABI extracted from a contract's crate root
+//! file, following its module declarations (or from stdin), as JSON, or
+//! `{"error": ...}` with its location and exit code 1.
+
+use std::io::Read;
+use std::path::Path;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let contract = match std::env::args().nth(1).filter(|path| path != "-") {
+        Some(path) => alkali_abi::extract_crate(Path::new(&path)),
+        None => {
+            let mut source = String::new();
+            if let Err(e) = std::io::stdin().read_to_string(&mut source) {
+                eprintln!("cannot read stdin: {e}");
+                return ExitCode::from(2);
+            }
+            alkali_abi::extract(&source)
+        }
+    };
+
+    match contract {
+        Ok(contract) => {
+            println!(
+                "{}",
+                serde_json::to_string(&contract).expect("serializable")
+            );
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            println!("{}", serde_json::json!({ "error": error }));
+            ExitCode::FAILURE
+        }
+    }
+}
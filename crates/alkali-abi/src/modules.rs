@@ -0,0 +1,165 @@
+//! Contracts split over files: the crate root and the files of the modules
+//! it declares with `mod name;`, found as rustc finds them. Test-only
+//! (`#[cfg(test)]`) modules are not read.
+
+use crate::ExtractError;
+use proc_macro2::Span;
+use std::path::{Path, PathBuf};
+use syn::{Attribute, Expr, Item, Lit, Meta};
+
+/// A source file of the contract.
+pub struct Module {
+    /// The file, or none for source passed directly
+    pub path: Option<PathBuf>,
+    pub source: String,
+    pub file: syn::File,
+}
+
+impl Module {
+    pub fn parse(path: Option<PathBuf>, source: String) -> Result<Self, ExtractError> {
+        let file = syn::parse_file(&source)
+            .map_err(|e| ExtractError::at(e.span(), e.to_string()).in_file(path.as_deref()))?;
+        Ok(Module { path, source, file })
+    }
+}
+
+/// Reads the crate rooted at `root` (its `lib.rs`, or a single-file
+/// contract), root first.
+pub fn load_crate(root: &Path) -> Result<Vec<Module>, ExtractError> {
+    let mut modules = Vec::new();
+    // The crate root's modules are next to it, like a `mod.rs`'s
+    let dir = root.parent().unwrap_or(Path::new("")).to_path_buf();
+    load(root, &dir, &mut modules)?;
+    Ok(modules)
+}
+
+/// Reads `path`, then the files of the modules it declares, which are
+/// under `dir`.
+fn load(path: &Path, dir: &Path, modules: &mut Vec<Module>) -> Result<(), ExtractError> {
+    let source = std::fs::read_to_string(path).map_err(|e| {
+        ExtractError::at(
+            Span::call_site(),
+            format!("cannot read {}: {e}", path.display()),
+        )
+    })?;
+    let module = Module::parse(Some(path.to_path_buf()), source)?;
+    let mut declared = Vec::new();
+    declarations(&module.file.items, dir, &mut declared).map_err(|e| e.in_file(Some(path)))?;
+    modules.push(module);
+
+    for file in declared {
+        // `name.rs` keeps its modules in `name/`, `name/mod.rs` next to it
+        let dir = if file.file_name().is_some_and(|name| name == "mod.rs") {
+            file.parent().unwrap_or(Path::new("")).to_path_buf()
+        } else {
+            file.with_extension("")
+        };
+        load(&file, &dir, modules)?;
+    }
+    Ok(())
+}
+
+/// Files of the `mod name;` declarations among `items`, whose files are
+/// under `dir`.
+fn declarations(items: &[Item], dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), ExtractError> {
+    for item in items {
+        let Item::Mod(module) = item else {
+            continue;
+        };
+        if module.attrs.iter().any(is_cfg_test) {
+            continue;
+        }
+        let name = module.ident.to_string();
+        if let Some((_, items)) = &module.content {
+            // Inline modules nest the directory of their own declarations
+            declarations(items, &dir.join(&name), files)?;
+            continue;
+        }
+        let file = match module.attrs.iter().find_map(path_attribute) {
+            Some(path) => dir.join(path),
+            None => [dir.join(format!("{name}.rs")), dir.join(&name).join("mod.rs")]
+                .into_iter()
+                .find(|file| file.is_file())
+                .ok_or_else(|| {
+                    ExtractError::at(
+                        module.ident.span(),
+                        format!(
+                            "file not found for module `{name}`; expected {name}.rs or {name}/mod.rs in {}",
+                            dir.display()
+                        ),
+                    )
+                })?,
+        };
+        files.push(file);
+    }
+    Ok(())
+}
+
+fn is_cfg_test(attr: &Attribute) -> bool {
+    attr.path().is_ident("cfg")
+        && attr
+            .parse_args::<syn::Ident>()
+            .is_ok_and(|cfg| cfg == "test")
+}
+
+/// The file of `#[path = "file.rs"]`.
+fn path_attribute(attr: &Attribute) -> Option<String> {
+    match &attr.meta {
+        Meta::NameValue(meta) if meta.path.is_ident("path") => match &meta.value {
+            Expr::Lit(expr) => match &expr.lit {
+                Lit::Str(path) => Some(path.value()),
+                _ => None,
+            },
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn follows_module_declarations_but_not_test_modules() {
+        let root = std::env::temp_dir().join(format!("alkali-abi-modules-{}", std::process::id()));
+        fs::create_dir_all(root.join("math/ops")).unwrap();
+        fs::write(
+            root.join("lib.rs"),
+            "mod math;\n#[path = \"other.rs\"]\nmod renamed;\n#[cfg(test)]\nmod tests;\n",
+        )
+        .unwrap();
+        fs::write(root.join("math.rs"), "pub mod ops;\n").unwrap();
+        fs::write(root.join("math/ops/mod.rs"), "pub fn add() {}\n").unwrap();
+        fs::write(root.join("other.rs"), "").unwrap();
+        // Neither declared nor compiled
+        fs::write(root.join("tests.rs"), "not rust").unwrap();
+        fs::write(root.join("stray.rs"), "not rust").unwrap();
+
+        let paths: Vec<PathBuf> = load_crate(&root.join("lib.rs"))
+            .unwrap()
+            .into_iter()
+            .map(|module| {
+                module
+                    .path
+                    .unwrap()
+                    .strip_prefix(&root)
+                    .unwrap()
+                    .to_path_buf()
+            })
+            .collect();
+        assert_eq!(
+            paths,
+            ["lib.rs", "math.rs", "math/ops/mod.rs", "other.rs"].map(PathBuf::from)
+        );
+
+        fs::write(root.join("math.rs"), "mod missing;\n").unwrap();
+        let error = load_crate(&root.join("lib.rs")).err().unwrap();
+        assert_eq!(error.file, Some(root.join("math.rs").display().to_string()));
+        assert!(error
+            .message
+            .starts_with("file not found for module `missing`"));
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,476 @@
+//! Storage layout inferred from how `StoragePointer`s are used: the keyword a
+//! pointer starts from, the sub-keys selected under it, and the value type
+//! read or written through it.
+//!
+//! Pointers are followed through method chains, `let` bindings and helper
+//! methods returning a pointer, such as
+//! `fn supply_pointer(&self) -> StoragePointer { StoragePointer::from_keyword("/supply") }`.
+
+use crate::attributes::abi_type;
+use crate::Location;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+use syn::{Expr, GenericArgument, Lit, Pat, Stmt};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Storage {
+    /// The `from_keyword` key, with any literal keywords appended to it
+    pub key: String,
+    /// `AlkanesType` of the stored value; `Vec<u8>` when only raw bytes are
+    /// read or written
+    #[serde(rename = "type")]
+    pub ty: Value,
+    /// Sub-keys appended after `key`, for pointers keyed by runtime values
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub path: Vec<Value>,
+    #[serde(flatten)]
+    pub location: Location,
+}
+
+/// A pointer expression, as far as it can be followed statically.
+#[derive(Clone)]
+struct Pointer {
+    /// The `from_keyword` argument
+    keyword: String,
+    key: String,
+    path: Vec<Value>,
+    /// Selected with `select_index`, so values are list items
+    list: bool,
+    location: Location,
+}
+
+impl Pointer {
+    fn select(mut self, segment: Value) -> Self {
+        self.path.push(segment);
+        self
+    }
+
+    fn keyword(mut self, word: String) -> Self {
+        if self.path.is_empty() {
+            self.key.push_str(&word);
+            self
+        } else {
+            self.select(json!({ "keyword": word }))
+        }
+    }
+}
+
+/// How an access reveals the value type.
+enum Access {
+    /// `get_value::<T>()`, `set_value::<T>(..)`
+    Value(Value),
+    /// `append_value::<T>(..)`, `get_list_values::<T>()`
+    List(Value),
+    /// `get()`, `set(..)` and untyped accesses
+    Raw,
+}
+
+/// Storage of a crate's files, in file order. Pointer helpers may be
+/// declared in any of them.
+pub fn storage_layout(files: &[&syn::File]) -> Vec<Storage> {
+    // Helpers may return pointers from other helpers, so resolve twice
+    let mut helpers = HashMap::new();
+    for _ in 0..2 {
+        let mut finder = HelperFinder {
+            helpers: &helpers,
+            found: HashMap::new(),
+        };
+        for file in files {
+            finder.visit_file(file);
+        }
+        helpers = finder.found;
+    }
+
+    // Accesses and keywords, with the index of the file they are in
+    let mut entries = Vec::new();
+    let mut keywords = Vec::new();
+    for (index, file) in files.iter().enumerate() {
+        let mut recorder = Recorder {
+            helpers: &helpers,
+            locals: HashMap::new(),
+            entries: Vec::new(),
+            keywords: Vec::new(),
+        };
+        recorder.visit_file(file);
+        entries.extend(
+            recorder
+                .entries
+                .into_iter()
+                .map(|(pointer, access)| (index, pointer, access)),
+        );
+        keywords.extend(
+            recorder
+                .keywords
+                .into_iter()
+                .map(|pointer| (index, pointer)),
+        );
+    }
+
+    // Entries, the file they are in, whether they are typed, and their
+    // pointer's keyword
+    let mut storage: Vec<(Storage, usize, bool, String)> = Vec::new();
+    for (index, pointer, access) in entries {
+        let (ty, typed) = match access {
+            Access::Value(ty) if pointer.list => (json!({ "vec": { "type": ty } }), true),
+            Access::Value(ty) => (ty, true),
+            Access::List(ty) => (json!({ "vec": { "type": ty } }), true),
+            Access::Raw => (json!("Vec<u8>"), false),
+        };
+        match storage
+            .iter_mut()
+            .find(|(entry, ..)| entry.key == pointer.key && entry.path == pointer.path)
+        {
+            // The first typed access wins over raw ones
+            Some((entry, _, entry_typed, _)) => {
+                if typed && !*entry_typed {
+                    entry.ty = ty;
+                    *entry_typed = true;
+                }
+            }
+            None => storage.push((
+                Storage {
+                    key: pointer.key,
+                    ty,
+                    path: pointer.path,
+                    location: pointer.location,
+                },
+                index,
+                typed,
+                pointer.keyword,
+            )),
+        }
+    }
+
+    // Keywords never accessed through a pointer still take up storage
+    for (index, pointer) in keywords {
+        if !storage
+            .iter()
+            .any(|(_, _, _, keyword)| *keyword == pointer.keyword)
+        {
+            storage.push((
+                Storage {
+                    key: pointer.key,
+                    ty: json!("Vec<u8>"),
+                    path: Vec::new(),
+                    location: pointer.location,
+                },
+                index,
+                false,
+                pointer.keyword,
+            ));
+        }
+    }
+
+    storage.sort_by_key(|(entry, index, ..)| (*index, entry.location.line, entry.location.column));
+    storage.into_iter().map(|(entry, ..)| entry).collect()
+}
+
+/// Follows `expr` back to the pointer it evaluates to.
+fn pointer_of(
+    expr: &Expr,
+    locals: &HashMap<String, Pointer>,
+    helpers: &HashMap<String, Pointer>,
+) -> Option<Pointer> {
+    match expr {
+        Expr::Call(call) => keyword_pointer(call).or_else(|| {
+            let Expr::Path(func) = &*call.func else {
+                return None;
+            };
+            helpers
+                .get(&func.path.segments.last()?.ident.to_string())
+                .cloned()
+        }),
+        Expr::MethodCall(call) => {
+            let method = call.method.to_string();
+            if matches!(&*call.receiver, Expr::Path(path) if path.path.is_ident("self")) {
+                return helpers.get(&method).cloned();
+            }
+            let pointer = pointer_of(&call.receiver, locals, helpers)?;
+            match method.as_str() {
+                "clone" => Some(pointer),
+                "select" => Some(pointer.select(json!({ "select": "Vec<u8>" }))),
+                "select_value" => Some(pointer.select(json!({
+                    "select": turbofish(call).unwrap_or_else(|| json!("Vec<u8>"))
+                }))),
+                "keyword" => match call.args.first().and_then(string_literal) {
+                    Some(word) => Some(pointer.keyword(word)),
+                    None => Some(pointer.select(json!({ "select": "String" }))),
+                },
+                "select_index" => Some(Pointer {
+                    list: true,
+                    ..pointer
+                }),
+                _ => None,
+            }
+        }
+        Expr::Path(path) => locals.get(&path.path.get_ident()?.to_string()).cloned(),
+        Expr::Reference(reference) => pointer_of(&reference.expr, locals, helpers),
+        Expr::Paren(paren) => pointer_of(&paren.expr, locals, helpers),
+        Expr::Block(block) => match block.block.stmts.last()? {
+            Stmt::Expr(expr, None) => pointer_of(expr, locals, helpers),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// `StoragePointer::from_keyword("...")`
+fn keyword_pointer(call: &syn::ExprCall) -> Option<Pointer> {
+    let Expr::Path(func) = &*call.func else {
+        return None;
+    };
+    if func.path.segments.last()?.ident != "from_keyword" {
+        return None;
+    }
+    let Some(Expr::Lit(syn::ExprLit {
+        lit: Lit::Str(key), ..
+    })) = call.args.first()
+    else {
+        return None;
+    };
+    Some(Pointer {
+        keyword: key.value(),
+        key: key.value(),
+        path: Vec::new(),
+        list: false,
+        location: call.span().start().into(),
+    })
+}
+
+fn string_literal(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Lit(syn::ExprLit {
+            lit: Lit::Str(word),
+            ..
+        }) => Some(word.value()),
+        Expr::Reference(reference) => string_literal(&reference.expr),
+        _ => None,
+    }
+}
+
+/// The `AlkanesType` of `method::<T>`, when `T` is one.
+fn turbofish(call: &syn::ExprMethodCall) -> Option<Value> {
+    match call.turbofish.as_ref()?.args.first()? {
+        GenericArgument::Type(ty) => abi_type(ty).ok(),
+        _ => None,
+    }
+}
+
+fn access(call: &syn::ExprMethodCall) -> Option<Access> {
+    let typed = turbofish(call);
+    Some(match call.method.to_string().as_str() {
+        "get_value" | "set_value" => typed.map_or(Access::Raw, Access::Value),
+        "append_value" | "get_list_values" => {
+            typed.map_or(Access::List(json!("Vec<u8>")), Access::List)
+        }
+        "append" | "get_list" => Access::List(json!("Vec<u8>")),
+        "get" | "set" | "nullify" | "length" => Access::Raw,
+        _ => return None,
+    })
+}
+
+/// The pointer a function body evaluates to, binding `let` pointers first.
+fn returned_pointer(block: &syn::Block, helpers: &HashMap<String, Pointer>) -> Option<Pointer> {
+    let mut locals = HashMap::new();
+    for stmt in &block.stmts {
+        match stmt {
+            Stmt::Local(local) => {
+                if let (Some(name), Some(init)) = (binding(&local.pat), &local.init) {
+                    if let Some(pointer) = pointer_of(&init.expr, &locals, helpers) {
+                        locals.insert(name, pointer);
+                    }
+                }
+            }
+            Stmt::Expr(Expr::Return(ret), _) => {
+                return pointer_of(ret.expr.as_deref()?, &locals, helpers)
+            }
+            Stmt::Expr(expr, None) => return pointer_of(expr, &locals, helpers),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn binding(pat: &Pat) -> Option<String> {
+    match pat {
+        Pat::Ident(ident) => Some(ident.ident.to_string()),
+        Pat::Type(typed) => binding(&typed.pat),
+        _ => None,
+    }
+}
+
+/// Functions and methods returning a pointer, by name.
+struct HelperFinder<'a> {
+    helpers: &'a HashMap<String, Pointer>,
+    found: HashMap<String, Pointer>,
+}
+
+impl<'ast> Visit<'ast> for HelperFinder<'_> {
+    fn visit_item_fn(&mut self, node: &'ast syn::ItemFn) {
+        if let Some(pointer) = returned_pointer(&node.block, self.helpers) {
+            self.found.insert(node.sig.ident.to_string(), pointer);
+        }
+        visit::visit_item_fn(self, node);
+    }
+
+    fn visit_impl_item_fn(&mut self, node: &'ast syn::ImplItemFn) {
+        if let Some(pointer) = returned_pointer(&node.block, self.helpers) {
+            self.found.insert(node.sig.ident.to_string(), pointer);
+        }
+        visit::visit_impl_item_fn(self, node);
+    }
+}
+
+/// Records every access through a pointer, and every keyword used.
+struct Recorder<'a> {
+    helpers: &'a HashMap<String, Pointer>,
+    /// Pointers bound in the function being visited
+    locals: HashMap<String, Pointer>,
+    entries: Vec<(Pointer, Access)>,
+    keywords: Vec<Pointer>,
+}
+
+impl<'ast> Visit<'ast> for Recorder<'_> {
+    fn visit_item_fn(&mut self, node: &'ast syn::ItemFn) {
+        self.locals.clear();
+        visit::visit_item_fn(self, node);
+    }
+
+    fn visit_impl_item_fn(&mut self, node: &'ast syn::ImplItemFn) {
+        self.locals.clear();
+        visit::visit_impl_item_fn(self, node);
+    }
+
+    fn visit_local(&mut self, node: &'ast syn::Local) {
+        visit::visit_local(self, node);
+        let (Some(name), Some(init)) = (binding(&node.pat), &node.init) else {
+            return;
+        };
+        if let Some(pointer) = pointer_of(&init.expr, &self.locals, self.helpers) {
+            self.locals.insert(name, pointer);
+            return;
+        }
+
+        // `let supply: u128 = pointer.get_value();`
+        if let (Pat::Type(typed), Expr::MethodCall(call)) = (&node.pat, &*init.expr) {
+            if call.method == "get_value" && call.turbofish.is_none() {
+                if let (Some(pointer), Ok(ty)) = (
+                    pointer_of(&call.receiver, &self.locals, self.helpers),
+                    abi_type(&typed.ty),
+                ) {
+                    self.entries.push((pointer, Access::Value(ty)));
+                }
+            }
+        }
+    }
+
+    fn visit_expr_method_call(&mut self, node: &'ast syn::ExprMethodCall) {
+        if let Some(access) = access(node) {
+            if let Some(pointer) = pointer_of(&node.receiver, &self.locals, self.helpers) {
+                self.entries.push((pointer, access));
+            }
+        }
+        visit::visit_expr_method_call(self, node);
+    }
+
+    fn visit_expr_call(&mut self, node: &'ast syn::ExprCall) {
+        if let Some(pointer) = keyword_pointer(node) {
+            self.keywords.push(pointer);
+        }
+        visit::visit_expr_call(self, node);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(source: &str) -> Value {
+        let file = syn::parse_file(source).unwrap();
+        let storage: Vec<Value> = storage_layout(&[&file])
+            .into_iter()
+            .map(|entry| {
+                let mut entry = serde_json::to_value(entry).unwrap();
+                let entry = entry.as_object_mut().unwrap();
+                entry.remove("line");
+                entry.remove("column");
+                Value::Object(entry.clone())
+            })
+            .collect();
+        json!(storage)
+    }
+
+    #[test]
+    fn types_values_through_helpers_and_bindings() {
+        let storage = layout(
+            r#"
+            impl Token {
+                fn supply_pointer(&self) -> StoragePointer {
+                    StoragePointer::from_keyword("/totalsupply")
+                }
+                fn supply(&self) -> u128 {
+                    self.supply_pointer().get_value::<u128>()
+                }
+                fn initialize(&self) {
+                    let mut pointer = StoragePointer::from_keyword("/initialized");
+                    pointer.set(Arc::new(vec![1]));
+                    let name = StoragePointer::from_keyword("/name").keyword("/value");
+                    let value: u64 = name.get_value();
+                    StoragePointer::from_keyword("/unused");
+                }
+            }
+            "#,
+        );
+        assert_eq!(
+            storage,
+            json!([
+                { "key": "/totalsupply", "type": "u128" },
+                { "key": "/initialized", "type": "Vec<u8>" },
+                { "key": "/name/value", "type": "u64" },
+                { "key": "/unused", "type": "Vec<u8>" },
+            ])
+        );
+    }
+
+    #[test]
+    fn describes_maps_and_lists() {
+        let storage = layout(
+            r#"
+            fn balances() -> StoragePointer {
+                StoragePointer::from_keyword("/balances/")
+            }
+            fn record(owner: &Vec<u8>, id: u128, name: &String) {
+                balances().select(owner).get();
+                balances().select(owner).set_value::<u128>(1);
+                StoragePointer::from_keyword("/names/")
+                    .select_value::<u128>(id)
+                    .keyword(name)
+                    .keyword("/label")
+                    .set(Arc::new(vec![]));
+                StoragePointer::from_keyword("/holders").append_value::<u32>(7);
+                StoragePointer::from_keyword("/log").select_index(0).get_value::<u64>();
+            }
+            "#,
+        );
+        assert_eq!(
+            storage,
+            json!([
+                { "key": "/balances/", "type": "u128", "path": [{ "select": "Vec<u8>" }] },
+                {
+                    "key": "/names/",
+                    "type": "Vec<u8>",
+                    "path": [
+                        { "select": "u128" },
+                        { "select": "String" },
+                        { "keyword": "/label" },
+                    ],
+                },
+                { "key": "/holders", "type": { "vec": { "type": "u32" } } },
+                { "key": "/log", "type": { "vec": { "type": "u64" } } },
+            ])
+        );
+    }
+}
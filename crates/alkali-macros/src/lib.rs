@@ -0,0 +1,163 @@
+//! `#[alkali::contract]`: turns an impl block of `#[opcode(n)]` methods into
+//! an `AlkaneResponder` whose `execute` shifts the opcode and each method's
+//! inputs off the cellpack and dispatches to it, writing the returned value
+//! into the response data.
+
+use alkali_abi::attributes::{self, OpcodeMethod};
+use proc_macro::TokenStream;
+use quote::quote;
+use serde_json::json;
+use std::collections::HashMap;
+use syn::{parse_macro_input, ImplItem, ItemImpl};
+
+#[proc_macro_attribute]
+pub fn contract(attr: TokenStream, item: TokenStream) -> TokenStream {
+    if !attr.is_empty() {
+        return syn::Error::new_spanned(
+            proc_macro2::TokenStream::from(attr),
+            "#[alkali::contract] takes no arguments",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    let mut imp = parse_macro_input!(item as ItemImpl);
+    match expand(&mut imp) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+fn expand(imp: &mut ItemImpl) -> syn::Result<proc_macro2::TokenStream> {
+    if let Some((_, path, _)) = &imp.trait_ {
+        return Err(syn::Error::new_spanned(
+            path,
+            "#[alkali::contract] goes on an inherent `impl Contract` block",
+        ));
+    }
+
+    let self_ty = &imp.self_ty;
+    let name = match &**self_ty {
+        syn::Type::Path(ty) => ty.path.segments.last().map(|s| s.ident.to_string()),
+        _ => None,
+    }
+    .ok_or_else(|| syn::Error::new_spanned(self_ty, "cannot name the contract type"))?;
+
+    let mut arms = Vec::new();
+    let mut abi_methods = Vec::new();
+    let mut opcodes = serde_json::Map::new();
+    let mut seen = HashMap::new();
+    for item in &imp.items {
+        let ImplItem::Fn(f) = item else {
+            continue;
+        };
+        let Some(method) = attributes::opcode_method(f)? else {
+            continue;
+        };
+        if let Some(previous) = seen.insert(method.opcode, method.ident.to_string()) {
+            return Err(syn::Error::new_spanned(
+                &f.sig.ident,
+                format!(
+                    "opcode {} is already dispatched to `{previous}`",
+                    method.opcode
+                ),
+            ));
+        }
+
+        abi_methods.push(method.abi()?);
+        opcodes.insert(method.ident.to_string(), json!(method.opcode));
+        arms.push(dispatch_arm(&method));
+    }
+
+    let abi = json!({
+        "name": name,
+        "methods": abi_methods,
+        "storage": [],
+        "opcodes": opcodes,
+    })
+    .to_string();
+
+    // The #[opcode] markers are only meaningful to this macro
+    for item in &mut imp.items {
+        if let ImplItem::Fn(f) = item {
+            f.attrs
+                .retain(|attr| !attributes::is_opcode_attribute(attr));
+        }
+    }
+
+    let (impl_generics, _, where_clause) = imp.generics.split_for_impl();
+    Ok(quote! {
+        #imp
+
+        impl #impl_generics #self_ty #where_clause {
+            /// The contract's alkali ABI as JSON.
+            pub const ALKALI_ABI: &'static str = #abi;
+        }
+
+        impl #impl_generics alkanes_runtime::runtime::AlkaneResponder for #self_ty #where_clause {
+            fn execute(&self) -> ::alkali::__private::anyhow::Result<alkanes_support::response::CallResponse> {
+                let context = self.context()?;
+                let mut inputs = context.inputs.clone();
+                let opcode = <u128 as ::alkali::Input>::shift(&mut inputs)?;
+                match opcode {
+                    #(#arms)*
+                    _ => Err(::alkali::__private::anyhow::anyhow!("unrecognized opcode {}", opcode)),
+                }
+            }
+        }
+    })
+}
+
+fn dispatch_arm(method: &OpcodeMethod) -> proc_macro2::TokenStream {
+    let opcode = proc_macro2::Literal::u128_unsuffixed(method.opcode.into());
+    let ident = method.ident;
+    let names: Vec<_> = method.inputs.iter().map(|(name, _)| name).collect();
+    let types: Vec<_> = method.inputs.iter().map(|(_, ty)| ty).collect();
+    let call = quote! {
+        #(let #names = <#types as ::alkali::Input>::shift(&mut inputs)?;)*
+    };
+
+    if method.output.is_none() {
+        return quote! {
+            #opcode => {
+                #call
+                self.#ident(#(#names),*)
+            }
+        };
+    }
+
+    // Each output in turn, the last one in its trailing layout
+    let outputs = method.output_types();
+    let writes = outputs.iter().enumerate().map(|(index, ty)| {
+        let output = match method.output {
+            Some(syn::Type::Tuple(_)) => {
+                let index = syn::Index::from(index);
+                quote!(value.#index)
+            }
+            _ => quote!(value),
+        };
+        let write = if index + 1 == outputs.len() {
+            quote!(write_last)
+        } else {
+            quote!(write)
+        };
+        quote! {
+            <#ty as ::alkali::Output>::#write(&#output, &mut response.data);
+        }
+    });
+    let value = if outputs.is_empty() {
+        quote!(_)
+    } else {
+        quote!(value)
+    };
+    quote! {
+        #opcode => {
+            #call
+            let #value = self.#ident(#(#names),*)?;
+            let mut response =
+                alkanes_support::response::CallResponse::forward(&context.incoming_alkanes);
+            #(#writes)*
+            Ok(response)
+        }
+    }
+}
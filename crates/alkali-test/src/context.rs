@@ -0,0 +1,95 @@
+use std::fmt;
+
+/// An alkane's `[block, tx]` id.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AlkaneId {
+    pub block: u128,
+    pub tx: u128,
+}
+
+impl AlkaneId {
+    pub const fn new(block: u128, tx: u128) -> Self {
+        Self { block, tx }
+    }
+
+    pub(crate) fn write(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&self.block.to_le_bytes());
+        bytes.extend_from_slice(&self.tx.to_le_bytes());
+    }
+}
+
+impl fmt::Display for AlkaneId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.block, self.tx)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AlkaneTransfer {
+    pub id: AlkaneId,
+    pub value: u128,
+}
+
+/// The `Context` a contract reads through `self.context()`: who it is, who
+/// called it, the alkanes sent along and the cellpack inputs after the
+/// target. The caller defaults to `[0, 0]`, the transaction itself.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MockContext {
+    pub myself: AlkaneId,
+    pub caller: AlkaneId,
+    pub vout: u128,
+    pub incoming_alkanes: Vec<AlkaneTransfer>,
+    pub inputs: Vec<u128>,
+}
+
+impl MockContext {
+    pub fn new(myself: AlkaneId) -> Self {
+        Self {
+            myself,
+            ..Default::default()
+        }
+    }
+
+    pub fn caller(mut self, caller: AlkaneId) -> Self {
+        self.caller = caller;
+        self
+    }
+
+    pub fn vout(mut self, vout: u128) -> Self {
+        self.vout = vout;
+        self
+    }
+
+    /// The opcode followed by its arguments.
+    pub fn inputs(mut self, inputs: impl IntoIterator<Item = u128>) -> Self {
+        self.inputs = inputs.into_iter().collect();
+        self
+    }
+
+    pub fn incoming(mut self, id: AlkaneId, value: u128) -> Self {
+        self.incoming_alkanes.push(AlkaneTransfer { id, value });
+        self
+    }
+
+    /// Layout `__load_context` writes: myself, caller, vout, the incoming
+    /// parcel, then the inputs, all as u128 words.
+    pub(crate) fn serialize(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.myself.write(&mut bytes);
+        self.caller.write(&mut bytes);
+        bytes.extend_from_slice(&self.vout.to_le_bytes());
+        write_parcel(&self.incoming_alkanes, &mut bytes);
+        for input in &self.inputs {
+            bytes.extend_from_slice(&input.to_le_bytes());
+        }
+        bytes
+    }
+}
+
+pub(crate) fn write_parcel(transfers: &[AlkaneTransfer], bytes: &mut Vec<u8>) {
+    bytes.extend_from_slice(&(transfers.len() as u128).to_le_bytes());
+    for transfer in transfers {
+        transfer.id.write(bytes);
+        bytes.extend_from_slice(&transfer.value.to_le_bytes());
+    }
+}
@@ -0,0 +1,454 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use crate::context::{write_parcel, AlkaneId, AlkaneTransfer, MockContext};
+use crate::memory::widen;
+
+// Revert data starts with the Solidity Error(string) selector
+const REVERT_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+const DEFAULT_FUEL: u64 = 100_000_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CallKind {
+    Call,
+    StaticCall,
+    DelegateCall,
+}
+
+/// A sub-call the contract made through `__call` and its variants.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MockCall {
+    pub kind: CallKind,
+    pub target: AlkaneId,
+    /// The cellpack after the target: opcode, then arguments
+    pub inputs: Vec<u128>,
+    pub alkanes: Vec<AlkaneTransfer>,
+}
+
+/// What a mocked sub-call returns to the contract.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MockResponse {
+    pub alkanes: Vec<AlkaneTransfer>,
+    pub data: Vec<u8>,
+}
+
+impl MockResponse {
+    pub fn data(data: impl Into<Vec<u8>>) -> Self {
+        Self {
+            data: data.into(),
+            ..Default::default()
+        }
+    }
+}
+
+type CallHandler = Box<dyn FnMut(&MockCall) -> Result<MockResponse, String>>;
+
+#[derive(Default)]
+struct HostState {
+    context: Vec<u8>,
+    storage: BTreeMap<Vec<u8>, Vec<u8>>,
+    balances: BTreeMap<(AlkaneId, AlkaneId), u128>,
+    height: u64,
+    sequence: u128,
+    fuel: u64,
+    transaction: Vec<u8>,
+    block: Vec<u8>,
+    handlers: BTreeMap<AlkaneId, CallHandler>,
+    calls: Vec<MockCall>,
+    logs: Vec<String>,
+    return_data: Vec<u8>,
+}
+
+thread_local! {
+    static HOST: RefCell<Option<HostState>> = const { RefCell::new(None) };
+}
+
+/// The chain as one contract call sees it: its context, its storage, other
+/// alkanes' responses to its sub-calls and the block it runs in. Storage is
+/// the contract's own; sub-call checkpoints are written into it.
+pub struct MockHost {
+    state: HostState,
+}
+
+impl MockHost {
+    pub fn new(context: MockContext) -> Self {
+        Self {
+            state: HostState {
+                context: context.serialize(),
+                fuel: DEFAULT_FUEL,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Replaces the context, e.g. to make another call to the same storage.
+    pub fn set_context(&mut self, context: MockContext) -> &mut Self {
+        self.state.context = context.serialize();
+        self
+    }
+
+    pub fn set_storage(&mut self, key: impl AsRef<[u8]>, value: impl Into<Vec<u8>>) -> &mut Self {
+        self.state
+            .storage
+            .insert(key.as_ref().to_vec(), value.into());
+        self
+    }
+
+    pub fn storage(&self) -> &BTreeMap<Vec<u8>, Vec<u8>> {
+        &self.state.storage
+    }
+
+    pub fn get_storage(&self, key: impl AsRef<[u8]>) -> Option<&[u8]> {
+        self.state.storage.get(key.as_ref()).map(Vec::as_slice)
+    }
+
+    /// Balance `__balance` reports for `token` held by `holder`.
+    pub fn set_balance(&mut self, holder: AlkaneId, token: AlkaneId, value: u128) -> &mut Self {
+        self.state.balances.insert((holder, token), value);
+        self
+    }
+
+    pub fn set_height(&mut self, height: u64) -> &mut Self {
+        self.state.height = height;
+        self
+    }
+
+    pub fn set_sequence(&mut self, sequence: u128) -> &mut Self {
+        self.state.sequence = sequence;
+        self
+    }
+
+    pub fn set_fuel(&mut self, fuel: u64) -> &mut Self {
+        self.state.fuel = fuel;
+        self
+    }
+
+    /// Raw transaction and block returned by `__load_transaction` and
+    /// `__load_block`.
+    pub fn set_transaction(&mut self, transaction: impl Into<Vec<u8>>) -> &mut Self {
+        self.state.transaction = transaction.into();
+        self
+    }
+
+    pub fn set_block(&mut self, block: impl Into<Vec<u8>>) -> &mut Self {
+        self.state.block = block.into();
+        self
+    }
+
+    /// Answers sub-calls to `target`; an `Err` reverts the sub-call with
+    /// that message. Calls to alkanes without a handler revert.
+    pub fn mock_call(
+        &mut self,
+        target: AlkaneId,
+        handler: impl FnMut(&MockCall) -> Result<MockResponse, String> + 'static,
+    ) -> &mut Self {
+        self.state.handlers.insert(target, Box::new(handler));
+        self
+    }
+
+    /// Sub-calls made so far, in order.
+    pub fn calls(&self) -> &[MockCall] {
+        &self.state.calls
+    }
+
+    /// Messages passed to `__log` so far, in order.
+    pub fn logs(&self) -> &[String] {
+        &self.state.logs
+    }
+
+    /// Runs `f`, typically `contract.execute()`, with this host serving the
+    /// alkanes imports on the current thread.
+    pub fn run<R>(&mut self, f: impl FnOnce() -> R) -> R {
+        struct Installed<'a>(&'a mut HostState);
+
+        impl Drop for Installed<'_> {
+            fn drop(&mut self) {
+                if let Some(state) = HOST.with(|host| host.borrow_mut().take()) {
+                    *self.0 = state;
+                }
+            }
+        }
+
+        let state = std::mem::take(&mut self.state);
+        HOST.with(|host| {
+            let mut host = host.borrow_mut();
+            assert!(
+                host.is_none(),
+                "a MockHost is already running on this thread"
+            );
+            *host = Some(state);
+        });
+        let _installed = Installed(&mut self.state);
+        f()
+    }
+
+    /// Runs a contract's `__execute` export, as `declare_alkane!` defines
+    /// it, and applies the storage its response carries. Unlike calling
+    /// `execute()` directly, this sees the writes the runtime caches until
+    /// the call returns.
+    pub fn execute(&mut self, export: unsafe extern "C" fn() -> i32) -> MockResponse {
+        let pointer = self.run(|| unsafe { export() });
+        let mut reader = Reader(read_buffer(pointer));
+        let alkanes = reader.parcel();
+        for (key, value) in reader.storage_map() {
+            self.state.storage.insert(key, value);
+        }
+        MockResponse {
+            alkanes,
+            data: reader.0,
+        }
+    }
+}
+
+fn with_host<R>(f: impl FnOnce(&mut HostState) -> R) -> R {
+    HOST.with(|host| {
+        let mut host = host.borrow_mut();
+        let state = host
+            .as_mut()
+            .expect("alkanes host import called outside of MockHost::run");
+        f(state)
+    })
+}
+
+// Guest buffers use the ArrayBuffer layout: a u32 length before the data
+fn read_buffer(pointer: i32) -> Vec<u8> {
+    // The host learns the length from the prefix, so any fits
+    let data = widen(pointer, 0, |_| true);
+    unsafe {
+        let mut length = [0u8; 4];
+        std::ptr::copy_nonoverlapping(data.sub(4), length.as_mut_ptr(), 4);
+        std::slice::from_raw_parts(data, u32::from_le_bytes(length) as usize).to_vec()
+    }
+}
+
+fn write_buffer(pointer: i32, bytes: &[u8]) {
+    unsafe {
+        std::ptr::copy_nonoverlapping(
+            bytes.as_ptr(),
+            widen(pointer, bytes.len(), |length| {
+                length as usize == bytes.len()
+            }),
+            bytes.len(),
+        )
+    }
+}
+
+struct Reader(Vec<u8>);
+
+impl Reader {
+    fn take(&mut self, length: usize) -> Vec<u8> {
+        assert!(length <= self.0.len(), "malformed buffer from the contract");
+        let rest = self.0.split_off(length);
+        std::mem::replace(&mut self.0, rest)
+    }
+
+    fn u128(&mut self) -> u128 {
+        u128::from_le_bytes(self.take(16).try_into().unwrap())
+    }
+
+    fn u32(&mut self) -> u32 {
+        u32::from_le_bytes(self.take(4).try_into().unwrap())
+    }
+
+    fn id(&mut self) -> AlkaneId {
+        AlkaneId::new(self.u128(), self.u128())
+    }
+
+    fn parcel(&mut self) -> Vec<AlkaneTransfer> {
+        if self.0.is_empty() {
+            return Vec::new();
+        }
+        (0..self.u128())
+            .map(|_| AlkaneTransfer {
+                id: self.id(),
+                value: self.u128(),
+            })
+            .collect()
+    }
+
+    fn storage_map(&mut self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        if self.0.is_empty() {
+            return Vec::new();
+        }
+        (0..self.u32())
+            .map(|_| {
+                let length = self.u32() as usize;
+                let key = self.take(length);
+                let length = self.u32() as usize;
+                (key, self.take(length))
+            })
+            .collect()
+    }
+
+    fn words(&mut self) -> Vec<u128> {
+        let mut words = Vec::new();
+        while self.0.len() >= 16 {
+            words.push(self.u128());
+        }
+        words
+    }
+}
+
+fn extcall(kind: CallKind, cellpack: i32, incoming_alkanes: i32, checkpoint: i32) -> i32 {
+    let mut words = Reader(read_buffer(cellpack)).words();
+    let alkanes = Reader(read_buffer(incoming_alkanes)).parcel();
+    let checkpoint = Reader(read_buffer(checkpoint)).storage_map();
+    if words.len() < 2 {
+        panic!("cellpack passed to __call has no target");
+    }
+    let inputs = words.split_off(2);
+    let call = MockCall {
+        kind,
+        target: AlkaneId::new(words[0], words[1]),
+        inputs,
+        alkanes,
+    };
+
+    with_host(|host| {
+        host.storage.extend(checkpoint);
+        let result = match host.handlers.get_mut(&call.target) {
+            Some(handler) => handler(&call),
+            None => Err(format!("no mock response for call to {}", call.target)),
+        };
+        host.calls.push(call);
+
+        match result {
+            Ok(response) => {
+                host.return_data.clear();
+                write_parcel(&response.alkanes, &mut host.return_data);
+                host.return_data.extend_from_slice(&response.data);
+                host.return_data.len() as i32
+            }
+            Err(message) => {
+                host.return_data = REVERT_SELECTOR.to_vec();
+                host.return_data.extend_from_slice(message.as_bytes());
+                -1
+            }
+        }
+    })
+}
+
+// The `env` imports of the alkanes runtime, named and typed as the indexer
+// provides them to WASM. `abort` is left to libc.
+#[cfg(not(target_arch = "wasm32"))]
+mod imports {
+    use super::*;
+
+    #[no_mangle]
+    pub extern "C" fn __log(message: i32) {
+        let message = String::from_utf8_lossy(&read_buffer(message)).into_owned();
+        with_host(|host| host.logs.push(message));
+    }
+
+    #[no_mangle]
+    pub extern "C" fn __request_context() -> i32 {
+        with_host(|host| host.context.len() as i32)
+    }
+
+    #[no_mangle]
+    pub extern "C" fn __load_context(output: i32) -> i32 {
+        with_host(|host| write_buffer(output, &host.context));
+        0
+    }
+
+    #[no_mangle]
+    pub extern "C" fn __request_storage(key: i32) -> i32 {
+        let key = read_buffer(key);
+        with_host(|host| host.storage.get(&key).map_or(0, Vec::len) as i32)
+    }
+
+    #[no_mangle]
+    pub extern "C" fn __load_storage(key: i32, output: i32) -> i32 {
+        let key = read_buffer(key);
+        with_host(|host| {
+            let value = host.storage.get(&key).map_or(&[][..], Vec::as_slice);
+            write_buffer(output, value);
+            value.len() as i32
+        })
+    }
+
+    #[no_mangle]
+    pub extern "C" fn __height(output: i32) {
+        with_host(|host| write_buffer(output, &host.height.to_le_bytes()));
+    }
+
+    #[no_mangle]
+    pub extern "C" fn __sequence(output: i32) {
+        with_host(|host| write_buffer(output, &host.sequence.to_le_bytes()));
+    }
+
+    #[no_mangle]
+    pub extern "C" fn __fuel(output: i32) {
+        with_host(|host| write_buffer(output, &host.fuel.to_le_bytes()));
+    }
+
+    #[no_mangle]
+    pub extern "C" fn __balance(who: i32, what: i32, output: i32) {
+        let holder = Reader(read_buffer(who)).id();
+        let token = Reader(read_buffer(what)).id();
+        with_host(|host| {
+            let balance = host.balances.get(&(holder, token)).copied().unwrap_or(0);
+            write_buffer(output, &balance.to_le_bytes());
+        });
+    }
+
+    #[no_mangle]
+    pub extern "C" fn __returndatacopy(output: i32) {
+        with_host(|host| write_buffer(output, &host.return_data));
+    }
+
+    #[no_mangle]
+    pub extern "C" fn __request_transaction() -> i32 {
+        with_host(|host| host.transaction.len() as i32)
+    }
+
+    #[no_mangle]
+    pub extern "C" fn __load_transaction(output: i32) {
+        with_host(|host| write_buffer(output, &host.transaction));
+    }
+
+    #[no_mangle]
+    pub extern "C" fn __request_block() -> i32 {
+        with_host(|host| host.block.len() as i32)
+    }
+
+    #[no_mangle]
+    pub extern "C" fn __load_block(output: i32) {
+        with_host(|host| write_buffer(output, &host.block));
+    }
+
+    #[no_mangle]
+    pub extern "C" fn __call(
+        cellpack: i32,
+        incoming_alkanes: i32,
+        checkpoint: i32,
+        _fuel: u64,
+    ) -> i32 {
+        extcall(CallKind::Call, cellpack, incoming_alkanes, checkpoint)
+    }
+
+    #[no_mangle]
+    pub extern "C" fn __staticcall(
+        cellpack: i32,
+        incoming_alkanes: i32,
+        checkpoint: i32,
+        _fuel: u64,
+    ) -> i32 {
+        extcall(CallKind::StaticCall, cellpack, incoming_alkanes, checkpoint)
+    }
+
+    #[no_mangle]
+    pub extern "C" fn __delegatecall(
+        cellpack: i32,
+        incoming_alkanes: i32,
+        checkpoint: i32,
+        _fuel: u64,
+    ) -> i32 {
+        extcall(
+            CallKind::DelegateCall,
+            cellpack,
+            incoming_alkanes,
+            checkpoint,
+        )
+    }
+}
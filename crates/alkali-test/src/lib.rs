@@ -0,0 +1,34 @@
+//! Native unit tests for Alkanes contracts.
+//!
+//! ```ignore
+//! use alkali_test::{AlkaneId, MockContext, MockHost, MockResponse};
+//! use alkanes_runtime::runtime::AlkaneResponder;
+//!
+//! #[test]
+//! fn returns_its_name() {
+//!     let mut host = MockHost::new(MockContext::new(AlkaneId::new(2, 1)).inputs([99]));
+//!     host.set_storage("/owner", [1; 32]);
+//!     host.mock_call(AlkaneId::new(2, 7), |_| Ok(MockResponse::data(1u128.to_le_bytes())));
+//!
+//!     let response = host.run(|| Example::default().execute()).unwrap();
+//!     assert_eq!(response.data, b"Example");
+//! }
+//! ```
+//!
+//! Contracts reach the host through the alkanes `env` imports
+//! (`__request_context`, `__load_storage`, `__call`, ...). On native
+//! targets this crate defines those symbols and serves them from the
+//! [`MockHost`] running the current call, so `execute()` runs unchanged
+//! outside of WASM.
+//!
+//! The imports pass guest pointers as `i32`. On 64-bit targets the host
+//! recovers the upper half of each address from the heap windows of the
+//! calling thread, checking the buffer's length prefix before using it, so
+//! contracts and tests keep their own global allocator.
+
+mod context;
+mod host;
+mod memory;
+
+pub use context::{AlkaneId, AlkaneTransfer, MockContext};
+pub use host::{CallKind, MockCall, MockHost, MockResponse};
@@ -0,0 +1,125 @@
+//! Guest pointers reach the imports as `i32`: the runtime passes
+//! `buffer.as_mut_ptr() as usize as i32`, which on 64-bit targets keeps only
+//! the low half of the address. The guest allocated the buffer on this
+//! thread just before the call, so the upper half is one of the 4 GiB
+//! windows heap blocks lie in: those of buffers widened before, those of
+//! blocks allocated here, and those past the program's data, which the
+//! main heap follows. A window is taken once the buffer's length prefix in
+//! it is readable and fits what the host expects.
+
+use std::cell::RefCell;
+
+const LOW_HALF: usize = 0xffff_ffff;
+// Probe sizes: the allocator's small bins, and one it maps on its own
+const PROBES: [usize; 2] = [64, 1 << 20];
+
+thread_local! {
+    // Upper halves of the buffers widened on this thread, latest first
+    static WINDOWS: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The native address of `pointer`, the data of a guest buffer of `size`
+/// bytes whose length prefix satisfies `fits`.
+///
+/// # Panics
+///
+/// If no window holds such a buffer.
+pub fn widen(pointer: i32, size: usize, fits: impl Fn(u32) -> bool) -> *mut u8 {
+    let low = pointer as u32 as usize;
+    if usize::BITS <= 32 {
+        return low as *mut u8;
+    }
+
+    let mut windows = WINDOWS.with(|windows| windows.borrow().clone());
+    for probe in PROBES.into_iter().chain([size.max(1)]) {
+        let block: Vec<u8> = Vec::with_capacity(probe);
+        windows.push(block.as_ptr() as usize & !LOW_HALF);
+    }
+    // The main heap starts up to a randomized gigabyte past the data
+    let data = &WINDOWS as *const _ as usize & !LOW_HALF;
+    windows.extend([data, data + LOW_HALF + 1]);
+    let found = windows.iter().copied().find(|&window| {
+        let data = window | low;
+        data >= 4
+            && read_prefix(data - 4).is_some_and(|length| {
+                fits(length) && (length == 0 || readable((data + length as usize - 1) as *const u8))
+            })
+    });
+    let Some(window) = found else {
+        panic!("no guest buffer of {size} bytes at {low:#010x}");
+    };
+
+    WINDOWS.with(|windows| {
+        let mut windows = windows.borrow_mut();
+        windows.retain(|&known| known != window);
+        windows.insert(0, window);
+    });
+    (window | low) as *mut u8
+}
+
+fn read_prefix(address: usize) -> Option<u32> {
+    let first = address as *const u8;
+    if !readable(first) || !readable((address + 3) as *const u8) {
+        return None;
+    }
+    let mut length = [0u8; 4];
+    unsafe { std::ptr::copy_nonoverlapping(first, length.as_mut_ptr(), 4) };
+    Some(u32::from_le_bytes(length))
+}
+
+/// Whether reading `address` is safe: the kernel copies it into a pipe,
+/// and reports an unmapped or protected byte as an error instead of
+/// faulting.
+#[cfg(unix)]
+fn readable(address: *const u8) -> bool {
+    thread_local! {
+        static PIPE: [libc::c_int; 2] = {
+            let mut fds = [0; 2];
+            assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0, "cannot open a pipe");
+            fds
+        };
+    }
+    PIPE.with(|&[read, write]| unsafe {
+        if libc::write(write, address.cast(), 1) != 1 {
+            return false;
+        }
+        let mut byte = 0u8;
+        libc::read(read, (&mut byte as *mut u8).cast(), 1);
+        true
+    })
+}
+
+#[cfg(not(unix))]
+fn readable(_address: *const u8) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn passback(buffer: &mut [u8]) -> i32 {
+        buffer.as_mut_ptr() as usize as i32 + 4
+    }
+
+    #[test]
+    fn widens_truncated_heap_pointers() {
+        for size in [1, 24, 4096, 1 << 20] {
+            let mut buffer = (size as u32).to_le_bytes().to_vec();
+            buffer.resize(4 + size, 7);
+            let data = buffer[4..].as_mut_ptr();
+            assert_eq!(
+                widen(passback(&mut buffer), size, |n| n as usize == size),
+                data
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "no guest buffer")]
+    fn rejects_buffers_whose_prefix_does_not_fit() {
+        let mut buffer = 8u32.to_le_bytes().to_vec();
+        buffer.resize(12, 0);
+        widen(passback(&mut buffer), 16, |n| n >= 16);
+    }
+}
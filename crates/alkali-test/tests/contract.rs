@@ -0,0 +1,250 @@
+//! Runs an `#[alkali::contract]` through the mock host, with the runtime
+//! types the generated code refers to reading the context and storage
+//! through the host's imports, as alkanes-rs does.
+
+use alkali_test::{AlkaneId, AlkaneTransfer, MockContext, MockHost, MockResponse};
+use anyhow::{anyhow, Result};
+
+mod alkanes_support {
+    pub mod response {
+        #[derive(Clone, Debug, Default, PartialEq)]
+        pub struct AlkaneTransfer {
+            pub id: [u128; 2],
+            pub value: u128,
+        }
+
+        #[derive(Clone, Debug, Default, PartialEq)]
+        pub struct AlkaneTransferParcel(pub Vec<AlkaneTransfer>);
+
+        #[derive(Debug, Default, PartialEq)]
+        pub struct CallResponse {
+            pub alkanes: AlkaneTransferParcel,
+            pub data: Vec<u8>,
+        }
+
+        impl CallResponse {
+            pub fn forward(incoming_alkanes: &AlkaneTransferParcel) -> Self {
+                Self {
+                    alkanes: incoming_alkanes.clone(),
+                    data: Vec::new(),
+                }
+            }
+        }
+    }
+}
+
+mod alkanes_runtime {
+    pub mod runtime {
+        use crate::alkanes_support::response::{
+            AlkaneTransfer, AlkaneTransferParcel, CallResponse,
+        };
+        use std::cell::RefCell;
+
+        extern "C" {
+            fn __request_context() -> i32;
+            fn __load_context(output: i32) -> i32;
+            fn __request_storage(key: i32) -> i32;
+            fn __load_storage(key: i32, output: i32) -> i32;
+        }
+
+        thread_local! {
+            // Writes are cached until the call returns, as in alkanes-rs
+            static CACHE: RefCell<Vec<(Vec<u8>, Vec<u8>)>> = Default::default();
+        }
+
+        pub struct Context {
+            pub caller: [u128; 2],
+            pub incoming_alkanes: AlkaneTransferParcel,
+            pub inputs: Vec<u128>,
+        }
+
+        fn to_arraybuffer_layout(data: &[u8]) -> Vec<u8> {
+            let mut buffer = (data.len() as u32).to_le_bytes().to_vec();
+            buffer.extend_from_slice(data);
+            buffer
+        }
+
+        fn to_passback_ptr(buffer: &mut [u8]) -> i32 {
+            buffer.as_mut_ptr() as usize as i32 + 4
+        }
+
+        fn load(length: i32, load: impl FnOnce(i32)) -> Vec<u8> {
+            let mut buffer = to_arraybuffer_layout(&vec![0; length as usize]);
+            load(to_passback_ptr(&mut buffer));
+            buffer.split_off(4)
+        }
+
+        pub trait AlkaneResponder {
+            fn context(&self) -> anyhow::Result<Context> {
+                let bytes = load(unsafe { __request_context() }, |output| unsafe {
+                    __load_context(output);
+                });
+                let words: Vec<u128> = bytes
+                    .chunks(16)
+                    .map(|word| u128::from_le_bytes(word.try_into().unwrap()))
+                    .collect();
+                let transfers = words[5] as usize;
+                Ok(Context {
+                    caller: [words[2], words[3]],
+                    incoming_alkanes: AlkaneTransferParcel(
+                        words[6..6 + 3 * transfers]
+                            .chunks(3)
+                            .map(|transfer| AlkaneTransfer {
+                                id: [transfer[0], transfer[1]],
+                                value: transfer[2],
+                            })
+                            .collect(),
+                    ),
+                    inputs: words[6 + 3 * transfers..].to_vec(),
+                })
+            }
+
+            fn load(&self, key: &[u8]) -> Vec<u8> {
+                let cached = CACHE.with(|cache| {
+                    let cache = cache.borrow();
+                    cache
+                        .iter()
+                        .rev()
+                        .find(|(k, _)| k == key)
+                        .map(|(_, v)| v.clone())
+                });
+                if let Some(value) = cached {
+                    return value;
+                }
+                let mut key = to_arraybuffer_layout(key);
+                let key = to_passback_ptr(&mut key);
+                load(unsafe { __request_storage(key) }, |output| unsafe {
+                    __load_storage(key, output);
+                })
+            }
+
+            fn store(&self, key: &[u8], value: Vec<u8>) {
+                CACHE.with(|cache| cache.borrow_mut().push((key.to_vec(), value)));
+            }
+
+            fn execute(&self) -> anyhow::Result<CallResponse>;
+        }
+
+        /// What `declare_alkane!`'s `__execute` export returns: the
+        /// response's parcel, the cached storage writes, then its data.
+        pub fn run(responder: &impl AlkaneResponder) -> i32 {
+            let response = responder.execute().unwrap();
+            let mut bytes = (response.alkanes.0.len() as u128).to_le_bytes().to_vec();
+            for transfer in &response.alkanes.0 {
+                for word in [transfer.id[0], transfer.id[1], transfer.value] {
+                    bytes.extend_from_slice(&word.to_le_bytes());
+                }
+            }
+            let cache = CACHE.with(|cache| cache.take());
+            bytes.extend_from_slice(&(cache.len() as u32).to_le_bytes());
+            for (key, value) in cache {
+                for part in [key, value] {
+                    bytes.extend_from_slice(&(part.len() as u32).to_le_bytes());
+                    bytes.extend(part);
+                }
+            }
+            bytes.extend(response.data);
+
+            let buffer = to_arraybuffer_layout(&bytes);
+            Box::leak(buffer.into_boxed_slice()).as_mut_ptr() as usize as i32 + 4
+        }
+    }
+}
+
+use alkanes_runtime::runtime::AlkaneResponder;
+
+#[derive(Default)]
+struct Token(());
+
+#[alkali::contract]
+impl Token {
+    /// Mints `amount` new tokens, up to the cap
+    #[opcode(77)]
+    fn mint(&self, amount: u128) -> Result<u128> {
+        let supply = self.total_supply()? + amount;
+        if supply > 1000 {
+            return Err(anyhow!("mint exceeds the cap"));
+        }
+        self.store(b"/supply", supply.to_le_bytes().to_vec());
+        Ok(supply)
+    }
+
+    #[opcode(101)]
+    fn total_supply(&self) -> Result<u128> {
+        let supply = self.load(b"/supply");
+        Ok(match supply.len() {
+            0 => 0,
+            _ => u128::from_le_bytes(supply.try_into().unwrap()),
+        })
+    }
+
+    #[opcode(102)]
+    fn owner(&self) -> Result<(u128, u128)> {
+        let [block, tx] = self.context()?.caller;
+        Ok((block, tx))
+    }
+}
+
+extern "C" fn execute_export() -> i32 {
+    alkanes_runtime::runtime::run(&Token::default())
+}
+
+const CONTRACT: AlkaneId = AlkaneId::new(2, 1);
+const TOKEN: AlkaneId = AlkaneId::new(2, 7);
+
+fn words(words: &[u128]) -> Vec<u8> {
+    words.iter().flat_map(|word| word.to_le_bytes()).collect()
+}
+
+#[test]
+fn dispatches_the_host_context_to_opcodes() {
+    let context = MockContext::new(CONTRACT)
+        .caller(AlkaneId::new(4, 2))
+        .inputs([102]);
+    let mut host = MockHost::new(context);
+
+    let response = host.run(|| Token::default().execute()).unwrap();
+
+    assert_eq!(response.data, words(&[4, 2]));
+}
+
+#[test]
+fn executes_against_host_storage() {
+    let context = MockContext::new(CONTRACT)
+        .incoming(TOKEN, 5)
+        .inputs([77, 250]);
+    let mut host = MockHost::new(context);
+    host.set_storage("/supply", 100u128.to_le_bytes());
+
+    let response = host.execute(execute_export);
+
+    // The response forwards the incoming alkanes and the write lands
+    assert_eq!(
+        response,
+        MockResponse {
+            alkanes: vec![AlkaneTransfer {
+                id: TOKEN,
+                value: 5
+            }],
+            data: words(&[350]),
+        }
+    );
+    assert_eq!(host.get_storage("/supply"), Some(&words(&[350])[..]));
+
+    host.set_context(MockContext::new(CONTRACT).inputs([101]));
+    assert_eq!(host.execute(execute_export).data, words(&[350]));
+}
+
+#[test]
+fn reports_errors_of_opcodes() {
+    let mut host = MockHost::new(MockContext::new(CONTRACT).inputs([77, 2000]));
+
+    let error = host.run(|| Token::default().execute()).unwrap_err();
+
+    assert_eq!(error.to_string(), "mint exceeds the cap");
+    assert_eq!(host.get_storage("/supply"), None);
+
+    host.set_context(MockContext::new(CONTRACT).inputs([12]));
+    let error = host.run(|| Token::default().execute()).unwrap_err();
+    assert_eq!(error.to_string(), "unrecognized opcode 12");
+}
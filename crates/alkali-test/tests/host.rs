@@ -0,0 +1,252 @@
+//! Drives the mock host through a minimal stand-in for the alkanes-rs
+//! runtime, which passes buffers to the imports the same way: as truncated
+//! pointers past a u32 length prefix.
+
+use alkali_test::{
+    AlkaneId, AlkaneTransfer, CallKind, MockCall, MockContext, MockHost, MockResponse,
+};
+
+mod runtime {
+    extern "C" {
+        fn __log(message: i32);
+        fn __request_context() -> i32;
+        fn __load_context(output: i32) -> i32;
+        fn __request_storage(key: i32) -> i32;
+        fn __load_storage(key: i32, output: i32) -> i32;
+        fn __height(output: i32);
+        fn __balance(who: i32, what: i32, output: i32);
+        fn __returndatacopy(output: i32);
+        fn __call(cellpack: i32, incoming_alkanes: i32, checkpoint: i32, fuel: u64) -> i32;
+    }
+
+    fn to_arraybuffer_layout(data: &[u8]) -> Vec<u8> {
+        let mut buffer = (data.len() as u32).to_le_bytes().to_vec();
+        buffer.extend_from_slice(data);
+        buffer
+    }
+
+    fn to_passback_ptr(buffer: &mut [u8]) -> i32 {
+        buffer.as_mut_ptr() as usize as i32 + 4
+    }
+
+    fn load(request: impl FnOnce() -> i32, load: impl FnOnce(i32)) -> Vec<u8> {
+        let mut buffer = to_arraybuffer_layout(&vec![0; request() as usize]);
+        load(to_passback_ptr(&mut buffer));
+        buffer.split_off(4)
+    }
+
+    fn words(bytes: &[u8]) -> Vec<u128> {
+        bytes
+            .chunks(16)
+            .map(|word| u128::from_le_bytes(word.try_into().unwrap()))
+            .collect()
+    }
+
+    pub fn context() -> Vec<u128> {
+        words(&load(
+            || unsafe { __request_context() },
+            |output| unsafe {
+                __load_context(output);
+            },
+        ))
+    }
+
+    pub fn storage(key: &[u8]) -> Vec<u8> {
+        let mut key = to_arraybuffer_layout(key);
+        let key = to_passback_ptr(&mut key);
+        load(
+            || unsafe { __request_storage(key) },
+            |output| unsafe {
+                __load_storage(key, output);
+            },
+        )
+    }
+
+    pub fn log(message: &str) {
+        let mut message = to_arraybuffer_layout(message.as_bytes());
+        unsafe { __log(to_passback_ptr(&mut message)) }
+    }
+
+    pub fn height() -> u64 {
+        let mut buffer = to_arraybuffer_layout(&[0; 8]);
+        unsafe { __height(to_passback_ptr(&mut buffer)) };
+        u64::from_le_bytes(buffer[4..].try_into().unwrap())
+    }
+
+    pub fn balance(who: [u128; 2], what: [u128; 2]) -> u128 {
+        let id = |id: [u128; 2]| {
+            to_arraybuffer_layout(&[id[0].to_le_bytes(), id[1].to_le_bytes()].concat())
+        };
+        let (mut who, mut what) = (id(who), id(what));
+        let mut output = to_arraybuffer_layout(&[0; 16]);
+        unsafe {
+            __balance(
+                to_passback_ptr(&mut who),
+                to_passback_ptr(&mut what),
+                to_passback_ptr(&mut output),
+            )
+        };
+        u128::from_le_bytes(output[4..].try_into().unwrap())
+    }
+
+    /// Calls `cellpack` sending `[block, tx, value]` transfers with the
+    /// storage `checkpoint`; the return data, or the negative length a
+    /// revert reports.
+    pub fn call(
+        cellpack: &[u128],
+        transfers: &[[u128; 3]],
+        checkpoint: &[(&[u8], &[u8])],
+    ) -> Result<Vec<u8>, i32> {
+        let cellpack: Vec<u8> = cellpack
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .collect();
+        let mut parcel = (transfers.len() as u128).to_le_bytes().to_vec();
+        for transfer in transfers {
+            parcel.extend(transfer.iter().flat_map(|word| word.to_le_bytes()));
+        }
+        let mut storage = (checkpoint.len() as u32).to_le_bytes().to_vec();
+        for (key, value) in checkpoint {
+            storage.extend_from_slice(&(key.len() as u32).to_le_bytes());
+            storage.extend_from_slice(key);
+            storage.extend_from_slice(&(value.len() as u32).to_le_bytes());
+            storage.extend_from_slice(value);
+        }
+
+        let (mut cellpack, mut parcel, mut storage) = (
+            to_arraybuffer_layout(&cellpack),
+            to_arraybuffer_layout(&parcel),
+            to_arraybuffer_layout(&storage),
+        );
+        let length = unsafe {
+            __call(
+                to_passback_ptr(&mut cellpack),
+                to_passback_ptr(&mut parcel),
+                to_passback_ptr(&mut storage),
+                u64::MAX,
+            )
+        };
+
+        if length < 0 {
+            return Err(length);
+        }
+        Ok(load(
+            || length,
+            |output| unsafe { __returndatacopy(output) },
+        ))
+    }
+}
+
+const CONTRACT: AlkaneId = AlkaneId::new(2, 1);
+const TOKEN: AlkaneId = AlkaneId::new(2, 7);
+
+#[test]
+fn serves_the_context() {
+    let context = MockContext::new(CONTRACT)
+        .caller(AlkaneId::new(4, 2))
+        .vout(3)
+        .incoming(TOKEN, 500)
+        .inputs([77, 1000]);
+    let mut host = MockHost::new(context);
+
+    assert_eq!(
+        host.run(runtime::context),
+        [2, 1, 4, 2, 3, 1, 2, 7, 500, 77, 1000]
+    );
+}
+
+#[test]
+fn serves_storage_and_chain_state() {
+    let mut host = MockHost::new(MockContext::new(CONTRACT));
+    host.set_storage("/n", 7u128.to_le_bytes())
+        .set_height(840_000)
+        .set_balance(CONTRACT, TOKEN, 25);
+
+    host.run(|| {
+        assert_eq!(runtime::storage(b"/n"), 7u128.to_le_bytes());
+        assert_eq!(runtime::storage(b"/missing"), b"");
+        assert_eq!(runtime::height(), 840_000);
+        assert_eq!(runtime::balance([2, 1], [2, 7]), 25);
+        assert_eq!(runtime::balance([2, 1], [2, 8]), 0);
+        runtime::log("hello");
+    });
+    assert_eq!(host.logs(), ["hello"]);
+}
+
+#[test]
+fn answers_sub_calls_from_mocks() {
+    let mut host = MockHost::new(MockContext::new(CONTRACT));
+    host.mock_call(TOKEN, |call| {
+        Ok(MockResponse {
+            alkanes: vec![AlkaneTransfer {
+                id: TOKEN,
+                value: call.inputs[1],
+            }],
+            data: b"ok".to_vec(),
+        })
+    });
+
+    let returned = host.run(|| runtime::call(&[2, 7, 77, 10], &[[2, 1, 5]], &[(b"/x", b"y")]));
+    let mut expected = 1u128.to_le_bytes().to_vec();
+    for word in [2u128, 7, 10] {
+        expected.extend_from_slice(&word.to_le_bytes());
+    }
+    expected.extend_from_slice(b"ok");
+    assert_eq!(returned, Ok(expected));
+
+    // The checkpoint lands in storage before the callee runs
+    assert_eq!(host.get_storage("/x"), Some(&b"y"[..]));
+    assert_eq!(
+        host.calls(),
+        [MockCall {
+            kind: CallKind::Call,
+            target: TOKEN,
+            inputs: vec![77, 10],
+            alkanes: vec![AlkaneTransfer {
+                id: CONTRACT,
+                value: 5
+            }],
+        }]
+    );
+}
+
+#[test]
+fn reverts_failed_and_unmocked_sub_calls() {
+    let mut host = MockHost::new(MockContext::new(CONTRACT));
+    host.mock_call(TOKEN, |_| Err("insufficient balance".to_string()));
+
+    let (failed, unmocked) = host.run(|| {
+        (
+            runtime::call(&[2, 7, 77], &[], &[]),
+            runtime::call(&[2, 9, 1], &[], &[]),
+        )
+    });
+
+    assert_eq!(failed, Err(-1));
+    assert_eq!(unmocked, Err(-1));
+    assert_eq!(host.calls().len(), 2);
+}
+
+extern "C" fn execute_export() -> i32 {
+    let mut response = 0u128.to_le_bytes().to_vec();
+    response.extend_from_slice(&1u32.to_le_bytes());
+    for part in [&b"/n"[..], &9u128.to_le_bytes()] {
+        response.extend_from_slice(&(part.len() as u32).to_le_bytes());
+        response.extend_from_slice(part);
+    }
+    response.extend_from_slice(b"done");
+
+    let mut buffer = (response.len() as u32).to_le_bytes().to_vec();
+    buffer.extend(response);
+    Box::leak(buffer.into_boxed_slice()).as_mut_ptr() as usize as i32 + 4
+}
+
+#[test]
+fn applies_the_storage_of_executed_responses() {
+    let mut host = MockHost::new(MockContext::new(CONTRACT).inputs([0]));
+
+    let response = host.execute(execute_export);
+
+    assert_eq!(response, MockResponse::data(b"done".to_vec()));
+    assert_eq!(host.get_storage("/n"), Some(&9u128.to_le_bytes()[..]));
+}
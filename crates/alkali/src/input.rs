@@ -0,0 +1,191 @@
+use anyhow::{anyhow, Result};
+
+const WORD_BYTES: usize = 16;
+
+/// A value decoded from the front of a cellpack's u128 inputs, in the layout
+/// alkali's `AlkanesEncoder.encodeWords` writes.
+pub trait Input: Sized {
+    fn shift(inputs: &mut Vec<u128>) -> Result<Self>;
+
+    /// Layout of a `Vec<Self>`: a length word followed by the items, except
+    /// for bytes, which are packed 16 per word.
+    #[doc(hidden)]
+    fn shift_vec(inputs: &mut Vec<u128>) -> Result<Vec<Self>> {
+        let length = shift_length(inputs, 1)?;
+        (0..length).map(|_| Self::shift(inputs)).collect()
+    }
+}
+
+fn shift_word(inputs: &mut Vec<u128>) -> Result<u128> {
+    if inputs.is_empty() {
+        return Err(anyhow!("expected u128 value in list but list is exhausted"));
+    }
+    Ok(inputs.remove(0))
+}
+
+fn shift_length(inputs: &mut Vec<u128>, per_word: usize) -> Result<usize> {
+    let length = shift_word(inputs)?;
+    if length > (inputs.len() * per_word) as u128 {
+        return Err(anyhow!("length {length} exceeds the remaining inputs"));
+    }
+    Ok(length as usize)
+}
+
+impl Input for u128 {
+    fn shift(inputs: &mut Vec<u128>) -> Result<Self> {
+        shift_word(inputs)
+    }
+}
+
+macro_rules! unsigned_input {
+    ($($ty:ty),*) => {$(
+        impl Input for $ty {
+            fn shift(inputs: &mut Vec<u128>) -> Result<Self> {
+                let word = shift_word(inputs)?;
+                <$ty>::try_from(word)
+                    .map_err(|_| anyhow!("{word} out of range for {}", stringify!($ty)))
+            }
+        }
+    )*};
+}
+
+// Signed values are two's complement, widened to 128 bits
+macro_rules! signed_input {
+    ($($ty:ty),*) => {$(
+        impl Input for $ty {
+            fn shift(inputs: &mut Vec<u128>) -> Result<Self> {
+                let value = shift_word(inputs)? as i128;
+                <$ty>::try_from(value)
+                    .map_err(|_| anyhow!("{value} out of range for {}", stringify!($ty)))
+            }
+        }
+    )*};
+}
+
+unsigned_input!(u16, u32, u64);
+signed_input!(i8, i16, i32, i64, i128);
+
+impl Input for u8 {
+    fn shift(inputs: &mut Vec<u128>) -> Result<Self> {
+        let word = shift_word(inputs)?;
+        u8::try_from(word).map_err(|_| anyhow!("{word} out of range for u8"))
+    }
+
+    fn shift_vec(inputs: &mut Vec<u128>) -> Result<Vec<Self>> {
+        let length = shift_length(inputs, WORD_BYTES)?;
+        let mut bytes = Vec::with_capacity(length);
+        while bytes.len() < length {
+            let chunk = shift_word(inputs)?.to_le_bytes();
+            let take = (length - bytes.len()).min(WORD_BYTES);
+            bytes.extend_from_slice(&chunk[..take]);
+        }
+        Ok(bytes)
+    }
+}
+
+impl Input for bool {
+    fn shift(inputs: &mut Vec<u128>) -> Result<Self> {
+        match shift_word(inputs)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            word => Err(anyhow!("invalid bool word {word}")),
+        }
+    }
+}
+
+/// Null-terminated UTF-8, packed little-endian 16 bytes per word.
+impl Input for String {
+    fn shift(inputs: &mut Vec<u128>) -> Result<Self> {
+        let mut bytes = Vec::new();
+        loop {
+            let chunk = shift_word(inputs)?.to_le_bytes();
+            match chunk.iter().position(|byte| *byte == 0) {
+                Some(nul) => {
+                    bytes.extend_from_slice(&chunk[..nul]);
+                    break;
+                }
+                None => bytes.extend_from_slice(&chunk),
+            }
+        }
+        String::from_utf8(bytes).map_err(|e| anyhow!("invalid UTF-8 string input: {e}"))
+    }
+}
+
+impl<T: Input> Input for Vec<T> {
+    fn shift(inputs: &mut Vec<u128>) -> Result<Self> {
+        T::shift_vec(inputs)
+    }
+}
+
+impl<T: Input, const N: usize> Input for [T; N] {
+    fn shift(inputs: &mut Vec<u128>) -> Result<Self> {
+        let items = (0..N)
+            .map(|_| T::shift(inputs))
+            .collect::<Result<Vec<_>>>()?;
+        items
+            .try_into()
+            .map_err(|_| anyhow!("expected {N} array items"))
+    }
+}
+
+macro_rules! tuple_input {
+    ($($name:ident),+) => {
+        impl<$($name: Input),+> Input for ($($name,)+) {
+            fn shift(inputs: &mut Vec<u128>) -> Result<Self> {
+                Ok(($($name::shift(inputs)?,)+))
+            }
+        }
+    };
+}
+
+tuple_input!(A);
+tuple_input!(A, B);
+tuple_input!(A, B, C);
+tuple_input!(A, B, C, D);
+tuple_input!(A, B, C, D, E);
+tuple_input!(A, B, C, D, E, F);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shift_all<T: Input>(mut inputs: Vec<u128>) -> Result<T> {
+        let value = T::shift(&mut inputs)?;
+        assert!(inputs.is_empty(), "{} word(s) left over", inputs.len());
+        Ok(value)
+    }
+
+    #[test]
+    fn shifts_integers_and_bools() {
+        assert_eq!(shift_all::<u8>(vec![255]).unwrap(), 255);
+        assert!(shift_all::<u8>(vec![256]).is_err());
+        assert_eq!(shift_all::<i64>(vec![u128::MAX]).unwrap(), -1);
+        assert!(shift_all::<bool>(vec![1]).unwrap());
+        assert!(shift_all::<bool>(vec![2]).is_err());
+        assert!(shift_all::<u128>(vec![]).is_err());
+    }
+
+    #[test]
+    fn shifts_strings_and_bytes() {
+        // "hi" null-terminated
+        assert_eq!(shift_all::<String>(vec![0x6968]).unwrap(), "hi");
+        // A 16-byte string is followed by a zero word
+        let full = u128::from_le_bytes(*b"0123456789abcdef");
+        assert_eq!(
+            shift_all::<String>(vec![full, 0]).unwrap(),
+            "0123456789abcdef"
+        );
+        assert_eq!(
+            shift_all::<Vec<u8>>(vec![3, 0x030201]).unwrap(),
+            vec![1, 2, 3]
+        );
+        assert!(shift_all::<Vec<u8>>(vec![17, 0]).is_err());
+    }
+
+    #[test]
+    fn shifts_composites() {
+        assert_eq!(shift_all::<[u32; 2]>(vec![1, 2]).unwrap(), [1, 2]);
+        assert_eq!(shift_all::<Vec<u16>>(vec![2, 7, 8]).unwrap(), vec![7, 8]);
+        assert_eq!(shift_all::<(bool, u128)>(vec![0, 9]).unwrap(), (false, 9));
+    }
+}
@@ -0,0 +1,37 @@
+//! Authoring support for Alkanes contracts.
+//!
+//! ```ignore
+//! #[derive(Default)]
+//! pub struct Token(());
+//!
+//! #[alkali::contract]
+//! impl Token {
+//!     /// Mints `amount` new tokens to the caller
+//!     #[opcode(77)]
+//!     fn mint(&self, amount: u128) -> Result<CallResponse> {
+//!         ...
+//!     }
+//! }
+//!
+//! declare_alkane! {Token}
+//! ```
+//!
+//! The attribute implements `AlkaneResponder` for the contract, shifting each
+//! method's inputs off the cellpack with [`Input`], and exposes the ABI
+//! derived from the signatures as `Token::ALKALI_ABI`.
+//!
+//! Methods returning `Result<T>` rather than `Result<CallResponse>` have `T`
+//! written into the response data with [`Output`]; a tuple lists several
+//! outputs. The response forwards the incoming alkanes.
+
+mod input;
+mod output;
+
+pub use alkali_macros::contract;
+pub use input::Input;
+pub use output::Output;
+
+#[doc(hidden)]
+pub mod __private {
+    pub use anyhow;
+}
@@ -0,0 +1,149 @@
+/// A value written into a `CallResponse`'s data, in the layout alkali's
+/// `AlkanesEncoder.decodeResponse` reads: integers little-endian at their
+/// own width, bools as one byte, and strings, byte vectors and vectors
+/// behind a u32 length.
+pub trait Output {
+    fn write(&self, data: &mut Vec<u8>);
+
+    /// Layout as the last output of a response, where strings and bytes run
+    /// to the end of the data without a length.
+    #[doc(hidden)]
+    fn write_last(&self, data: &mut Vec<u8>) {
+        self.write(data)
+    }
+
+    /// Layout of a `Vec<Self>` as the last output; only bytes differ.
+    #[doc(hidden)]
+    fn write_last_vec(items: &[Self], data: &mut Vec<u8>)
+    where
+        Self: Sized,
+    {
+        write_items(items, data)
+    }
+}
+
+fn write_length(length: usize, data: &mut Vec<u8>) {
+    data.extend_from_slice(&(length as u32).to_le_bytes());
+}
+
+fn write_items<T: Output>(items: &[T], data: &mut Vec<u8>) {
+    write_length(items.len(), data);
+    for item in items {
+        item.write(data);
+    }
+}
+
+macro_rules! integer_output {
+    ($($ty:ty),*) => {$(
+        impl Output for $ty {
+            fn write(&self, data: &mut Vec<u8>) {
+                data.extend_from_slice(&self.to_le_bytes());
+            }
+        }
+    )*};
+}
+
+integer_output!(u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl Output for u8 {
+    fn write(&self, data: &mut Vec<u8>) {
+        data.push(*self);
+    }
+
+    fn write_last_vec(items: &[Self], data: &mut Vec<u8>) {
+        data.extend_from_slice(items);
+    }
+}
+
+impl Output for bool {
+    fn write(&self, data: &mut Vec<u8>) {
+        data.push(*self as u8);
+    }
+}
+
+impl Output for String {
+    fn write(&self, data: &mut Vec<u8>) {
+        write_length(self.len(), data);
+        data.extend_from_slice(self.as_bytes());
+    }
+
+    fn write_last(&self, data: &mut Vec<u8>) {
+        data.extend_from_slice(self.as_bytes());
+    }
+}
+
+impl<T: Output> Output for Vec<T> {
+    fn write(&self, data: &mut Vec<u8>) {
+        write_items(self, data);
+    }
+
+    fn write_last(&self, data: &mut Vec<u8>) {
+        T::write_last_vec(self, data);
+    }
+}
+
+impl<T: Output, const N: usize> Output for [T; N] {
+    fn write(&self, data: &mut Vec<u8>) {
+        for item in self {
+            item.write(data);
+        }
+    }
+}
+
+macro_rules! tuple_output {
+    ($($name:ident $index:tt),+) => {
+        impl<$($name: Output),+> Output for ($($name,)+) {
+            fn write(&self, data: &mut Vec<u8>) {
+                $(self.$index.write(data);)+
+            }
+        }
+    };
+}
+
+tuple_output!(A 0);
+tuple_output!(A 0, B 1);
+tuple_output!(A 0, B 1, C 2);
+tuple_output!(A 0, B 1, C 2, D 3);
+tuple_output!(A 0, B 1, C 2, D 3, E 4);
+tuple_output!(A 0, B 1, C 2, D 3, E 4, F 5);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn written<T: Output>(value: T, last: bool) -> Vec<u8> {
+        let mut data = Vec::new();
+        if last {
+            value.write_last(&mut data);
+        } else {
+            value.write(&mut data);
+        }
+        data
+    }
+
+    #[test]
+    fn writes_integers_and_bools() {
+        assert_eq!(written(0x0102u16, false), [2, 1]);
+        assert_eq!(written(-1i32, false), [0xff; 4]);
+        assert_eq!(written(true, true), [1]);
+    }
+
+    #[test]
+    fn writes_strings_and_bytes() {
+        assert_eq!(written("hi".to_string(), false), [2, 0, 0, 0, b'h', b'i']);
+        assert_eq!(written("hi".to_string(), true), *b"hi");
+        assert_eq!(written(vec![7u8, 8], false), [2, 0, 0, 0, 7, 8]);
+        assert_eq!(written(vec![7u8, 8], true), [7, 8]);
+        // Only bytes and strings drop their length at the end
+        assert_eq!(written(vec![7u16], true), [1, 0, 0, 0, 7, 0]);
+    }
+
+    #[test]
+    fn writes_composites() {
+        assert_eq!(written([1u8, 2], false), [1, 2]);
+        assert_eq!(
+            written((true, "a".to_string()), true),
+            [1, 1, 0, 0, 0, b'a']
+        );
+    }
+}
@@ -0,0 +1,199 @@
+//! Expands `#[alkali::contract]` against minimal stand-ins for the
+//! alkanes-rs runtime types the generated code refers to.
+
+use anyhow::Result;
+use std::cell::RefCell;
+
+mod alkanes_support {
+    pub mod response {
+        #[derive(Clone, Debug, Default, PartialEq)]
+        pub struct AlkaneTransferParcel(pub Vec<u128>);
+
+        #[derive(Debug, Default, PartialEq)]
+        pub struct CallResponse {
+            pub alkanes: AlkaneTransferParcel,
+            pub data: Vec<u8>,
+        }
+
+        impl CallResponse {
+            pub fn forward(incoming_alkanes: &AlkaneTransferParcel) -> Self {
+                Self {
+                    alkanes: incoming_alkanes.clone(),
+                    data: Vec::new(),
+                }
+            }
+        }
+    }
+}
+
+mod alkanes_runtime {
+    pub mod runtime {
+        use crate::alkanes_support::response::{AlkaneTransferParcel, CallResponse};
+
+        pub struct Context {
+            pub inputs: Vec<u128>,
+            pub incoming_alkanes: AlkaneTransferParcel,
+        }
+
+        thread_local! {
+            pub static INPUTS: std::cell::RefCell<Vec<u128>> = Default::default();
+        }
+
+        pub trait AlkaneResponder {
+            fn context(&self) -> anyhow::Result<Context> {
+                Ok(Context {
+                    inputs: INPUTS.with(|inputs| inputs.borrow().clone()),
+                    incoming_alkanes: AlkaneTransferParcel(vec![1]),
+                })
+            }
+
+            fn execute(&self) -> anyhow::Result<CallResponse>;
+        }
+    }
+}
+
+use alkanes_runtime::runtime::{AlkaneResponder, INPUTS};
+use alkanes_support::response::{AlkaneTransferParcel, CallResponse};
+
+#[derive(Default)]
+struct Token {
+    minted: RefCell<Vec<(u128, String)>>,
+}
+
+#[alkali::contract]
+impl Token {
+    /// Mints `amount` tokens
+    /// to `memo`'s author
+    #[opcode(77)]
+    fn mint(&self, amount: u128, memo: String) -> Result<CallResponse> {
+        self.minted.borrow_mut().push((amount, memo));
+        Ok(CallResponse::default())
+    }
+
+    #[opcode(99)]
+    fn name(&self) -> Result<CallResponse> {
+        Ok(CallResponse {
+            data: b"Token".to_vec(),
+            ..Default::default()
+        })
+    }
+
+    #[opcode(5)]
+    fn configure(
+        &self,
+        _flags: [bool; 2],
+        _owners: Vec<u64>,
+        _raw: Vec<u8>,
+    ) -> Result<CallResponse> {
+        Ok(CallResponse::default())
+    }
+
+    #[opcode(101)]
+    fn total_supply(&self) -> Result<u128> {
+        Ok(self.minted.borrow().iter().map(|(amount, _)| amount).sum())
+    }
+
+    #[opcode(102)]
+    fn last_mint(&self, index: u8) -> Result<(u128, String)> {
+        Ok((index.into(), "memo".to_string()))
+    }
+
+    #[opcode(103)]
+    fn touch(&self) -> Result<()> {
+        Ok(())
+    }
+
+    // Methods without an opcode are left alone
+    fn minted(&self) -> Vec<(u128, String)> {
+        self.minted.borrow().clone()
+    }
+}
+
+fn execute(inputs: Vec<u128>) -> (Token, Result<CallResponse>) {
+    INPUTS.with(|current| *current.borrow_mut() = inputs);
+    let token = Token::default();
+    let response = token.execute();
+    (token, response)
+}
+
+#[test]
+fn dispatches_opcodes_with_shifted_inputs() {
+    let (token, response) = execute(vec![77, 1000, 0x6968]);
+    assert_eq!(response.unwrap(), CallResponse::default());
+    assert_eq!(token.minted(), [(1000, "hi".to_string())]);
+
+    let (_, response) = execute(vec![99]);
+    assert_eq!(response.unwrap().data, b"Token");
+}
+
+#[test]
+fn writes_returned_values_into_the_response() {
+    let (_, response) = execute(vec![101]);
+    let response = response.unwrap();
+    assert_eq!(response.data, 0u128.to_le_bytes());
+    assert_eq!(response.alkanes, AlkaneTransferParcel(vec![1]));
+
+    // The trailing string runs to the end of the data
+    let (_, response) = execute(vec![102, 7]);
+    let mut expected = 7u128.to_le_bytes().to_vec();
+    expected.extend_from_slice(b"memo");
+    assert_eq!(response.unwrap().data, expected);
+
+    let (_, response) = execute(vec![103]);
+    assert_eq!(response.unwrap().data, b"");
+}
+
+#[test]
+fn rejects_unknown_opcodes_and_missing_inputs() {
+    let (_, response) = execute(vec![12]);
+    assert_eq!(response.unwrap_err().to_string(), "unrecognized opcode 12");
+
+    let (_, response) = execute(vec![77]);
+    assert!(response.is_err());
+}
+
+#[test]
+fn embeds_the_abi() {
+    let abi: serde_json::Value = serde_json::from_str(Token::ALKALI_ABI).unwrap();
+    assert_eq!(abi["name"], "Token");
+    assert_eq!(
+        abi["opcodes"],
+        serde_json::json!({
+            "mint": 77,
+            "name": 99,
+            "configure": 5,
+            "total_supply": 101,
+            "last_mint": 102,
+            "touch": 103,
+        })
+    );
+    assert_eq!(
+        abi["methods"][0],
+        serde_json::json!({
+            "opcode": 77,
+            "name": "mint",
+            "doc": "Mints `amount` tokens\nto `memo`'s author",
+            "inputs": [
+                { "name": "amount", "type": "u128" },
+                { "name": "memo", "type": "String" },
+            ],
+            "outputs": [],
+        })
+    );
+    assert_eq!(
+        abi["methods"][2]["inputs"],
+        serde_json::json!([
+            { "name": "_flags", "type": { "array": { "type": "bool", "length": 2 } } },
+            { "name": "_owners", "type": { "vec": { "type": "u64" } } },
+            { "name": "_raw", "type": "Vec<u8>" },
+        ])
+    );
+    assert_eq!(
+        abi["methods"][4]["outputs"],
+        serde_json::json!([
+            { "name": "output0", "type": "u128" },
+            { "name": "output1", "type": "String" },
+        ])
+    );
+    assert_eq!(abi["methods"][5]["outputs"], serde_json::json!([]));
+}
@@ -0,0 +1,52 @@
+import { AlkaneId } from "../alkaneId";
+
+describe("AlkaneId", () => {
+  it("should parse and format block:tx", () => {
+    const id = AlkaneId.parse("2:1");
+    expect(id.block).toBe(2n);
+    expect(id.tx).toBe(1n);
+    expect(id.toString()).toBe("2:1");
+    expect(JSON.stringify({ id })).toBe('{"id":"2:1"}');
+  });
+
+  it("should reject malformed ids", () => {
+    expect(() => AlkaneId.parse("2")).toThrow('expected "block:tx"');
+    expect(() => AlkaneId.parse("a:1")).toThrow('expected "block:tx"');
+    expect(() => new AlkaneId(-1, 0)).toThrow("block -1 is not a u128");
+    expect(() => new AlkaneId(0, 1n << 128n)).toThrow("is not a u128");
+  });
+
+  it("should convert from any id-like value", () => {
+    const id = new AlkaneId(4, 7);
+    expect(AlkaneId.from(id)).toBe(id);
+    expect(AlkaneId.from("4:7").equals(id)).toBe(true);
+    expect(AlkaneId.from([4, 7]).equals(id)).toBe(true);
+    expect(AlkaneId.from({ block: "4", tx: 7n }).equals(id)).toBe(true);
+  });
+
+  it("should serialize to and from cellpack words", () => {
+    expect(new AlkaneId(2, 9).toWords()).toEqual([2n, 9n]);
+    expect(AlkaneId.fromWords([2n, 9n, 77n]).toString()).toBe("2:9");
+  });
+
+  it("should order by block, then tx", () => {
+    const ids = ["2:10", "1:0", "2:3", "4:0"].map(AlkaneId.parse);
+    ids.sort((a, b) => a.compare(b));
+    expect(ids.map(String)).toEqual(["1:0", "2:3", "2:10", "4:0"]);
+  });
+
+  it("should describe the reserved deploy targets", () => {
+    expect(AlkaneId.create().toString()).toBe("1:0");
+    expect(AlkaneId.create().isCreate()).toBe(true);
+    expect(AlkaneId.create().deployedId()).toBeUndefined();
+
+    const reserved = AlkaneId.reserved(42);
+    expect(reserved.isReserved()).toBe(true);
+    expect(reserved.deployedId()?.toString()).toBe("4:42");
+
+    expect(AlkaneId.factory("2:5").toString()).toBe("5:5");
+    expect(AlkaneId.factory("4:5").toString()).toBe("6:5");
+    expect(AlkaneId.factory("4:5").isFactory()).toBe(true);
+    expect(() => AlkaneId.factory("3:5")).toThrow("Cannot clone 3:5");
+  });
+});
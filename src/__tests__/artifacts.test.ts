@@ -0,0 +1,167 @@
+import fs from "fs/promises";
+import os from "os";
+import path from "path";
+import {
+  compileProject,
+  findContracts,
+  loadArtifact,
+  readManifest,
+} from "../artifacts";
+import { AlkanesCompiler } from "../compiler";
+import { AlkaliConfig, loadConfig } from "../config";
+import { embedAbi } from "../wasm";
+import { counterAbi, counterWasm } from "./fixtures/counter";
+
+// Compiles every contract to the counter, named after the source's content
+const stubCompiler = () =>
+  ({
+    compileFiles: jest.fn(async (files: string[]) =>
+      Promise.all(
+        files.map(async (file) => {
+          const entry = (await fs.stat(file)).isDirectory()
+            ? path.join(file, "lib.rs")
+            : file;
+          const name = (await fs.readFile(entry, "utf8")).trim();
+          const abi = { ...counterAbi, name };
+          return {
+            bytecode: Buffer.from(embedAbi(counterWasm, abi)).toString(
+              "base64"
+            ),
+            abi,
+            buildInfo: {
+              rustc: "rustc 1.84.0",
+              cargo: "cargo 1.84.0",
+              target: "wasm32-unknown-unknown",
+              optimizeLevel: 3,
+              alkanes: { git: "https://example.com/alkanes-rs", commit: "abc" },
+              dependencies: [],
+              rustflags: [],
+              wasmSha256: "00".repeat(32),
+            },
+          };
+        })
+      )
+    ),
+  } as unknown as AlkanesCompiler);
+
+describe("compileProject", () => {
+  let config: AlkaliConfig;
+
+  beforeEach(async () => {
+    const root = await fs.mkdtemp(path.join(os.tmpdir(), "alkali-artifacts-"));
+    config = await loadConfig({ cwd: root });
+    await fs.mkdir(config.paths.contracts);
+    await fs.writeFile(path.join(config.paths.contracts, "Token.rs"), "Token");
+    await fs.writeFile(path.join(config.paths.contracts, "Vault.rs"), "Vault");
+    await fs.writeFile(path.join(config.paths.contracts, "notes.md"), "");
+    await fs.writeFile(path.join(config.paths.contracts, "lib.rs"), "");
+    // A multi-file contract, and a directory that is not one
+    const pool = path.join(config.paths.contracts, "Pool");
+    await fs.mkdir(path.join(pool, "math"), { recursive: true });
+    await fs.writeFile(path.join(pool, "lib.rs"), "Pool");
+    await fs.mkdir(path.join(config.paths.contracts, "docs"));
+  });
+
+  afterEach(async () => {
+    await fs.rm(config.root, { recursive: true, force: true });
+  });
+
+  it("should build every contract into its own directory", async () => {
+    expect(await findContracts(config)).toEqual(
+      ["Pool", "Token.rs", "Vault.rs"].map((file) =>
+        path.join(config.paths.contracts, file)
+      )
+    );
+    const compiled = await compileProject(config, undefined, {
+      compiler: stubCompiler(),
+    });
+
+    expect(compiled.map(({ artifact }) => artifact.name)).toEqual([
+      "Pool",
+      "Token",
+      "Vault",
+    ]);
+    const { contracts } = await readManifest(config.paths.build);
+    expect(contracts.Vault).toEqual({
+      name: "Vault",
+      contract: "Vault",
+      source: "contracts/Vault.rs",
+      wasm: "Vault/Vault.wasm",
+      abi: "Vault/abi.json",
+      metadata: "Vault/metadata.json",
+      buildInfo: "Vault/build-info.json",
+      types: "types/Vault.ts",
+    });
+
+    const build = (file: string) => path.join(config.paths.build, file);
+    const metadata = JSON.parse(
+      await fs.readFile(build("Vault/metadata.json"), "utf8")
+    );
+    expect(metadata).toMatchObject({
+      name: "Vault",
+      source: "contracts/Vault.rs",
+      size: compiled[2].bytecode.length,
+      target: "wasm32-unknown-unknown",
+      optimizeLevel: 3,
+    });
+    expect(metadata.sha256).toMatch(/^[0-9a-f]{64}$/);
+    expect(
+      JSON.parse(await fs.readFile(build("Vault/build-info.json"), "utf8"))
+    ).toMatchObject({
+      name: "Vault",
+      sha256: metadata.sha256,
+      rustc: "rustc 1.84.0",
+      alkanes: { commit: "abc" },
+    });
+    expect(await fs.readFile(build("types/Token.ts"), "utf8")).toContain(
+      "class Token"
+    );
+
+    expect(contracts.Pool.source).toBe("contracts/Pool");
+
+    const { bytecode, abi } = await loadArtifact(config.paths.build, "Token");
+    expect(bytecode).toEqual(compiled[1].bytecode);
+    expect(abi.name).toBe("Token");
+  });
+
+  it("should update the manifest when compiling single files", async () => {
+    await compileProject(config, undefined, { compiler: stubCompiler() });
+    await fs.writeFile(path.join(config.paths.contracts, "Token.rs"), "Coin");
+
+    const compiler = stubCompiler();
+    await compileProject(
+      config,
+      [path.join(config.paths.contracts, "Token.rs")],
+      { compiler }
+    );
+
+    expect(compiler.compileFiles).toHaveBeenCalledWith([
+      path.join(config.paths.contracts, "Token.rs"),
+    ]);
+    const { contracts } = await readManifest(config.paths.build);
+    expect(Object.keys(contracts)).toEqual(["Pool", "Token", "Vault"]);
+    // Artifacts are found by file name or by contract name
+    const coin = await loadArtifact(config.paths.build, "Coin");
+    expect(coin.artifact.name).toBe("Token");
+    await expect(loadArtifact(config.paths.build, "Missing")).rejects.toThrow(
+      "No compiled contract Missing"
+    );
+  });
+
+  it("should reject contracts that would share a build directory", async () => {
+    const other = path.join(config.root, "other");
+    await fs.mkdir(other);
+    await fs.writeFile(path.join(other, "Token.rs"), "Token");
+
+    await expect(
+      compileProject(
+        config,
+        [
+          path.join(config.paths.contracts, "Token.rs"),
+          path.join(other, "Token.rs"),
+        ],
+        { compiler: stubCompiler() }
+      )
+    ).rejects.toThrow("would both compile to Token");
+  });
+});
@@ -0,0 +1,281 @@
+import {
+  addressToScript,
+  fromHex,
+  getTxid,
+  p2trAddress,
+  parseTransaction,
+  serializeTransaction,
+  toHex,
+} from "../bitcoin";
+import {
+  getXOnlyPublicKey,
+  schnorrSign,
+  schnorrVerify,
+  tweakPrivateKey,
+  tweakPublicKey,
+} from "../secp256k1";
+
+describe("bitcoin", () => {
+  describe("BIP340 schnorr", () => {
+    // Vectors from bips/bip-0340/test-vectors.csv
+    const MESSAGE =
+      "243f6a8885a308d313198a2e03707344a4093822299f31d0082efa98ec4e6c89";
+
+    const signing = [
+      {
+        key: "00".repeat(31) + "03",
+        publicKey:
+          "f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9",
+        aux: "00".repeat(32),
+        message: "00".repeat(32),
+        signature:
+          "e907831f80848d1069a5371b402410364bdf1c5f8307b0084c55f1ce2dca8215" +
+          "25f66a4a85ea8b71e482a74f382d2ce5ebeee8fdb2172f477df4900d310536c0",
+      },
+      {
+        key: "b7e151628aed2a6abf7158809cf4f3c762e7160f38b4da56a784d9045190cfef",
+        publicKey:
+          "dff1d77f2a671c5f36183726db2341be58feae1da2deced843240f7b502ba659",
+        aux: "00".repeat(31) + "01",
+        message: MESSAGE,
+        signature:
+          "6896bd60eeae296db48a229ff71dfe071bde413e6d43f917dc8dcf8c78de3341" +
+          "8906d11ac976abccb20b091292bff4ea897efcb639ea871cfa95f6de339e4b0a",
+      },
+      {
+        key: "c90fdaa22168c234c4c6628b80dc1cd129024e088a67cc74020bbea63b14e5c9",
+        publicKey:
+          "dd308afec5777e13121fa72b9cc1b7cc0139715309b086c960e18fd969774eb8",
+        aux: "c87aa53824b4d7ae2eb035a2b5bbbccc080e76cdc6d1692c4b0b62d798e6d906",
+        message:
+          "7e2d58d8b3bcdf1abadec7829054f90dda9805aab56c77333024b9d0a508b75c",
+        signature:
+          "5831aaeed7b44bb74e5eab94ba9d4294c49bcf2a60728d8b4c200f50dd313c1b" +
+          "ab745879a5ad954a72c45a91c3a51d3c7adea98d82f8481e0e1e03674a6f3fb7",
+      },
+      {
+        key: "0b432b2677937381aef05bb02a66ecd012773062cf3fa2549e44f58ed2401710",
+        publicKey:
+          "25d1dff95105f5253c4022f628a996ad3a0d95fbf21d468a1b33f8c160d8f517",
+        aux: "ff".repeat(32),
+        message: "ff".repeat(32),
+        signature:
+          "7eb0509757e246f19449885651611cb965ecc1a187dd51b64fda1edc9637d5ec" +
+          "97582b9cb13db3933705b32ba982af5af25fd78881ebb32771fc5922efc66ea3",
+      },
+    ];
+
+    it("should match the signing vectors", () => {
+      for (const { key, publicKey, aux, message, signature } of signing) {
+        expect(toHex(getXOnlyPublicKey(fromHex(key)))).toBe(publicKey);
+        expect(
+          toHex(schnorrSign(fromHex(message), fromHex(key), fromHex(aux)))
+        ).toBe(signature);
+        expect(
+          schnorrVerify(
+            fromHex(signature),
+            fromHex(message),
+            fromHex(publicKey)
+          )
+        ).toBe(true);
+      }
+    });
+
+    const verification = [
+      {
+        why: "a valid signature with a small r",
+        publicKey:
+          "d69c3509bb99e412e68b0fe8544e72837dfa30746d8be2aa65975f29d22dc7b9",
+        message:
+          "4df3c3f68fcc83b27e9d42c90431a72499f17875c81a599b566c9889b9696703",
+        signature:
+          "00000000000000000000003b78ce563f89a0ed9414f5aa28ad0d96d6795f9c63" +
+          "76afb1548af603b3eb45c9f8207dee1060cb71c04e80f593060b07d28308d7f4",
+        valid: true,
+      },
+      {
+        why: "a public key not on the curve",
+        publicKey:
+          "eefdea4cdb677750a420fee807eacf21eb9898ae79b9768766e4faa04a2d4a34",
+        message: MESSAGE,
+        signature:
+          "6cff5c3ba86c69ea4b7376f31a9bcb4f74c1976089b2d9963da2e5543e177769" +
+          "69e89b4c5564d00349106b8497785dd7d1d713a8ae82b32fa79d5f7fc407d39b",
+        valid: false,
+      },
+      {
+        why: "an R with odd y",
+        publicKey:
+          "dff1d77f2a671c5f36183726db2341be58feae1da2deced843240f7b502ba659",
+        message: MESSAGE,
+        signature:
+          "fff97bd5755eeea420453a14355235d382f6472f8568a18b2f057a1460297556" +
+          "3cc27944640ac607cd107ae10923d9ef7a73c643e166be5ebeafa34b1ac553e2",
+        valid: false,
+      },
+      {
+        why: "an r equal to the field size",
+        publicKey:
+          "dff1d77f2a671c5f36183726db2341be58feae1da2deced843240f7b502ba659",
+        message: MESSAGE,
+        signature:
+          "fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f" +
+          "69e89b4c5564d00349106b8497785dd7d1d713a8ae82b32fa79d5f7fc407d39b",
+        valid: false,
+      },
+      {
+        why: "an s equal to the curve order",
+        publicKey:
+          "dff1d77f2a671c5f36183726db2341be58feae1da2deced843240f7b502ba659",
+        message: MESSAGE,
+        signature:
+          "6cff5c3ba86c69ea4b7376f31a9bcb4f74c1976089b2d9963da2e5543e177769" +
+          "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141",
+        valid: false,
+      },
+      {
+        why: "a public key exceeding the field size",
+        publicKey:
+          "fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc30",
+        message: MESSAGE,
+        signature:
+          "6cff5c3ba86c69ea4b7376f31a9bcb4f74c1976089b2d9963da2e5543e177769" +
+          "69e89b4c5564d00349106b8497785dd7d1d713a8ae82b32fa79d5f7fc407d39b",
+        valid: false,
+      },
+    ];
+
+    it("should match the verification vectors", () => {
+      for (const vector of verification) {
+        const { why, publicKey, message, signature, valid } = vector;
+        expect([
+          why,
+          schnorrVerify(
+            fromHex(signature),
+            fromHex(message),
+            fromHex(publicKey)
+          ),
+        ]).toEqual([why, valid]);
+      }
+    });
+
+    it("should verify its own signatures and reject tampering", () => {
+      const key = fromHex("01".repeat(32));
+      const message = fromHex("ab".repeat(32));
+      const signature = schnorrSign(message, key);
+      const publicKey = getXOnlyPublicKey(key);
+
+      expect(schnorrVerify(signature, message, publicKey)).toBe(true);
+      // Only a 64-byte signature and a 32-byte x-only key are accepted
+      const padded = new Uint8Array(65);
+      padded.set(signature);
+      expect(schnorrVerify(padded, message, publicKey)).toBe(false);
+      expect(
+        schnorrVerify(signature, message, new Uint8Array([2, ...publicKey]))
+      ).toBe(false);
+      signature[63] ^= 1;
+      expect(schnorrVerify(signature, message, publicKey)).toBe(false);
+    });
+  });
+
+  describe("taproot", () => {
+    it("should derive the BIP86 output key and address", () => {
+      const internalKey = fromHex(
+        "cc8a4bc64d897bddc5fbc2f670f7a8ba0b386779106cf1223c6fc5d7cd6fc115"
+      );
+      const { outputKey } = tweakPublicKey(internalKey);
+      expect(toHex(outputKey)).toBe(
+        "a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c"
+      );
+      expect(p2trAddress(outputKey, "mainnet")).toBe(
+        "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr"
+      );
+    });
+
+    it("should tweak private keys to match tweaked public keys", () => {
+      const key = fromHex("07".repeat(32));
+      const merkleRoot = fromHex("11".repeat(32));
+      const { outputKey } = tweakPublicKey(getXOnlyPublicKey(key), merkleRoot);
+      expect(getXOnlyPublicKey(tweakPrivateKey(key, merkleRoot))).toEqual(
+        outputKey
+      );
+    });
+  });
+
+  describe("addresses", () => {
+    it("should decode segwit v0 and v1 addresses to scripts", () => {
+      expect(
+        toHex(
+          addressToScript(
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+            "mainnet"
+          )
+        )
+      ).toBe("0014751e76e8199196d454941c45d1b3a323f1433bd6");
+      expect(() =>
+        addressToScript(
+          "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t5",
+          "mainnet"
+        )
+      ).toThrow("Invalid checksum");
+      expect(() =>
+        addressToScript(
+          "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+          "regtest"
+        )
+      ).toThrow("is not a regtest address");
+    });
+  });
+
+  describe("transactions", () => {
+    it("should serialize legacy-form transactions and compute txids", () => {
+      const tx = {
+        version: 1,
+        inputs: [{ txid: "11".repeat(32), vout: 0, sequence: 0xffffffff }],
+        outputs: [{ value: 5000000000n, script: fromHex("51") }],
+        locktime: 0,
+      };
+      expect(toHex(serializeTransaction(tx))).toBe(
+        "01000000" +
+          "01" +
+          "11".repeat(32) +
+          "00000000" +
+          "00" +
+          "ffffffff" +
+          "01" +
+          "00f2052a01000000" +
+          "0151" +
+          "00000000"
+      );
+      expect(getTxid(tx)).toHaveLength(64);
+    });
+
+    it("should parse what it serializes", () => {
+      const tx = {
+        version: 2,
+        inputs: [
+          {
+            txid: "ab".repeat(32),
+            vout: 3,
+            sequence: 0xfffffffd,
+            witness: [new Uint8Array(64), new Uint8Array(300).fill(7)],
+          },
+        ],
+        outputs: [
+          { value: 546n, script: fromHex("5120" + "11".repeat(32)) },
+          { value: 0n, script: fromHex("6a5d0401020304") },
+        ],
+        locktime: 0,
+      };
+      expect(parseTransaction(serializeTransaction(tx))).toEqual(tx);
+
+      const legacy = { ...tx, inputs: [{ ...tx.inputs[0], witness: [] }] };
+      const parsed = parseTransaction(serializeTransaction(legacy));
+      expect(parsed.inputs[0].witness).toBeUndefined();
+      expect(getTxid(parsed)).toBe(getTxid(tx));
+      expect(() =>
+        parseTransaction(serializeTransaction(tx).subarray(0, 50))
+      ).toThrow("Transaction is truncated");
+    });
+  });
+});
@@ -0,0 +1,120 @@
+import fs from "fs/promises";
+import os from "os";
+import path from "path";
+import { abiExtractorPath } from "../abiExtractor";
+import { program } from "../cli";
+import { exampleWasm } from "./fixtures/example";
+
+const PACKAGE_ROOT = path.join(__dirname, "..", "..");
+
+// Stands in for `npm install`: the project's packages come from this
+// package's node_modules, and @jonatns/alkali from its sources
+async function install(dir: string): Promise<void> {
+  const modules = path.join(dir, "node_modules");
+  await fs.mkdir(path.join(modules, "@jonatns"), { recursive: true });
+  const installed = path.join(PACKAGE_ROOT, "node_modules");
+  for (const entry of await fs.readdir(installed)) {
+    if (entry !== "@jonatns") {
+      await fs.symlink(path.join(installed, entry), path.join(modules, entry));
+    }
+  }
+  const alkali = path.join(dir, ".alkali-package");
+  await fs.mkdir(alkali);
+  await fs.writeFile(
+    path.join(alkali, "package.json"),
+    JSON.stringify({ name: "@jonatns/alkali", main: "index.ts" })
+  );
+  await fs.writeFile(
+    path.join(alkali, "index.ts"),
+    `export * from ${JSON.stringify(path.join(PACKAGE_ROOT, "src", "index"))};\n`
+  );
+  await fs.symlink(alkali, path.join(modules, "@jonatns", "alkali"));
+}
+
+describe("alkali CLI", () => {
+  let dir: string;
+  let cwd: string;
+  let systemPath: string | undefined;
+
+  // The first extraction builds the alkali-abi helper with cargo
+  beforeAll(() => abiExtractorPath(), 600_000);
+
+  beforeEach(async () => {
+    dir = await fs.mkdtemp(path.join(os.tmpdir(), "alkali-cli-"));
+    const bin = path.join(dir, ".bin");
+    await fs.mkdir(bin);
+    await fs.writeFile(path.join(bin, "example.wasm"), exampleWasm);
+
+    // Stands in for the toolchain: "builds" the Example contract
+    await fs.writeFile(
+      path.join(bin, "cargo"),
+      `#!/bin/sh
+[ "$1" = "-V" ] && echo "cargo 1.84.0 (stub)" && exit 0
+mkdir -p target/wasm32-unknown-unknown/release
+cp ${JSON.stringify(path.join(bin, "example.wasm"))} target/wasm32-unknown-unknown/release/example.wasm
+echo "version = 3" > Cargo.lock
+`,
+      { mode: 0o755 }
+    );
+    await fs.writeFile(
+      path.join(bin, "rustc"),
+      '#!/bin/sh\necho "rustc 1.84.0 (stub)"\n',
+      { mode: 0o755 }
+    );
+    systemPath = process.env.PATH;
+    process.env.PATH = `${bin}${path.delimiter}${systemPath}`;
+    cwd = process.cwd();
+    process.chdir(dir);
+  });
+
+  afterEach(async () => {
+    process.chdir(cwd);
+    process.env.PATH = systemPath;
+    process.exitCode = undefined;
+    await fs.rm(dir, { recursive: true, force: true });
+  });
+
+  it("should initialize a project whose tests pass", async () => {
+    await program.parseAsync(["init"], { from: "user" });
+
+    const manifest = JSON.parse(await fs.readFile("package.json", "utf8"));
+    expect(manifest.name).toBe(path.basename(dir).toLowerCase());
+    expect(manifest.scripts.test).toBe("alkali test");
+    expect(Object.keys(manifest.devDependencies)).toEqual(
+      expect.arrayContaining(["@jonatns/alkali", "jest", "ts-jest"])
+    );
+    await fs.access(path.join("scripts", "deploy.ts"));
+    expect(await fs.readFile("Cargo.toml", "utf8")).toContain(
+      'alkali-test = { path = "vendor/alkali-test" }'
+    );
+
+    await install(dir);
+    await program.parseAsync(["test"], { from: "user" });
+    expect(process.exitCode).toBe(0);
+  }, 600_000);
+
+  it("should merge into the project's package.json", async () => {
+    await fs.writeFile(
+      "package.json",
+      JSON.stringify({
+        name: "existing",
+        scripts: { test: "jest" },
+        dependencies: { "@jonatns/alkali": "file:../alkali" },
+      })
+    );
+
+    await program.parseAsync(["init"], { from: "user" });
+
+    const manifest = JSON.parse(await fs.readFile("package.json", "utf8"));
+    expect(manifest.name).toBe("existing");
+    expect(manifest.scripts).toMatchObject({
+      test: "jest",
+      compile: "alkali compile",
+    });
+    expect(manifest.dependencies).toEqual({
+      "@jonatns/alkali": "file:../alkali",
+    });
+    expect(manifest.devDependencies).not.toHaveProperty("@jonatns/alkali");
+    expect(manifest.devDependencies).toHaveProperty("ts-jest");
+  });
+});
@@ -1,8 +1,38 @@
-import { AlkanesCompiler } from "../compiler";
+import fs from "fs/promises";
+import os from "os";
+import path from "path";
+import { abiExtractorPath } from "../abiExtractor";
+import { toHex } from "../bitcoin";
+import { ALKANES_RS_REV } from "../config";
+import {
+  AlkanesCompiler,
+  contractCrateName,
+  contractManifest,
+  dependencyValue,
+  vendorCrates,
+} from "../compiler";
+import { sha256 } from "../secp256k1";
+import { embedAbi } from "../wasm";
+import { counterWasm } from "./fixtures/counter";
+
+// Wraps a dispatch block in a minimal AlkaneResponder contract
+const contract = (body: string) => `
+pub struct TestContract(());
+
+impl AlkaneResponder for TestContract {
+    fn execute(&self) -> Result<CallResponse> {
+        let context = self.context()?;
+        let mut inputs = context.inputs.clone();
+${body}
+    }
+}`;
 
 describe("AlkanesCompiler", () => {
   const compiler = new AlkanesCompiler();
 
+  // The first extraction builds the alkali-abi helper with cargo
+  beforeAll(() => abiExtractorPath(), 600_000);
+
   describe("parseABI", () => {
     it("should parse a basic contract", async () => {
       const sourceCode = `
@@ -58,7 +88,7 @@ impl AlkaneResponder for SimpleToken {
           {
             opcode: 77,
             name: "mint",
-            inputs: [{ name: "param0", type: "u128" }],
+            inputs: [{ name: "amount", type: "u128" }],
             outputs: [],
           },
           {
@@ -83,14 +113,13 @@ impl AlkaneResponder for SimpleToken {
     });
 
     it("should parse array parameters", async () => {
-      const sourceCode = `
-// ... contract setup ...
+      const sourceCode = contract(`
 match shift_or_err(&mut inputs)? {
     /* setArray(u128[2]) */
     1 => {
         Ok(response)
     }
-}`;
+}`);
 
       const abi = await compiler.parseABI(sourceCode);
       expect(abi.methods[0]).toMatchObject({
@@ -110,12 +139,100 @@ match shift_or_err(&mut inputs)? {
       });
     });
 
+    it("should parse return types", async () => {
+      const sourceCode = contract(`
+match shift_or_err(&mut inputs)? {
+    /* name() -> String */
+    99 => Ok(response),
+    /* balances(u128[2], Vec<u128>) -> (Vec<u8>, (u128, bool)[2]) */
+    100 => Ok(response),
+}`);
+
+      const abi = await compiler.parseABI(sourceCode);
+      expect(abi.methods[0].outputs).toEqual([
+        { name: "output0", type: "String" },
+      ]);
+      expect(abi.methods[1]).toMatchObject({
+        name: "balances",
+        inputs: [
+          { name: "param0", type: { array: { type: "u128", length: 2 } } },
+          { name: "param1", type: { vec: { type: "u128" } } },
+        ],
+        outputs: [
+          { name: "output0", type: "Vec<u8>" },
+          {
+            name: "output1",
+            type: {
+              array: { type: { tuple: ["u128", "bool"] }, length: 2 },
+            },
+          },
+        ],
+      });
+    });
+
+    it("should name parameters and describe methods", async () => {
+      const sourceCode = contract(`
+match shift_or_err(&mut inputs)? {
+    /// Sets up the mint
+    /// initialize(token_units: u128, value_per_mint: u128)
+    0 => Ok(response),
+    // Mints to the caller
+    /* mint(u128, u128) */
+    77 => {
+        let amount = shift_or_err(&mut inputs)?;
+        let fee: u64 = shift_or_err(&mut inputs)?.try_into()?;
+        Ok(response)
+    }
+    /* burn(u128, u8[2]) */
+    88 => {
+        let amount = shift_or_err(&mut inputs)?;
+        Ok(response)
+    }
+}`);
+
+      const abi = await compiler.parseABI(sourceCode);
+      expect(abi.methods).toEqual([
+        {
+          opcode: 0,
+          name: "initialize",
+          doc: "Sets up the mint",
+          inputs: [
+            { name: "token_units", type: "u128" },
+            { name: "value_per_mint", type: "u128" },
+          ],
+          outputs: [],
+        },
+        {
+          opcode: 77,
+          name: "mint",
+          doc: "Mints to the caller",
+          inputs: [
+            { name: "amount", type: "u128" },
+            { name: "fee", type: "u128" },
+          ],
+          outputs: [],
+        },
+        {
+          // Bindings that do not line up with the parameters are ignored
+          opcode: 88,
+          name: "burn",
+          inputs: [
+            { name: "param0", type: "u128" },
+            { name: "param1", type: { array: { type: "u8", length: 2 } } },
+          ],
+          outputs: [],
+        },
+      ]);
+    });
+
     it("should handle multiple storage pointers", async () => {
-      const sourceCode = `
-// ... contract setup ...
+      const sourceCode = contract(`
 let initialized = StoragePointer::from_keyword("/initialized");
 let total_supply = StoragePointer::from_keyword("/total-supply");
-let owner = StoragePointer::from_keyword("/owner");`;
+let owner = StoragePointer::from_keyword("/owner");
+match shift_or_err(&mut inputs)? {
+    _ => Err(anyhow!("unrecognized opcode"))
+}`);
 
       const abi = await compiler.parseABI(sourceCode);
       expect(abi.storage).toEqual([
@@ -125,15 +242,656 @@ let owner = StoragePointer::from_keyword("/owner");`;
       ]);
     });
 
-    it("should handle missing method comments gracefully", async () => {
+    it("should infer storage types from pointer usage", async () => {
       const sourceCode = `
+pub struct Token(());
+
+impl Token {
+    fn total_supply_pointer(&self) -> StoragePointer {
+        StoragePointer::from_keyword("/totalsupply")
+    }
+
+    fn balance(&self, owner: &Vec<u8>) -> u128 {
+        StoragePointer::from_keyword("/balances/")
+            .select(owner)
+            .get_value::<u128>()
+    }
+}
+
+impl AlkaneResponder for Token {
+    fn execute(&self) -> Result<CallResponse> {
+        let mut inputs = self.context()?.inputs.clone();
+        match shift_or_err(&mut inputs)? {
+            /* totalSupply() -> u128 */
+            101 => {
+                let supply = self.total_supply_pointer().get_value::<u128>();
+                Ok(response)
+            }
+            _ => Err(anyhow!("unrecognized opcode")),
+        }
+    }
+}`;
+
+      const abi = await compiler.parseABI(sourceCode);
+      expect(abi.storage).toEqual([
+        { key: "/totalsupply", type: "u128" },
+        {
+          key: "/balances/",
+          type: "u128",
+          path: [{ select: "Vec<u8>" }],
+        },
+      ]);
+    });
+
+    it("should handle missing method comments gracefully", async () => {
+      const sourceCode = contract(`
 match shift_or_err(&mut inputs)? {
     0 => { Ok(response) },
     1 => { Ok(response) }
-}`;
+}`);
 
       const abi = await compiler.parseABI(sourceCode);
       expect(abi.methods).toEqual([]);
     });
+
+    it("should accept line comments, attributes and constant opcodes", async () => {
+      const sourceCode = `
+const MINT: u128 = 77;
+
+pub struct Helper;
+
+pub struct Token(());
+
+impl Token {
+    const BURN: u128 = 88;
+}
+
+impl AlkaneResponder for Token {
+    fn execute(&self) -> Result<CallResponse> {
+        let mut inputs = self.context()?.inputs.clone();
+        match shift_or_err(&mut inputs)?
+        {
+            // Mints new tokens
+            // mint(u128)
+            #[allow(unused)]
+            MINT => Ok(response),
+            /// burn(u128)
+            Self::BURN => Ok(response),
+            _ => Err(anyhow!("unrecognized opcode")),
+        }
+    }
+}`;
+
+      const abi = await compiler.parseABI(sourceCode);
+      expect(abi.name).toBe("Token");
+      expect(abi.opcodes).toEqual({ mint: 77, burn: 88 });
+    });
+
+    it("should read #[alkali::contract] methods from their signatures", async () => {
+      const sourceCode = `
+#[derive(Default)]
+pub struct Token(());
+
+#[alkali::contract]
+impl Token {
+    /// Mints new tokens
+    #[opcode(77)]
+    fn mint(&self, amount: u128, to: [u128; 2]) -> Result<CallResponse> {
+        let pointer = StoragePointer::from_keyword("/minted");
+        Ok(response)
+    }
+
+    #[opcode(101)]
+    fn total_supply(&self) -> anyhow::Result<u128> {
+        Ok(0)
+    }
+
+    fn helper(&self) {}
+}`;
+
+      const abi = await compiler.parseABI(sourceCode);
+      expect(abi).toMatchObject({
+        name: "Token",
+        methods: [
+          {
+            opcode: 77,
+            name: "mint",
+            doc: "Mints new tokens",
+            inputs: [
+              { name: "amount", type: "u128" },
+              { name: "to", type: { array: { type: "u128", length: 2 } } },
+            ],
+            outputs: [],
+          },
+          {
+            opcode: 101,
+            name: "total_supply",
+            inputs: [],
+            outputs: [{ name: "output0", type: "u128" }],
+          },
+        ],
+        storage: [{ key: "/minted", type: "Vec<u8>" }],
+        opcodes: { mint: 77, total_supply: 101 },
+      });
+    });
+
+    it("should report where the source cannot be understood", async () => {
+      await expect(
+        compiler.parseABI(
+          contract(`
+match shift_or_err(&mut inputs)? {
+    /* burn(u128) */
+    BURN => Ok(response),
+}`)
+        )
+      ).rejects.toThrow("11:5: cannot resolve opcode `BURN`");
+      await expect(
+        compiler.parseABI(
+          contract(`
+match shift_or_err(&mut inputs)? {
+    /* transfer(to: Address, u128[2]) */
+    78 => Ok(response),
+}`)
+        )
+      ).rejects.toThrow(
+        "11:5: unknown type Address in the signature of opcode 78; expected u8, u16"
+      );
+      await expect(compiler.parseABI("fn broken( {")).rejects.toThrow(
+        "1:"
+      );
+      await expect(
+        compiler.parseABI("pub struct NotAContract;")
+      ).rejects.toThrow("no `impl AlkaneResponder for ...` block found");
+    });
+  });
+
+  describe("contractManifest", () => {
+    it("should build a cdylib and an rlib with alkali-test for tests", () => {
+      const manifest = contractManifest({
+        name: "my-project",
+        lib: { name: "example", path: "contracts/Example.rs" },
+      });
+
+      expect(manifest).toContain('name = "my-project"');
+      expect(manifest).toContain(
+        'name = "example"\npath = "contracts/Example.rs"\ncrate-type = ["cdylib", "rlib"]'
+      );
+      expect(manifest).toMatch(
+        /\[dev-dependencies\]\nalkali-test = \{ path = ".*alkali-test" \}/
+      );
+      expect(contractManifest()).toContain(
+        '[lib]\ncrate-type = ["cdylib", "rlib"]'
+      );
+    });
+
+    it("should depend on crates of a local alkanes-rs checkout", () => {
+      const manifest = contractManifest({
+        alkanes: { git: "unused", path: "/src/alkanes-rs" },
+      });
+      expect(manifest).toContain(
+        'metashrew-support = { path = "/src/alkanes-rs/crates/metashrew-support" }'
+      );
+      expect(manifest).not.toContain("git =");
+    });
+
+    it("should write local crate paths relative to the manifest", () => {
+      const dir = path.join(__dirname, "..", "..", "my-project");
+      const manifest = contractManifest({
+        alkanes: { git: "unused", path: path.join(dir, "alkanes-rs") },
+        dir,
+      });
+      expect(manifest).toContain('alkali = { path = "../crates/alkali" }');
+      expect(manifest).toContain(
+        'alkali-test = { path = "../crates/alkali-test" }'
+      );
+      expect(manifest).toContain(
+        'alkanes-support = { path = "alkanes-rs/crates/alkanes-support" }'
+      );
+    });
+
+    it("should depend on alkali crates vendored into the project", async () => {
+      const dir = await fs.mkdtemp(path.join(os.tmpdir(), "alkali-vendor-"));
+      try {
+        await vendorCrates(path.join(dir, "vendor"));
+        for (const file of [
+          "alkali/src/lib.rs",
+          "alkali-macros/Cargo.toml",
+          "alkali-test/src/host.rs",
+        ]) {
+          await fs.access(path.join(dir, "vendor", file));
+        }
+        const manifest = contractManifest({
+          dir,
+          crates: path.join(dir, "vendor"),
+        });
+        expect(manifest).toContain('alkali = { path = "vendor/alkali" }');
+        expect(manifest).toContain(
+          'alkali-test = { path = "vendor/alkali-test" }'
+        );
+      } finally {
+        await fs.rm(dir, { recursive: true, force: true });
+      }
+    });
+
+    it("should depend on the configured alkanes-rs source", () => {
+      expect(contractManifest()).toContain(
+        `alkanes-runtime = { git = "https://github.com/kungfuflex/alkanes-rs", rev = "${ALKANES_RS_REV}" }`
+      );
+      const manifest = contractManifest({
+        alkanes: { git: "https://example.com/alkanes-rs", tag: "v1.2.0" },
+      });
+      for (const crate of ["alkanes-runtime", "alkanes-support"]) {
+        expect(manifest).toContain(
+          `${crate} = { git = "https://example.com/alkanes-rs", tag = "v1.2.0" }`
+        );
+      }
+    });
+  });
+
+  describe("createWorkspace", () => {
+    let dir: string;
+    let workspace: AlkanesCompiler;
+    const contracts = (file: string) => path.join(dir, "contracts", file);
+    const cache = (file: string) => path.join(dir, ".alkanes", file);
+    const read = (file: string) => fs.readFile(cache(file), "utf8");
+
+    beforeEach(async () => {
+      dir = await fs.mkdtemp(path.join(os.tmpdir(), "alkali-workspace-"));
+      await fs.mkdir(path.join(dir, "contracts"));
+      for (const file of ["Token.rs", "MyVault.rs", "lib.rs"]) {
+        await fs.writeFile(contracts(file), "");
+      }
+      workspace = new AlkanesCompiler({
+        cacheDir: path.join(dir, ".alkanes"),
+        contractsDir: path.join(dir, "contracts"),
+      });
+    });
+
+    afterEach(async () => {
+      await fs.rm(dir, { recursive: true, force: true });
+    });
+
+    it("should build each contract in place as a workspace member", async () => {
+      const members = await workspace.createWorkspace([
+        contracts("Token.rs"),
+        contracts("MyVault.rs"),
+      ]);
+
+      expect(members).toEqual([
+        {
+          name: "token",
+          dir: cache("contracts/token"),
+          source: contracts("Token.rs"),
+        },
+        {
+          name: "my_vault",
+          dir: cache("contracts/my_vault"),
+          source: contracts("MyVault.rs"),
+        },
+      ]);
+      expect(await read("Cargo.toml")).toBe(
+        '[workspace]\nmembers = ["contracts/*", "lib"]\nresolver = "2"\n'
+      );
+      const vault = await read("contracts/my_vault/Cargo.toml");
+      expect(vault).toContain(
+        'name = "my_vault"\npath = "../../../contracts/MyVault.rs"'
+      );
+      expect(vault).toContain('lib = { path = "../../lib" }');
+      expect(await read("lib/Cargo.toml")).toContain(
+        'path = "../../contracts/lib.rs"\ncrate-type = ["rlib"]'
+      );
+      expect(contractCrateName("ERC20Token.rs")).toBe("erc20_token");
+    });
+
+    it("should keep unchanged manifests and drop deleted contracts", async () => {
+      await workspace.createWorkspace([
+        contracts("Token.rs"),
+        contracts("MyVault.rs"),
+      ]);
+      const { mtimeMs } = await fs.stat(cache("contracts/token/Cargo.toml"));
+      await fs.rm(contracts("MyVault.rs"));
+      await new Promise((resolve) => setTimeout(resolve, 20));
+
+      await workspace.createWorkspace([contracts("Token.rs")]);
+
+      const token = await fs.stat(cache("contracts/token/Cargo.toml"));
+      expect(token.mtimeMs).toBe(mtimeMs);
+      expect(await fs.readdir(cache("contracts"))).toEqual(["token"]);
+
+      await fs.rm(contracts("lib.rs"));
+      await workspace.createWorkspace([contracts("Token.rs")]);
+
+      expect(await fs.readdir(cache("."))).not.toContain("lib");
+      expect(await read("Cargo.toml")).toContain('members = ["contracts/*"]');
+      expect(await read("contracts/token/Cargo.toml")).not.toContain("lib =");
+      await expect(
+        workspace.createWorkspace([
+          contracts("Token.rs"),
+          path.join(dir, "token.rs"),
+        ])
+      ).rejects.toThrow("would both build crate token");
+    });
+
+    it("should add the configured and a directory's own dependencies", async () => {
+      const vault = contracts("Vault");
+      await fs.mkdir(path.join(vault, "math"), { recursive: true });
+      await fs.writeFile(path.join(vault, "lib.rs"), "mod math;");
+      await fs.writeFile(
+        path.join(vault, "Cargo.toml"),
+        `# Vault's own crates
+[dependencies]
+serde = { version = "1.0", default-features = false } # no std
+shared = { path = "../../shared" }
+
+[dependencies.hashbrown]
+version = "0.15"
+features = [
+  "alloc", # no std
+  "inline-more",
+]
+
+[target.'cfg(target_arch = "wasm32")'.dependencies]
+getrandom = { version = "0.2", features = ["custom"] }
+`
+      );
+      const compiler = new AlkanesCompiler({
+        cacheDir: path.join(dir, ".alkanes"),
+        contractsDir: path.join(dir, "contracts"),
+        dependencies: {
+          serde: "1.0",
+          "alkanes-std-owned": { git: "https://example.com/std", tag: "v1" },
+        },
+      });
+
+      await compiler.createWorkspace([contracts("Token.rs"), vault]);
+
+      const manifest = await read("contracts/vault/Cargo.toml");
+      expect(manifest).toContain(
+        '[lib]\nname = "vault"\npath = "../../../contracts/Vault/lib.rs"'
+      );
+      expect(manifest).toContain(
+        'serde = { version = "1.0", default-features = false }\n'
+      );
+      expect(manifest).toContain('shared = { path = "../../../shared" }\n');
+      expect(manifest).toContain(
+        'alkanes-std-owned = { git = "https://example.com/std", tag = "v1" }'
+      );
+      expect(manifest).toContain(
+        'hashbrown = { version = "0.15", features = ["alloc", "inline-more"] }\n'
+      );
+      expect(manifest).toContain(
+        `[target."cfg(target_arch = \\"wasm32\\")".dependencies]
+getrandom = { version = "0.2", features = ["custom"] }
+`
+      );
+      expect(await read("contracts/token/Cargo.toml")).toContain(
+        'serde = "1.0"\n'
+      );
+      expect(await read("lib/Cargo.toml")).toContain('serde = "1.0"\n');
+      expect(
+        dependencyValue({ features: ["derive"], "default-features": false })
+      ).toBe('{ features = ["derive"], default-features = false }');
+    });
+
+    it("should reject dependencies alkali cannot merge", async () => {
+      const vault = contracts("Vault");
+      await fs.mkdir(vault);
+      await fs.writeFile(path.join(vault, "lib.rs"), "");
+      const manifest = path.join(vault, "Cargo.toml");
+
+      await fs.writeFile(manifest, "[features]\nstd = []\n");
+      await expect(workspace.createWorkspace([vault])).rejects.toThrow(
+        `${manifest}: only [dependencies] and [target.<cfg>.dependencies] can be set for a contract, not [features]`
+      );
+      await fs.writeFile(
+        manifest,
+        "[target.wasm32-unknown-unknown.build-dependencies]\ncc = \"1\"\n"
+      );
+      await expect(workspace.createWorkspace([vault])).rejects.toThrow(
+        "not [target.wasm32-unknown-unknown.build-dependencies]"
+      );
+      await fs.writeFile(manifest, '[dependencies]\nanyhow = "1"\n');
+      await expect(workspace.createWorkspace([vault])).rejects.toThrow(
+        `anyhow in ${manifest} is already a dependency of every contract`
+      );
+      const configured = new AlkanesCompiler({
+        cacheDir: path.join(dir, ".alkanes"),
+        dependencies: { "alkanes-runtime": "0.1" },
+      });
+      await expect(
+        configured.createWorkspace([contracts("Token.rs")])
+      ).rejects.toThrow(
+        "alkanes-runtime in the config's dependencies is already a dependency of every contract; set its source with the alkanes config"
+      );
+    });
+  });
+
+  describe("compileFiles", () => {
+    let dir: string;
+    let systemPath: string | undefined;
+
+    // Stands in for the toolchain: builds the counter and locks it
+    const lock = `version = 3
+
+[[package]]
+name = "alkanes-runtime"
+version = "0.1.0"
+source = "git+https://github.com/kungfuflex/alkanes-rs#0123abcd"
+dependencies = [
+ "anyhow",
+]
+
+[[package]]
+name = "anyhow"
+version = "1.0.95"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "token"
+version = "0.1.0"
+dependencies = [
+ "alkanes-runtime",
+ "anyhow 1.0.95",
+]
+
+[[package]]
+name = "unused"
+version = "1.0.0"
+`;
+
+    beforeEach(async () => {
+      dir = await fs.mkdtemp(path.join(os.tmpdir(), "alkali-build-"));
+      const bin = path.join(dir, "bin");
+      await fs.mkdir(bin);
+      await fs.mkdir(path.join(dir, "contracts"));
+      await fs.writeFile(path.join(dir, "counter.wasm"), counterWasm);
+      await fs.writeFile(path.join(dir, "lock"), lock);
+      await fs.writeFile(
+        path.join(bin, "cargo"),
+        `#!/bin/sh
+[ "$1" = "-V" ] && echo "cargo 1.84.0 (stub)" && exit 0
+echo "$@" > ${JSON.stringify(path.join(dir, "args"))}
+printf '%s|%s' "$CARGO_ENCODED_RUSTFLAGS" "$RUSTFLAGS" > ${JSON.stringify(path.join(dir, "rustflags"))}
+cat alkali-build.lock >> ${JSON.stringify(path.join(dir, "holders"))}
+mkdir -p target/wasm32-unknown-unknown/release
+cp ${JSON.stringify(path.join(dir, "counter.wasm"))} target/wasm32-unknown-unknown/release/token.wasm
+[ -f Cargo.lock ] || cp ${JSON.stringify(path.join(dir, "lock"))} Cargo.lock
+`,
+        { mode: 0o755 }
+      );
+      await fs.writeFile(
+        path.join(bin, "rustc"),
+        '#!/bin/sh\necho "rustc 1.84.0 (stub)"\n',
+        { mode: 0o755 }
+      );
+      await fs.writeFile(
+        path.join(dir, "contracts", "Token.rs"),
+        contract(`
+match shift_or_err(&mut inputs)? {
+    /* get() -> u128 */
+    1 => Ok(response),
+}`)
+      );
+      systemPath = process.env.PATH;
+      process.env.PATH = `${bin}${path.delimiter}${systemPath}`;
+    });
+
+    afterEach(async () => {
+      process.env.PATH = systemPath;
+      await fs.rm(dir, { recursive: true, force: true });
+    });
+
+    it("should keep the Cargo.lock and record what the WASM was built with", async () => {
+      process.env.RUSTFLAGS = "-C target-feature=+bulk-memory";
+      const compiler = new AlkanesCompiler({
+        root: dir,
+        cacheDir: path.join(dir, ".alkanes"),
+        contractsDir: path.join(dir, "contracts"),
+        lockfile: path.join(dir, "contracts", "Cargo.lock"),
+        locked: true,
+        offline: true,
+      });
+
+      const [result] = await compiler
+        .compileFiles([path.join(dir, "contracts", "Token.rs")])
+        .finally(() => delete process.env.RUSTFLAGS);
+
+      expect(Buffer.from(result.bytecode, "base64")).toEqual(
+        Buffer.from(embedAbi(counterWasm, result.abi))
+      );
+      expect(result.buildInfo).toEqual({
+        rustc: "rustc 1.84.0 (stub)",
+        cargo: "cargo 1.84.0 (stub)",
+        target: "wasm32-unknown-unknown",
+        optimizeLevel: 3,
+        alkanes: {
+          git: "https://github.com/kungfuflex/alkanes-rs",
+          rev: ALKANES_RS_REV,
+          commit: "0123abcd",
+        },
+        dependencies: [
+          {
+            name: "alkanes-runtime",
+            version: "0.1.0",
+            source: "git+https://github.com/kungfuflex/alkanes-rs#0123abcd",
+          },
+          {
+            name: "anyhow",
+            version: "1.0.95",
+            source: "registry+https://github.com/rust-lang/crates.io-index",
+          },
+        ],
+        rustflags: expect.any(Array),
+        wasmSha256: toHex(sha256(counterWasm)),
+      });
+      // Paths rustc would embed are remapped, after the inherited flags
+      const { rustflags } = result.buildInfo;
+      expect(rustflags.slice(0, 2)).toEqual([
+        "-C",
+        "target-feature=+bulk-memory",
+      ]);
+      expect(rustflags).toContain(`--remap-path-prefix=${dir}=/project`);
+      expect(rustflags).toContain(
+        `--remap-path-prefix=${path.join(dir, ".alkanes")}=/alkali/cache`
+      );
+      expect(await fs.readFile(path.join(dir, "rustflags"), "utf8")).toBe(
+        `${rustflags.join("\x1f")}|`
+      );
+      expect(await fs.readFile(path.join(dir, "args"), "utf8")).toBe(
+        "build --release --target wasm32-unknown-unknown -p token --locked --offline\n"
+      );
+      const kept = path.join(dir, "contracts", "Cargo.lock");
+      expect(await fs.readFile(kept, "utf8")).toBe(lock);
+
+      // Later builds start from the kept lock
+      const pinned = lock.replace(/0123abcd/g, "4567ef01");
+      await fs.writeFile(kept, pinned);
+      const [rebuilt] = await compiler.compileFiles([
+        path.join(dir, "contracts", "Token.rs"),
+      ]);
+      expect(rebuilt.buildInfo.alkanes.commit).toBe("4567ef01");
+      expect(await fs.readFile(kept, "utf8")).toBe(pinned);
+    });
+
+    it("should read a directory contract through its module declarations", async () => {
+      const token = path.join(dir, "contracts", "Token");
+      await fs.mkdir(token);
+      await fs.rename(
+        path.join(dir, "contracts", "Token.rs"),
+        path.join(token, "dispatch.rs")
+      );
+      await fs.writeFile(
+        path.join(token, "lib.rs"),
+        "mod dispatch;\n#[cfg(test)]\nmod tests;\n"
+      );
+      // Neither is compiled into the contract
+      await fs.writeFile(path.join(token, "tests.rs"), "fn broken( {");
+      await fs.writeFile(path.join(token, "stray.rs"), "fn broken( {");
+      const compiler = new AlkanesCompiler({
+        cacheDir: path.join(dir, ".alkanes"),
+        contractsDir: path.join(dir, "contracts"),
+        lockfile: path.join(dir, "contracts", "Cargo.lock"),
+      });
+
+      const [result] = await compiler.compileFiles([token]);
+      expect(result.abi.opcodes).toEqual({ get: 1 });
+
+      await fs.writeFile(
+        path.join(token, "lib.rs"),
+        "mod dispatch;\nmod stray;\n"
+      );
+      await expect(compiler.compileFiles([token])).rejects.toThrow(
+        `Compilation failed: ${path.join(token, "stray.rs")}:1:`
+      );
+    });
+
+    it("should build the workspace one compiler at a time", async () => {
+      const compiler = () =>
+        new AlkanesCompiler({
+          cacheDir: path.join(dir, ".alkanes"),
+          contractsDir: path.join(dir, "contracts"),
+          lockfile: path.join(dir, "contracts", "Cargo.lock"),
+        });
+      const token = path.join(dir, "contracts", "Token.rs");
+      // A lock left by a process that died is taken over
+      const buildLock = path.join(dir, ".alkanes", "alkali-build.lock");
+      await fs.mkdir(path.dirname(buildLock), { recursive: true });
+      await fs.writeFile(buildLock, String(0x3fffffff));
+
+      await Promise.all([
+        compiler().compileFiles([token]),
+        compiler().compileFiles([token]),
+      ]);
+
+      expect(await fs.readFile(path.join(dir, "holders"), "utf8")).toBe(
+        `${process.pid}`.repeat(2)
+      );
+      await expect(fs.access(buildLock)).rejects.toThrow("ENOENT");
+      await expect(compiler().compile("fn broken( {")).rejects.toThrow(
+        "Compilation failed: 1:"
+      );
+    });
+
+    it("should seed the native test crate's Cargo.lock", async () => {
+      const compiler = new AlkanesCompiler({
+        root: dir,
+        cacheDir: path.join(dir, ".alkanes"),
+        contractsDir: path.join(dir, "contracts"),
+        lockfile: path.join(dir, "contracts", "Cargo.lock"),
+      });
+      await fs.writeFile(path.join(dir, "Cargo.toml"), contractManifest());
+
+      await compiler.compileFiles([path.join(dir, "contracts", "Token.rs")]);
+      expect(await fs.readFile(path.join(dir, "Cargo.lock"), "utf8")).toBe(
+        lock
+      );
+
+      // An existing one is cargo's to update
+      await fs.writeFile(path.join(dir, "Cargo.lock"), "# native\n");
+      await compiler.compileFiles([path.join(dir, "contracts", "Token.rs")]);
+      expect(await fs.readFile(path.join(dir, "Cargo.lock"), "utf8")).toBe(
+        "# native\n"
+      );
+    });
   });
 });
@@ -0,0 +1,160 @@
+import fs from "fs/promises";
+import os from "os";
+import path from "path";
+import {
+  ALKANES_RS_GIT,
+  ALKANES_RS_REV,
+  ConfigError,
+  getNetworkConfig,
+  loadConfig,
+  resolveConfig,
+} from "../config";
+
+describe("config", () => {
+  let dir: string;
+
+  beforeEach(async () => {
+    dir = await fs.mkdtemp(path.join(os.tmpdir(), "alkali-config-"));
+  });
+
+  afterEach(async () => {
+    await fs.rm(dir, { recursive: true, force: true });
+  });
+
+  it("should fall back to defaults without a config file", async () => {
+    const config = await loadConfig({ cwd: dir });
+
+    expect(config.configPath).toBeUndefined();
+    expect(config.name).toBe(path.basename(dir));
+    expect(config.network).toBe("regtest");
+    expect(config.networks.regtest).toEqual({
+      url: "http://127.0.0.1:18888",
+      network: "regtest",
+      accounts: [],
+    });
+    expect(config.compiler).toEqual({
+      target: "wasm32-unknown-unknown",
+      optimizeLevel: 3,
+      locked: false,
+      offline: false,
+    });
+    expect(config.paths.contracts).toBe(path.join(dir, "contracts"));
+    expect(config.paths.cache).toBe(path.join(dir, ".alkanes"));
+    expect(config.alkanes).toEqual({
+      git: ALKANES_RS_GIT,
+      rev: ALKANES_RS_REV,
+    });
+  });
+
+  it("should resolve alkali.config.json against its directory", async () => {
+    const key = "11".repeat(32);
+    await fs.writeFile(
+      path.join(dir, "alkali.config.json"),
+      JSON.stringify({
+        network: "testnet",
+        networks: {
+          testnet: { url: "https://testnet.example", accounts: [key] },
+          staging: { url: "https://staging.example" },
+        },
+        compiler: { optimizeLevel: "z", locked: true },
+        paths: { contracts: "src/contracts", build: "out" },
+        alkanes: { rev: "abc123" },
+        dependencies: {
+          hex: "0.4",
+          serde: { version: "1.0", "default-features": false },
+          shared: { path: "crates/shared" },
+        },
+      })
+    );
+
+    const config = await loadConfig({ cwd: dir });
+
+    expect(config.configPath).toBe(path.join(dir, "alkali.config.json"));
+    expect(getNetworkConfig(config)).toEqual({
+      name: "testnet",
+      url: "https://testnet.example",
+      network: "testnet",
+      accounts: [key],
+    });
+    expect(config.networks.staging.network).toBe("regtest");
+    expect(config.networks.regtest.url).toBe("http://127.0.0.1:18888");
+    expect(config.compiler.optimizeLevel).toBe("z");
+    expect(config.compiler.locked).toBe(true);
+    expect(config.paths.contracts).toBe(path.join(dir, "src", "contracts"));
+    expect(config.paths.build).toBe(path.join(dir, "out"));
+    expect(config.alkanes).toEqual({ git: ALKANES_RS_GIT, rev: "abc123" });
+    expect(config.dependencies).toEqual({
+      hex: "0.4",
+      serde: { version: "1.0", "default-features": false },
+      shared: { path: path.join(dir, "crates", "shared") },
+    });
+    // Only the default repository has a default pin
+    expect(
+      resolveConfig({ alkanes: { git: "https://example.com/fork" } }, dir)
+        .alkanes
+    ).toEqual({ git: "https://example.com/fork" });
+    expect(
+      resolveConfig({ alkanes: { path: "vendor/alkanes-rs" } }, dir).alkanes
+    ).toEqual({
+      git: ALKANES_RS_GIT,
+      path: path.join(dir, "vendor", "alkanes-rs"),
+    });
+    expect(() => getNetworkConfig(config, "mainnet")).toThrow(
+      'No RPC url configured for network "mainnet" in alkali.config.json'
+    );
+  });
+
+  it("should load JavaScript and TypeScript configs", async () => {
+    await fs.writeFile(
+      path.join(dir, "alkali.config.js"),
+      'module.exports = { name: "js-project" };'
+    );
+    expect((await loadConfig({ cwd: dir })).name).toBe("js-project");
+
+    await fs.writeFile(
+      path.join(dir, "alkali.config.ts"),
+      `const level: 0 | 1 | 2 | 3 = 2;
+export default { name: "ts-project", compiler: { optimizeLevel: level } };`
+    );
+    const config = await loadConfig({ cwd: dir });
+    expect(config.configPath).toBe(path.join(dir, "alkali.config.ts"));
+    expect(config.name).toBe("ts-project");
+    expect(config.compiler.optimizeLevel).toBe(2);
+  });
+
+  it("should reject unknown keys and invalid values", () => {
+    const resolve = (config: unknown) =>
+      resolveConfig(config, dir, path.join(dir, "alkali.config.json"));
+
+    expect(() => resolve({ compiler: { optimiseLevel: 3 } })).toThrow(
+      'alkali.config.json: unknown key "compiler.optimiseLevel" (expected one of target, optimizeLevel, locked, offline)'
+    );
+    expect(() => resolve({ compiler: { optimizeLevel: 4 } })).toThrow(
+      'compiler.optimizeLevel must be one of 0, 1, 2, 3, "s", "z"'
+    );
+    expect(() => resolve({ networks: { testnet: { url: 1 } } })).toThrow(
+      "networks.testnet.url must be a string"
+    );
+    expect(() => resolve({ networks: { testnet: {} } })).toThrow(
+      "networks.testnet.url is required"
+    );
+    expect(() =>
+      resolve({ networks: { regtest: { url: "x", accounts: ["beef"] } } })
+    ).toThrow("networks.regtest.accounts[0] must be a 32-byte hex private key");
+    expect(() => resolve({ alkanes: { rev: "a", tag: "v1" } })).toThrow(
+      "alkanes may pin only one of rev, tag or branch, not rev and tag"
+    );
+    expect(() =>
+      resolve({ alkanes: { path: "../alkanes-rs", tag: "v1" } })
+    ).toThrow(
+      "alkanes.path is a local checkout and cannot be combined with tag"
+    );
+    expect(() => resolve({ dependencies: { serde: 1 } })).toThrow(
+      "dependencies.serde must be a string or an object"
+    );
+    expect(() =>
+      resolve({ dependencies: { serde: { "default-features": "no" } } })
+    ).toThrow("dependencies.serde.default-features must be a boolean");
+    expect(() => resolve([])).toThrow(ConfigError);
+  });
+});
@@ -0,0 +1,425 @@
+import { AlkaneId } from "../alkaneId";
+import {
+  decodeSegwitAddress,
+  fromHex,
+  getTxid,
+  parseTransaction,
+  tapLeafHash,
+  taprootSighash,
+} from "../bitcoin";
+import { AlkanesContract } from "../contract";
+import { Provider, SimulateResult } from "../provider";
+import { signerAddress } from "../envelope";
+import { decipherCellpack, decodeRunestone } from "../protostone";
+import {
+  getXOnlyPublicKey,
+  schnorrVerify,
+  tweakPublicKey,
+} from "../secp256k1";
+import { AlkanesABI } from "../types";
+import { embedAbi } from "../wasm";
+import { gunzipSync, gzipSync } from "zlib";
+
+const abi: AlkanesABI = {
+  name: "Token",
+  methods: [
+    {
+      opcode: 0,
+      name: "initialize",
+      inputs: [
+        { name: "token_units", type: "u128" },
+        { name: "value_per_mint", type: "u128" },
+      ],
+      outputs: [],
+    },
+    {
+      opcode: 77,
+      name: "mint",
+      inputs: [{ name: "amount", type: "u128" }],
+      outputs: [],
+    },
+    {
+      opcode: 99,
+      name: "name",
+      inputs: [],
+      outputs: [{ name: "name", type: "String" }],
+    },
+    {
+      opcode: 101,
+      name: "totalSupply",
+      inputs: [],
+      outputs: [{ name: "supply", type: "u128" }],
+    },
+  ],
+  storage: [],
+  opcodes: { initialize: 0, mint: 77, name: 99, totalSupply: 101 },
+};
+
+const wasm = new Uint8Array([0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00]);
+const privateKey = fromHex("42".repeat(32));
+const utxo = { txid: "ab".repeat(32), vout: 1, value: 100000n };
+
+describe("AlkanesContract", () => {
+  const newContract = () =>
+    new AlkanesContract({
+      abi,
+      bytecode: Buffer.from(wasm).toString("base64"),
+    });
+
+  describe("buildDeploy", () => {
+    const { transactions, alkaneId } = newContract().buildDeploy(
+      {
+        privateKey,
+        utxo,
+        feeRate: 2,
+        network: "regtest",
+        reservedNumber: 9n,
+      },
+      [1000n, 5n]
+    );
+    const { commit, reveal } = transactions;
+
+    it("should return the reserved AlkaneId", () => {
+      expect(alkaneId?.equals("4:9")).toBe(true);
+    });
+
+    it("should chain the reveal onto the commit output", () => {
+      expect(commit.inputs[0]).toMatchObject({ txid: utxo.txid, vout: 1 });
+      expect(reveal.inputs[0]).toMatchObject({
+        txid: transactions.commitTxid,
+        vout: 0,
+      });
+      const fees =
+        utxo.value -
+        commit.outputs.reduce((sum, output) => sum + output.value, 0n);
+      expect(fees).toBeGreaterThan(0n);
+      expect(commit.outputs[0].value).toBeGreaterThan(546n);
+    });
+
+    it("should sign both transactions", () => {
+      const signer = decodeSegwitAddress(
+        signerAddress(privateKey, "regtest"),
+        "regtest"
+      ).program;
+      const signerScript = new Uint8Array([0x51, 0x20, ...signer]);
+      const commitSighash = taprootSighash(commit, 0, [
+        { value: utxo.value, script: signerScript },
+      ]);
+      expect(
+        schnorrVerify(commit.inputs[0].witness![0], commitSighash, signer)
+      ).toBe(true);
+
+      const [signature, envelope] = reveal.inputs[0].witness!;
+      const revealSighash = taprootSighash(
+        reveal,
+        0,
+        [commit.outputs[0]],
+        tapLeafHash(envelope)
+      );
+      expect(
+        schnorrVerify(signature, revealSighash, getXOnlyPublicKey(privateKey))
+      ).toBe(true);
+    });
+
+    it("should commit to the envelope in the commit output", () => {
+      const [, envelope, controlBlock] = reveal.inputs[0].witness!;
+      const internalKey = controlBlock.subarray(1);
+      const { outputKey, parity } = tweakPublicKey(
+        internalKey,
+        tapLeafHash(envelope)
+      );
+      expect(commit.outputs[0].script.subarray(2)).toEqual(outputKey);
+      expect(controlBlock[0]).toBe(0xc0 | parity);
+    });
+
+    it("should inscribe the gzipped bytecode", () => {
+      const [, envelope] = reveal.inputs[0].witness!;
+      // <32-byte key> OP_CHECKSIG OP_FALSE OP_IF "BIN" OP_0 <push> OP_ENDIF
+      expect(Buffer.from(envelope.subarray(33, 41)).toString("hex")).toBe(
+        "ac00630342494e00"
+      );
+      const bodyLength = envelope[41];
+      const body = envelope.subarray(42, 42 + bodyLength);
+      expect(new Uint8Array(gunzipSync(body))).toEqual(wasm);
+      expect(envelope[envelope.length - 1]).toBe(0x68);
+    });
+
+    it("should target the deploy cellpack with initialize args", () => {
+      const [protostone] = decodeRunestone(reveal.outputs[1].script)
+        .protostones;
+      const words = decipherCellpack(protostone.message!);
+      expect(words.slice(0, 5)).toEqual([3n, 9n, 0n, 1000n, 5n]);
+      expect(protostone).toMatchObject({ pointer: 0, refund: 0 });
+    });
+  });
+
+  describe("deploy", () => {
+    it("should broadcast commit then reveal and address the contract", async () => {
+      const contract = newContract();
+      const broadcast = jest.fn(async (hex: string) => `txid-${hex.length}`);
+
+      const result = await contract.deploy(
+        {
+          privateKey,
+          utxo,
+          feeRate: 1,
+          network: "regtest",
+          broadcast,
+          resolveAlkaneId: async () => new AlkaneId(2, 17),
+        },
+        [1n, 2n]
+      );
+
+      expect(broadcast).toHaveBeenCalledTimes(2);
+      expect(result.alkaneId.toString()).toBe("2:17");
+      expect(result.revealTxid).toMatch(/^txid-/);
+
+      const [protostone] = decodeRunestone(contract.encodeCall("mint", [5n]))
+        .protostones;
+      expect(decipherCellpack(protostone.message!).slice(0, 4)).toEqual([
+        2n,
+        17n,
+        77n,
+        5n,
+      ]);
+    });
+
+    it("should require a way to learn the id of [1, 0] deployments", async () => {
+      await expect(
+        newContract().deploy(
+          {
+            privateKey,
+            utxo,
+            feeRate: 1,
+            network: "regtest",
+            broadcast: async () => "txid",
+          },
+          [1n, 2n]
+        )
+      ).rejects.toThrow("needs a provider or resolveAlkaneId");
+    });
+
+    it("should fail when the UTXO cannot cover fees", () => {
+      expect(() =>
+        newContract().buildDeploy(
+          {
+            privateKey,
+            utxo: { ...utxo, value: 1000n },
+            feeRate: 10,
+            network: "regtest",
+          },
+          [1n, 2n]
+        )
+      ).toThrow("Insufficient funds");
+    });
+  });
+
+  describe("fromAlkaneId", () => {
+    const minimal = new Uint8Array([0x00, 0x61, 0x73, 0x6d, 1, 0, 0, 0]);
+    const withBytecode = (bytecode: Uint8Array) =>
+      ({ getBytecode: jest.fn(async () => bytecode) } as unknown as Provider);
+
+    it("should load the ABI embedded in the deployed bytecode", async () => {
+      const provider = withBytecode(
+        new Uint8Array(gzipSync(embedAbi(minimal, abi)))
+      );
+
+      const contract = await AlkanesContract.fromAlkaneId("2:5", provider);
+
+      expect(provider.getBytecode).toHaveBeenCalledWith(new AlkaneId(2, 5));
+      expect(Object.keys(contract.read)).toContain("totalSupply");
+      const [protostone] = decodeRunestone(contract.encodeCall("mint", [1n]))
+        .protostones;
+      expect(decipherCellpack(protostone.message!).slice(0, 3)).toEqual([
+        2n,
+        5n,
+        77n,
+      ]);
+    });
+
+    it("should fail without bytecode or an embedded ABI", async () => {
+      await expect(
+        AlkanesContract.fromAlkaneId("2:5", withBytecode(new Uint8Array(0)))
+      ).rejects.toThrow("No bytecode found for 2:5");
+      await expect(
+        AlkanesContract.fromAlkaneId("2:5", withBytecode(minimal))
+      ).rejects.toThrow("has no alkali.abi section");
+    });
+  });
+
+  describe("method proxies", () => {
+    const deployed = () =>
+      new AlkanesContract({ abi, bytecode: "", address: new AlkaneId(2, 5) });
+
+    it("should generate a proxy per ABI method", () => {
+      const contract = deployed();
+      expect(Object.keys(contract.methods)).toEqual([
+        "initialize",
+        "mint",
+        "name",
+        "totalSupply",
+      ]);
+      expect(Object.keys(contract.read)).toEqual(Object.keys(contract.methods));
+    });
+
+    it("should sign and broadcast calls", async () => {
+      // The txid does not commit to the (randomized) signature
+      const broadcast = jest.fn(async (hex: string) =>
+        getTxid(parseTransaction(fromHex(hex)))
+      );
+      const contract = deployed();
+      const options = {
+        privateKey,
+        utxo,
+        feeRate: 2,
+        network: "regtest" as const,
+      };
+
+      const { transaction, txid } = contract.buildCall("mint", [5n], options);
+      expect(transaction.inputs[0]).toMatchObject({ txid: utxo.txid, vout: 1 });
+      const signer = decodeSegwitAddress(
+        signerAddress(privateKey, "regtest"),
+        "regtest"
+      ).program;
+      const sighash = taprootSighash(transaction, 0, [
+        { value: utxo.value, script: new Uint8Array([0x51, 0x20, ...signer]) },
+      ]);
+      expect(
+        schnorrVerify(transaction.inputs[0].witness![0], sighash, signer)
+      ).toBe(true);
+      expect(transaction.outputs[1].script).toEqual(
+        contract.encodeCall("mint", [5n])
+      );
+
+      expect(
+        await contract.call("mint", [5n], { ...options, broadcast })
+      ).toEqual({ txid });
+      expect(
+        await contract.methods.mint(5n, { ...options, broadcast })
+      ).toEqual({ txid });
+      expect(broadcast).toHaveBeenCalledTimes(2);
+      await expect(contract.methods.mint(5n)).rejects.toThrow(
+        "Method mint takes the call options after its 1 argument(s)"
+      );
+      await expect(contract.call("mint", [5n], options)).rejects.toThrow(
+        "Calling needs a provider or a broadcast function"
+      );
+    });
+
+    it("should accept the address in any AlkaneId form", async () => {
+      const expected = deployed().encodeCall("mint", [5n]);
+      for (const address of ["2:5", [2, 5] as [number, number]]) {
+        const contract = new AlkanesContract({ abi, bytecode: "", address });
+        expect(contract.encodeCall("mint", [5n])).toEqual(expected);
+      }
+    });
+
+    it("should validate argument count and types", async () => {
+      const contract = deployed();
+      await expect(contract.methods.mint()).rejects.toThrow(
+        "Method mint expects 1 argument(s), got 0"
+      );
+      await expect(contract.methods.mint("5")).rejects.toThrow(
+        "Invalid argument amount: expected u128, got string"
+      );
+      await expect(contract.methods.mint(1.5)).rejects.toThrow(
+        "expected u128, got number"
+      );
+      await expect(contract.methods.mint(-1n, {})).rejects.toThrow(
+        "Invalid argument amount: Value -1 out of range for u128"
+      );
+    });
+  });
+
+  describe("simulate", () => {
+    const simulating = (result: Partial<SimulateResult>) => {
+      const simulate = jest.fn(async () => ({
+        status: 0,
+        gasUsed: 900n,
+        response: { data: new Uint8Array(0), alkanes: { transfers: [] } },
+        ...result,
+      }));
+      const contract = new AlkanesContract({
+        abi,
+        bytecode: "",
+        address: new AlkaneId(2, 5),
+        provider: { simulate } as unknown as Provider,
+      });
+      return { contract, simulate };
+    };
+
+    it("should decode a raw String response", async () => {
+      const { contract, simulate } = simulating({
+        response: {
+          data: new TextEncoder().encode("Test Token"),
+          alkanes: { transfers: [] },
+        },
+      });
+
+      const result = await contract.simulate("name");
+
+      expect(simulate).toHaveBeenCalledWith({
+        target: new AlkaneId(2, 5),
+        inputs: [99n],
+      });
+      expect(result.value).toBe("Test Token");
+      expect(result.gasUsed).toBe(900n);
+    });
+
+    it("should decode integers and return transfers", async () => {
+      const supply = new Uint8Array(16);
+      supply[0] = 0x40;
+      supply[1] = 0x42;
+      supply[2] = 0x0f;
+      const transfers = [{ id: new AlkaneId(2, 5), value: 10n }];
+      const { contract } = simulating({
+        response: { data: supply, alkanes: { transfers } },
+      });
+
+      const result = await contract.simulate("totalSupply");
+
+      expect(result.value).toBe(1000000n);
+      expect(result.transfers).toEqual(transfers);
+    });
+
+    it("should leave the value undefined without declared outputs", async () => {
+      const { contract } = simulating({
+        response: { data: new Uint8Array([1, 2]), alkanes: { transfers: [] } },
+      });
+
+      const result = await contract.simulate("mint", [5n]);
+
+      expect(result.value).toBeUndefined();
+      expect(result.data).toEqual(new Uint8Array([1, 2]));
+    });
+
+    it("should fail on a reverted simulation", async () => {
+      const { contract } = simulating({ status: 1, error: "not enough" });
+
+      await expect(contract.simulate("totalSupply")).rejects.toThrow(
+        "Simulating totalSupply reverted: not enough"
+      );
+    });
+
+    it("should expose read proxies resolving to the value", async () => {
+      const { contract } = simulating({
+        response: {
+          data: new TextEncoder().encode("Test Token"),
+          alkanes: { transfers: [] },
+        },
+      });
+
+      expect(await contract.read.name()).toBe("Test Token");
+    });
+
+    it("should require a provider", async () => {
+      await expect(
+        new AlkanesContract({
+          abi,
+          bytecode: "",
+          address: new AlkaneId(2, 5),
+        }).simulate("name")
+      ).rejects.toThrow("no provider");
+    });
+  });
+});
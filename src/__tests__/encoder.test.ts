@@ -0,0 +1,333 @@
+import { AlkaneId } from "../alkaneId";
+import { AlkanesEncoder } from "../encoder";
+import { AlkanesMethod, AlkanesType } from "../types";
+
+describe("AlkanesEncoder", () => {
+  const encoder = new AlkanesEncoder();
+
+  const roundTrip = (type: AlkanesType, value: any) =>
+    encoder.decode(type, encoder.encode(type, value));
+
+  describe("primitives", () => {
+    it("should encode integers as fixed-width little-endian", () => {
+      expect(encoder.encode("u16", 0x0102)).toEqual(new Uint8Array([2, 1]));
+      expect(encoder.encode("u32", 1)).toEqual(new Uint8Array([1, 0, 0, 0]));
+      expect(encoder.encode("u64", 256n)).toEqual(
+        new Uint8Array([0, 1, 0, 0, 0, 0, 0, 0])
+      );
+      expect(encoder.encode("u128", 0n)).toHaveLength(16);
+      expect(encoder.encode("i8", -1)).toEqual(new Uint8Array([0xff]));
+      expect(encoder.encode("i16", -2)).toEqual(new Uint8Array([0xfe, 0xff]));
+    });
+
+    it("should round-trip unsigned integers", () => {
+      expect(roundTrip("u8", 255)).toBe(255);
+      expect(roundTrip("u16", 65535)).toBe(65535);
+      expect(roundTrip("u32", 4294967295)).toBe(4294967295);
+      expect(roundTrip("u64", 18446744073709551615n)).toBe(
+        18446744073709551615n
+      );
+      expect(roundTrip("u128", (1n << 128n) - 1n)).toBe((1n << 128n) - 1n);
+    });
+
+    it("should round-trip signed integers", () => {
+      expect(roundTrip("i8", -128)).toBe(-128);
+      expect(roundTrip("i16", -32768)).toBe(-32768);
+      expect(roundTrip("i32", -1)).toBe(-1);
+      expect(roundTrip("i64", -(1n << 63n))).toBe(-(1n << 63n));
+      expect(roundTrip("i128", (1n << 127n) - 1n)).toBe((1n << 127n) - 1n);
+      expect(roundTrip("i128", -42n)).toBe(-42n);
+    });
+
+    it("should reject out-of-range integers", () => {
+      expect(() => encoder.encode("u8", 256)).toThrow("out of range for u8");
+      expect(() => encoder.encode("u128", -1n)).toThrow("out of range");
+      expect(() => encoder.encode("i8", 128)).toThrow("out of range for i8");
+    });
+
+    it("should round-trip strings, bools and bytes", () => {
+      expect(roundTrip("String", "alkanes ⚗")).toBe("alkanes ⚗");
+      expect(roundTrip("bool", true)).toBe(true);
+      expect(roundTrip("bool", false)).toBe(false);
+      expect(roundTrip("Vec<u8>", new Uint8Array([1, 2, 3]))).toEqual(
+        new Uint8Array([1, 2, 3])
+      );
+    });
+
+    it("should reject invalid bool bytes", () => {
+      expect(() => encoder.decode("bool", new Uint8Array([2]))).toThrow(
+        "Invalid bool byte"
+      );
+    });
+  });
+
+  describe("complex types", () => {
+    it("should round-trip nested arrays", () => {
+      const type: AlkanesType = {
+        array: { type: { array: { type: "u16", length: 2 } }, length: 2 },
+      };
+      expect(
+        roundTrip(type, [
+          [1, 2],
+          [3, 4],
+        ])
+      ).toEqual([
+        [1, 2],
+        [3, 4],
+      ]);
+    });
+
+    it("should round-trip vecs of strings", () => {
+      const type: AlkanesType = { vec: { type: "String" } };
+      expect(roundTrip(type, ["a", "", "abc"])).toEqual(["a", "", "abc"]);
+      expect(roundTrip(type, [])).toEqual([]);
+    });
+
+    it("should round-trip tuples with mixed members", () => {
+      const type: AlkanesType = {
+        tuple: ["u128", "bool", { vec: { type: "i32" } }, "Vec<u8>"],
+      };
+      const value = [7n, true, [-1, 2], new Uint8Array([9])];
+      expect(roundTrip(type, value)).toEqual(value);
+    });
+  });
+
+  describe("decode errors", () => {
+    it("should fail on truncated data", () => {
+      expect(() => encoder.decode("u32", new Uint8Array([1, 2]))).toThrow(
+        "Unexpected end of data reading u32 at offset 0"
+      );
+      expect(() =>
+        encoder.decode("String", new Uint8Array([5, 0, 0, 0, 0x61]))
+      ).toThrow("need 5 byte(s), have 1");
+      expect(() =>
+        encoder.decode({ tuple: ["u8", "u16"] }, new Uint8Array([1, 2]))
+      ).toThrow("reading u16 at offset 1");
+    });
+
+    it("should fail on trailing bytes", () => {
+      expect(() => encoder.decode("u8", new Uint8Array([1, 2]))).toThrow(
+        "Unexpected 1 trailing byte(s)"
+      );
+    });
+  });
+
+  describe("validateArgs", () => {
+    const method: AlkanesMethod = {
+      opcode: 5,
+      name: "configure",
+      inputs: [
+        { name: "enabled", type: "bool" },
+        { name: "owners", type: { vec: { type: "u64" } } },
+        { name: "label", type: { tuple: ["String", "Vec<u8>"] } },
+      ],
+      outputs: [],
+    };
+
+    it("should accept matching values", () => {
+      expect(() =>
+        encoder.validateArgs(method, [
+          true,
+          [1, 2n],
+          ["x", new Uint8Array(0)],
+        ])
+      ).not.toThrow();
+    });
+
+    it("should reject mismatched values without coercing", () => {
+      expect(() =>
+        encoder.validateArgs(method, [1, [], ["x", new Uint8Array(0)]])
+      ).toThrow("Invalid argument enabled: expected bool, got number");
+      expect(() =>
+        encoder.validateArgs(method, [
+          true,
+          [1, "2"],
+          ["x", new Uint8Array(0)],
+        ])
+      ).toThrow("Invalid argument owners: expected u64, got string");
+      expect(() =>
+        encoder.validateArgs(method, [true, [], ["x", [1]]])
+      ).toThrow("Invalid argument label: expected Vec<u8>, got array");
+    });
+  });
+
+  describe("parseArgs", () => {
+    const method: AlkanesMethod = {
+      opcode: 6,
+      name: "setup",
+      inputs: [
+        { name: "enabled", type: "bool" },
+        { name: "supply", type: "u128" },
+        { name: "offset", type: "i32" },
+        { name: "salt", type: "Vec<u8>" },
+        { name: "pairs", type: { vec: { type: { tuple: ["u64", "bool"] } } } },
+      ],
+      outputs: [],
+    };
+    const args = ["false", "0x10", "-3", "0xbeef", '[[1, true], ["2", false]]'];
+
+    it("should convert arguments by their input types", () => {
+      const parsed = encoder.parseArgs(method, args);
+      expect(parsed).toEqual([
+        false,
+        16n,
+        -3n,
+        new Uint8Array([0xbe, 0xef]),
+        [
+          [1n, true],
+          [2n, false],
+        ],
+      ]);
+      expect(() => encoder.validateArgs(method, parsed)).not.toThrow();
+      expect(encoder.encodeWords("bool", parsed[0])).toEqual([0n]);
+    });
+
+    it("should reject arguments that do not fit their types", () => {
+      const replaced = (index: number, arg: string) =>
+        args.map((original, i) => (i === index ? arg : original));
+      expect(() => encoder.parseArgs(method, replaced(0, "no"))).toThrow(
+        "Invalid argument enabled: expected bool, got no"
+      );
+      expect(() => encoder.parseArgs(method, replaced(1, "-1"))).toThrow(
+        "Invalid argument supply: expected u128, got -1"
+      );
+      expect(() => encoder.parseArgs(method, replaced(3, "0xbee"))).toThrow(
+        "expected Vec<u8>, got 0xbee"
+      );
+      expect(() => encoder.parseArgs(method, replaced(4, "[[1, 1]]"))).toThrow(
+        "Invalid argument pairs: expected"
+      );
+      expect(() => encoder.parseArgs(method, [])).toThrow(
+        "Method setup expects 5 argument(s), got 0"
+      );
+      expect(() => encoder.encodeWords("bool", "false")).toThrow(
+        "Expected boolean for bool"
+      );
+    });
+  });
+
+  describe("decodeResponse", () => {
+    it("should read a trailing String or Vec<u8> as raw bytes", () => {
+      const data = new Uint8Array([7, 0x68, 0x69]);
+      expect(
+        encoder.decodeResponse(
+          [
+            { name: "decimals", type: "u8" },
+            { name: "name", type: "String" },
+          ],
+          data
+        )
+      ).toEqual([7, "hi"]);
+      expect(
+        encoder.decodeResponse([{ name: "data", type: "Vec<u8>" }], data)
+      ).toEqual([data]);
+    });
+
+    it("should ignore data when no outputs are declared", () => {
+      expect(encoder.decodeResponse([], new Uint8Array([1, 2, 3]))).toEqual(
+        []
+      );
+    });
+
+    it("should name the output that fails to decode", () => {
+      expect(() =>
+        encoder.decodeResponse(
+          [{ name: "supply", type: "u128" }],
+          new Uint8Array(4)
+        )
+      ).toThrow("Invalid output supply: Unexpected end of data");
+    });
+  });
+
+  describe("cellpack words", () => {
+    const wordTrip = (type: AlkanesType, value: any) =>
+      encoder.decodeWords(type, encoder.encodeWords(type, value));
+
+    it("should encode integers as single u128 words", () => {
+      expect(encoder.encodeWords("u128", 42n)).toEqual([42n]);
+      expect(encoder.encodeWords("u8", 7)).toEqual([7n]);
+      expect(encoder.encodeWords("bool", true)).toEqual([1n]);
+      expect(encoder.encodeWords("i8", -1)).toEqual([(1n << 128n) - 1n]);
+      expect(wordTrip("i64", -5n)).toBe(-5n);
+      expect(wordTrip("u32", 123)).toBe(123);
+    });
+
+    it("should pack strings little-endian and null-terminated", () => {
+      expect(encoder.encodeWords("String", "AB")).toEqual([0x4241n]);
+      expect(encoder.encodeWords("String", "a".repeat(16))).toEqual([
+        0x61616161616161616161616161616161n,
+        0n,
+      ]);
+      expect(wordTrip("String", "a longer token name")).toBe(
+        "a longer token name"
+      );
+    });
+
+    it("should length-prefix byte vectors", () => {
+      const bytes = new Uint8Array(17).map((_, i) => i + 1);
+      const words = encoder.encodeWords("Vec<u8>", bytes);
+      expect(words).toHaveLength(3);
+      expect(words[0]).toBe(17n);
+      expect(words[2]).toBe(17n);
+      expect(encoder.decodeWords("Vec<u8>", words)).toEqual(bytes);
+    });
+
+    it("should round-trip complex types", () => {
+      const type: AlkanesType = {
+        tuple: [
+          { vec: { type: "u128" } },
+          { array: { type: "bool", length: 2 } },
+        ],
+      };
+      expect(encoder.encodeWords(type, [[5n, 6n], [true, false]])).toEqual([
+        2n,
+        5n,
+        6n,
+        1n,
+        0n,
+      ]);
+      expect(wordTrip(type, [[5n, 6n], [true, false]])).toEqual([
+        [5n, 6n],
+        [true, false],
+      ]);
+    });
+
+    it("should reject malformed words", () => {
+      expect(() => encoder.decodeWords("u8", [256n])).toThrow(
+        "out of range for u8"
+      );
+      expect(() => encoder.decodeWords("Vec<u8>", [40n, 1n])).toThrow(
+        "exceeds remaining inputs"
+      );
+      expect(() =>
+        encoder.decodeWords("String", [0x61616161616161616161616161616161n])
+      ).toThrow("Unexpected end of inputs reading String at word 1");
+      expect(() => encoder.decodeWords("u8", [1n, 2n])).toThrow(
+        "1 trailing word(s)"
+      );
+    });
+
+    it("should build the cellpack for a method call", () => {
+      const target = new AlkaneId(2, 1);
+      const mint: AlkanesMethod = {
+        opcode: 77,
+        name: "mint",
+        inputs: [
+          { name: "amount", type: "u128" },
+          { name: "memo", type: "String" },
+        ],
+        outputs: [],
+      };
+
+      expect(
+        encoder.encodeCellpack(target, mint, [1000n, "hi"])
+      ).toEqual([2n, 1n, 77n, 1000n, 0x6968n]);
+      expect(() =>
+        encoder.encodeCellpack(target, mint, [1n])
+      ).toThrow("Method mint expects 2 argument(s), got 1");
+      expect(() =>
+        encoder.encodeCellpack(target, mint, [-1n, "x"])
+      ).toThrow("Invalid argument amount: Value -1 out of range for u128");
+    });
+  });
+});
@@ -0,0 +1,185 @@
+import { AlkanesABI } from "../../types";
+
+// Hand-assembled contract speaking the alkanes host ABI:
+//   0: stores inputs[1] (a u128) under "/n"
+//   1: returns the value under "/n"
+//   2: logs "boom" and traps
+//   3: calls opcode 1 of the alkane [inputs[1], inputs[2]], returning its data
+//   4: calls opcode 2 of [inputs[1], inputs[2]], succeeding although it reverts
+//   5: loops forever
+
+const leb = (n: number, signed = false): number[] => {
+  const bytes: number[] = [];
+  for (;;) {
+    const byte = n & 0x7f;
+    n >>= 7;
+    const done = signed
+      ? (n === 0 && !(byte & 0x40)) || (n === -1 && byte & 0x40)
+      : n === 0;
+    bytes.push(done ? byte : byte | 0x80);
+    if (done) return bytes;
+  }
+};
+const vec = (items: number[][]) => [...leb(items.length), ...items.flat()];
+const name = (text: string) => vec([...Buffer.from(text)].map((b) => [b]));
+const section = (id: number, body: number[]) => [
+  id,
+  ...leb(body.length),
+  ...body,
+];
+const i32 = (n: number) => [0x41, ...leb(n, true)];
+const u32le = (n: number) => [n & 0xff, (n >> 8) & 0xff, 0, 0];
+
+const [I32, I64] = [0x7f, 0x7e];
+const imports = [
+  ["__request_context", 0],
+  ["__load_context", 1],
+  ["__request_storage", 1],
+  ["__load_storage", 2],
+  ["__log", 3],
+  ["__call", 4],
+  ["__returndatacopy", 3],
+] as const;
+const call = (importName: string) => [
+  0x10,
+  imports.findIndex(([field]) => field === importName),
+];
+const load64 = [0x29, 3, 0];
+const store64 = [0x37, 3, 0];
+const store32 = [0x36, 2, 0];
+
+// prettier-ignore
+const body = [
+  ...[0x02, 0x01, I64, 0x01, I32], // locals: opcode, call result
+  ...i32(0x1000), ...call("__request_context"), ...store32,
+  ...i32(0x1004), ...call("__load_context"), 0x1a,
+  ...i32(0x1064), ...load64, 0x21, 0,
+  // 0: copy inputs[1] into the storage map of the prepared response
+  0x20, 0, 0x50, 0x04, 0x40,
+  ...i32(0x2022), ...i32(0x1074), ...load64, ...store64,
+  ...i32(0x202a), ...i32(0x107c), ...load64, ...store64,
+  ...i32(0x2004), 0x0f,
+  0x0b,
+  // 1: load "/n" into the response data
+  0x20, 0, 0x42, 1, 0x51, 0x04, 0x40,
+  ...i32(0x3000), ...i32(0x104), ...call("__request_storage"),
+  ...i32(20), 0x6a, ...store32,
+  ...i32(0x104), ...i32(0x3018), ...call("__load_storage"), 0x1a,
+  ...i32(0x3004), 0x0f,
+  0x0b,
+  // 3: call [inputs[1], inputs[2]] and forward its data
+  0x20, 0, 0x42, 3, 0x51, 0x04, 0x40,
+  ...i32(0x5004), ...i32(0x1074), ...load64, ...store64,
+  ...i32(0x5014), ...i32(0x1084), ...load64, ...store64,
+  ...i32(0x5004), ...i32(0x5104), ...i32(0x5204), 0x42, 0,
+  ...call("__call"), 0x21, 1,
+  ...i32(0x7004), ...call("__returndatacopy"),
+  ...i32(0x6018), ...i32(0x7014), 0x20, 1, ...i32(16), 0x6b,
+  ...[0xfc, 0x0a, 0, 0],
+  ...i32(0x6000), 0x20, 1, ...i32(4), 0x6a, ...store32,
+  ...i32(0x6004), 0x0f,
+  0x0b,
+  // 4: call [inputs[1], inputs[2]] with opcode 2 and ignore the revert
+  0x20, 0, 0x42, 4, 0x51, 0x04, 0x40,
+  ...i32(0x5304), ...i32(0x1074), ...load64, ...store64,
+  ...i32(0x5314), ...i32(0x1084), ...load64, ...store64,
+  ...i32(0x5304), ...i32(0x5104), ...i32(0x5204), 0x42, 0,
+  ...call("__call"), 0x1a,
+  ...i32(0x5404), 0x0f,
+  0x0b,
+  // 5: loop forever
+  0x20, 0, 0x42, 5, 0x51, 0x04, 0x40,
+  0x03, 0x40, 0x0c, 0, 0x0b,
+  0x0b,
+  // 2 and anything else
+  ...i32(0x404), ...call("__log"),
+  0x00, 0x0b,
+];
+
+const data: Array<[number, number[]]> = [
+  [0x100, [...u32le(2), ...Buffer.from("/n")]],
+  [0x400, [...u32le(4), ...Buffer.from("boom")]],
+  // ExtendedCallResponse: no transfers, one storage entry, no data
+  [0x2000, [...u32le(46), ...new Array(16).fill(0), ...u32le(1)]],
+  [0x2018, [...u32le(2), ...Buffer.from("/n"), ...u32le(16)]],
+  [0x5000, u32le(48)],
+  [0x5024, [1]],
+  [0x5100, u32le(16)],
+  [0x5200, u32le(4)],
+  [0x5300, u32le(48)],
+  [0x5324, [2]],
+  // ExtendedCallResponse: no transfers, no storage, no data
+  [0x5400, u32le(20)],
+];
+
+export const counterWasm = new Uint8Array([
+  ...[0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00],
+  ...section(
+    1,
+    vec([
+      [0x60, 0, 1, I32],
+      [0x60, 1, I32, 1, I32],
+      [0x60, 2, I32, I32, 1, I32],
+      [0x60, 1, I32, 0],
+      [0x60, 4, I32, I32, I32, I64, 1, I32],
+    ])
+  ),
+  ...section(
+    2,
+    vec(
+      imports.map(([field, type]) => [
+        ...name("env"),
+        ...name(field),
+        0,
+        type,
+      ])
+    )
+  ),
+  ...section(3, vec([[0]])),
+  ...section(5, vec([[0x00, 1]])),
+  ...section(
+    7,
+    vec([
+      [...name("memory"), 0x02, 0],
+      [...name("__execute"), 0x00, imports.length],
+    ])
+  ),
+  ...section(10, vec([[...leb(body.length), ...body]])),
+  ...section(
+    11,
+    vec(
+      data.map(([offset, bytes]) => [
+        0,
+        ...i32(offset),
+        0x0b,
+        ...vec(bytes.map((b) => [b])),
+      ])
+    )
+  ),
+]);
+
+export const u128le = (value: bigint) =>
+  Array.from({ length: 16 }, (_, i) =>
+    Number((value >> BigInt(8 * i)) & 0xffn)
+  );
+
+export const counterAbi: AlkanesABI = {
+  name: "Counter",
+  methods: [
+    {
+      opcode: 0,
+      name: "initialize",
+      inputs: [{ name: "value", type: "u128" }],
+      outputs: [],
+    },
+    {
+      opcode: 1,
+      name: "get",
+      inputs: [],
+      outputs: [{ name: "value", type: "u128" }],
+    },
+    { opcode: 2, name: "fail", inputs: [], outputs: [] },
+  ],
+  storage: [{ key: "/n", type: "u128" }],
+  opcodes: { initialize: 0, get: 1, fail: 2 },
+};
@@ -0,0 +1,21 @@
+// Hand-assembled stand-in for the template's Example contract: every call
+// returns "Example" as its data, with no transfers or storage writes
+
+// ExtendedCallResponse: no transfers, no storage, then the data
+const response = [...new Array(20).fill(0), ...Buffer.from("Example")];
+
+// prettier-ignore
+export const exampleWasm = new Uint8Array([
+  ...[0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00],
+  ...[0x01, 0x05, 0x01, 0x60, 0x00, 0x01, 0x7f], // type: () -> i32
+  ...[0x03, 0x02, 0x01, 0x00], // function
+  ...[0x05, 0x03, 0x01, 0x00, 0x01], // memory: one page
+  // exports: memory, __execute
+  ...[0x07, 0x16, 0x02, 0x06, ...Buffer.from("memory"), 0x02, 0x00],
+  ...[0x09, ...Buffer.from("__execute"), 0x00, 0x00],
+  // code: return the response past its length
+  ...[0x0a, 0x07, 0x01, 0x05, 0x00, 0x41, 0x84, 0x02, 0x0b],
+  // data: the response at 0x100, after its length
+  ...[0x0b, 11 + response.length, 0x01, 0x00, 0x41, 0x80, 0x02, 0x0b],
+  ...[4 + response.length, response.length, 0, 0, 0, ...response],
+]);
@@ -0,0 +1,117 @@
+import { AddressInfo } from "net";
+import { Server } from "http";
+import { AlkaneId } from "../alkaneId";
+import { fromHex } from "../bitcoin";
+import { AlkanesContract } from "../contract";
+import { MockChain, devAccounts, serveJsonRpc } from "../node";
+import { JsonRpcError, JsonRpcProvider } from "../provider";
+import { embedAbi } from "../wasm";
+import { counterAbi, counterWasm } from "./fixtures/counter";
+
+describe("MockChain", () => {
+  let server: Server;
+
+  const start = async (chain: MockChain) => {
+    await chain.start();
+    server = await serveJsonRpc(
+      (method, params) => chain.handle(method, params),
+      0
+    );
+    const { port } = server.address() as AddressInfo;
+    return new JsonRpcProvider(`http://127.0.0.1:${port}`);
+  };
+
+  const counter = (provider: JsonRpcProvider) =>
+    new AlkanesContract({
+      abi: counterAbi,
+      bytecode: Buffer.from(embedAbi(counterWasm, counterAbi)).toString(
+        "base64"
+      ),
+      provider,
+    });
+
+  afterEach(() => {
+    server?.close();
+  });
+
+  it("should derive the same funded dev accounts every run", async () => {
+    const chain = new MockChain({ accounts: 2 });
+    await chain.start();
+
+    expect(chain.accounts.map(({ address }) => address)).toEqual(
+      devAccounts(2, "regtest").map(({ address }) => address)
+    );
+    expect(chain.accounts[0].address).toMatch(/^bcrt1p/);
+    expect(chain.accounts[1].utxo).toEqual({
+      txid: chain.accounts[0].utxo!.txid,
+      vout: 1,
+      value: 5_000_000_000n,
+    });
+    expect(chain.height).toBe(1);
+  });
+
+  it("should deploy and serve contracts over JSON-RPC", async () => {
+    const chain = new MockChain();
+    const provider = await start(chain);
+    const [account] = chain.accounts;
+    const contract = counter(provider);
+
+    const result = await contract.deploy(
+      {
+        privateKey: fromHex(account.privateKey),
+        utxo: account.utxo!,
+        feeRate: 1,
+        network: "regtest",
+      },
+      [42n]
+    );
+
+    expect(result.alkaneId).toEqual(new AlkaneId(2, 0));
+    expect(await contract.read.get()).toBe(42n);
+    expect(await contract.storage.get("/n")).toBe(42n);
+    const keys = await provider.getStorageKeys(
+      new AlkaneId(2, 0),
+      new TextEncoder().encode("/")
+    );
+    expect(keys.map((key) => Buffer.from(key).toString())).toContain("/n");
+    // The commit and the reveal were each mined
+    expect(await provider.getBlockCount()).toBe(3);
+    expect(await provider.getIndexerHeight()).toBe(3);
+
+    const deployed = await AlkanesContract.fromAlkaneId([2, 0], provider);
+    expect(await deployed.read.get()).toBe(42n);
+  });
+
+  it("should mine on demand and reject double spends", async () => {
+    const chain = new MockChain({ autoMine: false });
+    const provider = await start(chain);
+    const [account] = chain.accounts;
+    const { transactions } = counter(provider).buildDeploy(
+      {
+        privateKey: fromHex(account.privateKey),
+        utxo: account.utxo!,
+        feeRate: 1,
+        network: "regtest",
+        reservedNumber: 9n,
+      },
+      [7n]
+    );
+
+    await provider.sendRawTransaction(transactions.commitHex);
+    await provider.sendRawTransaction(transactions.revealHex);
+    const vout = transactions.reveal.outputs.length + 1;
+    expect(await provider.trace(transactions.revealTxid, vout)).toEqual([]);
+
+    await provider.request("btc_generatetoaddress", [1, account.address]);
+    const events = await provider.trace(transactions.revealTxid, vout);
+    expect(events[0]).toEqual({
+      event: "create",
+      data: { block: "4", tx: "9" },
+    });
+    expect(events[events.length - 1].data.status).toBe("success");
+
+    await expect(
+      provider.sendRawTransaction(transactions.commitHex)
+    ).rejects.toThrow(JsonRpcError);
+  });
+});
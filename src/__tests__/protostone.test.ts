@@ -0,0 +1,151 @@
+import {
+  ALKANES_PROTOCOL_TAG,
+  decipherCellpack,
+  decodeRunestone,
+  decodeVarints,
+  encipherCellpack,
+  encodeCellpackRunestone,
+  encodeProtostones,
+  encodeRunestone,
+  encodeVarint,
+  encodeVarints,
+} from "../protostone";
+
+const hex = (bytes: Uint8Array) => Buffer.from(bytes).toString("hex");
+const fromHex = (str: string) => new Uint8Array(Buffer.from(str, "hex"));
+
+describe("protostone", () => {
+  describe("varints", () => {
+    it("should LEB128-encode u128 values", () => {
+      expect(hex(encodeVarint(0n))).toBe("00");
+      expect(hex(encodeVarint(127n))).toBe("7f");
+      expect(hex(encodeVarint(128n))).toBe("8001");
+      expect(hex(encodeVarint(16383n))).toBe("ff7f");
+      expect(encodeVarint((1n << 128n) - 1n)).toHaveLength(19);
+      expect(() => encodeVarint(1n << 128n)).toThrow("is not a u128");
+    });
+
+    it("should decode what it encodes", () => {
+      const values = [0n, 1n, 300n, (1n << 128n) - 1n];
+      expect(decodeVarints(encipherCellpack(values))).toEqual(values);
+      expect(() => decodeVarints(fromHex("80"))).toThrow("Truncated varint");
+    });
+  });
+
+  // Worked by hand from the protorune layout, not taken from alkanes-rs or
+  // a mined transaction: they pin each layer, and catch regressions
+  describe("layout vectors", () => {
+    it("should lay out a mint call layer by layer", () => {
+      // Cellpack [2, 1, 77, 1000] as LEB128 varints
+      const message = encipherCellpack([2n, 1n, 77n, 1000n]);
+      expect(hex(message)).toBe("02014de807");
+      // Protocol tag 1, 6 values: the message as one little-endian u128
+      // chunk (tag 81), pointer 0 (tag 91) and refund 0 (tag 93)
+      const protostone = encodeProtostones([
+        { protocolTag: 1n, message, pointer: 0, refund: 0 },
+      ]);
+      expect(protostone).toEqual([
+        1n, 6n, 81n, 0x07e84d0102n, 91n, 0n, 93n, 0n,
+      ]);
+      const stream = encodeVarints(protostone);
+      expect(hex(stream)).toBe("0106518282b4c27e5b005d00");
+      // The 12 bytes fit one u128 under the Protocol tag (16383), pushed
+      // after OP_RETURN OP_13
+      const chunk = BigInt(`0x${hex(stream.slice().reverse())}`);
+      expect(hex(encodeVarints([16383n, chunk]))).toBe(
+        "ff7f818cc492a890ade1feb681e805"
+      );
+    });
+
+    it("should encode a mint call", () => {
+      const script = encodeCellpackRunestone([2n, 1n, 77n, 1000n]);
+      expect(hex(script)).toBe("6a5d0fff7f818cc492a890ade1feb681e805");
+    });
+
+    it("should split long messages into 15-byte chunks", () => {
+      const cellpack = [3n, 797n, 101n];
+      for (let i = 1n; i <= 16n; i++) {
+        cellpack.push(i);
+      }
+      const script = encodeCellpackRunestone(cellpack);
+      expect(hex(script)).toBe(
+        "6a5d2aff7f8190c49aa8d7a6d49681838ec8d0a2ccb801ff7f80c38aacb8a194c69af1e287a8e096805dff7f00"
+      );
+    });
+
+    // Still owed: the OP_RETURN of a mined alkanes call, or a vector from
+    // the alkanes-rs tests, cited by txid or file and decoded here. Until
+    // then the vectors above only agree with this encoder's reading of the
+    // layout.
+    it.todo("should decode the protostone of a mined alkanes transaction");
+  });
+
+  describe("round trip", () => {
+    it("should parse back the protostone of a call", () => {
+      const cellpack = [2n, 1n, 77n, 1000n];
+      const runestone = decodeRunestone(
+        encodeCellpackRunestone(cellpack, { pointer: 1, refund: 2 })
+      );
+
+      expect(runestone.protostones).toHaveLength(1);
+      const [protostone] = runestone.protostones;
+      expect(protostone).toMatchObject({
+        protocolTag: ALKANES_PROTOCOL_TAG,
+        pointer: 1,
+        refund: 2,
+      });
+      // The final 15-byte chunk is zero padded, exactly as the indexer sees it
+      const words = decipherCellpack(protostone.message!);
+      expect(words.slice(0, cellpack.length)).toEqual(cellpack);
+      expect(words.slice(cellpack.length).every((w) => w === 0n)).toBe(true);
+    });
+
+    it("should round-trip edicts, pointers and burns", () => {
+      const runestone = {
+        pointer: 3,
+        edicts: [
+          { id: { block: 2n, tx: 5n }, amount: 10n, output: 1 },
+          { id: { block: 2n, tx: 1n }, amount: 20n, output: 0 },
+          { id: { block: 840000n, tx: 7n }, amount: 1n, output: 2 },
+        ],
+        protostones: [
+          {
+            protocolTag: ALKANES_PROTOCOL_TAG,
+            burn: 4,
+            pointer: 0,
+            edicts: [{ id: { block: 2n, tx: 1n }, amount: 5n, output: 0 }],
+          },
+          { protocolTag: 7n, refund: 1 },
+        ],
+      };
+
+      expect(decodeRunestone(encodeRunestone(runestone))).toEqual({
+        ...runestone,
+        // Edicts come back in the canonical sorted order
+        edicts: [runestone.edicts[1], runestone.edicts[0], runestone.edicts[2]],
+      });
+    });
+
+    it("should use multiple data pushes for large payloads", () => {
+      const cellpack = Array.from({ length: 200 }, () => (1n << 127n) + 1n);
+      const script = encodeCellpackRunestone(cellpack);
+      // OP_RETURN OP_13 OP_PUSHDATA2 <520 bytes> ...
+      expect(script[2]).toBe(0x4d);
+      expect(script[3] | (script[4] << 8)).toBe(520);
+
+      const [protostone] = decodeRunestone(script).protostones;
+      expect(decipherCellpack(protostone.message!).slice(0, 200)).toEqual(
+        cellpack
+      );
+    });
+
+    it("should reject scripts that are not runestones", () => {
+      expect(() => decodeRunestone(fromHex("6a0100"))).toThrow(
+        "not a runestone"
+      );
+      expect(() => decodeRunestone(fromHex("6a5d05ff7f"))).toThrow(
+        "Truncated data push"
+      );
+    });
+  });
+});
@@ -0,0 +1,220 @@
+import fs from "fs/promises";
+import http from "http";
+import { AddressInfo } from "net";
+import os from "os";
+import path from "path";
+import { AlkaneId } from "../alkaneId";
+import {
+  JsonRpcError,
+  JsonRpcProvider,
+  Provider,
+  TraceEvent,
+  resolveDeployedId,
+} from "../provider";
+
+interface RpcCall {
+  path: string;
+  method: string;
+  params: any[];
+}
+
+describe("JsonRpcProvider", () => {
+  let server: http.Server;
+  let url: string;
+  let calls: RpcCall[];
+  let results: Record<string, any>;
+
+  beforeAll(async () => {
+    server = http.createServer((req, res) => {
+      let body = "";
+      req.on("data", (chunk) => (body += chunk));
+      req.on("end", () => {
+        const { id, method, params } = JSON.parse(body);
+        calls.push({ path: req.url ?? "", method, params });
+        const payload =
+          method in results
+            ? { jsonrpc: "2.0", id, result: results[method] }
+            : {
+                jsonrpc: "2.0",
+                id,
+                error: { code: -32601, message: "Method not found" },
+              };
+        res.setHeader("content-type", "application/json");
+        res.end(JSON.stringify(payload));
+      });
+    });
+    await new Promise<void>((resolve) => server.listen(0, resolve));
+    url = `http://127.0.0.1:${(server.address() as AddressInfo).port}`;
+  });
+
+  afterAll(async () => {
+    await new Promise((resolve) => server.close(resolve));
+  });
+
+  beforeEach(() => {
+    calls = [];
+    results = {};
+  });
+
+  it("should simulate a cellpack and parse the response", async () => {
+    results.alkanes_simulate = {
+      status: 0,
+      gasUsed: 1200,
+      execution: {
+        data: "0x2a000000",
+        alkanes: [{ id: { block: "2", tx: "1" }, value: "500" }],
+        error: null,
+      },
+    };
+
+    const provider = new JsonRpcProvider(url);
+    const result = await provider.simulate({
+      target: new AlkaneId(2, 1),
+      inputs: [99n, 2n ** 100n],
+    });
+
+    expect(calls[0].method).toBe("alkanes_simulate");
+    expect(calls[0].params[0].target).toEqual({ block: "2", tx: "1" });
+    expect(calls[0].params[0].inputs).toEqual(["99", (2n ** 100n).toString()]);
+    expect(result.status).toBe(0);
+    expect(result.gasUsed).toBe(1200n);
+    expect(Array.from(result.response.data)).toEqual([42, 0, 0, 0]);
+    expect(result.response.alkanes.transfers).toEqual([
+      { id: new AlkaneId(2, 1), value: 500n },
+    ]);
+    expect(result.error).toBeUndefined();
+  });
+
+  it("should fetch bytecode and protorune balances", async () => {
+    results.alkanes_getbytecode = "0x0061736d";
+    results.alkanes_protorunesbyaddress = {
+      outpoints: [
+        {
+          outpoint: { txid: "ab".repeat(32), vout: 1 },
+          output: { value: 546 },
+          height: 880000,
+          runes: [{ rune: { id: { block: "4", tx: "7" } }, balance: "1000" }],
+        },
+      ],
+    };
+
+    const provider = new JsonRpcProvider({ url });
+    const bytecode = await provider.getBytecode(new AlkaneId(4, 7));
+    const outpoints = await provider.getProtorunesByAddress("bcrt1qxyz");
+
+    expect(Array.from(bytecode)).toEqual([0x00, 0x61, 0x73, 0x6d]);
+    expect(calls[1].params).toEqual([
+      { address: "bcrt1qxyz", protocolTag: "1" },
+    ]);
+    expect(outpoints).toEqual([
+      {
+        txid: "ab".repeat(32),
+        vout: 1,
+        value: 546n,
+        height: 880000,
+        balances: [{ id: new AlkaneId(4, 7), value: 1000n }],
+      },
+    ]);
+  });
+
+  it("should read raw storage slots", async () => {
+    results.alkanes_getstorageat = "0x0a00";
+
+    const provider = new JsonRpcProvider(url);
+    const value = await provider.getStorageAt(
+      new AlkaneId(2, 1),
+      new TextEncoder().encode("/supply")
+    );
+
+    expect(calls[0].params).toEqual([
+      { id: { block: "2", tx: "1" }, path: "0x2f737570706c79" },
+    ]);
+    expect(Array.from(value)).toEqual([10, 0]);
+  });
+
+  it("should route bitcoin calls to btc_ methods or bitcoinUrl", async () => {
+    results.btc_sendrawtransaction = "cd".repeat(32);
+    results.getblockcount = 150;
+
+    const gateway = new JsonRpcProvider(url);
+    expect(await gateway.sendRawTransaction("0200")).toBe("cd".repeat(32));
+    expect(calls[0]).toEqual({
+      path: "/",
+      method: "btc_sendrawtransaction",
+      params: ["0200"],
+    });
+
+    const split = new JsonRpcProvider({ url, bitcoinUrl: `${url}/bitcoind` });
+    expect(await split.getBlockCount()).toBe(150);
+    expect(calls[1].path).toBe("/bitcoind");
+    expect(calls[1].method).toBe("getblockcount");
+  });
+
+  it("should surface JSON-RPC errors", async () => {
+    const provider = new JsonRpcProvider(url);
+
+    await expect(provider.getIndexerHeight()).rejects.toThrow(JsonRpcError);
+    await expect(provider.getIndexerHeight()).rejects.toThrow(
+      "metashrew_height failed (-32601): Method not found"
+    );
+  });
+
+  it("should load the network url from alkali.config.json", async () => {
+    const dir = await fs.mkdtemp(path.join(os.tmpdir(), "alkali-provider-"));
+    const configPath = path.join(dir, "alkali.config.json");
+    await fs.writeFile(
+      configPath,
+      JSON.stringify({ network: "regtest", networks: { regtest: { url } } })
+    );
+    results.metashrew_height = "321";
+
+    const provider = await JsonRpcProvider.fromConfig(configPath);
+    expect(await provider.getIndexerHeight()).toBe(321);
+    await expect(
+      JsonRpcProvider.fromConfig(configPath, "mainnet")
+    ).rejects.toThrow('No RPC url configured for network "mainnet"');
+
+    await fs.rm(dir, { recursive: true, force: true });
+  });
+});
+
+describe("resolveDeployedId", () => {
+  const tracing = (traces: TraceEvent[][]) =>
+    ({
+      trace: jest.fn(async () => traces.shift() ?? []),
+    } as unknown as Provider);
+
+  it("should read the id from the create event once indexed", async () => {
+    const provider = tracing([
+      [],
+      [
+        { event: "invoke", data: {} },
+        { event: "create", data: { block: "2", tx: "17" } },
+      ],
+    ]);
+
+    const id = await resolveDeployedId(provider, "ef".repeat(32), 3, {
+      intervalMs: 0,
+    });
+
+    expect(id.equals([2, 17])).toBe(true);
+    expect(provider.trace).toHaveBeenCalledWith("ef".repeat(32), 3);
+    expect(provider.trace).toHaveBeenCalledTimes(2);
+  });
+
+  it("should fail on a reverted or untraced deployment", async () => {
+    await expect(
+      resolveDeployedId(
+        tracing([[{ event: "return", data: { status: "revert" } }]]),
+        "ef".repeat(32),
+        3
+      )
+    ).rejects.toThrow("reverted");
+    await expect(
+      resolveDeployedId(tracing([]), "ef".repeat(32), 3, {
+        attempts: 2,
+        intervalMs: 0,
+      })
+    ).rejects.toThrow("No create event traced");
+  });
+});
@@ -0,0 +1,192 @@
+import { AlkaneId } from "../alkaneId";
+import { AlkanesContract } from "../contract";
+import { Provider } from "../provider";
+import { parseStorageKey, parseSubKeys, storageKeyBytes } from "../storage";
+import { AlkanesABI } from "../types";
+
+const abi: AlkanesABI = {
+  name: "Token",
+  methods: [],
+  storage: [
+    { key: "/totalsupply", type: "u128" },
+    { key: "/name", type: "Vec<u8>" },
+    { key: "/paused", type: "bool" },
+    {
+      key: "/balances/",
+      type: "u128",
+      path: [{ select: "Vec<u8>" }],
+    },
+    {
+      key: "/allowances/",
+      type: "u64",
+      path: [{ select: "u32" }, { keyword: "/" }, { select: "String" }],
+    },
+    { key: "/holders", type: { vec: { type: "u32" } } },
+    { key: "/frozen/", type: "bool", path: [{ select: "bool" }] },
+  ],
+  opcodes: {},
+};
+
+const utf8 = (text: string) => new TextEncoder().encode(text);
+const hex = (bytes: Uint8Array) => Buffer.from(bytes).toString("hex");
+const u128 = (value: number) => [value, ...new Array(15).fill(0)];
+
+describe("ContractStorage", () => {
+  let slots: Map<string, Uint8Array>;
+  let contract: AlkanesContract;
+  let getStorageAt: jest.Mock;
+
+  const set = (key: Uint8Array, value: number[]) =>
+    slots.set(hex(key), new Uint8Array(value));
+
+  beforeEach(() => {
+    slots = new Map();
+    getStorageAt = jest.fn(
+      async (_: AlkaneId, key: Uint8Array) =>
+        slots.get(hex(key)) ?? new Uint8Array(0)
+    );
+    contract = new AlkanesContract({
+      abi,
+      bytecode: "",
+      address: new AlkaneId(2, 7),
+      provider: {
+        getStorageAt,
+        getStorageKeys: async (_: AlkaneId, prefix: Uint8Array) =>
+          [...slots.keys()]
+            .filter((key) => key.startsWith(hex(prefix)))
+            .sort()
+            .map((key) => new Uint8Array(Buffer.from(key, "hex"))),
+      } as unknown as Provider,
+    });
+  });
+
+  it("should decode values by their storage type", async () => {
+    set(utf8("/totalsupply"), [0xe8, 0x03, ...new Array(14).fill(0)]);
+    set(utf8("/name"), [1, 2, 3]);
+
+    expect(await contract.storage.get("/totalsupply")).toBe(1000n);
+    expect(await contract.storage.get("/name")).toEqual(
+      new Uint8Array([1, 2, 3])
+    );
+    expect(getStorageAt.mock.calls[0][0]).toEqual(new AlkaneId(2, 7));
+  });
+
+  it("should read unset values as zero, like get_value", async () => {
+    expect(await contract.storage.get("/totalsupply")).toBe(0n);
+    expect(await contract.storage.get("/paused")).toBe(false);
+    expect(await contract.storage.getRaw("/totalsupply")).toHaveLength(0);
+  });
+
+  it("should address map entries by their sub-keys", async () => {
+    const owner = new Uint8Array([0xaa, 0xbb]);
+    set(new Uint8Array([...utf8("/balances/"), 0xaa, 0xbb]), [
+      5,
+      ...new Array(15).fill(0),
+    ]);
+    set(
+      new Uint8Array([...utf8("/allowances/"), 9, 0, 0, 0, ...utf8("/bob")]),
+      [7, 0, 0, 0, 0, 0, 0, 0]
+    );
+
+    expect(await contract.storage.get("/balances/", owner)).toBe(5n);
+    expect(await contract.storage.get("/balances/", "0xaabb")).toBe(5n);
+    expect(await contract.storage.get("/allowances/", 9, "bob")).toBe(7n);
+    expect(
+      await contract.storage.entries("/balances/", ["aabb", "cc"])
+    ).toEqual([
+      ["aabb", 5n],
+      ["cc", 0n],
+    ]);
+    expect(
+      await contract.storage.entries("/allowances/", [[9, "bob"]])
+    ).toEqual([[[9, "bob"], 7n]]);
+  });
+
+  it("should list the map entries the contract has set", async () => {
+    set(new Uint8Array([...utf8("/balances/"), 0xaa, 0xbb]), u128(5));
+    set(new Uint8Array([...utf8("/balances/"), 0xcc]), u128(6));
+    set(
+      new Uint8Array([...utf8("/allowances/"), 9, 0, 0, 0, ...utf8("/bob")]),
+      [7, 0, 0, 0, 0, 0, 0, 0]
+    );
+    set(utf8("/balances-total"), [1]);
+
+    expect(await contract.storage.entries("/balances/")).toEqual([
+      ["aabb", 5n],
+      ["cc", 6n],
+    ]);
+    expect(await contract.storage.entries("/allowances/")).toEqual([
+      [[9, "bob"], 7n],
+    ]);
+
+    const indexer = new AlkanesContract({
+      abi,
+      bytecode: "",
+      address: new AlkaneId(2, 7),
+      provider: { getStorageAt } as unknown as Provider,
+    });
+    await expect(indexer.storage.entries("/balances/")).rejects.toThrow(
+      "The provider cannot list storage keys; pass the sub-keys of /balances/"
+    );
+  });
+
+  it("should read lists item by item", async () => {
+    set(utf8("/holders/length"), [2, 0, 0, 0]);
+    set(utf8("/holders/0"), [4, 0, 0, 0]);
+    set(utf8("/holders/1"), [8, 0, 0, 0]);
+
+    expect(await contract.storage.get("/holders")).toEqual([4, 8]);
+  });
+
+  it("should reject unknown keys and missing sub-keys", async () => {
+    await expect(contract.storage.get("/owner")).rejects.toThrow(
+      "Storage key /owner not found in ABI"
+    );
+    expect(() => storageKeyBytes(abi.storage[3], [])).toThrow(
+      "Storage key /balances/ takes 1 sub-key(s), got 0"
+    );
+    expect(() => storageKeyBytes(abi.storage[3], ["xyz"])).toThrow(
+      "Invalid sub-key for /balances/: expected Uint8Array or hex string"
+    );
+  });
+});
+
+describe("parseStorageKey", () => {
+  it("should recover the sub-keys of an entry's slots", () => {
+    const [, , , balances, allowances, holders] = abi.storage;
+    expect(
+      parseStorageKey(allowances, storageKeyBytes(allowances, [9, "bob"]))
+    ).toEqual([9, "bob"]);
+    expect(
+      parseStorageKey(balances, storageKeyBytes(balances, ["aabb"]))
+    ).toEqual(["aabb"]);
+    expect(parseStorageKey(holders, utf8("/holders/length"))).toEqual([]);
+
+    expect(parseStorageKey(holders, utf8("/holders/0"))).toBeUndefined();
+    expect(
+      parseStorageKey(allowances, utf8("/allowances/\x09\x00"))
+    ).toBeUndefined();
+  });
+});
+
+describe("parseSubKeys", () => {
+  it("should parse command-line sub-keys by their segment types", () => {
+    const [, , , balances, allowances, , frozen] = abi.storage;
+    expect(parseSubKeys(allowances, ["0x09", "bob"])).toEqual([9n, "bob"]);
+    expect(parseSubKeys(balances, ["aabb"])).toEqual(["aabb"]);
+    expect(parseSubKeys(frozen, ["false"])).toEqual([false]);
+    expect(hex(storageKeyBytes(frozen, parseSubKeys(frozen, ["false"])))).toBe(
+      hex(new Uint8Array([...utf8("/frozen/"), 0]))
+    );
+
+    expect(() => parseSubKeys(frozen, ["no"])).toThrow(
+      "Sub-key 0 of /frozen/ must be true or false, got no"
+    );
+    expect(() => parseSubKeys(allowances, ["nine", "bob"])).toThrow(
+      "Sub-key 0 of /allowances/ must be a u32, got nine"
+    );
+    expect(() => parseSubKeys(allowances, ["9"])).toThrow(
+      "Storage key /allowances/ takes 2 sub-key(s), got 1"
+    );
+  });
+});
@@ -0,0 +1,96 @@
+import fs from "fs/promises";
+import os from "os";
+import path from "path";
+import { AlkaneId } from "../alkaneId";
+import {
+  AlkanesTestEnvironment,
+  RevertError,
+  expectRevert,
+} from "../testing";
+import { embedAbi } from "../wasm";
+import { counterAbi, counterWasm } from "./fixtures/counter";
+
+describe("AlkanesTestEnvironment", () => {
+  let env: AlkanesTestEnvironment;
+
+  beforeEach(() => {
+    env = new AlkanesTestEnvironment();
+  });
+
+  it("should deploy with encoded arguments and call opcodes", async () => {
+    const counter = await env.deployBytecode(counterWasm, [5n], {
+      abi: counterAbi,
+    });
+
+    expect(counter.alkaneId).toEqual(new AlkaneId(2, 0));
+    const result = await counter.call("get");
+    expect(result.value).toBe(5n);
+    expect(result.transfers).toEqual([]);
+    expect(result.gasUsed).toBeGreaterThan(0n);
+    expect(await counter.storage.get("/n")).toBe(5n);
+  });
+
+  it("should report reverts", async () => {
+    const counter = await env.deployBytecode(counterWasm, [5n], {
+      abi: counterAbi,
+    });
+
+    const error = await expectRevert(counter.call("fail"), "boom");
+    expect(error).toBeInstanceOf(RevertError);
+    expect(error.result.logs).toEqual(["boom"]);
+
+    await expect(expectRevert(counter.call("get"))).rejects.toThrow(
+      "Expected the call to revert, but it succeeded"
+    );
+    await expect(expectRevert(counter.call("fail"), "bust")).rejects.toThrow(
+      "Expected a revert matching bust"
+    );
+  });
+
+  it("should advance blocks", () => {
+    expect(env.height).toBe(1n);
+    expect(env.mine()).toBe(2n);
+    expect(env.mine(10)).toBe(12n);
+    expect(env.vm.height).toBe(12n);
+  });
+
+  it("should deploy compiled contracts by name", async () => {
+    const artifactsDir = await fs.mkdtemp(path.join(os.tmpdir(), "alkali-"));
+    try {
+      await fs.mkdir(path.join(artifactsDir, "Counter"));
+      await fs.writeFile(
+        path.join(artifactsDir, "Counter", "Counter.wasm"),
+        embedAbi(counterWasm, counterAbi)
+      );
+      await fs.writeFile(
+        path.join(artifactsDir, "Counter", "abi.json"),
+        JSON.stringify(counterAbi)
+      );
+      await fs.writeFile(
+        path.join(artifactsDir, "manifest.json"),
+        JSON.stringify({
+          contracts: {
+            Counter: {
+              name: "Counter",
+              contract: "Counter",
+              source: "contracts/Counter.rs",
+              wasm: "Counter/Counter.wasm",
+              abi: "Counter/abi.json",
+              metadata: "Counter/metadata.json",
+              types: "types/Counter.ts",
+            },
+          },
+        })
+      );
+      env = new AlkanesTestEnvironment({ artifactsDir });
+
+      const counter = await env.deploy("Counter", [9n]);
+      expect((await counter.simulate("get")).value).toBe(9n);
+      await expect(env.deploy("Missing")).rejects.toThrow(
+        "No compiled contract Missing"
+      );
+    } finally {
+      await fs.rm(artifactsDir, { recursive: true, force: true });
+    }
+  });
+});
@@ -0,0 +1,97 @@
+import { generateTypes, tsType } from "../typegen";
+import { AlkanesABI } from "../types";
+
+describe("typegen", () => {
+  describe("tsType", () => {
+    it("should map primitives", () => {
+      expect(tsType("u32")).toBe("number");
+      expect(tsType("u128")).toBe("bigint");
+      expect(tsType("i64")).toBe("bigint");
+      expect(tsType("String")).toBe("string");
+      expect(tsType("bool")).toBe("boolean");
+      expect(tsType("Vec<u8>")).toBe("Uint8Array");
+    });
+
+    it("should map composite types", () => {
+      expect(tsType({ tuple: ["u8", "String"] })).toBe("[number, string]");
+      expect(tsType({ array: { type: "u128", length: 2 } })).toBe(
+        "[bigint, bigint]"
+      );
+      expect(tsType({ array: { type: "u8", length: 64 } })).toBe("number[]");
+      expect(tsType({ vec: { type: { tuple: ["u8", "bool"] } } })).toBe(
+        "[number, boolean][]"
+      );
+    });
+  });
+
+  describe("generateTypes", () => {
+    const abi: AlkanesABI = {
+      name: "Token",
+      methods: [
+        {
+          opcode: 77,
+          name: "mint",
+          doc: "Mints new tokens",
+          inputs: [{ name: "amount", type: "u128" }],
+          outputs: [],
+        },
+        {
+          opcode: 99,
+          name: "name",
+          inputs: [],
+          outputs: [{ name: "name", type: "String" }],
+        },
+        {
+          opcode: 5,
+          name: "transfer",
+          inputs: [
+            { name: "to", type: { tuple: ["u128", "u128"] } },
+            { name: "default", type: "u64" },
+          ],
+          outputs: [
+            { name: "ok", type: "bool" },
+            { name: "left", type: "u128" },
+          ],
+        },
+      ],
+      storage: [],
+      opcodes: { mint: 77, name: 99, transfer: 5 },
+    };
+    const source = generateTypes(abi);
+
+    it("should declare a typed contract class", () => {
+      expect(source).toContain(
+        '  CallResult,\n  ContractConfig,\n} from "@jonatns/alkali"'
+      );
+      expect(source).toContain("export const TokenABI: AlkanesABI = {");
+      expect(source).toContain("export class Token extends AlkanesContract {");
+      expect(source).toContain("declare readonly methods: TokenMethods;");
+      expect(source).toContain("super({ ...config, abi: TokenABI });");
+    });
+
+    it("should type method arguments and return values", () => {
+      expect(source).toContain(
+        "  mint(amount: bigint, options: CallOptions): Promise<CallResult>;"
+      );
+      expect(source).toContain(
+        "  name(options: CallOptions): Promise<CallResult>;"
+      );
+      expect(source).toContain("  name(): Promise<string>;");
+      expect(source).toContain(
+        "  transfer(to: [bigint, bigint], default_: bigint): Promise<[boolean, bigint]>;"
+      );
+    });
+
+    it("should carry docs and opcodes", () => {
+      expect(source).toContain(
+        "  /**\n   * Mints new tokens\n   * Opcode 77\n   */\n  mint("
+      );
+    });
+
+    it("should honour the import path", () => {
+      expect(generateTypes(abi, { importPath: "../../src" })).toContain(
+        'from "../../src"'
+      );
+    });
+  });
+});
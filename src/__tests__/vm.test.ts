@@ -0,0 +1,125 @@
+import { AlkaneId } from "../alkaneId";
+import { AlkanesContract } from "../contract";
+import { AlkanesVM } from "../vm";
+import { counterAbi, counterWasm, u128le } from "./fixtures/counter";
+
+const key = new TextEncoder().encode("/n");
+
+describe("AlkanesVM", () => {
+  let vm: AlkanesVM;
+
+  beforeEach(() => {
+    vm = new AlkanesVM({ height: 840_000n });
+  });
+
+  it("should deploy a contract and record its storage writes", async () => {
+    const { alkaneId, result } = await vm.deploy(counterWasm, [0n, 42n]);
+
+    expect(alkaneId).toEqual(new AlkaneId(2, 0));
+    expect(result.status).toBe(0);
+    expect(result.gasUsed).toBeGreaterThan(0n);
+    expect(result.storage).toEqual([
+      {
+        alkane: alkaneId,
+        key,
+        before: new Uint8Array(0),
+        after: new Uint8Array(u128le(42n)),
+      },
+    ]);
+    expect(Array.from(await vm.getStorageAt(alkaneId, key))).toEqual(
+      u128le(42n)
+    );
+    expect(await vm.getBytecode(alkaneId)).toEqual(counterWasm);
+  });
+
+  it("should serve contract reads as a provider", async () => {
+    const { alkaneId } = await vm.deploy(counterWasm, [0n, 42n]);
+    const contract = new AlkanesContract({
+      abi: counterAbi,
+      bytecode: "",
+      address: alkaneId,
+      provider: vm,
+    });
+
+    expect(await contract.read.get()).toBe(42n);
+    expect(await contract.storage.get("/n")).toBe(42n);
+  });
+
+  it("should only commit successful executions", async () => {
+    const { alkaneId } = await vm.deploy(counterWasm, [0n, 1n]);
+
+    const simulated = await vm.simulate({ target: alkaneId, inputs: [0n, 5n] });
+    expect(simulated.storage).toHaveLength(1);
+    expect((await vm.getStorageAt(alkaneId, key))[0]).toBe(1);
+
+    const executed = await vm.execute({ target: alkaneId, inputs: [0n, 5n] });
+    expect(executed.status).toBe(0);
+    expect((await vm.getStorageAt(alkaneId, key))[0]).toBe(5);
+  });
+
+  it("should report reverts with the contract's logs", async () => {
+    const { alkaneId } = await vm.deploy(counterWasm, [0n, 1n]);
+
+    const result = await vm.execute({ target: alkaneId, inputs: [2n] });
+    expect(result.status).toBe(1);
+    expect(result.logs).toEqual(["boom"]);
+    expect(result.error).toContain("boom");
+
+    const starved = await vm.execute({
+      target: alkaneId,
+      inputs: [0n, 9n],
+      fuel: 10n,
+    });
+    expect(starved.error).toContain("out of fuel");
+    expect((await vm.getStorageAt(alkaneId, key))[0]).toBe(1);
+
+    await expect(
+      vm.execute({ target: new AlkaneId(2, 9), inputs: [1n] })
+    ).resolves.toMatchObject({
+      status: 1,
+      error: "No contract deployed at 2:9",
+    });
+  });
+
+  it("should run a looping contract out of fuel", async () => {
+    const { alkaneId } = await vm.deploy(counterWasm, [0n, 1n]);
+
+    const result = await vm.execute({
+      target: alkaneId,
+      inputs: [5n],
+      fuel: 100_000n,
+    });
+    expect(result.status).toBe(1);
+    expect(result.error).toBe("out of fuel (limit 100000)");
+    expect(result.gasUsed).toBeGreaterThan(100_000n);
+  });
+
+  it("should run calls between contracts", async () => {
+    const { alkaneId: caller } = await vm.deploy(counterWasm, [0n, 1n]);
+    const { alkaneId: callee } = await vm.deploy(counterWasm, [0n, 7n]);
+
+    const result = await vm.execute({
+      target: caller,
+      inputs: [3n, callee.block, callee.tx],
+    });
+
+    expect(result.error).toBeUndefined();
+    expect(Array.from(result.response.data)).toEqual(u128le(7n));
+  });
+
+  it("should undo the clone of a reverted factory call", async () => {
+    const { alkaneId: caller } = await vm.deploy(counterWasm, [0n, 1n]);
+
+    // [5, n] clones [2, n], whose opcode 2 then traps
+    const result = await vm.execute({
+      target: caller,
+      inputs: [4n, 5n, caller.tx],
+    });
+
+    expect(result.status).toBe(0);
+    const next = new AlkaneId(2, caller.tx + 1n);
+    expect(await vm.getBytecode(next)).toHaveLength(0);
+    const { alkaneId } = await vm.deploy(counterWasm, [0n, 1n]);
+    expect(alkaneId).toEqual(next);
+  });
+});
@@ -0,0 +1,98 @@
+import { gzipSync } from "zlib";
+import { AlkanesABI } from "../types";
+import {
+  ABI_SECTION_NAME,
+  FUEL_GLOBAL_EXPORT,
+  START_EXPORT,
+  embedAbi,
+  extractAbi,
+  meterFuel,
+  readSections,
+} from "../wasm";
+
+// Header plus an empty type section (id 1)
+const minimal = new Uint8Array([
+  0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x01, 0x00,
+]);
+
+const abi: AlkanesABI = {
+  name: "Token",
+  methods: [
+    {
+      opcode: 99,
+      name: "name",
+      inputs: [],
+      outputs: [{ name: "name", type: "String" }],
+    },
+  ],
+  storage: [],
+  opcodes: { name: 99 },
+};
+
+describe("wasm", () => {
+  it("should append the ABI as a custom section", () => {
+    const wasm = embedAbi(minimal, abi);
+
+    const sections = readSections(wasm);
+    expect(sections.map((section) => section.id)).toEqual([1, 0]);
+    expect(sections[1].name).toBe(ABI_SECTION_NAME);
+    expect(Array.from(wasm.subarray(0, minimal.length))).toEqual(
+      Array.from(minimal)
+    );
+    expect(extractAbi(wasm)).toEqual(abi);
+  });
+
+  it("should replace an existing ABI section", () => {
+    const renamed = { ...abi, name: "Renamed" };
+    const wasm = embedAbi(embedAbi(minimal, abi), renamed);
+
+    expect(
+      readSections(wasm).filter((s) => s.name === ABI_SECTION_NAME)
+    ).toHaveLength(1);
+    expect(extractAbi(wasm)?.name).toBe("Renamed");
+  });
+
+  it("should read gzipped bytecode as deployed", () => {
+    const deployed = new Uint8Array(gzipSync(embedAbi(minimal, abi)));
+    expect(extractAbi(deployed)).toEqual(abi);
+    expect(extractAbi(minimal)).toBeUndefined();
+  });
+
+  it("should meter the start function instead of running it", () => {
+    // prettier-ignore
+    const wasm = new Uint8Array([
+      ...minimal.subarray(0, 8),
+      0x01, 0x04, 0x01, 0x60, 0x00, 0x00, // type () -> ()
+      // import global "env" "g" i32
+      0x02, 0x0a, 0x01, 0x03, ...Buffer.from("env"), 0x01, 0x67, 0x03, 0x7f, 0x00,
+      0x03, 0x02, 0x01, 0x00, // function 0
+      0x08, 0x01, 0x00, // start 0
+      0x0a, 0x07, 0x01, 0x05, 0x00, 0x23, 0x00, 0x1a, 0x0b, // global.get 0; drop
+    ]);
+    const metered = meterFuel(wasm, 3n);
+    expect(readSections(metered).map((section) => section.id)).toEqual([
+      1, 2, 3, 6, 7, 10,
+    ]);
+
+    // Instantiating no longer runs the start function, nor traps
+    const instance = new WebAssembly.Instance(new WebAssembly.Module(metered), {
+      env: { g: 7 },
+    });
+    const fuel = instance.exports[FUEL_GLOBAL_EXPORT] as WebAssembly.Global;
+    const start = instance.exports[START_EXPORT] as () => void;
+    expect(fuel.value).toBe(0n);
+    expect(() => start()).toThrow("unreachable");
+    fuel.value = 5n;
+    start();
+    expect(fuel.value).toBe(2n);
+  });
+
+  it("should reject malformed modules", () => {
+    expect(() => readSections(new Uint8Array([1, 2, 3]))).toThrow(
+      "Not a WASM module"
+    );
+    expect(() =>
+      readSections(new Uint8Array([...minimal.subarray(0, 8), 0x01, 0x05]))
+    ).toThrow("overruns the module");
+  });
+});
@@ -0,0 +1,167 @@
+// Runs the `alkali-abi` helper (crates/alkali-abi), which walks a contract's
+// Rust syntax tree and reports its dispatch arms and storage layout.
+
+import { execFile, spawn } from "child_process";
+import fs from "fs/promises";
+import os from "os";
+import path from "path";
+import { promisify } from "util";
+import { AlkanesMethod, StorageKey } from "./types";
+
+const execFileAsync = promisify(execFile);
+
+// Package root: the crates ship next to dist/ and src/
+const PACKAGE_ROOT = path.join(__dirname, "..");
+
+export interface SourceLocation {
+  line: number;
+  column: number;
+  /** The file, for contracts read from files */
+  file?: string;
+}
+
+export interface ExtractedMethod extends SourceLocation {
+  opcode: number;
+  /** Comments above the match arm, without delimiters, in order */
+  comments: string[];
+  /** Names bound by `let name = shift_or_err(&mut inputs)?`, in order */
+  bindings: string[];
+  /** Complete entry for `#[opcode(n)]` methods, read from their signature */
+  abi?: AlkanesMethod;
+}
+
+export interface ExtractedStorage extends SourceLocation, StorageKey {}
+
+export interface ExtractedContract {
+  name: string;
+  /** The file the methods are read from, for contracts read from files */
+  file?: string;
+  methods: ExtractedMethod[];
+  storage: ExtractedStorage[];
+}
+
+export class AbiExtractionError extends Error {
+  line: number;
+  column: number;
+  file?: string;
+
+  constructor(message: string, { line, column, file }: SourceLocation) {
+    super(`${file ? `${file}:` : ""}${line}:${column}: ${message}`);
+    this.name = "AbiExtractionError";
+    this.line = line;
+    this.column = column;
+    this.file = file;
+  }
+}
+
+let helperPath: Promise<string> | undefined;
+
+/**
+ * Path of the helper binary: `ALKALI_ABI_BIN` when set, otherwise built
+ * once with cargo from the bundled crate, against the bundled Cargo.lock,
+ * into the user's cache (the package directory may be read-only).
+ */
+export function abiExtractorPath(): Promise<string> {
+  if (process.env.ALKALI_ABI_BIN) {
+    return Promise.resolve(process.env.ALKALI_ABI_BIN);
+  }
+  helperPath ??= buildHelper().catch((error) => {
+    helperPath = undefined;
+    throw error;
+  });
+  return helperPath;
+}
+
+async function buildHelper(): Promise<string> {
+  const { version } = JSON.parse(
+    await fs.readFile(path.join(PACKAGE_ROOT, "package.json"), "utf8")
+  );
+  const targetDir = path.join(userCacheDir(), "alkali", version, "target");
+  try {
+    await execFileAsync("cargo", [
+      "build",
+      "--release",
+      "--quiet",
+      "--locked",
+      "--package",
+      "alkali-abi",
+      "--manifest-path",
+      path.join(PACKAGE_ROOT, "Cargo.toml"),
+      "--target-dir",
+      targetDir,
+    ]);
+  } catch (error: any) {
+    throw new Error(
+      `Building the alkali-abi helper failed: ${error.stderr || error.message}`
+    );
+  }
+  const exe = process.platform === "win32" ? "alkali-abi.exe" : "alkali-abi";
+  return path.join(targetDir, "release", exe);
+}
+
+// The platform's per-user cache directory
+function userCacheDir(): string {
+  if (process.env.XDG_CACHE_HOME) {
+    return process.env.XDG_CACHE_HOME;
+  }
+  switch (process.platform) {
+    case "win32":
+      return (
+        process.env.LOCALAPPDATA ?? path.join(os.homedir(), "AppData", "Local")
+      );
+    case "darwin":
+      return path.join(os.homedir(), "Library", "Caches");
+    default:
+      return path.join(os.homedir(), ".cache");
+  }
+}
+
+export function extractContract(
+  sourceCode: string
+): Promise<ExtractedContract> {
+  return runHelper([], sourceCode);
+}
+
+/**
+ * Extracts the contract of the crate rooted at `root` (a `lib.rs` or a
+ * single-file contract), following its `mod` declarations as rustc does.
+ */
+export function extractCrate(root: string): Promise<ExtractedContract> {
+  return runHelper([root]);
+}
+
+// Runs the helper with `args`, writing `input` to its stdin when given
+async function runHelper(
+  args: string[],
+  input?: string
+): Promise<ExtractedContract> {
+  const helper = await abiExtractorPath();
+
+  const { code, stdout, stderr } = await new Promise<{
+    code: number | null;
+    stdout: string;
+    stderr: string;
+  }>((resolve, reject) => {
+    const child = spawn(helper, args, {
+      stdio: [input === undefined ? "ignore" : "pipe", "pipe", "pipe"],
+    });
+    let stdout = "";
+    let stderr = "";
+    child.stdout.on("data", (chunk) => (stdout += chunk));
+    child.stderr.on("data", (chunk) => (stderr += chunk));
+    child.on("error", reject);
+    child.on("close", (code) => resolve({ code, stdout, stderr }));
+    child.stdin?.end(input);
+  });
+
+  let output: any;
+  try {
+    output = JSON.parse(stdout);
+  } catch {
+    throw new Error(`alkali-abi exited with ${code}: ${stderr.trim()}`);
+  }
+  if (output.error) {
+    throw new AbiExtractionError(output.error.message, output.error);
+  }
+  return output as ExtractedContract;
+}
@@ -0,0 +1,150 @@
+const U128_MAX = (1n << 128n) - 1n;
+
+// Reserved cellpack target blocks understood by the Alkanes runtime
+export enum AlkaneIdBlock {
+  Create = 1, // [1, 0] deploys the envelope's bytecode as the next [2, n]
+  Sequence = 2,
+  Reserved = 3, // [3, n] deploys the envelope's bytecode as [4, n]
+  ReservedDeployed = 4,
+  FactoryFromSequence = 5, // [5, n] clones [2, n]
+  FactoryFromReserved = 6, // [6, n] clones [4, n]
+}
+
+export type AlkaneIdLike =
+  | AlkaneId
+  | string
+  | [bigint | number, bigint | number]
+  | { block: bigint | number | string; tx: bigint | number | string };
+
+/** Identifier of an alkane: the `[block, tx]` pair of u128 cellpack words. */
+export class AlkaneId {
+  readonly block: bigint;
+  readonly tx: bigint;
+
+  constructor(block: bigint | number | string, tx: bigint | number | string) {
+    this.block = AlkaneId.toU128(block, "block");
+    this.tx = AlkaneId.toU128(tx, "tx");
+  }
+
+  /** Parses the canonical `block:tx` form. */
+  static parse(value: string): AlkaneId {
+    const match = value.trim().match(/^(\d+):(\d+)$/);
+    if (!match) {
+      throw new Error(`Invalid AlkaneId "${value}", expected "block:tx"`);
+    }
+    return new AlkaneId(match[1], match[2]);
+  }
+
+  static from(value: AlkaneIdLike): AlkaneId {
+    if (value instanceof AlkaneId) {
+      return value;
+    }
+    if (typeof value === "string") {
+      return AlkaneId.parse(value);
+    }
+    if (Array.isArray(value)) {
+      return new AlkaneId(value[0], value[1]);
+    }
+    return new AlkaneId(value.block, value.tx);
+  }
+
+  /** Reads an id from the first two words of a cellpack. */
+  static fromWords(words: bigint[]): AlkaneId {
+    if (words.length < 2) {
+      throw new Error("An AlkaneId needs two cellpack words");
+    }
+    return new AlkaneId(words[0], words[1]);
+  }
+
+  /** `[1, 0]`: deploy as the next sequence-numbered alkane `[2, n]`. */
+  static create(): AlkaneId {
+    return new AlkaneId(AlkaneIdBlock.Create, 0);
+  }
+
+  /** `[3, n]`: deploy to the deterministic id `[4, n]`. */
+  static reserved(n: bigint | number): AlkaneId {
+    return new AlkaneId(AlkaneIdBlock.Reserved, n);
+  }
+
+  /** `[5, n]` / `[6, n]`: clone an already deployed `[2, n]` / `[4, n]`. */
+  static factory(template: AlkaneIdLike): AlkaneId {
+    const id = AlkaneId.from(template);
+    switch (Number(id.block)) {
+      case AlkaneIdBlock.Sequence:
+        return new AlkaneId(AlkaneIdBlock.FactoryFromSequence, id.tx);
+      case AlkaneIdBlock.ReservedDeployed:
+        return new AlkaneId(AlkaneIdBlock.FactoryFromReserved, id.tx);
+      default:
+        throw new Error(`Cannot clone ${id}: only [2, n] and [4, n] can be`);
+    }
+  }
+
+  isCreate(): boolean {
+    return this.block === BigInt(AlkaneIdBlock.Create) && this.tx === 0n;
+  }
+
+  isReserved(): boolean {
+    return this.block === BigInt(AlkaneIdBlock.Reserved);
+  }
+
+  isFactory(): boolean {
+    return (
+      this.block === BigInt(AlkaneIdBlock.FactoryFromSequence) ||
+      this.block === BigInt(AlkaneIdBlock.FactoryFromReserved)
+    );
+  }
+
+  /**
+   * The id a deployment to this target ends up at, when it can be known
+   * without the indexer: `[3, n]` always lands at `[4, n]`.
+   */
+  deployedId(): AlkaneId | undefined {
+    return this.isReserved()
+      ? new AlkaneId(AlkaneIdBlock.ReservedDeployed, this.tx)
+      : undefined;
+  }
+
+  toWords(): [bigint, bigint] {
+    return [this.block, this.tx];
+  }
+
+  equals(other: AlkaneIdLike): boolean {
+    return this.compare(other) === 0;
+  }
+
+  /** Orders by block, then tx, like the Rust `Ord` impl. */
+  compare(other: AlkaneIdLike): number {
+    const id = AlkaneId.from(other);
+    if (this.block !== id.block) {
+      return this.block < id.block ? -1 : 1;
+    }
+    if (this.tx !== id.tx) {
+      return this.tx < id.tx ? -1 : 1;
+    }
+    return 0;
+  }
+
+  toString(): string {
+    return `${this.block}:${this.tx}`;
+  }
+
+  toJSON(): string {
+    return this.toString();
+  }
+
+  private static toU128(
+    value: bigint | number | string,
+    field: string
+  ): bigint {
+    let n: bigint;
+    try {
+      n = BigInt(value);
+    } catch {
+      throw new Error(`AlkaneId ${field} must be an integer, got ${value}`);
+    }
+    if (n < 0n || n > U128_MAX) {
+      throw new Error(`AlkaneId ${field} ${n} is not a u128`);
+    }
+    return n;
+  }
+}
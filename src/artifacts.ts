@@ -0,0 +1,255 @@
+// Build artifacts of a project: each contract compiles into
+// `<build>/<Name>/` (WASM, ABI, metadata and build info), and
+// `<build>/manifest.json` lists them for the CLI, scripts and the test
+// harness.
+
+import fs from "fs/promises";
+import path from "path";
+import { toHex } from "./bitcoin";
+import { AlkanesCompiler, BuildInfo } from "./compiler";
+import { AlkaliConfig, OptimizeLevel } from "./config";
+import { sha256 } from "./secp256k1";
+import { generateTypes } from "./typegen";
+import { AlkanesABI } from "./types";
+
+export const MANIFEST_FILE = "manifest.json";
+
+/** A compiled contract's files, as `/` paths relative to the build dir. */
+export interface ContractArtifact {
+  name: string;
+  /** Name of the contract type, from its ABI */
+  contract: string;
+  /** Source file or directory, relative to the project root */
+  source: string;
+  wasm: string;
+  abi: string;
+  metadata: string;
+  /** Toolchain, dependencies and hashes to reproduce the WASM */
+  buildInfo: string;
+  types: string;
+}
+
+export interface ArtifactsManifest {
+  contracts: Record<string, ContractArtifact>;
+}
+
+export interface ContractMetadata {
+  name: string;
+  contract: string;
+  source: string;
+  /** sha256 of the WASM, embedded ABI included */
+  sha256: string;
+  size: number;
+  target: string;
+  optimizeLevel: OptimizeLevel;
+}
+
+export interface ContractBuildInfo extends BuildInfo {
+  name: string;
+  source: string;
+  /** sha256 of the WASM, embedded ABI included */
+  sha256: string;
+}
+
+export interface CompileProjectOptions {
+  /** Compiler to use (default: one built from the config) */
+  compiler?: AlkanesCompiler;
+  /** Receives progress messages */
+  log?: (message: string) => void;
+}
+
+export interface CompiledContract {
+  artifact: ContractArtifact;
+  bytecode: Uint8Array;
+  abi: AlkanesABI;
+}
+
+/** The manifest in `buildDir`, empty when nothing was compiled yet. */
+export async function readManifest(
+  buildDir: string
+): Promise<ArtifactsManifest> {
+  let content: string;
+  try {
+    content = await fs.readFile(path.join(buildDir, MANIFEST_FILE), "utf8");
+  } catch {
+    return { contracts: {} };
+  }
+  return JSON.parse(content);
+}
+
+/**
+ * Reads a compiled contract by artifact name (the source file's name, e.g.
+ * `"Example"` for contracts/Example.rs) or by contract name.
+ */
+export async function loadArtifact(
+  buildDir: string,
+  name: string
+): Promise<CompiledContract> {
+  const { contracts } = await readManifest(buildDir);
+  const artifact =
+    contracts[name] ??
+    Object.values(contracts).find((entry) => entry.contract === name);
+  if (!artifact) {
+    throw new Error(
+      `No compiled contract ${name} in ${path.join(buildDir, MANIFEST_FILE)}; run \`alkali compile\``
+    );
+  }
+
+  const [bytecode, abi] = await Promise.all([
+    fs.readFile(path.join(buildDir, artifact.wasm)),
+    fs.readFile(path.join(buildDir, artifact.abi), "utf8"),
+  ]);
+  return {
+    artifact,
+    bytecode: new Uint8Array(bytecode),
+    abi: JSON.parse(abi),
+  };
+}
+
+/**
+ * Contract sources of the project: the `.rs` files directly under
+ * `paths.contracts`, except the shared `lib.rs`, and the directories there
+ * with a `lib.rs`.
+ */
+export async function findContracts(config: AlkaliConfig): Promise<string[]> {
+  let entries;
+  try {
+    entries = await fs.readdir(config.paths.contracts, {
+      withFileTypes: true,
+    });
+  } catch {
+    return [];
+  }
+  const contracts: string[] = [];
+  for (const entry of entries) {
+    const source = path.join(config.paths.contracts, entry.name);
+    const isContract = entry.isDirectory()
+      ? await fs.access(path.join(source, "lib.rs")).then(
+          () => true,
+          () => false
+        )
+      : entry.isFile() && entry.name.endsWith(".rs") && entry.name !== "lib.rs";
+    if (isContract) {
+      contracts.push(source);
+    }
+  }
+  return contracts.sort();
+}
+
+/**
+ * Compiles `files` (default: every contract of the project) into the build
+ * directory and records them in its manifest. Compiling the whole project
+ * rewrites the manifest; compiling some files updates their entries.
+ */
+export async function compileProject(
+  config: AlkaliConfig,
+  files?: string[],
+  options: CompileProjectOptions = {}
+): Promise<CompiledContract[]> {
+  const sources =
+    files?.map((file) => path.resolve(file)) ?? (await findContracts(config));
+  if (sources.length === 0) {
+    throw new Error(
+      `No contracts found in ${path.relative(config.root, config.paths.contracts) || "."}`
+    );
+  }
+
+  const names = new Map<string, string>();
+  for (const source of sources) {
+    const name = path.basename(source, ".rs");
+    if (names.has(name)) {
+      throw new Error(
+        `${names.get(name)} and ${source} would both compile to ${name}`
+      );
+    }
+    names.set(name, source);
+  }
+
+  const buildDir = config.paths.build;
+  const manifest: ArtifactsManifest = files
+    ? await readManifest(buildDir)
+    : { contracts: {} };
+  const compiler = options.compiler ?? AlkanesCompiler.fromConfig(config);
+  for (const source of names.values()) {
+    options.log?.(`🔨 Compiling ${path.relative(config.root, source)}`);
+  }
+  const results = await compiler.compileFiles([...names.values()]);
+
+  const compiled: CompiledContract[] = [];
+  for (const [index, [name, source]] of [...names].entries()) {
+    const { abi, buildInfo } = results[index];
+    const bytecode = new Uint8Array(
+      Buffer.from(results[index].bytecode, "base64")
+    );
+    const artifact = await writeArtifact(config, name, source, {
+      bytecode,
+      abi,
+      buildInfo,
+    });
+    manifest.contracts[name] = artifact;
+    compiled.push({ artifact, bytecode, abi });
+  }
+
+  await fs.mkdir(buildDir, { recursive: true });
+  await fs.writeFile(
+    path.join(buildDir, MANIFEST_FILE),
+    JSON.stringify(manifest, null, 2)
+  );
+  return compiled;
+}
+
+async function writeArtifact(
+  config: AlkaliConfig,
+  name: string,
+  source: string,
+  {
+    bytecode,
+    abi,
+    buildInfo,
+  }: { bytecode: Uint8Array; abi: AlkanesABI; buildInfo: BuildInfo }
+): Promise<ContractArtifact> {
+  const buildDir = config.paths.build;
+  const artifact: ContractArtifact = {
+    name,
+    contract: abi.name,
+    source: path.relative(config.root, source).split(path.sep).join("/"),
+    wasm: `${name}/${name}.wasm`,
+    abi: `${name}/abi.json`,
+    metadata: `${name}/metadata.json`,
+    buildInfo: `${name}/build-info.json`,
+    types: `types/${name}.ts`,
+  };
+  const metadata: ContractMetadata = {
+    name,
+    contract: abi.name,
+    source: artifact.source,
+    sha256: toHex(sha256(bytecode)),
+    size: bytecode.length,
+    target: config.compiler.target,
+    optimizeLevel: config.compiler.optimizeLevel,
+  };
+  const info: ContractBuildInfo = {
+    name,
+    source: artifact.source,
+    sha256: metadata.sha256,
+    ...buildInfo,
+  };
+
+  await fs.mkdir(path.join(buildDir, name), { recursive: true });
+  await fs.mkdir(path.join(buildDir, "types"), { recursive: true });
+  await fs.writeFile(path.join(buildDir, artifact.wasm), bytecode);
+  await fs.writeFile(
+    path.join(buildDir, artifact.abi),
+    JSON.stringify(abi, null, 2)
+  );
+  await fs.writeFile(
+    path.join(buildDir, artifact.metadata),
+    JSON.stringify(metadata, null, 2)
+  );
+  await fs.writeFile(
+    path.join(buildDir, artifact.buildInfo),
+    JSON.stringify(info, null, 2)
+  );
+  await fs.writeFile(path.join(buildDir, artifact.types), generateTypes(abi));
+  return artifact;
+}
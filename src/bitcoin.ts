@@ -0,0 +1,449 @@
+// Bitcoin transaction primitives: serialization, txids, BIP341 sighashes
+// and segwit addresses. Scoped to the taproot transactions alkali builds.
+
+import { sha256, taggedHash } from "./secp256k1";
+
+export type Network = "mainnet" | "testnet" | "signet" | "regtest";
+
+const HRP: Record<Network, string> = {
+  mainnet: "bc",
+  testnet: "tb",
+  signet: "tb",
+  regtest: "bcrt",
+};
+
+export const OP_0 = 0x00;
+export const OP_PUSHDATA1 = 0x4c;
+export const OP_PUSHDATA2 = 0x4d;
+export const OP_IF = 0x63;
+export const OP_ENDIF = 0x68;
+export const OP_RETURN = 0x6a;
+export const OP_CHECKSIG = 0xac;
+export const MAX_SCRIPT_ELEMENT_SIZE = 520;
+
+export const TAPROOT_LEAF_VERSION = 0xc0;
+export const DEFAULT_SEQUENCE = 0xfffffffd;
+
+export interface TxInput {
+  txid: string;
+  vout: number;
+  sequence?: number;
+  witness?: Uint8Array[];
+}
+
+export interface TxOutput {
+  value: bigint;
+  script: Uint8Array;
+}
+
+export interface Transaction {
+  version: number;
+  inputs: TxInput[];
+  outputs: TxOutput[];
+  locktime: number;
+}
+
+export function toHex(bytes: Uint8Array): string {
+  return Buffer.from(bytes).toString("hex");
+}
+
+export function fromHex(hex: string): Uint8Array {
+  if (!/^([0-9a-fA-F]{2})*$/.test(hex)) {
+    throw new Error(`Invalid hex string: ${hex}`);
+  }
+  return new Uint8Array(Buffer.from(hex, "hex"));
+}
+
+class Writer {
+  private parts: Uint8Array[] = [];
+
+  bytes(data: Uint8Array): this {
+    this.parts.push(data);
+    return this;
+  }
+
+  u32(n: number): this {
+    const buf = new Uint8Array(4);
+    new DataView(buf.buffer).setUint32(0, n, true);
+    return this.bytes(buf);
+  }
+
+  u64(n: bigint): this {
+    const buf = new Uint8Array(8);
+    new DataView(buf.buffer).setBigUint64(0, n, true);
+    return this.bytes(buf);
+  }
+
+  compactSize(n: number): this {
+    if (n < 0xfd) return this.bytes(new Uint8Array([n]));
+    if (n <= 0xffff) {
+      return this.bytes(new Uint8Array([0xfd, n & 0xff, n >> 8]));
+    }
+    return this.bytes(new Uint8Array([0xfe])).u32(n);
+  }
+
+  varBytes(data: Uint8Array): this {
+    return this.compactSize(data.length).bytes(data);
+  }
+
+  finish(): Uint8Array {
+    const result = new Uint8Array(
+      this.parts.reduce((sum, part) => sum + part.length, 0)
+    );
+    let offset = 0;
+    for (const part of this.parts) {
+      result.set(part, offset);
+      offset += part.length;
+    }
+    return result;
+  }
+}
+
+class Reader {
+  offset = 0;
+  private data: Uint8Array;
+
+  constructor(data: Uint8Array) {
+    this.data = data;
+  }
+
+  bytes(length: number): Uint8Array {
+    if (this.offset + length > this.data.length) {
+      throw new Error("Transaction is truncated");
+    }
+    const bytes = this.data.slice(this.offset, this.offset + length);
+    this.offset += length;
+    return bytes;
+  }
+
+  u32(): number {
+    const bytes = this.bytes(4);
+    return new DataView(bytes.buffer).getUint32(0, true);
+  }
+
+  u64(): bigint {
+    const bytes = this.bytes(8);
+    return new DataView(bytes.buffer).getBigUint64(0, true);
+  }
+
+  compactSize(): number {
+    const first = this.bytes(1)[0];
+    if (first < 0xfd) return first;
+    if (first === 0xfd) {
+      const [low, high] = this.bytes(2);
+      return low | (high << 8);
+    }
+    if (first === 0xfe) return this.u32();
+    return Number(this.u64());
+  }
+
+  varBytes(): Uint8Array {
+    return this.bytes(this.compactSize());
+  }
+}
+
+// txids are displayed byte-reversed relative to their serialization
+function outpoint(input: TxInput): Uint8Array {
+  return new Writer()
+    .bytes(fromHex(input.txid).reverse())
+    .u32(input.vout)
+    .finish();
+}
+
+function serializeOutput(output: TxOutput): Uint8Array {
+  return new Writer().u64(output.value).varBytes(output.script).finish();
+}
+
+export function serializeTransaction(
+  tx: Transaction,
+  withWitness = true
+): Uint8Array {
+  const segwit =
+    withWitness && tx.inputs.some((input) => input.witness?.length);
+  const writer = new Writer().u32(tx.version);
+  if (segwit) {
+    writer.bytes(new Uint8Array([0x00, 0x01]));
+  }
+
+  writer.compactSize(tx.inputs.length);
+  for (const input of tx.inputs) {
+    writer
+      .bytes(outpoint(input))
+      .varBytes(new Uint8Array(0))
+      .u32(input.sequence ?? DEFAULT_SEQUENCE);
+  }
+
+  writer.compactSize(tx.outputs.length);
+  for (const output of tx.outputs) {
+    writer.bytes(serializeOutput(output));
+  }
+
+  if (segwit) {
+    for (const input of tx.inputs) {
+      const witness = input.witness ?? [];
+      writer.compactSize(witness.length);
+      for (const item of witness) {
+        writer.varBytes(item);
+      }
+    }
+  }
+
+  return writer.u32(tx.locktime).finish();
+}
+
+/** Inverse of serializeTransaction, for segwit and legacy encodings. */
+export function parseTransaction(bytes: Uint8Array): Transaction {
+  const reader = new Reader(bytes);
+  const version = reader.u32();
+  const segwit =
+    bytes[reader.offset] === 0x00 && bytes[reader.offset + 1] === 0x01;
+  if (segwit) {
+    reader.bytes(2);
+  }
+
+  const inputs: TxInput[] = [];
+  for (let i = reader.compactSize(); i > 0; i--) {
+    const txid = toHex(reader.bytes(32).reverse());
+    const vout = reader.u32();
+    reader.varBytes(); // scriptSig
+    inputs.push({ txid, vout, sequence: reader.u32() });
+  }
+
+  const outputs: TxOutput[] = [];
+  for (let i = reader.compactSize(); i > 0; i--) {
+    outputs.push({ value: reader.u64(), script: reader.varBytes() });
+  }
+
+  if (segwit) {
+    for (const input of inputs) {
+      input.witness = [];
+      for (let i = reader.compactSize(); i > 0; i--) {
+        input.witness.push(reader.varBytes());
+      }
+    }
+  }
+
+  const locktime = reader.u32();
+  if (reader.offset !== bytes.length) {
+    throw new Error(
+      `Transaction has ${bytes.length - reader.offset} trailing byte(s)`
+    );
+  }
+  return { version, inputs, outputs, locktime };
+}
+
+export function getTxid(tx: Transaction): string {
+  const hash = sha256(sha256(serializeTransaction(tx, false)));
+  return toHex(hash.reverse());
+}
+
+export function getVirtualSize(tx: Transaction): number {
+  const base = serializeTransaction(tx, false).length;
+  const total = serializeTransaction(tx, true).length;
+  return Math.ceil((base * 3 + total) / 4);
+}
+
+/**
+ * BIP341 signature hash with SIGHASH_DEFAULT. Pass `leafHash` for a
+ * script-path spend of that tapleaf.
+ */
+export function taprootSighash(
+  tx: Transaction,
+  inputIndex: number,
+  prevouts: TxOutput[],
+  leafHash?: Uint8Array
+): Uint8Array {
+  if (prevouts.length !== tx.inputs.length) {
+    throw new Error("A prevout is required for every input");
+  }
+
+  const concat = (parts: Uint8Array[]) =>
+    parts.reduce((w, part) => w.bytes(part), new Writer()).finish();
+
+  const writer = new Writer()
+    .bytes(new Uint8Array([0x00, 0x00])) // epoch, hash type
+    .u32(tx.version)
+    .u32(tx.locktime)
+    .bytes(sha256(concat(tx.inputs.map(outpoint))))
+    .bytes(
+      sha256(concat(prevouts.map((p) => new Writer().u64(p.value).finish())))
+    )
+    .bytes(
+      sha256(
+        concat(prevouts.map((p) => new Writer().varBytes(p.script).finish()))
+      )
+    )
+    .bytes(
+      sha256(
+        concat(
+          tx.inputs.map((input) =>
+            new Writer().u32(input.sequence ?? DEFAULT_SEQUENCE).finish()
+          )
+        )
+      )
+    )
+    .bytes(sha256(concat(tx.outputs.map(serializeOutput))))
+    .bytes(new Uint8Array([leafHash ? 0x02 : 0x00]))
+    .u32(inputIndex);
+
+  if (leafHash) {
+    writer
+      .bytes(leafHash)
+      .bytes(new Uint8Array([0x00])) // key version
+      .u32(0xffffffff); // no OP_CODESEPARATOR
+  }
+
+  return taggedHash("TapSighash", writer.finish());
+}
+
+export function tapLeafHash(script: Uint8Array): Uint8Array {
+  return taggedHash(
+    "TapLeaf",
+    new Writer()
+      .bytes(new Uint8Array([TAPROOT_LEAF_VERSION]))
+      .varBytes(script)
+      .finish()
+  );
+}
+
+/** Minimal push of `data` onto the script stack (empty data is OP_0). */
+export function pushData(data: Uint8Array): Uint8Array {
+  if (data.length > MAX_SCRIPT_ELEMENT_SIZE) {
+    throw new Error(`Push of ${data.length} bytes exceeds the element limit`);
+  }
+  if (data.length < OP_PUSHDATA1) {
+    return new Uint8Array([data.length, ...data]);
+  }
+  if (data.length <= 0xff) {
+    return new Uint8Array([OP_PUSHDATA1, data.length, ...data]);
+  }
+  return new Uint8Array([
+    OP_PUSHDATA2,
+    data.length & 0xff,
+    data.length >> 8,
+    ...data,
+  ]);
+}
+
+export function p2trScript(outputKey: Uint8Array): Uint8Array {
+  return new Uint8Array([0x51, 0x20, ...outputKey]);
+}
+
+// ---- bech32 / bech32m (BIP173, BIP350) ----
+
+const CHARSET = "qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const BECH32M_CONST = 0x2bc830a3;
+
+function polymod(values: number[]): number {
+  const generators = [
+    0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3,
+  ];
+  let chk = 1;
+  for (const value of values) {
+    const top = chk >>> 25;
+    chk = ((chk & 0x1ffffff) << 5) ^ value;
+    for (let i = 0; i < 5; i++) {
+      if ((top >>> i) & 1) {
+        chk ^= generators[i];
+      }
+    }
+  }
+  return chk >>> 0;
+}
+
+function hrpExpand(hrp: string): number[] {
+  const chars = [...hrp].map((c) => c.charCodeAt(0));
+  return [...chars.map((c) => c >> 5), 0, ...chars.map((c) => c & 31)];
+}
+
+function convertBits(
+  data: ArrayLike<number>,
+  from: number,
+  to: number,
+  pad: boolean
+): number[] {
+  let acc = 0;
+  let bits = 0;
+  const result: number[] = [];
+  const maxv = (1 << to) - 1;
+  const maxAcc = (1 << (from + to - 1)) - 1;
+  for (let i = 0; i < data.length; i++) {
+    acc = ((acc << from) | data[i]) & maxAcc;
+    bits += from;
+    while (bits >= to) {
+      bits -= to;
+      result.push((acc >> bits) & maxv);
+    }
+  }
+  if (pad) {
+    if (bits > 0) {
+      result.push((acc << (to - bits)) & maxv);
+    }
+  } else if (bits >= from || (acc << (to - bits)) & maxv) {
+    throw new Error("Invalid padding in address");
+  }
+  return result;
+}
+
+export function encodeSegwitAddress(
+  program: Uint8Array,
+  version: number,
+  network: Network
+): string {
+  const hrp = HRP[network];
+  const data = [version, ...convertBits(program, 8, 5, true)];
+  const constant = version === 0 ? 1 : BECH32M_CONST;
+  const checksumValue =
+    polymod([...hrpExpand(hrp), ...data, 0, 0, 0, 0, 0, 0]) ^ constant;
+  const checksum = Array.from(
+    { length: 6 },
+    (_, i) => (checksumValue >>> (5 * (5 - i))) & 31
+  );
+  return hrp + "1" + [...data, ...checksum].map((d) => CHARSET[d]).join("");
+}
+
+export function decodeSegwitAddress(
+  address: string,
+  network: Network
+): { version: number; program: Uint8Array } {
+  const lower = address.toLowerCase();
+  const separator = lower.lastIndexOf("1");
+  const hrp = lower.slice(0, separator);
+  if (hrp !== HRP[network]) {
+    throw new Error(`Address ${address} is not a ${network} address`);
+  }
+
+  const data = [...lower.slice(separator + 1)].map((c) => {
+    const index = CHARSET.indexOf(c);
+    if (index < 0) {
+      throw new Error(`Invalid character in address ${address}`);
+    }
+    return index;
+  });
+  if (data.length < 7) {
+    throw new Error(`Address ${address} is too short`);
+  }
+
+  const version = data[0];
+  const constant = version === 0 ? 1 : BECH32M_CONST;
+  if (polymod([...hrpExpand(hrp), ...data]) !== constant) {
+    throw new Error(`Invalid checksum in address ${address}`);
+  }
+
+  const program = new Uint8Array(convertBits(data.slice(1, -6), 5, 8, false));
+  if (program.length < 2 || program.length > 40) {
+    throw new Error(`Invalid witness program length in ${address}`);
+  }
+  return { version, program };
+}
+
+export function p2trAddress(outputKey: Uint8Array, network: Network): string {
+  return encodeSegwitAddress(outputKey, 1, network);
+}
+
+/** scriptPubKey for a segwit (v0 or taproot) address. */
+export function addressToScript(address: string, network: Network): Uint8Array {
+  const { version, program } = decodeSegwitAddress(address, network);
+  const opcode = version === 0 ? 0x00 : 0x50 + version;
+  return new Uint8Array([opcode, program.length, ...program]);
+}
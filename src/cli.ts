@@ -1,7 +1,36 @@
 #!/usr/bin/env node
 
 import { Command } from "commander";
-import { AlkanesCompiler, AlkanesContract } from "./index";
+import {
+  AlkaneId,
+  AlkanesABI,
+  AlkanesContract,
+  AlkanesEncoder,
+  AlkanesMethod,
+  AlkanesOpcode,
+  AlkanesVM,
+  DEFAULT_NODE_PORT,
+  DevNode,
+  JsonRpcProvider,
+  MockChain,
+  Network,
+  RegtestNode,
+  compileProject,
+  contractManifest,
+  extractAbi,
+  fromHex,
+  generateTypes,
+  getNetworkConfig,
+  loadArtifact,
+  loadConfig,
+  parseSubKeys,
+  readManifest,
+  serveJsonRpc,
+  StorageKey,
+  vendorCrates,
+} from "./index";
+import { spawn } from "child_process";
+import { constants as fsConstants } from "fs";
 import fs from "fs/promises";
 import path from "path";
 
@@ -14,12 +43,131 @@ function handleCommandError(error: any) {
   process.exit(1);
 }
 
-const program = new Command();
+/**
+ * Writes the template's package.json as the project's, or adds its scripts
+ * and dev dependencies to the project's existing one, keeping what the
+ * project already declares.
+ */
+async function writePackageJson(
+  templateFile: string,
+  name: string
+): Promise<void> {
+  const template = JSON.parse(await fs.readFile(templateFile, "utf8"));
+  const existing = await fs
+    .readFile("package.json", "utf8")
+    .then(JSON.parse, () => undefined);
+  let merged;
+  if (existing === undefined) {
+    merged = { ...template, name };
+  } else {
+    const declared = {
+      ...existing.dependencies,
+      ...existing.devDependencies,
+    };
+    const devDependencies = Object.fromEntries(
+      Object.entries(template.devDependencies ?? {}).filter(
+        ([dependency]) => !(dependency in declared)
+      )
+    );
+    merged = {
+      ...existing,
+      scripts: { ...template.scripts, ...existing.scripts },
+      devDependencies: { ...existing.devDependencies, ...devDependencies },
+    };
+  }
+  await fs.writeFile("package.json", `${JSON.stringify(merged, null, 2)}\n`);
+}
+
+async function writeTypes(abi: AlkanesABI, outDir: string): Promise<string> {
+  await fs.mkdir(outDir, { recursive: true });
+  const typesPath = path.join(outDir, `${abi.name}.ts`);
+  await fs.writeFile(typesPath, generateTypes(abi));
+  return typesPath;
+}
+
+// The ABI's initialize, or the opcode-0 one of contracts without arguments
+function initializeMethod(abi: AlkanesABI): AlkanesMethod {
+  return (
+    abi.methods.find((m) => m.name === "initialize") ?? {
+      opcode: AlkanesOpcode.Initialize,
+      name: "initialize",
+      inputs: [],
+      outputs: [],
+    }
+  );
+}
+
+// Command-line arguments converted by the method's input types
+function parseMethodArgs(method: AlkanesMethod, args: string[]): any[] {
+  const encoder = new AlkanesEncoder();
+  const params = encoder.parseArgs(method, args);
+  encoder.validateArgs(method, params);
+  return params;
+}
+
+// `/balances/{Vec<u8>}`: the key with placeholders for its sub-keys
+function describeStorageKey(entry: StorageKey): string {
+  const path = (entry.path ?? []).map((segment) =>
+    "keyword" in segment
+      ? segment.keyword
+      : `{${formatStorageValue(segment.select)}}`
+  );
+  return entry.key + path.join("");
+}
+
+function formatStorageValue(value: any): string {
+  if (typeof value === "string") {
+    return value;
+  }
+  return JSON.stringify(value, (_, item) => {
+    if (typeof item === "bigint") {
+      return item.toString();
+    }
+    if (item instanceof Uint8Array) {
+      return "0x" + Buffer.from(item).toString("hex");
+    }
+    return item;
+  });
+}
+
+// Test files `alkali test` discovers under test/ and tests/
+const TEST_FILE_REGEX = /\.(test|spec)\.[jt]s$/;
+
+// Files under `dir` (recursively) whose name matches `pattern`
+async function findFiles(dir: string, pattern: RegExp): Promise<string[]> {
+  let entries;
+  try {
+    entries = await fs.readdir(dir, { withFileTypes: true });
+  } catch {
+    return [];
+  }
+  const files: string[] = [];
+  for (const entry of entries) {
+    const file = path.join(dir, entry.name);
+    if (entry.isDirectory()) {
+      files.push(...(await findFiles(file, pattern)));
+    } else if (pattern.test(entry.name)) {
+      files.push(file);
+    }
+  }
+  return files.sort();
+}
+
+export const program = new Command();
 
 program
   .name("alkali")
   .description("Smart contract development toolkit for Bitcoin Alkanes")
-  .version("0.1.0");
+  .version("0.1.0")
+  .option(
+    "-c, --config <file>",
+    "Config file (default: alkali.config.ts, .js or .json)"
+  );
+
+// The project config every command runs with
+function projectConfig() {
+  return loadConfig({ configPath: program.opts().config });
+}
 
 program
   .command("init")
@@ -33,6 +181,8 @@ program
       await fs.mkdir("contracts", { recursive: true });
       await fs.mkdir("build", { recursive: true });
       await fs.mkdir("scripts", { recursive: true });
+      await fs.mkdir("test", { recursive: true });
+      await fs.mkdir("tests", { recursive: true });
 
       // Get template path
       const templatePath = path.join(
@@ -59,6 +209,60 @@ program
       const contractDest = path.join("contracts", "Example.rs");
       await fs.copyFile(contractTemplatePath, contractDest);
 
+      // Copy test templates: TypeScript tests against the local VM, and
+      // native Rust unit tests under tests/
+      await fs.copyFile(
+        path.join(templatePath, "test", "Example.test.ts"),
+        path.join("test", "Example.test.ts")
+      );
+      await fs.copyFile(
+        path.join(templatePath, "tests", "example.rs"),
+        path.join("tests", "example.rs")
+      );
+
+      // Copy the deploy script, and the TypeScript settings it and the
+      // tests compile with unless the project has its own
+      await fs.copyFile(
+        path.join(templatePath, "scripts", "deploy.ts"),
+        path.join("scripts", "deploy.ts")
+      );
+      await fs
+        .copyFile(
+          path.join(templatePath, "tsconfig.json"),
+          "tsconfig.json",
+          fsConstants.COPYFILE_EXCL
+        )
+        .catch((error) => {
+          if (error.code !== "EEXIST") throw error;
+        });
+
+      // package.json with the scripts and the Jest setup `alkali test`
+      // runs, merged into the project's own when it has one
+      const packageName = path
+        .basename(process.cwd())
+        .toLowerCase()
+        .replace(/[^a-z0-9_-]+/g, "-");
+      await writePackageJson(
+        path.join(templatePath, "package.json"),
+        packageName
+      );
+
+      // Cargo manifest so `cargo test` builds the contract natively, with
+      // the alkali crates vendored under vendor/ and paths relative to the
+      // project so it can be moved or shared. It pins alkanes-rs like the
+      // contracts, and `alkali compile` seeds its Cargo.lock from theirs.
+      const vendorDir = path.resolve("vendor");
+      await vendorCrates(vendorDir);
+      await fs.writeFile(
+        "Cargo.toml",
+        contractManifest({
+          name: packageName,
+          lib: { name: "example", path: contractDest },
+          dir: process.cwd(),
+          crates: vendorDir,
+        })
+      );
+
       // Create config file
       const configContent = {
         name: path.basename(process.cwd()),
@@ -66,6 +270,10 @@ program
           target: "wasm32-unknown-unknown",
           optimizeLevel: 3,
         },
+        network: "regtest",
+        networks: {
+          regtest: { url: "http://127.0.0.1:18888" },
+        },
       };
       await fs.writeFile(
         "alkali.config.json",
@@ -74,9 +282,13 @@ program
 
       console.log("✅ Project initialized successfully");
       console.log("\nNext steps:");
-      console.log("  1. npx alkali compile            # Compile contracts");
-      console.log("  2. npx alkali test               # Run tests");
-      console.log("  3. npx alkali deploy             # Deploy contracts");
+      console.log("  1. npm install                   # Install dependencies");
+      console.log("  2. npx alkali compile            # Compile contracts");
+      console.log("  3. npx alkali test               # Run tests");
+      console.log("  4. cargo test                    # Run Rust unit tests");
+      console.log(
+        "  5. npx alkali deploy Example --utxo <txid:vout:value>  # Deploy it"
+      );
     } catch (error) {
       console.error("❌ Failed to initialize project", error);
       process.exit(1);
@@ -84,65 +296,521 @@ program
   });
 
 program
-  .command("compile <file>")
-  .description("Compile a Rust contract to WASM")
-  .option("-o, --output <dir>", "Output directory", "./build")
-  .action(async (file: string, options) => {
+  .command("compile [files...]")
+  .description(
+    "Compile the project's contracts, or the given files, into the build directory"
+  )
+  .option("-o, --output <dir>", "Build directory (default: paths.build)")
+  .option("--locked", "Fail if the contracts' Cargo.lock would change")
+  .option("--offline", "Build without network access")
+  .action(async (files: string[], options) => {
     try {
-      const sourceCode = await fs.readFile(file, "utf8");
-      const compiler = new AlkanesCompiler("http://localhost:3000");
+      const config = await projectConfig();
+      if (options.output) {
+        config.paths.build = path.resolve(options.output);
+      }
+      config.compiler.locked ||= Boolean(options.locked);
+      config.compiler.offline ||= Boolean(options.offline);
+
+      const compiled = await compileProject(
+        config,
+        files.length ? files : undefined,
+        { log: console.log }
+      );
+
+      const build = path.relative(process.cwd(), config.paths.build) || ".";
+      console.log(`✅ Compiled ${compiled.length} contract(s) into ${build}:`);
+      for (const { artifact, bytecode } of compiled) {
+        console.log(
+          `- ${artifact.name}: ${artifact.wasm} (${bytecode.length} bytes), ${artifact.abi}, ${artifact.types}`
+        );
+      }
+    } catch (error) {
+      handleCommandError(error);
+    }
+  });
 
-      const result = await compiler.compile(sourceCode);
-      if (!result) {
-        throw new Error("Compilation failed, no result returned");
+program
+  .command("typegen [abi]")
+  .description(
+    "Generate typed contract classes from an ABI or WASM file, or for every compiled contract"
+  )
+  .option(
+    "-o, --output <dir>",
+    "Output directory (default: the types directory under paths.build)"
+  )
+  .action(async (file: string | undefined, options) => {
+    try {
+      const config = await projectConfig();
+      const outDir = options.output ?? path.join(config.paths.build, "types");
+      if (!file) {
+        const { contracts } = await readManifest(config.paths.build);
+        const names = Object.keys(contracts);
+        if (names.length === 0) {
+          throw new Error(
+            "No compiled contracts; run `alkali compile` or pass an ABI file"
+          );
+        }
+        await fs.mkdir(outDir, { recursive: true });
+        for (const name of names) {
+          const { abi } = await loadArtifact(config.paths.build, name);
+          const typesPath = path.join(outDir, `${name}.ts`);
+          await fs.writeFile(typesPath, generateTypes(abi));
+          console.log(`✅ Types generated: ${typesPath}`);
+        }
+        return;
       }
-      const { bytecode, abi } = result;
 
-      // Create output directory
-      await fs.mkdir(options.output, { recursive: true });
+      const abi = file.endsWith(".wasm")
+        ? extractAbi(await fs.readFile(file))
+        : JSON.parse(await fs.readFile(file, "utf8"));
+      if (!abi) {
+        throw new Error(`${file} has no embedded ABI`);
+      }
+      const typesPath = await writeTypes(abi, outDir);
+      console.log(`✅ Types generated: ${typesPath}`);
+    } catch (error) {
+      handleCommandError(error);
+    }
+  });
 
-      // Save bytecode
-      const wasmPath = path.join(options.output, "contract.wasm");
-      await fs.writeFile(wasmPath, Buffer.from(bytecode, "base64"));
+program
+  .command("test [files...]")
+  .description("Compile the contracts and run the project's tests")
+  .option("--no-compile", "Use the contracts compiled by the last run")
+  .action(async (files: string[], options) => {
+    try {
+      const config = await projectConfig();
+      const testDirs = [
+        ...new Set([config.paths.tests, path.join(config.root, "tests")]),
+      ];
+      const testFiles = files.length
+        ? files
+        : (
+            await Promise.all(
+              testDirs.map((dir) => findFiles(dir, TEST_FILE_REGEX))
+            )
+          ).flat();
+      if (testFiles.length === 0) {
+        const dirs = testDirs.map((dir) => path.relative(config.root, dir));
+        throw new Error(`No test files found in ${dirs.join(" or ")}`);
+      }
 
-      // Save ABI
-      const abiPath = path.join(options.output, "abi.json");
-      await fs.writeFile(abiPath, JSON.stringify(abi, null, 2));
+      // Tests deploy the same artifacts `alkali compile` writes
+      if (options.compile) {
+        await compileProject(config, undefined, { log: console.log });
+      }
 
-      console.log(`✅ Contract compiled successfully:
-- Bytecode: ${wasmPath}
-- ABI: ${abiPath}`);
+      // Tests run under the project's own Jest, compiling TypeScript with
+      // ts-jest, so they can import the harness from this package
+      let jest: string;
+      try {
+        jest = require.resolve("jest/bin/jest", { paths: [config.root] });
+      } catch {
+        throw new Error(
+          "Jest is not installed; run `npm install --save-dev jest ts-jest @types/jest`"
+        );
+      }
+      const jestConfig = {
+        rootDir: config.root,
+        testEnvironment: "node",
+        transform: { "^.+\\.tsx?$": "ts-jest" },
+      };
+      const child = spawn(
+        process.execPath,
+        [
+          jest,
+          "--config",
+          JSON.stringify(jestConfig),
+          "--runTestsByPath",
+          ...testFiles,
+        ],
+        {
+          stdio: "inherit",
+          env: {
+            ...process.env,
+            ALKALI_ARTIFACTS: config.paths.build,
+          },
+        }
+      );
+      const code = await new Promise<number | null>((resolve, reject) => {
+        child.on("error", reject);
+        child.on("close", resolve);
+      });
+      process.exitCode = code ?? 1;
     } catch (error) {
       handleCommandError(error);
     }
   });
 
 program
-  .command("deploy")
-  .description("Deploy a compiled contract")
-  .requiredOption("--wasm <file>", "WASM bytecode file")
-  .requiredOption("--abi <file>", "ABI JSON file")
+  .command("deploy [contract]")
+  .description(
+    "Deploy a compiled contract, by name, or a WASM file with a commit/reveal pair"
+  )
+  .option("--wasm <file>", "WASM bytecode file, instead of a compiled contract")
+  .option(
+    "--abi <file>",
+    "ABI JSON file (default: the contract's, or the one embedded in --wasm)"
+  )
+  .option(
+    "--key-file <file>",
+    "File holding the hex private key controlling the funding UTXO (default: $ALKALI_PRIVATE_KEY, then the network's first account)"
+  )
+  .requiredOption("--utxo <txid:vout:value>", "Funding UTXO")
+  .option("--fee-rate <sat/vB>", "Fee rate", "10")
+  .option(
+    "--network <name>",
+    "Network from the config (default: its network)"
+  )
+  .option("--reserved <n>", "Deploy to reserved target [3, n], yielding [4, n]")
   .option("--args <args...>", "Constructor arguments")
-  .action(async (options) => {
+  .option("--no-broadcast", "Only print the signed transactions")
+  .action(async (name: string | undefined, options) => {
     try {
-      // Load files
-      const bytecode = await fs.readFile(options.wasm);
-      const abi = JSON.parse(await fs.readFile(options.abi, "utf8"));
+      if (!name === !options.wasm) {
+        throw new Error("Pass either a compiled contract's name or --wasm");
+      }
+      const config = await projectConfig();
+      let bytecode: Uint8Array;
+      let abi: AlkanesABI | undefined;
+      if (name) {
+        ({ bytecode, abi } = await loadArtifact(config.paths.build, name));
+      } else {
+        bytecode = await fs.readFile(options.wasm);
+        abi = extractAbi(bytecode);
+      }
+      if (options.abi) {
+        abi = JSON.parse(await fs.readFile(options.abi, "utf8"));
+      }
+      if (!abi) {
+        throw new Error(`${options.wasm} has no embedded ABI; pass --abi`);
+      }
+
+      const [txid, vout, value, ...rest] = options.utxo.split(":");
+      if (
+        !/^[0-9a-f]{64}$/i.test(txid) ||
+        !/^\d+$/.test(vout ?? "") ||
+        Number(vout) > 0xffffffff ||
+        !/^\d+$/.test(value ?? "") ||
+        rest.length > 0
+      ) {
+        throw new Error(
+          "--utxo must be formatted as txid:vout:value, with a 32-byte hex txid and integer vout and value"
+        );
+      }
+      const feeRate = Number(options.feeRate);
+      if (!(feeRate > 0)) {
+        throw new Error("--fee-rate must be a positive number");
+      }
+
+      // Only broadcasting needs the network to be configured
+      const networkName: string = options.network ?? config.network;
+      const settings = options.broadcast
+        ? getNetworkConfig(config, networkName)
+        : config.networks[networkName];
+      // Keys stay out of argv, where other processes and shell history
+      // would see them
+      const key = options.keyFile
+        ? (await fs.readFile(options.keyFile, "utf8")).trim()
+        : process.env.ALKALI_PRIVATE_KEY ?? settings?.accounts[0];
+      if (!key) {
+        throw new Error(
+          `Pass --key-file, set ALKALI_PRIVATE_KEY or set networks.${networkName}.accounts in the config`
+        );
+      }
+      if (!/^[0-9a-f]{64}$/i.test(key)) {
+        throw new Error("The private key must be 32 bytes of hex");
+      }
 
       // Create contract instance
       const contract = new AlkanesContract({
-        bytecode: bytecode.toString("base64"),
+        bytecode: Buffer.from(bytecode).toString("base64"),
         abi,
+        provider:
+          settings && options.broadcast
+            ? new JsonRpcProvider({
+                url: settings.url,
+                bitcoinUrl: settings.bitcoinUrl,
+                headers: settings.headers,
+              })
+            : undefined,
       });
 
-      // Deploy
-      const address = await contract.deploy(options.args || []);
+      const params = parseMethodArgs(initializeMethod(abi), options.args ?? []);
+
+      const deployOptions = {
+        privateKey: fromHex(key),
+        utxo: { txid, vout: Number(vout), value: BigInt(value) },
+        feeRate,
+        network: settings?.network ?? (networkName as Network),
+        reservedNumber:
+          options.reserved === undefined ? undefined : BigInt(options.reserved),
+      };
 
-      console.log(`✅ Contract deployed successfully:
-Address: ${address}`);
+      if (options.broadcast) {
+        const result = await contract.deploy(deployOptions, params);
+        console.log(`✅ Contract deployed successfully:
+- Commit: ${result.commitTxid}
+- Reveal: ${result.revealTxid}
+AlkaneId: ${result.alkaneId}`);
+        return;
+      }
+
+      const { transactions, alkaneId } = contract.buildDeploy(
+        deployOptions,
+        params
+      );
+
+      console.log(`✅ Deployment transactions built (broadcast in order):
+- Commit ${transactions.commitTxid}:
+${transactions.commitHex}
+- Reveal ${transactions.revealTxid}:
+${transactions.revealHex}`);
+      if (alkaneId) {
+        console.log(`AlkaneId: ${alkaneId}`);
+      }
     } catch (error) {
       handleCommandError(error);
     }
   });
 
-program.parse();
+program
+  .command("storage <alkaneId> [key] [subKeys...]")
+  .description(
+    "Read a deployed contract's storage, decoded by its ABI; map entries are listed when the node can enumerate them (alkali node)"
+  )
+  .option(
+    "--abi <file>",
+    "ABI JSON file (default: the ABI embedded in the deployed bytecode)"
+  )
+  .option(
+    "--network <name>",
+    "Network from the config (default: its network)"
+  )
+  .action(
+    async (
+      alkaneId: string,
+      key: string | undefined,
+      subKeys: string[],
+      options
+    ) => {
+      try {
+        const provider = await JsonRpcProvider.fromConfig(
+          program.opts().config,
+          options.network
+        );
+        const contract = options.abi
+          ? new AlkanesContract({
+              abi: JSON.parse(await fs.readFile(options.abi, "utf8")),
+              bytecode: "",
+              address: AlkaneId.from(alkaneId),
+              provider,
+            })
+          : await AlkanesContract.fromAlkaneId(alkaneId, provider);
+
+        const entries = key
+          ? contract.storage.keys.filter((entry) => entry.key === key)
+          : contract.storage.keys;
+        if (key && entries.length === 0) {
+          throw new Error(`Storage key ${key} not found in ABI`);
+        }
+        const isMap = (entry: StorageKey) =>
+          entry.path?.some((segment) => "select" in segment) ?? false;
+
+        if (key && (subKeys.length > 0 || !isMap(entries[0]))) {
+          const parsed = parseSubKeys(entries[0], subKeys);
+          const value = await contract.storage.get(key, ...parsed);
+          console.log(formatStorageValue(value));
+          return;
+        }
+
+        for (const entry of entries) {
+          const name = describeStorageKey(entry);
+          const type = formatStorageValue(entry.type);
+          if (!isMap(entry)) {
+            const value = await contract.storage.get(entry.key);
+            console.log(`${name}: ${type} = ${formatStorageValue(value)}`);
+            continue;
+          }
+          // Map entries are listed where the provider can enumerate them
+          console.log(`${name}: ${type}`);
+          let listed: Array<[any, any]>;
+          try {
+            listed = await contract.storage.entries(entry.key);
+          } catch (error) {
+            const message = error instanceof Error ? error.message : error;
+            console.log(`  (${message}; pass sub-keys to read an entry)`);
+            continue;
+          }
+          for (const [subKey, value] of listed) {
+            const path = formatStorageValue(subKey);
+            console.log(`  ${path} = ${formatStorageValue(value)}`);
+          }
+        }
+      } catch (error) {
+        handleCommandError(error);
+      }
+    }
+  );
+
+program
+  .command("run <wasm> <method> [args...]")
+  .description("Deploy a contract in a local VM and execute one of its methods")
+  .option(
+    "--abi <file>",
+    "ABI JSON file (default: the ABI embedded in the WASM)"
+  )
+  .option("--init <args...>", "Arguments of initialize")
+  .action(
+    async (wasm: string, methodName: string, args: string[], options) => {
+      try {
+        const bytecode = new Uint8Array(await fs.readFile(wasm));
+        const abi: AlkanesABI | undefined = options.abi
+          ? JSON.parse(await fs.readFile(options.abi, "utf8"))
+          : extractAbi(bytecode);
+        if (!abi) {
+          throw new Error(`${wasm} has no embedded ABI; pass --abi`);
+        }
+        const method = abi.methods.find((m) => m.name === methodName);
+        if (!method) {
+          throw new Error(`Method ${methodName} not found in ABI`);
+        }
+
+        const initialize = initializeMethod(abi);
+        const initArgs = parseMethodArgs(initialize, options.init ?? []);
+        const params = parseMethodArgs(method, args);
+
+        const vm = new AlkanesVM();
+        const encoder = new AlkanesEncoder();
+        const deployment = encoder.buildCellpack(
+          AlkaneId.create(),
+          initialize,
+          initArgs
+        );
+        const { alkaneId } = await vm.deploy(bytecode, deployment.inputs);
+
+        const result = await vm.execute(
+          encoder.buildCellpack(alkaneId, method, params)
+        );
+        if (result.status !== 0) {
+          throw new Error(
+            `${methodName} reverted: ${result.error ?? `status ${result.status}`}`
+          );
+        }
+
+        const values = encoder.decodeResponse(
+          method.outputs,
+          result.response.data
+        );
+        console.log(`✅ ${abi.name}.${methodName} on ${alkaneId}:`);
+        values.forEach((value, i) => {
+          const name = method.outputs[i].name;
+          console.log(`- ${name}: ${formatStorageValue(value)}`);
+        });
+        console.log(`Gas used: ${result.gasUsed}`);
+        for (const diff of result.storage) {
+          const key = JSON.stringify(Buffer.from(diff.key).toString("utf8"));
+          const before = formatStorageValue(diff.before);
+          const after = formatStorageValue(diff.after);
+          console.log(`Storage ${diff.alkane} ${key}: ${before} -> ${after}`);
+        }
+        result.logs.forEach((log) => console.log(`Log: ${log}`));
+      } catch (error) {
+        handleCommandError(error);
+      }
+    }
+  );
+
+program
+  .command("node")
+  .description(
+    "Run a local regtest chain with the alkanes indexer, or a mock chain"
+  )
+  .option("--mock", "Run an in-process mock chain needing no binaries")
+  .option(
+    "-p, --port <port>",
+    `JSON-RPC port (default: from networks.regtest.url, else ${DEFAULT_NODE_PORT})`
+  )
+  .option("--accounts <n>", "Number of funded dev accounts", "10")
+  .option("--no-auto-mine", "Only mine blocks on btc_generatetoaddress")
+  .action(async (options) => {
+    try {
+      const config = await projectConfig();
+      const configuredUrl = config.networks.regtest?.url;
+      const port = Number(
+        options.port ||
+          (configuredUrl && new URL(configuredUrl).port) ||
+          DEFAULT_NODE_PORT
+      );
+
+      const accounts = Number(options.accounts);
+      if (!Number.isInteger(accounts) || accounts < 1) {
+        throw new Error("--accounts must be a positive integer");
+      }
+
+      const settings = config.node;
+      const nodeOptions = { accounts, autoMine: options.autoMine };
+      let node: DevNode;
+      if (options.mock) {
+        node = new MockChain(nodeOptions);
+      } else {
+        if (!settings.indexer) {
+          throw new Error(
+            "Set node.indexer in the config to the alkanes indexer WASM, or pass --mock"
+          );
+        }
+        node = new RegtestNode({
+          ...settings,
+          indexer: settings.indexer,
+          ...nodeOptions,
+        });
+      }
+
+      console.log(
+        options.mock
+          ? "⛏️  Starting mock chain..."
+          : "⛏️  Starting bitcoind and metashrew..."
+      );
+      await node.start();
+      const server = await serveJsonRpc(
+        (method, params) => node.handle(method, params),
+        port
+      );
+
+      // The config is the user's: only say where to point the provider
+      const url = `http://127.0.0.1:${port}`;
+      if (configuredUrl !== url) {
+        console.log(
+          `⚠️  Set networks.regtest.url to ${url} in ${path.basename(
+            config.configPath ?? "alkali.config.json"
+          )}`
+        );
+      }
+
+      console.log(`✅ JSON-RPC listening on ${url}\n`);
+      console.log("Accounts (never send real funds to these keys):");
+      node.accounts.forEach((account, i) => {
+        console.log(`#${i} ${account.address}`);
+        console.log(`   key:  ${account.privateKey}`);
+        if (account.utxo) {
+          const { txid, vout, value } = account.utxo;
+          console.log(`   utxo: ${txid}:${vout}:${value}`);
+        }
+      });
+
+      process.once("SIGINT", async () => {
+        console.log("\nStopping node...");
+        server.close();
+        await node.stop();
+        process.exit(0);
+      });
+    } catch (error) {
+      handleCommandError(error);
+    }
+  });
+
+// Parsed when run as the `alkali` bin; tests drive `program` directly
+if (require.main === module) {
+  program.parse();
+}
@@ -1,142 +1,783 @@
 import { exec } from "child_process";
 import { promisify } from "util";
 import fs from "fs/promises";
+import os from "os";
 import path from "path";
+import { parse as parseToml } from "smol-toml";
+import {
+  AbiExtractionError,
+  ExtractedContract,
+  ExtractedMethod,
+  extractContract,
+  extractCrate,
+} from "./abiExtractor";
+import {
+  ALKANES_RS_GIT,
+  ALKANES_RS_REV,
+  AlkaliConfig,
+  AlkanesSource,
+  DependencySpec,
+  OptimizeLevel,
+} from "./config";
 import {
   AlkanesABI,
   AlkanesMethod,
+  AlkanesParam,
   AlkanesPrimitive,
   AlkanesType,
   StorageKey,
 } from "./types";
+import { toHex } from "./bitcoin";
+import { sha256 } from "./secp256k1";
+import { embedAbi } from "./wasm";
 
 const execAsync = promisify(exec);
 
+// Root of this package, whose crates the contracts depend on
+const PACKAGE_ROOT = path.join(__dirname, "..");
+
+// The crates shipped in this package: `alkali` providing
+// #[alkali::contract] and `alkali-test` for native unit tests of contracts,
+// with the crates they depend on
+const ALKALI_CRATES_DIR = path.join(PACKAGE_ROOT, "crates");
+const ALKALI_CRATES = ["alkali", "alkali-abi", "alkali-macros", "alkali-test"];
+
+// `name(type, ...)` method signature comments, optionally `-> type`
+const SIGNATURE_REGEX = /^(\w+)\s*\((.*?)\)\s*(?:->\s*(.+))?$/s;
+
+// Types a signature comment may name besides arrays, vectors and tuples
+const PRIMITIVES: AlkanesPrimitive[] = [
+  "u8",
+  "u16",
+  "u32",
+  "u64",
+  "u128",
+  "i8",
+  "i16",
+  "i32",
+  "i64",
+  "i128",
+  "String",
+  "bool",
+  "Vec<u8>",
+];
+
+// Held in the workspace while a compiler writes and builds it
+const BUILD_LOCK = "alkali-build.lock";
+
+// Crate built from `lib.rs` in the contracts directory, shared by contracts
+const SHARED_CRATE = "lib";
+
+// alkanes-rs when the compiler is given no source
+const DEFAULT_ALKANES: AlkanesSource = {
+  git: ALKANES_RS_GIT,
+  rev: ALKANES_RS_REV,
+};
+
+// Crates of alkanes-rs the contracts build with
+const ALKANES_CRATES = [
+  "alkanes-runtime",
+  "alkanes-support",
+  "metashrew-support",
+];
+
+// Dependencies every contract crate has; users cannot redefine them
+const PROVIDED_CRATES = [
+  ...ALKANES_CRATES,
+  "anyhow",
+  "hex-lit",
+  "alkali",
+  SHARED_CRATE,
+];
+
+export interface ContractManifestOptions {
+  /** Package name (default: alkanes-contract) */
+  name?: string;
+  /** Library target other than src/lib.rs, e.g. a file under contracts/ */
+  lib?: { name: string; path: string };
+  /** Library crate types (default: cdylib and rlib) */
+  crateTypes?: string[];
+  /** alkanes-rs repository and pin, or checkout (default: ALKANES_RS_REV) */
+  alkanes?: AlkanesSource;
+  /** Further dependencies, as TOML values by crate name */
+  dependencies?: Record<string, string>;
+  /** Dependencies for some targets only, by `cfg(...)` or target triple */
+  targets?: Record<string, Record<string, string>>;
+  /** Directory the manifest is written to, making local crate paths relative */
+  dir?: string;
+  /** Directory of the alkali crates, e.g. vendored (default: the package's) */
+  crates?: string;
+}
+
+export interface AlkanesCompilerOptions {
+  /** Project root, remapped out of the built WASM (default: the cwd) */
+  root?: string;
+  /** Directory of the generated cargo workspace (default: .alkanes) */
+  cacheDir?: string;
+  /** Contract sources; a `lib.rs` here becomes the shared `lib` crate */
+  contractsDir?: string;
+  /** Rust target (default: wasm32-unknown-unknown) */
+  target?: string;
+  /** Release profile opt-level (default: 3) */
+  optimizeLevel?: OptimizeLevel;
+  alkanes?: AlkanesSource;
+  /** Crates added to every contract */
+  dependencies?: Record<string, DependencySpec>;
+  /**
+   * Cargo.lock kept with the project: builds start from it and update it
+   * (default: the workspace's own lock only)
+   */
+  lockfile?: string;
+  /** Pass `--locked`: fail when the lock would change */
+  locked?: boolean;
+  /** Pass `--offline`, e.g. with vendored crates or a local alkanes-rs */
+  offline?: boolean;
+}
+
+/** A crate as recorded in Cargo.lock. */
+export interface LockedPackage {
+  name: string;
+  version: string;
+  /** Registry or git URL (`#<commit>` for git); none for local crates */
+  source?: string;
+}
+
+/** What a contract was built with, to reproduce its WASM. */
+export interface BuildInfo {
+  /** `rustc -V` of the toolchain */
+  rustc: string;
+  /** `cargo -V` of the toolchain */
+  cargo: string;
+  target: string;
+  optimizeLevel: OptimizeLevel;
+  /**
+   * alkanes-rs as built: the repository and its pin, or the checkout, at
+   * the commit they resolved to
+   */
+  alkanes: Partial<AlkanesSource> & { commit?: string };
+  /** The locked crates the contract's crate depends on */
+  dependencies: LockedPackage[];
+  /** Flags rustc got: inherited RUSTFLAGS, then the path remappings */
+  rustflags: string[];
+  /** sha256 of the WASM cargo built, before the ABI is embedded */
+  wasmSha256: string;
+}
+
+export interface CompileResult {
+  /** Base64 WASM with the ABI embedded */
+  bytecode: string;
+  abi: AlkanesABI;
+  buildInfo: BuildInfo;
+}
+
+/** A contract crate of the generated workspace. */
+export interface WorkspaceMember {
+  /** Package and library name, e.g. `my_token` for MyToken.rs */
+  name: string;
+  /** Crate directory */
+  dir: string;
+  /** The contract file, or directory with a `lib.rs`, the crate builds */
+  source: string;
+}
+
+/**
+ * Cargo.toml of a contract crate: a cdylib for the WASM build plus an rlib
+ * that `cargo test` links, with `alkali-test` for native unit tests.
+ */
+export function contractManifest(
+  options: ContractManifestOptions = {}
+): string {
+  const local = (dir: string) =>
+    JSON.stringify(
+      options.dir === undefined ? dir : path.relative(options.dir, dir)
+    );
+  const lib = options.lib
+    ? `name = ${JSON.stringify(options.lib.name)}
+path = ${local(options.lib.path)}
+`
+    : "";
+  const crates = options.crates ?? ALKALI_CRATES_DIR;
+  const crateTypes = (options.crateTypes ?? ["cdylib", "rlib"])
+    .map((type) => JSON.stringify(type))
+    .join(", ");
+  const alkanes = (crate: string) =>
+    alkanesDependency(options.alkanes ?? DEFAULT_ALKANES, crate, local);
+  const table = (dependencies: Record<string, string>) =>
+    Object.entries(dependencies)
+      .map(([name, value]) => `${name} = ${value}\n`)
+      .join("");
+  const targets = Object.entries(options.targets ?? {})
+    .map(
+      ([target, dependencies]) =>
+        `\n[target.${JSON.stringify(target)}.dependencies]\n${table(dependencies)}`
+    )
+    .join("");
+  return `[package]
+name = ${JSON.stringify(options.name ?? "alkanes-contract")}
+version = "0.1.0"
+edition = "2021"
+
+[lib]
+${lib}crate-type = [${crateTypes}]
+
+[dependencies]
+alkanes-runtime = ${alkanes("alkanes-runtime")}
+alkanes-support = ${alkanes("alkanes-support")}
+metashrew-support = ${alkanes("metashrew-support")}
+anyhow = "1.0"
+hex-lit = "0.1.1"
+alkali = { path = ${local(path.join(crates, "alkali"))} }
+${table(options.dependencies ?? {})}${targets}
+[dev-dependencies]
+alkali-test = { path = ${local(path.join(crates, "alkali-test"))} }
+`;
+}
+
+/**
+ * Copies the alkali crates, their manifests and sources, into `dir`, so a
+ * project depends on them by paths inside it rather than into wherever
+ * this package is installed.
+ */
+export async function vendorCrates(dir: string): Promise<void> {
+  for (const crate of ALKALI_CRATES) {
+    const source = path.join(ALKALI_CRATES_DIR, crate);
+    const destination = path.join(dir, crate);
+    await fs.rm(destination, { recursive: true, force: true });
+    await fs.mkdir(destination, { recursive: true });
+    await fs.copyFile(
+      path.join(source, "Cargo.toml"),
+      path.join(destination, "Cargo.toml")
+    );
+    await fs.cp(path.join(source, "src"), path.join(destination, "src"), {
+      recursive: true,
+    });
+  }
+}
+
+/**
+ * Inline table for an alkanes-rs crate: the repository and its pin, or the
+ * crate's directory in a local checkout.
+ */
+function alkanesDependency(
+  source: AlkanesSource,
+  crate: string,
+  local: (dir: string) => string
+): string {
+  if (source.path !== undefined) {
+    return `{ path = ${local(path.join(source.path, "crates", crate))} }`;
+  }
+  const fields = (["git", "rev", "tag", "branch"] as const)
+    .filter((field) => source[field] !== undefined)
+    .map((field) => `${field} = ${JSON.stringify(source[field])}`);
+  return `{ ${fields.join(", ")} }`;
+}
+
+/** TOML value of a dependency, e.g. `{ version = "1.0", features = [...] }`. */
+export function dependencyValue(spec: DependencySpec): string {
+  if (typeof spec === "string") {
+    return JSON.stringify(spec);
+  }
+  const fields = Object.entries(spec)
+    .filter(([, value]) => value !== undefined)
+    .map(([field, value]) => `${field} = ${JSON.stringify(value)}`);
+  return `{ ${fields.join(", ")} }`;
+}
+
+/** Crate name of a contract source: `MyToken.rs` builds `my_token`. */
+export function contractCrateName(file: string): string {
+  return path
+    .basename(file, ".rs")
+    .replace(/([a-z0-9])([A-Z])/g, "$1_$2")
+    .replace(/[^A-Za-z0-9_]+/g, "_")
+    .toLowerCase();
+}
+
+/**
+ * Compiles contracts as crates of a cargo workspace in the cache directory:
+ * one member under `contracts/` per contract, building the source in place,
+ * plus a shared `lib` crate when the contracts directory has a `lib.rs`.
+ * A contract is a `.rs` file, or a directory whose `lib.rs` declares its
+ * modules and whose optional `Cargo.toml` lists its own `[dependencies]`.
+ * Cargo's target directory is kept between builds, so only what changed is
+ * rebuilt, and cargo's build lock serializes concurrent compiles.
+ */
 export class AlkanesCompiler {
+  private root: string;
   private tempDir: string;
+  private contractsDir: string;
+  private target: string;
+  private optimizeLevel: OptimizeLevel;
+  private alkanes?: AlkanesSource;
+  private dependencies: Record<string, DependencySpec>;
+  private lockfile?: string;
+  private locked: boolean;
+  private offline: boolean;
+  private toolchain?: Promise<{ rustc: string; cargo: string }>;
+
+  constructor(options: string | AlkanesCompilerOptions = {}) {
+    const settings =
+      typeof options === "string" ? { cacheDir: options } : options;
+    this.root = path.resolve(settings.root ?? ".");
+    this.tempDir = path.resolve(settings.cacheDir ?? ".alkanes");
+    this.contractsDir = path.resolve(settings.contractsDir ?? "contracts");
+    this.target = settings.target ?? "wasm32-unknown-unknown";
+    this.optimizeLevel = settings.optimizeLevel ?? 3;
+    this.alkanes = settings.alkanes;
+    this.dependencies = settings.dependencies ?? {};
+    this.lockfile = settings.lockfile && path.resolve(settings.lockfile);
+    this.locked = settings.locked ?? false;
+    this.offline = settings.offline ?? false;
+  }
 
-  constructor(tempDir: string = ".alkanes") {
-    this.tempDir = tempDir;
+  /** A compiler using the project's compiler settings, cache and pins. */
+  static fromConfig(config: AlkaliConfig): AlkanesCompiler {
+    return new AlkanesCompiler({
+      root: config.root,
+      cacheDir: config.paths.cache,
+      contractsDir: config.paths.contracts,
+      target: config.compiler.target,
+      optimizeLevel: config.compiler.optimizeLevel,
+      alkanes: config.alkanes,
+      dependencies: config.dependencies,
+      lockfile: path.join(config.paths.contracts, "Cargo.lock"),
+      locked: config.compiler.locked,
+      offline: config.compiler.offline,
+    });
   }
 
-  async compile(
-    sourceCode: string
-  ): Promise<{ bytecode: string; abi: AlkanesABI } | void> {
+  /**
+   * Compiles contract source code that is not in a file, as a workspace
+   * member named after the contract.
+   */
+  async compile(sourceCode: string): Promise<CompileResult> {
     try {
-      // Create temporary project
-      await this.createProject(sourceCode);
+      const abi = await this.parseABI(sourceCode);
+      const name = contractCrateName(abi.name);
+      const dir = path.join(this.tempDir, "contracts", name);
+      return await this.exclusive(async () => {
+        await this.createWorkspace([]);
+        await fs.mkdir(path.join(dir, "src"), { recursive: true });
+        await writeIfChanged(
+          path.join(dir, "Cargo.toml"),
+          contractManifest({
+            name,
+            alkanes: this.alkanes,
+            dir,
+            ...(await this.memberDependencies(dir)),
+          })
+        );
+        await writeIfChanged(path.join(dir, "src", "lib.rs"), sourceCode);
 
-      // Run wasm-pack build
-      const { stdout, stderr } = await execAsync(
-        "wasm-pack build --target web",
-        { cwd: this.tempDir }
+        await this.build([name]);
+        return this.readResult(name, abi);
+      });
+    } catch (error) {
+      throw new Error(`Compilation failed: ${errorMessage(error)}`);
+    }
+  }
+
+  /**
+   * Compiles contract files or directories with a single cargo build,
+   * returning their results in order.
+   */
+  async compileFiles(files: string[]): Promise<CompileResult[]> {
+    try {
+      // ABI errors surface before the (much slower) cargo build
+      const abis = await Promise.all(
+        files.map(async (file) =>
+          this.contractABI(await extractCrate(await crateRoot(file)))
+        )
       );
+      return await this.exclusive(async () => {
+        const members = await this.createWorkspace(files);
+        await this.build(members.map(({ name }) => name));
+        return Promise.all(
+          members.map(({ name }, index) => this.readResult(name, abis[index]))
+        );
+      });
+    } catch (error) {
+      throw new Error(`Compilation failed: ${errorMessage(error)}`);
+    }
+  }
+
+  /**
+   * Writes the workspace with a member for each of `files`, and removes
+   * members whose source no longer exists. Manifests are only rewritten
+   * when they change, so cargo does not rebuild untouched crates.
+   */
+  async createWorkspace(files: string[]): Promise<WorkspaceMember[]> {
+    const contractsDir = path.join(this.tempDir, "contracts");
+    await fs.mkdir(contractsDir, { recursive: true });
 
-      if (stderr) {
-        console.warn("Build warnings:", stderr);
+    const checkout = this.alkanes?.path;
+    if (checkout !== undefined) {
+      for (const crate of ALKANES_CRATES) {
+        if (!(await pathExists(path.join(checkout, "crates", crate)))) {
+          throw new Error(
+            `alkanes.path ${checkout} has no crates/${crate}; it should be an alkanes-rs checkout`
+          );
+        }
       }
+    }
 
-      // Read the WASM file
-      const wasmPath = path.join(
-        this.tempDir,
-        "pkg",
-        "alkanes_contract_bg.wasm"
-      );
-      const wasmBuffer = await fs.readFile(wasmPath);
+    // Cargo fails to load a workspace with a member pointing at a
+    // deleted source
+    for (const entry of await fs.readdir(contractsDir)) {
+      const dir = path.join(contractsDir, entry);
+      const manifest = await fs
+        .readFile(path.join(dir, "Cargo.toml"), "utf8")
+        .catch(() => undefined);
+      const source = manifest?.match(/^path = (".*")$/m);
+      const exists =
+        manifest !== undefined &&
+        (!source ||
+          (await pathExists(path.resolve(dir, JSON.parse(source[1])))));
+      if (!exists) {
+        await fs.rm(dir, { recursive: true, force: true });
+      }
+    }
 
-      // Parse ABI from source code
-      const abi = await this.parseABI(sourceCode);
+    const sharedSource = path.join(this.contractsDir, `${SHARED_CRATE}.rs`);
+    const sharedDir = path.join(this.tempDir, SHARED_CRATE);
+    const shared = await pathExists(sharedSource);
+    if (shared) {
+      await fs.mkdir(sharedDir, { recursive: true });
+      await writeIfChanged(
+        path.join(sharedDir, "Cargo.toml"),
+        contractManifest({
+          name: SHARED_CRATE,
+          lib: { name: SHARED_CRATE, path: sharedSource },
+          crateTypes: ["rlib"],
+          alkanes: this.alkanes,
+          dir: sharedDir,
+          dependencies: mapDependencies(this.dependencies),
+        })
+      );
+    } else {
+      await fs.rm(sharedDir, { recursive: true, force: true });
+    }
 
-      // Clean up (optional)
-      // await fs.rm(this.tempDir, { recursive: true, force: true });
+    const members = files.map((file) => {
+      const source = path.resolve(file);
+      const name = contractCrateName(source);
+      return { name, dir: path.join(contractsDir, name), source };
+    });
+    const seen = new Map<string, string>();
+    for (const { name, source } of members) {
+      if (name === SHARED_CRATE) {
+        throw new Error(
+          `${source} would build the shared ${SHARED_CRATE} crate; ${SHARED_CRATE}.rs belongs in ${this.contractsDir}`
+        );
+      }
+      if (seen.has(name)) {
+        throw new Error(
+          `${seen.get(name)} and ${source} would both build crate ${name}`
+        );
+      }
+      seen.set(name, source);
+    }
 
-      return {
-        bytecode: wasmBuffer.toString("base64"),
-        abi,
-      };
-    } catch (error) {
-      if (error instanceof Error) {
-        throw new Error(`Compilation failed: ${error.message}`);
+    for (const { name, dir, source } of members) {
+      const isDirectory = (await fs.stat(source)).isDirectory();
+      const entry = isDirectory ? path.join(source, "lib.rs") : source;
+      if (isDirectory && !(await pathExists(entry))) {
+        throw new Error(`Contract directory ${source} has no lib.rs`);
       }
+      await fs.mkdir(dir, { recursive: true });
+      await writeIfChanged(
+        path.join(dir, "Cargo.toml"),
+        contractManifest({
+          name,
+          lib: { name, path: entry },
+          alkanes: this.alkanes,
+          dir,
+          ...(await this.memberDependencies(
+            dir,
+            isDirectory ? path.join(source, "Cargo.toml") : undefined
+          )),
+        })
+      );
+    }
+
+    const workspaceMembers = ["contracts/*", ...(shared ? [SHARED_CRATE] : [])]
+      .map((member) => JSON.stringify(member))
+      .join(", ");
+    await writeIfChanged(
+      path.join(this.tempDir, "Cargo.toml"),
+      `[workspace]
+members = [${workspaceMembers}]
+resolver = "2"
+`
+    );
+    return members;
+  }
+
+  /**
+   * Dependencies of the contract crate in `dir` besides the provided ones:
+   * the configured crates, overridden by those of its `fragment`
+   * Cargo.toml, and the shared crate when there is one.
+   */
+  private async memberDependencies(
+    dir: string,
+    fragment?: string
+  ): Promise<ContractDependencies> {
+    const own = fragment
+      ? await readDependencies(fragment, dir)
+      : { dependencies: {}, targets: {} };
+    const dependencies = {
+      ...mapDependencies(this.dependencies),
+      ...own.dependencies,
+    };
+    const shared = await pathExists(
+      path.join(this.contractsDir, `${SHARED_CRATE}.rs`)
+    );
+    if (shared) {
+      dependencies[SHARED_CRATE] = `{ path = "../../${SHARED_CRATE}" }`;
     }
+    return { dependencies, targets: own.targets };
   }
 
-  private async createProject(sourceCode: string) {
-    // Create project directory
+  /**
+   * Runs `task` while no other compiler writes or builds the workspace, so
+   * one's crates, Cargo.lock and WASM are not copied over another's.
+   */
+  private async exclusive<T>(task: () => Promise<T>): Promise<T> {
     await fs.mkdir(this.tempDir, { recursive: true });
-    await fs.mkdir(path.join(this.tempDir, "src"), { recursive: true });
+    return withFileLock(path.join(this.tempDir, BUILD_LOCK), task);
+  }
 
-    // Create Cargo.toml
-    const cargoToml = `
-[package]
-name = "alkanes-contract"
-version = "0.1.0"
-edition = "2021"
+  /**
+   * Builds the cdylibs of `crates` for the configured target, starting
+   * from the kept Cargo.lock and keeping what cargo resolved.
+   */
+  private async build(crates: string[]): Promise<void> {
+    const workspaceLock = path.join(this.tempDir, "Cargo.lock");
+    if (this.lockfile) {
+      const kept = await fs
+        .readFile(this.lockfile, "utf8")
+        .catch(() => undefined);
+      if (kept === undefined) {
+        await fs.rm(workspaceLock, { force: true });
+      } else {
+        await writeIfChanged(workspaceLock, kept);
+      }
+    }
 
-[lib]
-crate-type = ["cdylib"]
+    const flags = [
+      ...crates.map((name) => `-p ${name}`),
+      ...(this.locked ? ["--locked"] : []),
+      ...(this.offline ? ["--offline"] : []),
+    ].join(" ");
+    let stderr: string;
+    try {
+      const env: NodeJS.ProcessEnv = {
+        ...process.env,
+        CARGO_PROFILE_RELEASE_OPT_LEVEL: String(this.optimizeLevel),
+        CARGO_ENCODED_RUSTFLAGS: this.rustflags().join("\x1f"),
+      };
+      delete env.RUSTFLAGS;
+      ({ stderr } = await execAsync(
+        `cargo build --release --target ${this.target} ${flags}`,
+        { cwd: this.tempDir, env }
+      ));
+    } catch (error: any) {
+      // A failing crate that is not a contract is one of their
+      // dependencies, most likely one that needs std or an OS. This only
+      // reads cargo's `could not compile` lines: a crate that compiles but
+      // fails to link for the target, or whose build script fails, is
+      // reported with cargo's own error.
+      const failed = [
+        ...new Set(
+          [...String(error.stderr).matchAll(/could not compile `([^`]+)`/g)]
+            .map((match) => match[1])
+            .filter((crate) => !crates.includes(crate))
+        ),
+      ];
+      if (failed.length > 0) {
+        throw new Error(
+          `dependency ${failed.join(", ")} does not build for ${this.target}; use a version or features without std (e.g. default-features = false)\n${error.stderr}`
+        );
+      }
+      throw error;
+    }
 
-[dependencies]
-alkanes-runtime = { git = "https://github.com/kungfuflex/alkanes-rs" }
-alkanes-support = { git = "https://github.com/kungfuflex/alkanes-rs" }
-metashrew-support = { git = "https://github.com/kungfuflex/alkanes-rs" }
-anyhow = "1.0"
-hex-lit = "0.1.1"
-    `;
+    if (/^warning/m.test(stderr)) {
+      console.warn("Build warnings:", stderr);
+    }
+    if (this.lockfile) {
+      const lock = await fs.readFile(workspaceLock, "utf8");
+      await writeIfChanged(this.lockfile, lock);
+      // Seed the lock of the project's native test crate, which `alkali
+      // init` generates, so `cargo test` resolves the same versions
+      const native = path.join(this.root, "Cargo.lock");
+      if (
+        (await pathExists(path.join(this.root, "Cargo.toml"))) &&
+        !(await pathExists(native))
+      ) {
+        await fs.writeFile(native, lock);
+      }
+    }
+  }
+
+  /**
+   * Flags for rustc: the inherited RUSTFLAGS (or CARGO_ENCODED_RUSTFLAGS),
+   * then remappings of this machine's paths, which rustc embeds in panic
+   * locations, to fixed ones so the WASM does not depend on where it was
+   * built. rustc applies the last matching remapping, so nested
+   * directories come after their parents.
+   */
+  private rustflags(): string[] {
+    const encoded = process.env.CARGO_ENCODED_RUSTFLAGS;
+    const inherited = encoded
+      ? encoded.split("\x1f")
+      : (process.env.RUSTFLAGS ?? "").split(/\s+/).filter(Boolean);
+    const cargoHome =
+      process.env.CARGO_HOME ?? path.join(os.homedir(), ".cargo");
+    const remaps: [string, string][] = [
+      [path.resolve(cargoHome), "/cargo"],
+      [this.root, "/project"],
+      [this.tempDir, "/alkali/cache"],
+      [path.resolve(PACKAGE_ROOT), "/alkali/package"],
+    ];
+    if (this.alkanes?.path !== undefined) {
+      remaps.push([this.alkanes.path, "/alkali/alkanes-rs"]);
+    }
+    return [
+      ...inherited,
+      ...remaps.map(([from, to]) => `--remap-path-prefix=${from}=${to}`),
+    ];
+  }
+
+  // The built WASM with the ABI embedded, so deployed bytecode describes
+  // itself, and what it was built with
+  private async readResult(
+    name: string,
+    abi: AlkanesABI
+  ): Promise<CompileResult> {
+    const release = path.join(this.tempDir, "target", this.target, "release");
+    const wasm = new Uint8Array(
+      await fs.readFile(path.join(release, `${name}.wasm`))
+    );
+    const bytecode = embedAbi(wasm, abi);
+    return {
+      bytecode: Buffer.from(bytecode).toString("base64"),
+      abi,
+      buildInfo: await this.buildInfo(name, wasm),
+    };
+  }
 
-    await fs.writeFile(path.join(this.tempDir, "Cargo.toml"), cargoToml);
+  private async buildInfo(name: string, wasm: Uint8Array): Promise<BuildInfo> {
+    // The toolchain cargo picked, honouring the project's rust-toolchain
+    this.toolchain ??= Promise.all([
+      execAsync("rustc -V", { cwd: this.tempDir }),
+      execAsync("cargo -V", { cwd: this.tempDir }),
+    ]).then(([rustc, cargo]) => ({
+      rustc: rustc.stdout.trim(),
+      cargo: cargo.stdout.trim(),
+    }));
+    const packages = parseLockfile(
+      await fs.readFile(path.join(this.tempDir, "Cargo.lock"), "utf8")
+    );
 
-    // Write source code
-    await fs.writeFile(path.join(this.tempDir, "src", "lib.rs"), sourceCode);
+    const source = this.alkanes ?? DEFAULT_ALKANES;
+    let alkanes: BuildInfo["alkanes"];
+    if (source.path !== undefined) {
+      const commit = await execAsync("git rev-parse HEAD", {
+        cwd: source.path,
+      }).then(
+        ({ stdout }) => stdout.trim(),
+        () => undefined
+      );
+      alkanes = { path: source.path, ...(commit && { commit }) };
+    } else {
+      const locked = packages.find(
+        (entry) => entry.name === "alkanes-runtime"
+      )?.source;
+      const commit = locked?.match(/#([0-9a-f]+)$/)?.[1];
+      alkanes = { ...source, ...(commit && { commit }) };
+    }
+
+    return {
+      ...(await this.toolchain),
+      target: this.target,
+      optimizeLevel: this.optimizeLevel,
+      alkanes,
+      dependencies: lockedDependencies(packages, name),
+      rustflags: this.rustflags(),
+      wasmSha256: toHex(sha256(wasm)),
+    };
   }
 
+  /**
+   * Builds the ABI from the contract's syntax tree: the `AlkaneResponder`
+   * impl names the contract, and each arm of its `match shift_or_err(...)`
+   * dispatch with a `name(types)` comment above it becomes a method, whose
+   * outputs come from an optional `-> type` (or `-> (types)`) suffix.
+   * `#[alkali::contract]` contracts are read from their `#[opcode(n)]`
+   * method signatures instead. Storage types and sub-keys are inferred from
+   * how each `StoragePointer` is read and written.
+   */
   public async parseABI(sourceCode: string): Promise<AlkanesABI> {
+    return this.contractABI(await extractContract(sourceCode));
+  }
+
+  private contractABI(contract: ExtractedContract): AlkanesABI {
     const methods: AlkanesMethod[] = [];
     const opcodes: Record<string, number> = {};
 
-    // Updated regex to better match method comments and opcodes
-    // This looks for comments between match arms
-    const methodRegex = /\/\*\s*([^*]+?)\s*\*\/\s*(\d+)\s*=>/g;
-    let match;
+    for (const extracted of contract.methods) {
+      // Arms are in the file of the contract's `execute`
+      const arm = { ...extracted, file: contract.file };
+      if (arm.abi) {
+        methods.push(arm.abi);
+        opcodes[arm.abi.name] = arm.opcode;
+        continue;
+      }
+
+      // The signature is the last comment above the arm shaped like a call
+      let signatureIndex = arm.comments.length - 1;
+      while (
+        signatureIndex >= 0 &&
+        !SIGNATURE_REGEX.test(arm.comments[signatureIndex])
+      ) {
+        signatureIndex--;
+      }
+      if (signatureIndex < 0) {
+        continue;
+      }
 
-    while ((match = methodRegex.exec(sourceCode)) !== null) {
-      const [_, comment, opcode] = match;
-      const opcodeNum = parseInt(opcode);
+      const methodInfo = this.parseMethodSignature(
+        arm.comments[signatureIndex],
+        arm
+      );
+      if (methodInfo.name in opcodes) {
+        throw new AbiExtractionError(
+          `method ${methodInfo.name} is already dispatched by opcode ${opcodes[methodInfo.name]}`,
+          arm
+        );
+      }
 
-      // Parse method signature from comment
-      const methodInfo = this.parseMethodSignature(comment.trim());
+      // The other comments describe the method
+      const doc = arm.comments
+        .filter((_, index) => index !== signatureIndex)
+        .join("\n");
 
-      // Add to methods array
-      const method: AlkanesMethod = {
-        opcode: opcodeNum,
+      methods.push({
+        opcode: arm.opcode,
         name: methodInfo.name,
+        ...(doc && { doc }),
         inputs: methodInfo.inputs,
         outputs: methodInfo.outputs,
-      };
-      methods.push(method);
-
-      // Add to opcodes map
-      opcodes[methodInfo.name] = opcodeNum;
-    }
-
-    // Parse struct name (captures pub struct Name)
-    const structRegex = /pub\s+struct\s+(\w+)/;
-    const structMatch = sourceCode.match(structRegex);
-    const name = structMatch ? structMatch[1] : "UnknownContract";
-
-    // Parse storage
-    const storage: StorageKey[] = [];
-    const storageRegex = /StoragePointer::from_keyword\("([^"]+)"\)/g;
-    let storageMatch;
-    while ((storageMatch = storageRegex.exec(sourceCode)) !== null) {
-      storage.push({
-        key: storageMatch[1],
-        type: "Vec<u8>", // Default type
       });
+      opcodes[methodInfo.name] = arm.opcode;
     }
 
+    const storage: StorageKey[] = contract.storage.map(
+      ({ key, type, path }) => ({ key, type, ...(path && { path }) })
+    );
+
     return {
-      name,
+      name: contract.name,
       version: "1.0.0",
       methods,
       storage,
@@ -144,49 +785,371 @@ hex-lit = "0.1.1"
     };
   }
 
-  private parseMethodSignature(comment: string): {
+  /**
+   * Parses `name(type, ...) -> type`. Parameters may be written `name: type`;
+   * unnamed ones take the arm's `shift_or_err` binding names when there is
+   * one per parameter, and are numbered otherwise.
+   */
+  private parseMethodSignature(
+    comment: string,
+    arm: ExtractedMethod
+  ): {
     name: string;
-    inputs: Array<{ name: string; type: AlkanesType }>;
-    outputs: Array<{ name: string; type: AlkanesType }>;
+    inputs: AlkanesParam[];
+    outputs: AlkanesParam[];
   } {
-    // Updated regex to better handle method signatures
-    const match = comment.trim().match(/(\w+)\((.*?)\)/);
+    const match = comment.trim().match(SIGNATURE_REGEX);
     if (!match) {
       return { name: "unknown", inputs: [], outputs: [] };
     }
 
-    const [_, name, paramsStr] = match;
-    const inputs = paramsStr
-      .split(",")
-      .map((param) => param.trim())
-      .filter((param) => param.length > 0)
-      .map((param, index) => {
-        // Parse array types like "u128[2]"
-        const arrayMatch = param.match(/(\w+)\[(\d+)\]/);
-        if (arrayMatch) {
-          const [_, baseType, length] = arrayMatch;
-          return {
-            name: `param${index}`,
-            type: {
-              array: {
-                type: baseType as AlkanesPrimitive,
-                length: parseInt(length),
-              },
-            },
-          };
-        }
+    const [_, name, paramsStr, returnStr] = match;
+    const params = splitTypes(paramsStr);
+    const names = arm.bindings.length === params.length ? arm.bindings : [];
+    const inputs = params.map((param, index) =>
+      parseParam(param, names[index] ?? `param${index}`, arm)
+    );
 
-        // Handle primitive types
-        return {
-          name: `param${index}`,
-          type: param as AlkanesPrimitive,
-        };
-      });
+    // A tuple return type lists each value written to the response data
+    const returns = returnStr?.trim() ?? "";
+    const outputTypes =
+      returns.startsWith("(") && returns.endsWith(")")
+        ? splitTypes(returns.slice(1, -1))
+        : splitTypes(returns);
+    const outputs = outputTypes.map((output, index) =>
+      parseParam(output, `output${index}`, arm)
+    );
+
+    return { name, inputs, outputs };
+  }
+}
 
+/** Splits a comma-separated type list, leaving nested lists intact. */
+function splitTypes(list: string): string[] {
+  const types: string[] = [];
+  let depth = 0;
+  let current = "";
+  for (const char of list) {
+    if ("(<[".includes(char)) {
+      depth++;
+    } else if (")>]".includes(char)) {
+      depth--;
+    }
+    if (char === "," && depth === 0) {
+      types.push(current.trim());
+      current = "";
+    } else {
+      current += char;
+    }
+  }
+  types.push(current.trim());
+  return types.filter((type) => type.length > 0);
+}
+
+/** Parses `name: type`, or a bare type named `fallbackName`. */
+function parseParam(
+  text: string,
+  fallbackName: string,
+  arm: ExtractedMethod
+): AlkanesParam {
+  const named = text.match(/^(\w+)\s*:\s*(.+)$/s);
+  if (named) {
+    return { name: named[1], type: parseType(named[2], arm) };
+  }
+  return { name: fallbackName, type: parseType(text, arm) };
+}
+
+/**
+ * Parses a type written in the signature comment of `arm`: primitives,
+ * `T[n]` arrays, `Vec<T>` and `(A, B)` tuples.
+ */
+function parseType(text: string, arm: ExtractedMethod): AlkanesType {
+  const type = text.trim();
+
+  // Parse array types like "u128[2]"
+  const arrayMatch = type.match(/^(.+)\[(\d+)\]$/s);
+  if (arrayMatch) {
+    const [_, baseType, length] = arrayMatch;
     return {
-      name,
-      inputs,
-      outputs: [], // Could parse return types later
+      array: { type: parseType(baseType, arm), length: parseInt(length) },
     };
   }
+
+  const vecMatch = type.match(/^Vec\s*<(.+)>$/s);
+  if (vecMatch) {
+    const item = parseType(vecMatch[1], arm);
+    return item === "u8" ? "Vec<u8>" : { vec: { type: item } };
+  }
+
+  if (type.startsWith("(") && type.endsWith(")")) {
+    return {
+      tuple: splitTypes(type.slice(1, -1)).map((item) => parseType(item, arm)),
+    };
+  }
+
+  if (!PRIMITIVES.includes(type as AlkanesPrimitive)) {
+    throw new AbiExtractionError(
+      `unknown type ${type} in the signature of opcode ${arm.opcode}; expected ${PRIMITIVES.join(", ")}, T[n], Vec<T> or a tuple`,
+      arm
+    );
+  }
+  return type as AlkanesPrimitive;
+}
+
+function pathExists(file: string): Promise<boolean> {
+  return fs.access(file).then(
+    () => true,
+    () => false
+  );
+}
+
+// Lock files by path, chained so this process's own tasks queue up
+const heldLocks = new Map<string, Promise<unknown>>();
+
+/**
+ * Runs `task` holding `file`, created exclusively and removed afterwards;
+ * other processes wait for it, unless the one that created it has died.
+ */
+async function withFileLock<T>(
+  file: string,
+  task: () => Promise<T>
+): Promise<T> {
+  const previous = heldLocks.get(file) ?? Promise.resolve();
+  const held = previous.catch(() => undefined).then(async () => {
+    await acquireFile(file);
+    try {
+      return await task();
+    } finally {
+      await fs.rm(file, { force: true });
+    }
+  });
+  heldLocks.set(file, held);
+  try {
+    return await held;
+  } finally {
+    if (heldLocks.get(file) === held) {
+      heldLocks.delete(file);
+    }
+  }
+}
+
+async function acquireFile(file: string): Promise<void> {
+  for (;;) {
+    try {
+      await fs.writeFile(file, String(process.pid), { flag: "wx" });
+      return;
+    } catch (error) {
+      if ((error as NodeJS.ErrnoException).code !== "EEXIST") {
+        throw error;
+      }
+    }
+    const owner = Number(await fs.readFile(file, "utf8").catch(() => ""));
+    if (owner > 0 && !processExists(owner)) {
+      await fs.rm(file, { force: true });
+    } else {
+      await new Promise((resolve) => setTimeout(resolve, 100));
+    }
+  }
+}
+
+function processExists(pid: number): boolean {
+  try {
+    process.kill(pid, 0);
+    return true;
+  } catch (error) {
+    // EPERM: it exists, as another user's
+    return (error as NodeJS.ErrnoException).code === "EPERM";
+  }
+}
+
+function errorMessage(error: unknown): string {
+  return error instanceof Error ? error.message : String(error);
+}
+
+// Writes through a temporary file, and not at all when nothing changed
+async function writeIfChanged(file: string, content: string): Promise<void> {
+  const current = await fs.readFile(file, "utf8").catch(() => undefined);
+  if (current === content) {
+    return;
+  }
+  const temp = `${file}.${process.pid}.tmp`;
+  await fs.writeFile(temp, content);
+  await fs.rename(temp, file);
+}
+
+// Configured dependencies as TOML values, rejecting the provided crates
+function mapDependencies(
+  dependencies: Record<string, DependencySpec>
+): Record<string, string> {
+  return Object.fromEntries(
+    Object.entries(dependencies).map(([name, spec]) => {
+      checkDependencyName(name, "the config's dependencies");
+      return [name, dependencyValue(spec)];
+    })
+  );
+}
+
+function checkDependencyName(name: string, source: string): void {
+  if (PROVIDED_CRATES.includes(name.replace(/_/g, "-"))) {
+    throw new Error(
+      `${name} in ${source} is already a dependency of every contract${
+        name.startsWith("alkanes") || name.startsWith("metashrew")
+          ? "; set its source with the alkanes config"
+          : ""
+      }`
+    );
+  }
+}
+
+/** Dependencies of a contract crate, as TOML values by crate name. */
+interface ContractDependencies {
+  dependencies: Record<string, string>;
+  /** Dependencies for some targets only, by `cfg(...)` or target triple */
+  targets: Record<string, Record<string, string>>;
+}
+
+/**
+ * Reads the `[dependencies]` and `[target.<cfg>.dependencies]` tables of a
+ * contract directory's Cargo.toml, as TOML values with `path`s made
+ * relative to `dir`, where the crate's manifest is generated. Other tables
+ * are rejected: the rest of the manifest is generated.
+ */
+async function readDependencies(
+  file: string,
+  dir: string
+): Promise<ContractDependencies> {
+  const content = await fs.readFile(file, "utf8").catch(() => undefined);
+  let manifest: Record<string, any>;
+  try {
+    manifest = parseToml(content ?? "");
+  } catch (error) {
+    throw new Error(`${file}: ${errorMessage(error)}`);
+  }
+  const unsupported = (table: string) =>
+    new Error(
+      `${file}: only [dependencies] and [target.<cfg>.dependencies] can be set for a contract, not [${table}]`
+    );
+  const table = (dependencies: Record<string, unknown>) =>
+    Object.fromEntries(
+      Object.entries(dependencies).map(([name, value]) => {
+        checkDependencyName(name, file);
+        return [name, tomlValue(relocate(value, path.dirname(file), dir))];
+      })
+    );
+
+  const { dependencies = {}, target = {}, ...rest } = manifest;
+  if (Object.keys(rest).length > 0) {
+    throw unsupported(Object.keys(rest)[0]);
+  }
+  const targets: Record<string, Record<string, string>> = {};
+  for (const [cfg, tables] of Object.entries<Record<string, any>>(target)) {
+    const { dependencies = {}, ...rest } = tables;
+    if (Object.keys(rest).length > 0) {
+      throw unsupported(`target.${cfg}.${Object.keys(rest)[0]}`);
+    }
+    targets[cfg] = table(dependencies);
+  }
+  return { dependencies: table(dependencies), targets };
+}
+
+/** A dependency with its `path`, if any, moved from `from` to `to`. */
+function relocate(value: unknown, from: string, to: string): unknown {
+  if (typeof value !== "object" || value === null || Array.isArray(value)) {
+    return value;
+  }
+  const table = value as Record<string, unknown>;
+  return typeof table.path === "string"
+    ? { ...table, path: path.relative(to, path.resolve(from, table.path)) }
+    : table;
+}
+
+/** Inline TOML of a parsed value, e.g. `{ version = "1.0" }`. */
+function tomlValue(value: unknown): string {
+  if (Array.isArray(value)) {
+    return `[${value.map(tomlValue).join(", ")}]`;
+  }
+  if (typeof value === "object" && value !== null) {
+    const fields = Object.entries(value).map(([key, field]) => {
+      const name = /^[A-Za-z0-9_-]+$/.test(key) ? key : JSON.stringify(key);
+      return `${name} = ${tomlValue(field)}`;
+    });
+    return `{ ${fields.join(", ")} }`;
+  }
+  return typeof value === "string" ? JSON.stringify(value) : String(value);
+}
+
+/**
+ * Crate root the ABI is read from: a directory contract's `lib.rs`, whose
+ * modules the helper follows, or the contract file itself.
+ */
+async function crateRoot(source: string): Promise<string> {
+  if (!(await fs.stat(source)).isDirectory()) {
+    return source;
+  }
+  const entry = path.join(source, "lib.rs");
+  if (!(await pathExists(entry))) {
+    throw new Error(`Contract directory ${source} has no lib.rs`);
+  }
+  return entry;
+}
+
+/** The packages of a Cargo.lock, with the `dependencies` they list. */
+function parseLockfile(
+  content: string
+): (LockedPackage & { dependencies: string[] })[] {
+  const { package: packages = [] } = parseToml(content) as {
+    package?: {
+      name: string;
+      version: string;
+      source?: string;
+      dependencies?: string[];
+    }[];
+  };
+  return packages.map(({ name, version, source, dependencies = [] }) => ({
+    name,
+    version,
+    ...(source && { source }),
+    dependencies,
+  }));
+}
+
+/**
+ * The locked packages `name` depends on, directly or not, by name. Lock
+ * entries name a dependency as `name`, `name version` or
+ * `name version (source)` when the name alone is ambiguous.
+ */
+function lockedDependencies(
+  packages: (LockedPackage & { dependencies: string[] })[],
+  name: string
+): LockedPackage[] {
+  const find = (reference: string) => {
+    const [dependency, version] = reference.split(" ");
+    return packages.find(
+      (entry) =>
+        entry.name === dependency &&
+        (version === undefined || entry.version === version)
+    );
+  };
+
+  const seen = new Set<LockedPackage & { dependencies: string[] }>();
+  const queue = [find(name)];
+  while (queue.length > 0) {
+    const entry = queue.pop();
+    if (!entry || seen.has(entry)) {
+      continue;
+    }
+    seen.add(entry);
+    queue.push(...entry.dependencies.map(find));
+  }
+  return [...seen]
+    .filter((entry) => entry.name !== name)
+    .map(({ name, version, source }) => ({
+      name,
+      version,
+      ...(source && { source }),
+    }))
+    .sort(
+      (a, b) =>
+        a.name.localeCompare(b.name) || a.version.localeCompare(b.version)
+    );
 }
@@ -0,0 +1,532 @@
+// Project configuration: `alkali.config.ts`, `.js` or `.json`, validated
+// against a schema and resolved with defaults.
+
+import fs from "fs/promises";
+import Module from "module";
+import path from "path";
+import { Network } from "./bitcoin";
+
+export const CONFIG_FILES = [
+  "alkali.config.ts",
+  "alkali.config.js",
+  "alkali.config.json",
+];
+
+export const DEFAULT_NODE_PORT = 18888;
+export const ALKANES_RS_GIT = "https://github.com/kungfuflex/alkanes-rs";
+// alkanes-rs release contracts build against unless the config pins another
+export const ALKANES_RS_REV = "v2.1.5";
+
+const NETWORKS: Network[] = ["mainnet", "testnet", "signet", "regtest"];
+const OPTIMIZE_LEVELS: OptimizeLevel[] = [0, 1, 2, 3, "s", "z"];
+
+export type OptimizeLevel = 0 | 1 | 2 | 3 | "s" | "z";
+
+export interface NetworkConfig {
+  /** metashrew/alkanes JSON-RPC endpoint */
+  url: string;
+  /** Separate bitcoind endpoint; defaults to `url` with `btc_` methods */
+  bitcoinUrl?: string;
+  headers?: Record<string, string>;
+  /** Address format (default: the network's name, else regtest) */
+  network: Network;
+  /** Hex private keys; the first one deploys by default */
+  accounts: string[];
+}
+
+export interface CompilerConfig {
+  /** Rust target the contracts build for */
+  target: string;
+  /** Cargo `opt-level` of the release profile */
+  optimizeLevel: OptimizeLevel;
+  /** Fail instead of updating the kept Cargo.lock */
+  locked: boolean;
+  /** Build without network access, from vendored or cached crates */
+  offline: boolean;
+}
+
+/** Project directories, absolute once resolved. */
+export interface PathsConfig {
+  contracts: string;
+  build: string;
+  scripts: string;
+  tests: string;
+  /** Generated cargo workspace of the contracts, with its target directory */
+  cache: string;
+}
+
+/**
+ * Where the alkanes-rs crates come from: the repository at one of
+ * rev/tag/branch (`ALKANES_RS_REV` of the default repository unless one is
+ * set), or a local checkout at `path` for offline builds.
+ */
+export interface AlkanesSource {
+  git: string;
+  rev?: string;
+  tag?: string;
+  branch?: string;
+  path?: string;
+}
+
+/**
+ * A crate dependency as written in Cargo.toml: a version requirement, or
+ * the fields of its table. `path` is relative to the project root.
+ */
+export type DependencySpec =
+  | string
+  | {
+      version?: string;
+      git?: string;
+      rev?: string;
+      tag?: string;
+      branch?: string;
+      path?: string;
+      package?: string;
+      features?: string[];
+      "default-features"?: boolean;
+    };
+
+/** Binaries and ports of `alkali node`. */
+export interface NodeConfig {
+  /** bitcoind binary (default: `bitcoind` on the PATH) */
+  bitcoind?: string;
+  /** metashrew binary (default: `rockshrew-mono` on the PATH) */
+  metashrew?: string;
+  /** Alkanes indexer WASM run by metashrew */
+  indexer?: string;
+  /** Chain and index data (default: `.alkali/node`) */
+  dataDir?: string;
+  bitcoinRpcPort?: number;
+  metashrewPort?: number;
+}
+
+export interface AlkaliConfig {
+  /** Directory holding the config file, or the working directory */
+  root: string;
+  /** The loaded file; undefined when running on defaults */
+  configPath?: string;
+  name: string;
+  /** Network commands use when none is given */
+  network: string;
+  networks: Record<string, NetworkConfig>;
+  compiler: CompilerConfig;
+  paths: PathsConfig;
+  alkanes: AlkanesSource;
+  /** Crates added to every contract, by name */
+  dependencies: Record<string, DependencySpec>;
+  node: NodeConfig;
+}
+
+/** The config as written: every field is optional. */
+export interface AlkaliUserConfig {
+  name?: string;
+  network?: string;
+  networks?: Record<
+    string,
+    Omit<NetworkConfig, "network" | "accounts"> &
+      Partial<Pick<NetworkConfig, "network" | "accounts">>
+  >;
+  compiler?: Partial<CompilerConfig>;
+  paths?: Partial<PathsConfig>;
+  alkanes?: Partial<AlkanesSource>;
+  dependencies?: Record<string, DependencySpec>;
+  node?: NodeConfig;
+}
+
+/** Types `export default defineConfig({...})` in `alkali.config.ts`. */
+export function defineConfig(config: AlkaliUserConfig): AlkaliUserConfig {
+  return config;
+}
+
+export class ConfigError extends Error {
+  constructor(message: string) {
+    super(message);
+    this.name = "ConfigError";
+  }
+}
+
+type Schema =
+  | { type: "string" | "number" | "boolean" }
+  | { type: "enum"; values: readonly (string | number)[] }
+  | { type: "array"; items: Schema }
+  | { type: "record"; values: Schema }
+  | {
+      type: "object";
+      properties: Record<string, Schema>;
+      required?: string[];
+    }
+  /** The first schema whose JSON type matches the value */
+  | { type: "union"; types: Schema[] };
+
+const STRING: Schema = { type: "string" };
+const NUMBER: Schema = { type: "number" };
+const BOOLEAN: Schema = { type: "boolean" };
+
+const CONFIG_SCHEMA: Schema = {
+  type: "object",
+  properties: {
+    name: STRING,
+    network: STRING,
+    networks: {
+      type: "record",
+      values: {
+        type: "object",
+        required: ["url"],
+        properties: {
+          url: STRING,
+          bitcoinUrl: STRING,
+          headers: { type: "record", values: STRING },
+          network: { type: "enum", values: NETWORKS },
+          accounts: { type: "array", items: STRING },
+        },
+      },
+    },
+    compiler: {
+      type: "object",
+      properties: {
+        target: STRING,
+        optimizeLevel: { type: "enum", values: OPTIMIZE_LEVELS },
+        locked: BOOLEAN,
+        offline: BOOLEAN,
+      },
+    },
+    paths: {
+      type: "object",
+      properties: {
+        contracts: STRING,
+        build: STRING,
+        scripts: STRING,
+        tests: STRING,
+        cache: STRING,
+      },
+    },
+    alkanes: {
+      type: "object",
+      properties: {
+        git: STRING,
+        rev: STRING,
+        tag: STRING,
+        branch: STRING,
+        path: STRING,
+      },
+    },
+    dependencies: {
+      type: "record",
+      values: {
+        type: "union",
+        types: [
+          STRING,
+          {
+            type: "object",
+            properties: {
+              version: STRING,
+              git: STRING,
+              rev: STRING,
+              tag: STRING,
+              branch: STRING,
+              path: STRING,
+              package: STRING,
+              features: { type: "array", items: STRING },
+              "default-features": BOOLEAN,
+            },
+          },
+        ],
+      },
+    },
+    node: {
+      type: "object",
+      properties: {
+        bitcoind: STRING,
+        metashrew: STRING,
+        indexer: STRING,
+        dataDir: STRING,
+        bitcoinRpcPort: NUMBER,
+        metashrewPort: NUMBER,
+      },
+    },
+  },
+};
+
+/**
+ * Loads the project config: `configPath` if given, else the first of
+ * `CONFIG_FILES` in `cwd`. Without a config file every setting takes its
+ * default.
+ */
+export async function loadConfig(
+  options: { cwd?: string; configPath?: string } = {}
+): Promise<AlkaliConfig> {
+  const cwd = path.resolve(options.cwd ?? process.cwd());
+  let file: string | undefined;
+  if (options.configPath) {
+    file = path.resolve(cwd, options.configPath);
+    await fs.access(file).catch(() => {
+      throw new ConfigError(`Config file ${options.configPath} not found`);
+    });
+  } else {
+    for (const name of CONFIG_FILES) {
+      const candidate = path.join(cwd, name);
+      const exists = await fs.stat(candidate).then(
+        (stat) => stat.isFile(),
+        () => false
+      );
+      if (exists) {
+        file = candidate;
+        break;
+      }
+    }
+  }
+
+  if (!file) {
+    return resolveConfig({}, cwd);
+  }
+  return resolveConfig(await readConfigFile(file), path.dirname(file), file);
+}
+
+/**
+ * Validates a user config and fills in defaults, resolving paths against
+ * `root`. Unknown keys are errors, so typos do not silently fall back.
+ */
+export function resolveConfig(
+  user: unknown,
+  root: string,
+  configPath?: string
+): AlkaliConfig {
+  const source = configPath ? path.basename(configPath) : "config";
+  validate(user, CONFIG_SCHEMA, "", source);
+  const config = user as AlkaliUserConfig;
+
+  const alkanes = { git: ALKANES_RS_GIT, ...config.alkanes };
+  const pins = (["rev", "tag", "branch"] as const).filter(
+    (pin) => alkanes[pin] !== undefined
+  );
+  if (pins.length > 1) {
+    throw new ConfigError(
+      `${source}: alkanes may pin only one of rev, tag or branch, not ${pins.join(" and ")}`
+    );
+  }
+  if (alkanes.path !== undefined) {
+    if (pins.length > 0) {
+      throw new ConfigError(
+        `${source}: alkanes.path is a local checkout and cannot be combined with ${pins[0]}`
+      );
+    }
+    alkanes.path = path.resolve(root, alkanes.path);
+  } else if (pins.length === 0 && alkanes.git === ALKANES_RS_GIT) {
+    alkanes.rev = ALKANES_RS_REV;
+  }
+
+  const networks: Record<string, NetworkConfig> = {};
+  const userNetworks: NonNullable<AlkaliUserConfig["networks"]> = {
+    regtest: { url: `http://127.0.0.1:${DEFAULT_NODE_PORT}` },
+    ...config.networks,
+  };
+  for (const [name, settings] of Object.entries(userNetworks)) {
+    const accounts = settings.accounts ?? [];
+    const invalid = accounts.findIndex((key) => !/^[0-9a-f]{64}$/i.test(key));
+    if (invalid >= 0) {
+      throw new ConfigError(
+        `${source}: networks.${name}.accounts[${invalid}] must be a 32-byte hex private key`
+      );
+    }
+    networks[name] = {
+      ...settings,
+      network:
+        settings.network ??
+        (NETWORKS.includes(name as Network) ? (name as Network) : "regtest"),
+      accounts,
+    };
+  }
+
+  const resolve = (dir: string | undefined, fallback: string) =>
+    path.resolve(root, dir ?? fallback);
+  const paths = config.paths ?? {};
+
+  return {
+    root,
+    configPath,
+    name: config.name ?? path.basename(root),
+    network: config.network ?? "regtest",
+    networks,
+    compiler: {
+      target: config.compiler?.target ?? "wasm32-unknown-unknown",
+      optimizeLevel: config.compiler?.optimizeLevel ?? 3,
+      locked: config.compiler?.locked ?? false,
+      offline: config.compiler?.offline ?? false,
+    },
+    paths: {
+      contracts: resolve(paths.contracts, "contracts"),
+      build: resolve(paths.build, "build"),
+      scripts: resolve(paths.scripts, "scripts"),
+      tests: resolve(paths.tests, "test"),
+      cache: resolve(paths.cache, ".alkanes"),
+    },
+    alkanes,
+    dependencies: Object.fromEntries(
+      Object.entries(config.dependencies ?? {}).map(([name, spec]) => [
+        name,
+        typeof spec === "object" && spec.path !== undefined
+          ? { ...spec, path: path.resolve(root, spec.path) }
+          : spec,
+      ])
+    ),
+    node: {
+      ...config.node,
+      indexer: config.node?.indexer && path.resolve(root, config.node.indexer),
+      dataDir: resolve(config.node?.dataDir, path.join(".alkali", "node")),
+    },
+  };
+}
+
+/** Settings of `name` (default: the config's `network`). */
+export function getNetworkConfig(
+  config: AlkaliConfig,
+  name = config.network
+): NetworkConfig & { name: string } {
+  const settings = config.networks[name];
+  if (!settings) {
+    const file = path.basename(config.configPath ?? "alkali.config.json");
+    throw new ConfigError(
+      `No RPC url configured for network "${name}" in ${file}`
+    );
+  }
+  return { name, ...settings };
+}
+
+async function readConfigFile(file: string): Promise<unknown> {
+  const source = await fs.readFile(file, "utf8");
+  if (file.endsWith(".json")) {
+    try {
+      return JSON.parse(source);
+    } catch (error) {
+      throw new ConfigError(
+        `${path.basename(file)} is not valid JSON: ${(error as Error).message}`
+      );
+    }
+  }
+
+  const code = file.endsWith(".ts") ? transpile(source, file) : source;
+  const loaded = new Module(file);
+  loaded.filename = file;
+  loaded.paths = (Module as any)._nodeModulePaths(path.dirname(file));
+  (loaded as any)._compile(code, file);
+  const exported = loaded.exports;
+  return exported?.__esModule || "default" in exported
+    ? exported.default
+    : exported;
+}
+
+// TypeScript configs are compiled with the project's own typescript
+function transpile(source: string, file: string): string {
+  let ts: any;
+  try {
+    ts = require(
+      require.resolve("typescript", { paths: [path.dirname(file), __dirname] })
+    );
+  } catch {
+    throw new ConfigError(
+      `Loading ${path.basename(file)} needs typescript; run \`npm install --save-dev typescript\``
+    );
+  }
+  return ts.transpileModule(source, {
+    fileName: file,
+    compilerOptions: {
+      module: ts.ModuleKind.CommonJS,
+      target: ts.ScriptTarget.ES2020,
+      esModuleInterop: true,
+    },
+  }).outputText;
+}
+
+function validate(
+  value: unknown,
+  schema: Schema,
+  key: string,
+  source: string
+): void {
+  const name = key || "the config";
+  const fail = (expected: string) => {
+    throw new ConfigError(`${source}: ${name} must be ${expected}`);
+  };
+
+  switch (schema.type) {
+    case "string":
+    case "number":
+    case "boolean":
+      if (typeof value !== schema.type) {
+        fail(`${article(schema.type)} ${schema.type}`);
+      }
+      return;
+    case "union": {
+      const type = Array.isArray(value) ? "array" : typeof value;
+      const matching = schema.types.find(
+        (option) => jsonTypeOf(option) === type && value !== null
+      );
+      if (!matching) {
+        const types = schema.types.map(jsonTypeOf);
+        fail(types.map((type) => `${article(type)} ${type}`).join(" or "));
+      }
+      validate(value, matching!, key, source);
+      return;
+    }
+    case "enum":
+      if (!schema.values.includes(value as string | number)) {
+        const values = schema.values.map((v) => JSON.stringify(v));
+        fail(`one of ${values.join(", ")}`);
+      }
+      return;
+    case "array":
+      if (!Array.isArray(value)) fail("an array");
+      (value as unknown[]).forEach((item, i) =>
+        validate(item, schema.items, `${key}[${i}]`, source)
+      );
+      return;
+  }
+
+  if (typeof value !== "object" || value === null || Array.isArray(value)) {
+    fail("an object");
+  }
+  const entries = Object.entries(value as Record<string, unknown>);
+  const child = (entry: string) => (key ? `${key}.${entry}` : entry);
+  if (schema.type === "record") {
+    for (const [entry, item] of entries) {
+      validate(item, schema.values, child(entry), source);
+    }
+    return;
+  }
+
+  const allowed = Object.keys(schema.properties);
+  for (const [entry, item] of entries) {
+    if (!(entry in schema.properties)) {
+      throw new ConfigError(
+        `${source}: unknown key "${child(entry)}" (expected one of ${allowed.join(", ")})`
+      );
+    }
+    if (item !== undefined) {
+      validate(item, schema.properties[entry], child(entry), source);
+    }
+  }
+  for (const required of schema.required ?? []) {
+    if ((value as Record<string, unknown>)[required] === undefined) {
+      throw new ConfigError(`${source}: ${child(required)} is required`);
+    }
+  }
+}
+
+function article(word: string): string {
+  return /^[aeiou]/.test(word) ? "an" : "a";
+}
+
+// JSON type of the values a schema accepts
+function jsonTypeOf(schema: Schema): string {
+  switch (schema.type) {
+    case "string":
+    case "number":
+    case "boolean":
+    case "array":
+      return schema.type;
+    case "enum":
+      return "value";
+    default:
+      return "object";
+  }
+}
@@ -1,31 +1,277 @@
-import { AlkanesMethod, ContractConfig } from "./types";
+import { AlkaneId, AlkaneIdLike } from "./alkaneId";
+import { AlkanesEncoder } from "./encoder";
+import {
+  CallTransaction,
+  CallTransactionOptions,
+  DeployTransactionOptions,
+  DeployTransactions,
+  buildCallTransaction,
+  buildDeployTransactions,
+} from "./envelope";
+import { encodeCellpackRunestone } from "./protostone";
+import { ContractStorage } from "./storage";
+import { Provider, SimulateRequest, resolveDeployedId } from "./provider";
+import {
+  AlkanesMethod,
+  AlkanesOpcode,
+  CallResult,
+  ContractConfig,
+  DecodedCallResponse,
+  DeployResult,
+} from "./types";
+import { ABI_SECTION_NAME, extractAbi, toWasm } from "./wasm";
+
+export interface BuildDeployOptions extends DeployTransactionOptions {
+  /** Deploy to the reserved [3, n] target so the alkane lands at [4, n] */
+  reservedNumber?: bigint;
+}
+
+export interface DeployOptions extends BuildDeployOptions {
+  /** Broadcasts a raw transaction, resolving to its txid (default: provider) */
+  broadcast?: (txHex: string) => Promise<string>;
+  /** Looks up the id the indexer assigned to a [1, 0] deployment
+   * (default: traced through the provider) */
+  resolveAlkaneId?: (revealTxid: string) => Promise<AlkaneId>;
+}
+
+export interface CallOptions extends CallTransactionOptions {
+  /** Broadcasts a raw transaction, resolving to its txid (default: provider) */
+  broadcast?: (txHex: string) => Promise<string>;
+}
+
+export type ContractMethod = (...args: any[]) => Promise<any>;
 
 export class AlkanesContract {
+  /** `contract.methods.mint(amount, options)`: sends the call like `call()` */
+  readonly methods: Record<string, ContractMethod> = {};
+  /** `contract.read.name()`: simulates the call, resolving to its value */
+  readonly read: Record<string, ContractMethod> = {};
+  /** `contract.storage.get("/totalsupply")`: reads state via the indexer */
+  readonly storage: ContractStorage;
+
   private config: ContractConfig;
+  private address?: AlkaneId;
+  private encoder = new AlkanesEncoder();
 
   constructor(config: ContractConfig) {
     this.config = config;
+    this.address =
+      config.address === undefined ? undefined : AlkaneId.from(config.address);
+    this.storage = new ContractStorage(
+      config.abi,
+      () => this.target(),
+      () => this.provider()
+    );
+
+    for (const method of config.abi.methods) {
+      this.methods[method.name] = async (...args: any[]) => {
+        const params = args.slice(0, method.inputs.length);
+        this.encoder.validateArgs(method, params);
+        if (args.length !== params.length + 1) {
+          throw new Error(
+            `Method ${method.name} takes the call options after its ${params.length} argument(s)`
+          );
+        }
+        return this.call(method.name, params, args[params.length]);
+      };
+      this.read[method.name] = async (...args: any[]) => {
+        this.encoder.validateArgs(method, args);
+        return (await this.simulate(method.name, args)).value;
+      };
+    }
   }
 
-  async deploy(params: any[] = []): Promise<string> {
-    // Implementation for deploying contract
-    // 1. Get deploy TX data
-    // 2. Sign transaction
-    // 3. Broadcast to network
-    // 4. Return contract address
-    return "contract_address";
+  /**
+   * Instantiates a deployed contract from the ABI embedded in its on-chain
+   * bytecode.
+   */
+  static async fromAlkaneId(
+    id: AlkaneIdLike,
+    provider: Provider
+  ): Promise<AlkanesContract> {
+    const address = AlkaneId.from(id);
+    const bytecode = await provider.getBytecode(address);
+    if (bytecode.length === 0) {
+      throw new Error(`No bytecode found for ${address}`);
+    }
+
+    const abi = extractAbi(bytecode);
+    if (!abi) {
+      throw new Error(
+        `Bytecode of ${address} has no ${ABI_SECTION_NAME} section`
+      );
+    }
+
+    return new AlkanesContract({
+      abi,
+      bytecode: Buffer.from(toWasm(bytecode)).toString("base64"),
+      address,
+      provider,
+    });
   }
 
-  async call(methodName: string, params: any[] = []): Promise<any> {
-    const method = this.findMethod(methodName);
-    if (!method) {
-      throw new Error(`Method ${methodName} not found in ABI`);
+  /**
+   * Builds the signed commit/reveal pair for deploying this contract with
+   * the given `initialize` arguments. `alkaneId` is only known up front for
+   * reserved deployments.
+   */
+  buildDeploy(
+    options: BuildDeployOptions,
+    params: any[] = []
+  ): { transactions: DeployTransactions; alkaneId?: AlkaneId } {
+    const target =
+      options.reservedNumber === undefined
+        ? AlkaneId.create()
+        : AlkaneId.reserved(options.reservedNumber);
+
+    const initialize = this.findMethod("initialize") ?? {
+      opcode: AlkanesOpcode.Initialize,
+      name: "initialize",
+      inputs: [],
+      outputs: [],
+    };
+    const cellpack = this.encoder.encodeCellpack(target, initialize, params);
+
+    const transactions = buildDeployTransactions(
+      new Uint8Array(Buffer.from(this.config.bytecode, "base64")),
+      cellpack,
+      options
+    );
+
+    return { transactions, alkaneId: target.deployedId() };
+  }
+
+  async deploy(
+    options: DeployOptions,
+    params: any[] = []
+  ): Promise<DeployResult> {
+    const provider = this.config.provider;
+    const broadcast = this.broadcaster(options, "Deploying");
+    if (
+      options.reservedNumber === undefined &&
+      !options.resolveAlkaneId &&
+      !provider
+    ) {
+      throw new Error(
+        "Deploying to [1, 0] needs a provider or resolveAlkaneId to learn the assigned id; pass reservedNumber for a deterministic [4, n] id"
+      );
+    }
+
+    const { transactions, alkaneId } = this.buildDeploy(options, params);
+    const commitTxid = await broadcast(transactions.commitHex);
+    const revealTxid = await broadcast(transactions.revealHex);
+
+    // The protostone is traced at the first virtual vout after the outputs
+    const protostoneVout = transactions.reveal.outputs.length + 1;
+    const id =
+      alkaneId ??
+      (options.resolveAlkaneId
+        ? await options.resolveAlkaneId(revealTxid)
+        : await resolveDeployedId(provider!, revealTxid, protostoneVout));
+    this.address = id;
+
+    return { alkaneId: id, commitTxid, revealTxid };
+  }
+
+  /**
+   * Builds the signed transaction calling a method, spending
+   * `options.utxo` with the call's protostone in its OP_RETURN output.
+   */
+  buildCall(
+    methodName: string,
+    params: any[],
+    options: CallTransactionOptions
+  ): CallTransaction {
+    const method = this.getMethod(methodName);
+    const cellpack = this.encoder.encodeCellpack(this.target(), method, params);
+    return buildCallTransaction(cellpack, options);
+  }
+
+  /** Signs and broadcasts a method call, resolving to its txid. */
+  async call(
+    methodName: string,
+    params: any[],
+    options: CallOptions
+  ): Promise<CallResult> {
+    const { hex } = this.buildCall(methodName, params, options);
+    return { txid: await this.broadcaster(options, "Calling")(hex) };
+  }
+
+  /**
+   * Runs a method against the indexer's current state without broadcasting
+   * and decodes the returned data by the method's outputs.
+   */
+  async simulate(
+    methodName: string,
+    params: any[] = [],
+    options: Omit<SimulateRequest, "target" | "inputs"> = {}
+  ): Promise<DecodedCallResponse> {
+    const method = this.getMethod(methodName);
+    const cellpack = this.encoder.buildCellpack(this.target(), method, params);
+
+    const result = await this.provider().simulate({ ...cellpack, ...options });
+    if (result.status !== 0 || result.error) {
+      throw new Error(
+        `Simulating ${methodName} reverted: ${result.error ?? `status ${result.status}`}`
+      );
     }
 
-    // Implementation for calling contract method
-    // 1. Encode params according to ABI
-    // 2. Create transaction
-    // 3. Return result
+    const values = this.encoder.decodeResponse(
+      method.outputs,
+      result.response.data
+    );
+    return {
+      value: values.length > 1 ? values : values[0],
+      data: result.response.data,
+      transfers: result.response.alkanes.transfers,
+      gasUsed: result.gasUsed,
+    };
+  }
+
+  /**
+   * Encodes a method call into the runestone OP_RETURN script carrying the
+   * call's protostone.
+   */
+  encodeCall(methodName: string, params: any[] = []): Uint8Array {
+    const method = this.getMethod(methodName);
+    const cellpack = this.encoder.encodeCellpack(this.target(), method, params);
+    return encodeCellpackRunestone(cellpack);
+  }
+
+  private broadcaster(
+    options: { broadcast?: (txHex: string) => Promise<string> },
+    action: string
+  ): (txHex: string) => Promise<string> {
+    const provider = this.config.provider;
+    const broadcast =
+      options.broadcast ??
+      (provider && ((hex: string) => provider.sendRawTransaction(hex)));
+    if (!broadcast) {
+      throw new Error(`${action} needs a provider or a broadcast function`);
+    }
+    return broadcast;
+  }
+
+  private target(): AlkaneId {
+    if (!this.address) {
+      throw new Error("Contract has no address; deploy it or set one");
+    }
+    return this.address;
+  }
+
+  private provider(): Provider {
+    if (!this.config.provider) {
+      throw new Error("Contract has no provider to query the indexer with");
+    }
+    return this.config.provider;
+  }
+
+  private getMethod(name: string): AlkanesMethod {
+    const method = this.findMethod(name);
+    if (!method) {
+      throw new Error(`Method ${name} not found in ABI`);
+    }
+    return method;
   }
 
   private findMethod(name: string): AlkanesMethod | undefined {
@@ -1,6 +1,35 @@
-import { AlkanesType, AlkanesPrimitive } from "./types";
-
-export class AlkanesEncoder {
+import { AlkaneId } from "./alkaneId";
+import {
+  AlkanesMethod,
+  AlkanesParam,
+  AlkanesPrimitive,
+  AlkanesType,
+  Cellpack,
+  Encoder,
+} from "./types";
+
+// Fixed-width integer layouts, all little-endian like Rust's to_le_bytes()
+const INTEGER_WIDTHS: Record<string, { bytes: number; signed: boolean }> = {
+  u8: { bytes: 1, signed: false },
+  u16: { bytes: 2, signed: false },
+  u32: { bytes: 4, signed: false },
+  u64: { bytes: 8, signed: false },
+  u128: { bytes: 16, signed: false },
+  i8: { bytes: 1, signed: true },
+  i16: { bytes: 2, signed: true },
+  i32: { bytes: 4, signed: true },
+  i64: { bytes: 8, signed: true },
+  i128: { bytes: 16, signed: true },
+};
+
+// Length prefix used by String, Vec<u8> and vec types
+const LENGTH_PREFIX_BYTES = 4;
+
+// Cellpack inputs are u128 words; byte payloads are packed 16 bytes per word
+const WORD_BYTES = 16;
+const WORD_MASK = (1n << 128n) - 1n;
+
+export class AlkanesEncoder implements Encoder {
   encode(type: AlkanesType, value: any): Uint8Array {
     if (typeof type === "string") {
       return this.encodePrimitive(type, value);
@@ -24,49 +53,77 @@ export class AlkanesEncoder {
   private encodePrimitive(type: AlkanesPrimitive, value: any): Uint8Array {
     switch (type) {
       case "u8":
-        return new Uint8Array([value]);
-
       case "u16":
-        const u16 = new Uint16Array([value]);
-        return new Uint8Array(u16.buffer);
-
       case "u32":
-        const u32 = new Uint32Array([value]);
-        return new Uint8Array(u32.buffer);
-
       case "u64":
       case "u128":
-        // BigInt handling
-        const bytes: number[] = [];
-        let n = BigInt(value);
-        while (n > 0n) {
-          bytes.push(Number(n & 0xffn));
-          n >>= 8n;
-        }
-        return new Uint8Array(bytes.reverse());
+      case "i8":
+      case "i16":
+      case "i32":
+      case "i64":
+      case "i128":
+        return this.encodeInteger(type, value);
 
       case "String":
-        const encoder = new TextEncoder();
-        const strBytes = encoder.encode(value);
-        // Prepend length
-        const length = new Uint32Array([strBytes.length]);
-        return new Uint8Array([...new Uint8Array(length.buffer), ...strBytes]);
+        if (typeof value !== "string") {
+          throw new Error("Expected string for String");
+        }
+        return this.withLength(new TextEncoder().encode(value));
 
       case "bool":
+        if (typeof value !== "boolean") {
+          throw new Error("Expected boolean for bool");
+        }
         return new Uint8Array([value ? 1 : 0]);
 
       case "Vec<u8>":
         if (!(value instanceof Uint8Array)) {
           throw new Error("Expected Uint8Array for Vec<u8>");
         }
-        const lenBytes = new Uint32Array([value.length]);
-        return new Uint8Array([...new Uint8Array(lenBytes.buffer), ...value]);
+        return this.withLength(value);
 
       default:
         throw new Error(`Unsupported primitive type: ${type}`);
     }
   }
 
+  private encodeInteger(type: AlkanesPrimitive, value: any): Uint8Array {
+    const { bytes, signed } = INTEGER_WIDTHS[type];
+    const bits = BigInt(bytes * 8);
+
+    let n: bigint;
+    try {
+      n = BigInt(value);
+    } catch {
+      throw new Error(`Expected integer for ${type}, got ${value}`);
+    }
+
+    const min = signed ? -(1n << (bits - 1n)) : 0n;
+    const max = signed ? (1n << (bits - 1n)) - 1n : (1n << bits) - 1n;
+    if (n < min || n > max) {
+      throw new Error(`Value ${n} out of range for ${type}`);
+    }
+
+    // Two's complement for negative values
+    if (n < 0n) {
+      n += 1n << bits;
+    }
+
+    const result = new Uint8Array(bytes);
+    for (let i = 0; i < bytes; i++) {
+      result[i] = Number(n & 0xffn);
+      n >>= 8n;
+    }
+    return result;
+  }
+
+  private withLength(bytes: Uint8Array): Uint8Array {
+    const result = new Uint8Array(LENGTH_PREFIX_BYTES + bytes.length);
+    new DataView(result.buffer).setUint32(0, bytes.length, true);
+    result.set(bytes, LENGTH_PREFIX_BYTES);
+    return result;
+  }
+
   private encodeArray(
     type: AlkanesType,
     value: any[],
@@ -94,14 +151,13 @@ export class AlkanesEncoder {
       throw new Error("Expected array for Vec");
     }
 
-    const lenBytes = new Uint32Array([value.length]);
     const parts = value.map((item) => this.encode(type, item));
     const totalLength = parts.reduce((sum, part) => sum + part.length, 0);
 
-    const result = new Uint8Array(4 + totalLength);
-    result.set(new Uint8Array(lenBytes.buffer));
+    const result = new Uint8Array(LENGTH_PREFIX_BYTES + totalLength);
+    new DataView(result.buffer).setUint32(0, value.length, true);
 
-    let offset = 4;
+    let offset = LENGTH_PREFIX_BYTES;
     for (const part of parts) {
       result.set(part, offset);
       offset += part.length;
@@ -129,39 +185,612 @@ export class AlkanesEncoder {
   }
 
   decode(type: AlkanesType, data: Uint8Array): any {
+    const reader = new ByteReader(data);
+    const value = this.decodeValue(type, reader);
+
+    if (reader.remaining > 0) {
+      throw new Error(
+        `Unexpected ${reader.remaining} trailing byte(s) after decoding ${JSON.stringify(type)}`
+      );
+    }
+
+    return value;
+  }
+
+  /**
+   * Decodes `CallResponse.data` by a method's outputs. Contracts write a
+   * trailing String or Vec<u8> as raw bytes (`name.into_bytes()`), so the
+   * last output of those types takes the rest of the data unprefixed.
+   * Methods that declare no outputs decode to none, whatever the data.
+   */
+  decodeResponse(outputs: AlkanesParam[], data: Uint8Array): any[] {
+    if (outputs.length === 0) {
+      return [];
+    }
+    const reader = new ByteReader(data);
+    const values = outputs.map((param, i) => {
+      try {
+        if (
+          i === outputs.length - 1 &&
+          (param.type === "String" || param.type === "Vec<u8>")
+        ) {
+          const bytes = reader.read(reader.remaining, param.type);
+          return param.type === "String"
+            ? new TextDecoder("utf-8", { fatal: true }).decode(bytes)
+            : bytes.slice();
+        }
+        return this.decodeValue(param.type, reader);
+      } catch (error) {
+        const message = error instanceof Error ? error.message : error;
+        throw new Error(`Invalid output ${param.name}: ${message}`);
+      }
+    });
+
+    if (reader.remaining > 0) {
+      throw new Error(
+        `Unexpected ${reader.remaining} trailing byte(s) after decoding the response`
+      );
+    }
+
+    return values;
+  }
+
+  private decodeValue(type: AlkanesType, reader: ByteReader): any {
+    if (typeof type === "string") {
+      return this.decodePrimitive(type, reader);
+    }
+
+    if ("array" in type) {
+      return this.decodeArray(type.array.type, reader, type.array.length);
+    }
+
+    if ("vec" in type) {
+      return this.decodeVec(type.vec.type, reader);
+    }
+
+    if ("tuple" in type) {
+      return this.decodeTuple(type.tuple, reader);
+    }
+
+    throw new Error(`Unsupported type: ${JSON.stringify(type)}`);
+  }
+
+  private decodePrimitive(type: AlkanesPrimitive, reader: ByteReader): any {
+    switch (type) {
+      case "u8":
+      case "u16":
+      case "u32":
+      case "u64":
+      case "u128":
+      case "i8":
+      case "i16":
+      case "i32":
+      case "i64":
+      case "i128":
+        return this.decodeInteger(type, reader);
+
+      case "String":
+        const strBytes = reader.read(reader.readLength(), type);
+        return new TextDecoder("utf-8", { fatal: true }).decode(strBytes);
+
+      case "bool":
+        const flag = reader.read(1, type)[0];
+        if (flag > 1) {
+          throw new Error(`Invalid bool byte: ${flag}`);
+        }
+        return flag === 1;
+
+      case "Vec<u8>":
+        return reader.read(reader.readLength(), type).slice();
+
+      default:
+        throw new Error(`Unsupported primitive type: ${type}`);
+    }
+  }
+
+  private decodeInteger(type: AlkanesPrimitive, reader: ByteReader): any {
+    const { bytes, signed } = INTEGER_WIDTHS[type];
+    const bits = BigInt(bytes * 8);
+    const raw = reader.read(bytes, type);
+
+    let n = 0n;
+    for (let i = bytes - 1; i >= 0; i--) {
+      n = (n << 8n) | BigInt(raw[i]);
+    }
+
+    if (signed && n >= 1n << (bits - 1n)) {
+      n -= 1n << bits;
+    }
+
+    // Anything wider than 32 bits cannot be represented safely as a number
+    return bytes > 4 ? n : Number(n);
+  }
+
+  private decodeArray(type: AlkanesType, reader: ByteReader, length: number) {
+    const result: any[] = [];
+    for (let i = 0; i < length; i++) {
+      result.push(this.decodeValue(type, reader));
+    }
+    return result;
+  }
+
+  private decodeVec(type: AlkanesType, reader: ByteReader) {
+    return this.decodeArray(type, reader, reader.readLength());
+  }
+
+  private decodeTuple(types: AlkanesType[], reader: ByteReader) {
+    return types.map((type) => this.decodeValue(type, reader));
+  }
+
+  /**
+   * Builds the cellpack for a call: target block, target tx, opcode, then
+   * each argument packed into u128 words exactly as the contract's
+   * `shift_or_err(&mut inputs)` calls will consume them.
+   */
+  encodeCellpack(
+    target: AlkaneId,
+    method: AlkanesMethod,
+    args: any[] = []
+  ): bigint[] {
+    return this.toCellpackWords(this.buildCellpack(target, method, args));
+  }
+
+  buildCellpack(
+    target: AlkaneId,
+    method: AlkanesMethod,
+    args: any[] = []
+  ): Cellpack {
+    this.checkArity(method, args);
+
+    const inputs: bigint[] = [BigInt(method.opcode)];
+    method.inputs.forEach((param, i) => {
+      try {
+        inputs.push(...this.encodeWords(param.type, args[i]));
+      } catch (error) {
+        const message = error instanceof Error ? error.message : error;
+        throw new Error(`Invalid argument ${param.name}: ${message}`);
+      }
+    });
+
+    return { target, inputs };
+  }
+
+  /**
+   * Checks call arguments against a method's inputs without coercing them:
+   * integers must be bigints or safe integer numbers, bools booleans,
+   * strings strings and byte vectors Uint8Arrays.
+   */
+  validateArgs(method: AlkanesMethod, args: any[]): void {
+    this.checkArity(method, args);
+    method.inputs.forEach((param, i) => {
+      const problem = this.checkValue(param.type, args[i]);
+      if (problem) {
+        throw new Error(`Invalid argument ${param.name}: ${problem}`);
+      }
+    });
+  }
+
+  /**
+   * Converts command-line arguments to the values a method's inputs take:
+   * integers as decimal or 0x-prefixed hex, `true`/`false` for bools, hex
+   * for `Vec<u8>` and JSON for composite types, whose integers may be
+   * numbers or strings and byte vectors hex strings.
+   */
+  parseArgs(method: AlkanesMethod, args: string[]): any[] {
+    this.checkArity(method, args);
+    return method.inputs.map((param, i) => {
+      const parsed =
+        typeof param.type === "string"
+          ? parseArgument(param.type, args[i])
+          : fromJson(param.type, parseJson(args[i]));
+      if (parsed === undefined) {
+        throw new Error(
+          `Invalid argument ${param.name}: expected ${describeType(param.type)}, got ${args[i]}`
+        );
+      }
+      return parsed;
+    });
+  }
+
+  private checkArity(method: AlkanesMethod, args: any[]) {
+    if (args.length !== method.inputs.length) {
+      throw new Error(
+        `Method ${method.name} expects ${method.inputs.length} argument(s), got ${args.length}`
+      );
+    }
+  }
+
+  private checkValue(type: AlkanesType, value: any): string | undefined {
+    if (typeof type === "string") {
+      const valid =
+        type in INTEGER_WIDTHS
+          ? typeof value === "bigint" ||
+            (typeof value === "number" && Number.isSafeInteger(value))
+          : type === "bool"
+            ? typeof value === "boolean"
+            : type === "String"
+              ? typeof value === "string"
+              : value instanceof Uint8Array;
+      return valid
+        ? undefined
+        : `expected ${type}, got ${describeValue(value)}`;
+    }
+
+    const items: [AlkanesType, any][] = [];
+    if ("array" in type || "vec" in type) {
+      const itemType = "array" in type ? type.array.type : type.vec.type;
+      if (!Array.isArray(value)) {
+        return `expected array, got ${describeValue(value)}`;
+      }
+      if ("array" in type && value.length !== type.array.length) {
+        return `expected ${type.array.length} item(s), got ${value.length}`;
+      }
+      value.forEach((item) => items.push([itemType, item]));
+    } else if ("tuple" in type) {
+      if (!Array.isArray(value) || value.length !== type.tuple.length) {
+        return `expected tuple of length ${type.tuple.length}`;
+      }
+      type.tuple.forEach((member, i) => items.push([member, value[i]]));
+    }
+
+    for (const [itemType, item] of items) {
+      const problem = this.checkValue(itemType, item);
+      if (problem) {
+        return problem;
+      }
+    }
+    return undefined;
+  }
+
+  toCellpackWords(cellpack: Cellpack): bigint[] {
+    return [...cellpack.target.toWords(), ...cellpack.inputs];
+  }
+
+  encodeWords(type: AlkanesType, value: any): bigint[] {
+    if (typeof type === "string") {
+      return this.encodePrimitiveWords(type, value);
+    }
+
+    if ("array" in type) {
+      const { type: itemType, length } = type.array;
+      if (!Array.isArray(value) || value.length !== length) {
+        throw new Error(`Expected array of length ${length}`);
+      }
+      return value.flatMap((item) => this.encodeWords(itemType, item));
+    }
+
+    if ("vec" in type) {
+      const itemType = type.vec.type;
+      if (!Array.isArray(value)) {
+        throw new Error("Expected array for Vec");
+      }
+      return [
+        BigInt(value.length),
+        ...value.flatMap((item) => this.encodeWords(itemType, item)),
+      ];
+    }
+
+    if ("tuple" in type) {
+      if (!Array.isArray(value) || value.length !== type.tuple.length) {
+        throw new Error(`Expected tuple of length ${type.tuple.length}`);
+      }
+      return type.tuple.flatMap((member, i) =>
+        this.encodeWords(member, value[i])
+      );
+    }
+
+    throw new Error(`Unsupported type: ${JSON.stringify(type)}`);
+  }
+
+  private encodePrimitiveWords(type: AlkanesPrimitive, value: any): bigint[] {
+    switch (type) {
+      case "u8":
+      case "u16":
+      case "u32":
+      case "u64":
+      case "u128":
+      case "i8":
+      case "i16":
+      case "i32":
+      case "i64":
+      case "i128":
+        // Range-check against the declared width, then widen the
+        // two's complement representation to a full u128 word
+        this.encodeInteger(type, value);
+        return [BigInt.asUintN(128, BigInt(value))];
+
+      case "bool":
+        if (typeof value !== "boolean") {
+          throw new Error("Expected boolean for bool");
+        }
+        return [value ? 1n : 0n];
+
+      case "String":
+        if (typeof value !== "string") {
+          throw new Error("Expected string for String");
+        }
+        // Null-terminated, so a string filling its last word gets a zero word
+        const strBytes = new TextEncoder().encode(value);
+        if (strBytes.includes(0)) {
+          throw new Error(
+            "Strings passed as cellpack inputs cannot contain NUL"
+          );
+        }
+        const terminated = new Uint8Array(strBytes.length + 1);
+        terminated.set(strBytes);
+        return this.packBytes(terminated);
+
+      case "Vec<u8>":
+        if (!(value instanceof Uint8Array)) {
+          throw new Error("Expected Uint8Array for Vec<u8>");
+        }
+        return [BigInt(value.length), ...this.packBytes(value)];
+
+      default:
+        throw new Error(`Unsupported primitive type: ${type}`);
+    }
+  }
+
+  // Little-endian, 16 bytes per word, zero padded like u128::from_le_bytes
+  private packBytes(bytes: Uint8Array): bigint[] {
+    const words: bigint[] = [];
+    for (let start = 0; start < bytes.length; start += WORD_BYTES) {
+      let word = 0n;
+      const end = Math.min(start + WORD_BYTES, bytes.length);
+      for (let i = end - 1; i >= start; i--) {
+        word = (word << 8n) | BigInt(bytes[i]);
+      }
+      words.push(word);
+    }
+    return words;
+  }
+
+  decodeWords(type: AlkanesType, words: bigint[]): any {
+    const reader = new WordReader(words);
+    const value = this.decodeWordValue(type, reader);
+
+    if (reader.remaining > 0) {
+      throw new Error(
+        `Unexpected ${reader.remaining} trailing word(s) after decoding ${JSON.stringify(type)}`
+      );
+    }
+
+    return value;
+  }
+
+  private decodeWordValue(type: AlkanesType, reader: WordReader): any {
     if (typeof type === "string") {
-      return this.decodePrimitive(type, data);
+      return this.decodePrimitiveWords(type, reader);
     }
 
     if ("array" in type) {
-      return this.decodeArray(type.array.type, data, type.array.length);
+      const { type: itemType, length } = type.array;
+      return Array.from({ length }, () =>
+        this.decodeWordValue(itemType, reader)
+      );
     }
 
     if ("vec" in type) {
-      return this.decodeVec(type.vec.type, data);
+      const itemType = type.vec.type;
+      const length = reader.readLength("vec length", 1);
+      return Array.from({ length }, () =>
+        this.decodeWordValue(itemType, reader)
+      );
     }
 
     if ("tuple" in type) {
-      return this.decodeTuple(type.tuple, data);
+      return type.tuple.map((member) => this.decodeWordValue(member, reader));
     }
 
     throw new Error(`Unsupported type: ${JSON.stringify(type)}`);
   }
 
-  // Implement decode methods similar to encode...
-  private decodePrimitive(type: AlkanesPrimitive, data: Uint8Array) {
-    // Implementation here
+  private decodePrimitiveWords(
+    type: AlkanesPrimitive,
+    reader: WordReader
+  ): any {
+    switch (type) {
+      case "u8":
+      case "u16":
+      case "u32":
+      case "u64":
+      case "u128":
+      case "i8":
+      case "i16":
+      case "i32":
+      case "i64":
+      case "i128":
+        const { bytes, signed } = INTEGER_WIDTHS[type];
+        const bits = BigInt(bytes * 8);
+        const word = reader.read(type);
+        const n = signed ? BigInt.asIntN(128, word) : word;
+        const min = signed ? -(1n << (bits - 1n)) : 0n;
+        const max = signed ? (1n << (bits - 1n)) - 1n : (1n << bits) - 1n;
+        if (n < min || n > max) {
+          throw new Error(`Word ${n} out of range for ${type}`);
+        }
+        return bytes > 4 ? n : Number(n);
+
+      case "bool":
+        const flag = reader.read(type);
+        if (flag > 1n) {
+          throw new Error(`Invalid bool word: ${flag}`);
+        }
+        return flag === 1n;
+
+      case "String":
+        const strBytes: number[] = [];
+        for (;;) {
+          const chunk = this.unpackWord(reader.read(type), WORD_BYTES);
+          const nul = chunk.indexOf(0);
+          if (nul >= 0) {
+            strBytes.push(...chunk.subarray(0, nul));
+            break;
+          }
+          strBytes.push(...chunk);
+        }
+        return new TextDecoder("utf-8", { fatal: true }).decode(
+          new Uint8Array(strBytes)
+        );
+
+      case "Vec<u8>":
+        const length = reader.readLength(type, WORD_BYTES);
+        const result = new Uint8Array(length);
+        for (let offset = 0; offset < length; offset += WORD_BYTES) {
+          const size = Math.min(WORD_BYTES, length - offset);
+          result.set(this.unpackWord(reader.read(type), size), offset);
+        }
+        return result;
+
+      default:
+        throw new Error(`Unsupported primitive type: ${type}`);
+    }
+  }
+
+  private unpackWord(word: bigint, size: number): Uint8Array {
+    const bytes = new Uint8Array(size);
+    for (let i = 0; i < size; i++) {
+      bytes[i] = Number(word & 0xffn);
+      word >>= 8n;
+    }
+    return bytes;
+  }
+}
+
+// Cursor over cellpack words, mirroring shift_or_err on the contract side
+class WordReader {
+  private words: bigint[];
+  private offset = 0;
+
+  constructor(words: bigint[]) {
+    this.words = words;
+  }
+
+  get remaining(): number {
+    return this.words.length - this.offset;
+  }
+
+  read(what: string): bigint {
+    if (this.remaining === 0) {
+      throw new Error(
+        `Unexpected end of inputs reading ${what} at word ${this.offset}`
+      );
+    }
+    const word = BigInt(this.words[this.offset++]);
+    if (word < 0n || word > WORD_MASK) {
+      throw new Error(`Word ${word} at ${this.offset - 1} is not a u128`);
+    }
+    return word;
+  }
+
+  // Rejects lengths the remaining words could never satisfy
+  readLength(what: string, perWord: number): number {
+    const length = this.read(what);
+    if (length > BigInt(this.remaining * perWord)) {
+      throw new Error(`Length ${length} for ${what} exceeds remaining inputs`);
+    }
+    return Number(length);
+  }
+}
+
+function describeValue(value: any): string {
+  if (value === null) return "null";
+  if (Array.isArray(value)) return "array";
+  if (value instanceof Uint8Array) return "Uint8Array";
+  return typeof value;
+}
+
+// Cursor over a byte buffer that fails loudly on truncated input
+class ByteReader {
+  private data: Uint8Array;
+  private offset = 0;
+
+  constructor(data: Uint8Array) {
+    this.data = data;
+  }
+
+  get remaining(): number {
+    return this.data.length - this.offset;
+  }
+
+  read(length: number, what: string): Uint8Array {
+    if (length > this.remaining) {
+      throw new Error(
+        `Unexpected end of data reading ${what} at offset ${this.offset}: need ${length} byte(s), have ${this.remaining}`
+      );
+    }
+    const bytes = this.data.subarray(this.offset, this.offset + length);
+    this.offset += length;
+    return bytes;
+  }
+
+  readLength(): number {
+    const bytes = this.read(LENGTH_PREFIX_BYTES, "length prefix");
+    return new DataView(
+      bytes.buffer,
+      bytes.byteOffset,
+      bytes.byteLength
+    ).getUint32(0, true);
   }
+}
 
-  private decodeArray(type: AlkanesType, data: Uint8Array, length: number) {
-    // Implementation here
+// A command-line argument as a value of `type`, or undefined if malformed
+function parseArgument(type: AlkanesPrimitive, arg: string): any {
+  if (type === "String") {
+    return arg;
+  }
+  if (type === "bool") {
+    return arg === "true" ? true : arg === "false" ? false : undefined;
+  }
+  if (type === "Vec<u8>") {
+    const hex = arg.replace(/^0x/i, "");
+    return /^([0-9a-f]{2})*$/i.test(hex)
+      ? new Uint8Array(Buffer.from(hex, "hex"))
+      : undefined;
   }
+  const match = /^(-?)(\d+|0x[0-9a-f]+)$/i.exec(arg);
+  if (!match || (match[1] && !INTEGER_WIDTHS[type]?.signed)) {
+    return undefined;
+  }
+  const magnitude = BigInt(match[2]);
+  return match[1] ? -magnitude : magnitude;
+}
 
-  private decodeVec(type: AlkanesType, data: Uint8Array) {
-    // Implementation here
+// A parsed JSON value as a value of `type`, or undefined if it is not one
+function fromJson(type: AlkanesType, value: unknown): any {
+  if (typeof type === "string") {
+    if (type === "bool") {
+      return typeof value === "boolean" ? value : undefined;
+    }
+    if (type in INTEGER_WIDTHS && Number.isSafeInteger(value)) {
+      value = String(value);
+    }
+    return typeof value === "string" ? parseArgument(type, value) : undefined;
+  }
+  if (!Array.isArray(value)) {
+    return undefined;
+  }
+  const itemTypes: AlkanesType[] =
+    "tuple" in type
+      ? type.tuple
+      : "array" in type
+        ? Array(type.array.length).fill(type.array.type)
+        : value.map(() => type.vec.type);
+  if (value.length !== itemTypes.length) {
+    return undefined;
   }
+  const items = value.map((item, i) => fromJson(itemTypes[i], item));
+  return items.includes(undefined) ? undefined : items;
+}
 
-  private decodeTuple(types: AlkanesType[], data: Uint8Array) {
-    // Implementation here
+function parseJson(text: string): unknown {
+  try {
+    return JSON.parse(text);
+  } catch {
+    return undefined;
   }
 }
+
+function describeType(type: AlkanesType): string {
+  return typeof type === "string" ? type : JSON.stringify(type);
+}
@@ -0,0 +1,366 @@
+// Commit/reveal transactions that inscribe contract bytecode in a taproot
+// witness envelope and deploy it with an Alkanes protostone, and the
+// transactions that call a deployed contract.
+
+import { gzipSync } from "zlib";
+import {
+  MAX_SCRIPT_ELEMENT_SIZE,
+  Network,
+  OP_0,
+  OP_CHECKSIG,
+  OP_ENDIF,
+  OP_IF,
+  OP_PUSHDATA1,
+  OP_PUSHDATA2,
+  TAPROOT_LEAF_VERSION,
+  Transaction,
+  TxOutput,
+  addressToScript,
+  getTxid,
+  getVirtualSize,
+  p2trAddress,
+  p2trScript,
+  pushData,
+  serializeTransaction,
+  tapLeafHash,
+  taprootSighash,
+  toHex,
+} from "./bitcoin";
+import { encodeCellpackRunestone } from "./protostone";
+import {
+  getXOnlyPublicKey,
+  schnorrSign,
+  tweakPrivateKey,
+  tweakPublicKey,
+} from "./secp256k1";
+import { toWasm } from "./wasm";
+
+export const ENVELOPE_PROTOCOL_ID = new TextEncoder().encode("BIN");
+export const DUST_LIMIT = 546n;
+
+export interface Utxo {
+  txid: string;
+  vout: number;
+  value: bigint;
+}
+
+export interface DeployTransactionOptions {
+  /** Key controlling `utxo` through its BIP86 taproot key-path address */
+  privateKey: Uint8Array;
+  utxo: Utxo;
+  /** Fee rate in sat/vB */
+  feeRate: number;
+  network: Network;
+  /** Receives the deployed alkane's outgoing transfers (default: signer) */
+  recipientAddress?: string;
+  /** Change address for the commit transaction (default: signer) */
+  changeAddress?: string;
+}
+
+/** The change of a call goes to `changeAddress` (default: signer). */
+export type CallTransactionOptions = DeployTransactionOptions;
+
+export interface CallTransaction {
+  transaction: Transaction;
+  txid: string;
+  hex: string;
+}
+
+export interface DeployTransactions {
+  commit: Transaction;
+  reveal: Transaction;
+  commitTxid: string;
+  revealTxid: string;
+  commitHex: string;
+  revealHex: string;
+}
+
+/** Alkanes expects contract bytecode gzip-compressed inside the envelope. */
+export function compressBytecode(wasm: Uint8Array): Uint8Array {
+  return new Uint8Array(gzipSync(wasm, { level: 9 }));
+}
+
+/**
+ * Tapscript `<pubkey> OP_CHECKSIG OP_FALSE OP_IF "BIN" OP_0 <payload...>
+ * OP_ENDIF`, with the payload split into maximum-size pushes.
+ */
+export function buildEnvelopeScript(
+  internalKey: Uint8Array,
+  payload: Uint8Array
+): Uint8Array {
+  const parts: Uint8Array[] = [
+    pushData(internalKey),
+    new Uint8Array([OP_CHECKSIG, OP_0, OP_IF]),
+    pushData(ENVELOPE_PROTOCOL_ID),
+    pushData(new Uint8Array(0)), // body tag
+  ];
+  for (let i = 0; i < payload.length; i += MAX_SCRIPT_ELEMENT_SIZE) {
+    parts.push(pushData(payload.subarray(i, i + MAX_SCRIPT_ELEMENT_SIZE)));
+  }
+  parts.push(new Uint8Array([OP_ENDIF]));
+
+  const script = new Uint8Array(parts.reduce((sum, p) => sum + p.length, 0));
+  let offset = 0;
+  for (const part of parts) {
+    script.set(part, offset);
+    offset += part.length;
+  }
+  return script;
+}
+
+/**
+ * Inverse of `buildEnvelopeScript`: the decompressed bytecode inscribed in
+ * a reveal input's tapscript (the second-to-last witness element).
+ */
+export function extractEnvelopeBytecode(script: Uint8Array): Uint8Array {
+  const invalid = (reason: string) =>
+    new Error(`Script is not a bytecode envelope: ${reason}`);
+  let offset = 0;
+  const push = (): Uint8Array | undefined => {
+    const opcode = script[offset++];
+    let length: number;
+    if (opcode < OP_PUSHDATA1) {
+      length = opcode;
+    } else if (opcode === OP_PUSHDATA1) {
+      length = script[offset++];
+    } else if (opcode === OP_PUSHDATA2) {
+      length = script[offset] | (script[offset + 1] << 8);
+      offset += 2;
+    } else {
+      offset--;
+      return undefined;
+    }
+    if (offset + length > script.length) {
+      throw invalid("truncated data push");
+    }
+    offset += length;
+    return script.subarray(offset - length, offset);
+  };
+
+  push(); // internal key
+  if (
+    script[offset] !== OP_CHECKSIG ||
+    script[offset + 1] !== OP_0 ||
+    script[offset + 2] !== OP_IF
+  ) {
+    throw invalid("missing OP_CHECKSIG OP_FALSE OP_IF");
+  }
+  offset += 3;
+  const protocol = push();
+  if (
+    !protocol ||
+    Buffer.compare(Buffer.from(protocol), Buffer.from(ENVELOPE_PROTOCOL_ID))
+  ) {
+    throw invalid('protocol id is not "BIN"');
+  }
+  if (push()?.length !== 0) {
+    throw invalid("missing body tag");
+  }
+
+  const chunks: Uint8Array[] = [];
+  for (let chunk = push(); chunk; chunk = push()) {
+    chunks.push(chunk);
+  }
+  if (script[offset] !== OP_ENDIF) {
+    throw invalid("missing OP_ENDIF");
+  }
+  return toWasm(Buffer.concat(chunks));
+}
+
+/** Taproot address of the signer's key-path (BIP86) wallet output. */
+export function signerAddress(
+  privateKey: Uint8Array,
+  network: Network
+): string {
+  const { outputKey } = tweakPublicKey(getXOnlyPublicKey(privateKey));
+  return p2trAddress(outputKey, network);
+}
+
+/**
+ * Builds and signs the commit transaction (funding a taproot output that
+ * commits to the bytecode envelope) and the reveal transaction (spending it
+ * through the envelope script, with the deploy cellpack as its protostone).
+ */
+export function buildDeployTransactions(
+  bytecode: Uint8Array,
+  cellpack: bigint[],
+  options: DeployTransactionOptions
+): DeployTransactions {
+  const { privateKey, utxo, feeRate, network } = options;
+  const internalKey = getXOnlyPublicKey(privateKey);
+  const signerKey = tweakPublicKey(internalKey).outputKey;
+  const signerScript = p2trScript(signerKey);
+
+  const envelope = buildEnvelopeScript(
+    internalKey,
+    compressBytecode(bytecode)
+  );
+  const leafHash = tapLeafHash(envelope);
+  const commitKey = tweakPublicKey(internalKey, leafHash);
+  const commitScript = p2trScript(commitKey.outputKey);
+  const controlBlock = new Uint8Array([
+    TAPROOT_LEAF_VERSION | commitKey.parity,
+    ...internalKey,
+  ]);
+
+  const fee = (tx: Transaction) =>
+    BigInt(Math.ceil(getVirtualSize(tx) * feeRate));
+
+  // Reveal: spend the commit output through the envelope leaf
+  const reveal: Transaction = {
+    version: 2,
+    inputs: [
+      {
+        txid: "00".repeat(32),
+        vout: 0,
+        witness: [new Uint8Array(64), envelope, controlBlock],
+      },
+    ],
+    outputs: [
+      {
+        value: DUST_LIMIT,
+        script: options.recipientAddress
+          ? addressToScript(options.recipientAddress, network)
+          : signerScript,
+      },
+      { value: 0n, script: encodeCellpackRunestone(cellpack) },
+    ],
+    locktime: 0,
+  };
+  const commitValue = DUST_LIMIT + fee(reveal);
+
+  // Commit: fund the envelope output from the signer's key-path UTXO
+  const commit: Transaction = {
+    version: 2,
+    inputs: [
+      {
+        txid: utxo.txid,
+        vout: utxo.vout,
+        witness: [new Uint8Array(64)],
+      },
+    ],
+    outputs: [
+      { value: commitValue, script: commitScript },
+      {
+        value: 0n,
+        script: options.changeAddress
+          ? addressToScript(options.changeAddress, network)
+          : signerScript,
+      },
+    ],
+    locktime: 0,
+  };
+  const change = utxo.value - commitValue - fee(commit);
+  if (change < 0n) {
+    throw new Error(
+      `Insufficient funds: UTXO holds ${utxo.value} sats but deployment needs ${utxo.value - change}`
+    );
+  }
+  if (change < DUST_LIMIT) {
+    commit.outputs.pop();
+  } else {
+    commit.outputs[1].value = change;
+  }
+
+  const commitPrevouts: TxOutput[] = [
+    { value: utxo.value, script: signerScript },
+  ];
+  commit.inputs[0].witness = [
+    schnorrSign(
+      taprootSighash(commit, 0, commitPrevouts),
+      tweakPrivateKey(privateKey)
+    ),
+  ];
+  const commitTxid = getTxid(commit);
+
+  reveal.inputs[0].txid = commitTxid;
+  const revealPrevouts: TxOutput[] = [
+    { value: commitValue, script: commitScript },
+  ];
+  reveal.inputs[0].witness = [
+    schnorrSign(
+      taprootSighash(reveal, 0, revealPrevouts, leafHash),
+      privateKey
+    ),
+    envelope,
+    controlBlock,
+  ];
+
+  return {
+    commit,
+    reveal,
+    commitTxid,
+    revealTxid: getTxid(reveal),
+    commitHex: toHex(serializeTransaction(commit)),
+    revealHex: toHex(serializeTransaction(reveal)),
+  };
+}
+
+/**
+ * Builds and signs a transaction spending the signer's key-path UTXO with
+ * the call cellpack as its protostone. The called alkane's outgoing
+ * transfers go to the first output.
+ */
+export function buildCallTransaction(
+  cellpack: bigint[],
+  options: CallTransactionOptions
+): CallTransaction {
+  const { privateKey, utxo, feeRate, network } = options;
+  const signerScript = p2trScript(
+    tweakPublicKey(getXOnlyPublicKey(privateKey)).outputKey
+  );
+
+  const transaction: Transaction = {
+    version: 2,
+    inputs: [
+      {
+        txid: utxo.txid,
+        vout: utxo.vout,
+        witness: [new Uint8Array(64)],
+      },
+    ],
+    outputs: [
+      {
+        value: DUST_LIMIT,
+        script: options.recipientAddress
+          ? addressToScript(options.recipientAddress, network)
+          : signerScript,
+      },
+      { value: 0n, script: encodeCellpackRunestone(cellpack) },
+      {
+        value: 0n,
+        script: options.changeAddress
+          ? addressToScript(options.changeAddress, network)
+          : signerScript,
+      },
+    ],
+    locktime: 0,
+  };
+  const fee = BigInt(Math.ceil(getVirtualSize(transaction) * feeRate));
+  const change = utxo.value - DUST_LIMIT - fee;
+  if (change < 0n) {
+    throw new Error(
+      `Insufficient funds: UTXO holds ${utxo.value} sats but the call needs ${utxo.value - change}`
+    );
+  }
+  if (change < DUST_LIMIT) {
+    transaction.outputs.pop();
+  } else {
+    transaction.outputs[2].value = change;
+  }
+
+  transaction.inputs[0].witness = [
+    schnorrSign(
+      taprootSighash(transaction, 0, [
+        { value: utxo.value, script: signerScript },
+      ]),
+      tweakPrivateKey(privateKey)
+    ),
+  ];
+
+  return {
+    transaction,
+    txid: getTxid(transaction),
+    hex: toHex(serializeTransaction(transaction)),
+  };
+}
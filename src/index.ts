@@ -1,3 +1,23 @@
-export { AlkanesContract } from "./contract";
-export { AlkanesCompiler } from "./compiler";
+export * from "./abiExtractor";
+export * from "./alkaneId";
+export * from "./artifacts";
+export { AlkanesContract, CallOptions } from "./contract";
+export {
+  AlkanesCompiler,
+  contractCrateName,
+  contractManifest,
+  vendorCrates,
+} from "./compiler";
+export { AlkanesEncoder } from "./encoder";
+export * from "./bitcoin";
+export * from "./config";
+export * from "./envelope";
+export * from "./node";
+export * from "./protostone";
+export * from "./provider";
+export * from "./storage";
+export * from "./testing";
+export * from "./typegen";
+export * from "./wasm";
+export * from "./vm";
 export * from "./types";
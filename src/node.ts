@@ -0,0 +1,621 @@
+// Local development chains behind one JSON-RPC endpoint: a regtest bitcoind
+// with the metashrew/alkanes indexer, or an in-process mock chain for CI.
+
+import { ChildProcess, spawn } from "child_process";
+import fs from "fs/promises";
+import http from "http";
+import path from "path";
+import { AlkaneId, AlkaneIdBlock } from "./alkaneId";
+import { NodeConfig } from "./config";
+import {
+  Network,
+  OP_RETURN,
+  Transaction,
+  TxOutput,
+  addressToScript,
+  fromHex,
+  getTxid,
+  parseTransaction,
+  toHex,
+} from "./bitcoin";
+import { Utxo, extractEnvelopeBytecode, signerAddress } from "./envelope";
+import {
+  ALKANES_PROTOCOL_TAG,
+  decipherCellpack,
+  decodeRunestone,
+} from "./protostone";
+import { JsonRpcProvider, TraceEvent } from "./provider";
+import { sha256 } from "./secp256k1";
+import { AlkanesVM, ExecutionResult } from "./vm";
+
+const DEFAULT_BITCOIN_RPC_PORT = 18443;
+const DEFAULT_METASHREW_PORT = 18889;
+const RPC_CREDENTIALS = "alkali:alkali";
+const COINBASE_MATURITY = 100;
+const MOCK_FUNDING = 5_000_000_000n;
+const OP_13 = 0x5d;
+
+// JSON-RPC error codes
+const METHOD_NOT_FOUND = -32601;
+const INTERNAL_ERROR = -32603;
+const RPC_VERIFY_ERROR = -25; // bitcoind's code for rejected transactions
+
+export interface DevAccount {
+  address: string;
+  /** Hex private key of the account's BIP86 taproot address */
+  privateKey: string;
+  /** Spendable output funding the account, once the node has started */
+  utxo?: Utxo;
+}
+
+/** A chain that `alkali node` serves over JSON-RPC. */
+export interface DevNode {
+  readonly accounts: DevAccount[];
+  start(): Promise<void>;
+  handle(method: string, params: any[]): Promise<any>;
+  stop(): Promise<void>;
+}
+
+export type JsonRpcHandler = (method: string, params: any[]) => Promise<any>;
+
+/** Error answered to a JSON-RPC request with its own error code. */
+export class NodeRpcError extends Error {
+  code: number;
+
+  constructor(code: number, message: string) {
+    super(message);
+    this.name = "NodeRpcError";
+    this.code = code;
+  }
+}
+
+/**
+ * Deterministic dev accounts, the same on every run so scripts and tests
+ * can hard-code them. Never send real funds to these keys.
+ */
+export function devAccounts(count: number, network: Network): DevAccount[] {
+  return Array.from({ length: count }, (_, i) => {
+    const privateKey = sha256(
+      new TextEncoder().encode(`alkali dev account ${i}`)
+    );
+    return {
+      address: signerAddress(privateKey, network),
+      privateKey: toHex(privateKey),
+    };
+  });
+}
+
+/** Serves `handler` as a JSON-RPC 2.0 endpoint, resolving once listening. */
+export async function serveJsonRpc(
+  handler: JsonRpcHandler,
+  port: number,
+  host = "127.0.0.1"
+): Promise<http.Server> {
+  const server = http.createServer(async (req, res) => {
+    let id: any = null;
+    let reply: any;
+    try {
+      const chunks: Buffer[] = [];
+      for await (const chunk of req) {
+        chunks.push(chunk as Buffer);
+      }
+      const request = JSON.parse(Buffer.concat(chunks).toString("utf8"));
+      id = request.id ?? null;
+      const result = await handler(request.method, request.params ?? []);
+      reply = { jsonrpc: "2.0", id, result: result ?? null };
+    } catch (error: any) {
+      reply = {
+        jsonrpc: "2.0",
+        id,
+        error: {
+          code: typeof error?.code === "number" ? error.code : INTERNAL_ERROR,
+          message: error instanceof Error ? error.message : String(error),
+        },
+      };
+    }
+
+    res.setHeader("content-type", "application/json");
+    res.end(
+      JSON.stringify(reply, (_, value) =>
+        typeof value === "bigint" ? value.toString() : value
+      )
+    );
+  });
+
+  await new Promise<void>((resolve, reject) => {
+    server.once("error", reject);
+    server.listen(port, host, () => resolve());
+  });
+  return server;
+}
+
+export interface MockChainOptions {
+  /** Number of funded dev accounts (default: 10) */
+  accounts?: number;
+  /** Mine a block after every accepted transaction (default: true) */
+  autoMine?: boolean;
+}
+
+/**
+ * A deterministic chain needing no external binaries: transactions are
+ * checked against an in-memory UTXO set and their Alkanes protostones run
+ * in an `AlkanesVM` when mined. Protorune balances are not tracked, so
+ * edicts are ignored.
+ */
+export class MockChain implements DevNode {
+  readonly accounts: DevAccount[];
+  readonly vm = new AlkanesVM();
+  private autoMine: boolean;
+  private utxos = new Map<string, TxOutput>();
+  private mempool: { tx: Transaction; bytes: Uint8Array }[] = [];
+  private traces = new Map<string, TraceEvent[]>();
+
+  constructor(options: MockChainOptions = {}) {
+    this.accounts = devAccounts(options.accounts ?? 10, "regtest");
+    this.autoMine = options.autoMine ?? true;
+  }
+
+  get height(): number {
+    return Number(this.vm.height);
+  }
+
+  /** Funds every dev account with 50 BTC in the first block. */
+  async start(): Promise<void> {
+    const funding: Transaction = {
+      version: 2,
+      inputs: [{ txid: "00".repeat(32), vout: 0xffffffff }],
+      outputs: this.accounts.map((account) => ({
+        value: MOCK_FUNDING,
+        script: addressToScript(account.address, "regtest"),
+      })),
+      locktime: 0,
+    };
+    const txid = getTxid(funding);
+    funding.outputs.forEach((output, vout) => {
+      this.utxos.set(`${txid}:${vout}`, output);
+      this.accounts[vout].utxo = { txid, vout, value: output.value };
+    });
+    await this.mine(1);
+  }
+
+  async stop(): Promise<void> {}
+
+  /** Accepts a raw transaction into the mempool, resolving to its txid. */
+  async submitTransaction(txHex: string): Promise<string> {
+    const bytes = fromHex(txHex);
+    const tx = parseTransaction(bytes);
+    const txid = getTxid(tx);
+
+    for (const input of tx.inputs) {
+      if (!this.utxos.has(`${input.txid}:${input.vout}`)) {
+        throw new NodeRpcError(
+          RPC_VERIFY_ERROR,
+          `${txid} spends missing or spent output ${input.txid}:${input.vout}`
+        );
+      }
+    }
+    for (const input of tx.inputs) {
+      this.utxos.delete(`${input.txid}:${input.vout}`);
+    }
+    tx.outputs.forEach((output, vout) => {
+      if (output.script[0] !== OP_RETURN) {
+        this.utxos.set(`${txid}:${vout}`, output);
+      }
+    });
+
+    this.mempool.push({ tx, bytes });
+    if (this.autoMine) {
+      await this.mine(1);
+    }
+    return txid;
+  }
+
+  /** Mines `blocks` blocks, the first one including the mempool. */
+  async mine(blocks = 1): Promise<void> {
+    for (let i = 0; i < blocks; i++) {
+      this.vm.height++;
+      const included = this.mempool;
+      this.mempool = [];
+      for (const { tx, bytes } of included) {
+        await this.index(tx, bytes);
+      }
+    }
+  }
+
+  async handle(method: string, params: any[]): Promise<any> {
+    switch (method) {
+      case "alkanes_simulate": {
+        const [request] = params;
+        const result = await this.vm.simulate({
+          target: request.target,
+          inputs: (request.inputs ?? []).map(BigInt),
+          alkanes: (request.alkanes ?? []).map((transfer: any) => ({
+            id: AlkaneId.from(transfer.id),
+            value: BigInt(transfer.value),
+          })),
+          vout: request.vout,
+          transaction: fromHex(strip0x(request.transaction)),
+          block: fromHex(strip0x(request.block)),
+        });
+        return {
+          status: result.status,
+          gasUsed: result.gasUsed,
+          execution: { ...formatResponse(result), error: result.error },
+        };
+      }
+      case "alkanes_trace": {
+        const [{ txid, vout }] = params;
+        return this.traces.get(`${txid}:${vout}`) ?? [];
+      }
+      case "alkanes_getbytecode":
+        return hex(await this.vm.getBytecode(AlkaneId.from(params[0])));
+      case "alkanes_getstorageat": {
+        const [{ id, path }] = params;
+        const key = fromHex(strip0x(path));
+        return hex(await this.vm.getStorageAt(AlkaneId.from(id), key));
+      }
+      case "alkali_storagekeys": {
+        const [{ id, prefix }] = params;
+        const keys = await this.vm.getStorageKeys(
+          AlkaneId.from(id),
+          fromHex(strip0x(prefix))
+        );
+        return keys.map(hex);
+      }
+      case "alkanes_protorunesbyaddress":
+        return { outpoints: [] };
+      case "metashrew_height":
+      case "btc_getblockcount":
+        return this.height;
+      case "btc_sendrawtransaction":
+        return this.submitTransaction(params[0]);
+      case "btc_generatetoaddress": {
+        const start = this.height;
+        await this.mine(Number(params[0]));
+        return Array.from({ length: this.height - start }, (_, i) =>
+          toHex(sha256(new TextEncoder().encode(`block ${start + i + 1}`)))
+        );
+      }
+      default:
+        throw new NodeRpcError(
+          METHOD_NOT_FOUND,
+          `Method ${method} is not supported by the mock chain`
+        );
+    }
+  }
+
+  // Protostone traces live at the virtual vouts after the real outputs
+  private async index(tx: Transaction, bytes: Uint8Array): Promise<void> {
+    const txid = getTxid(tx);
+    for (const output of tx.outputs) {
+      if (output.script[0] !== OP_RETURN || output.script[1] !== OP_13) {
+        continue;
+      }
+      const { protostones } = decodeRunestone(output.script);
+      for (const [i, protostone] of protostones.entries()) {
+        if (protostone.protocolTag !== ALKANES_PROTOCOL_TAG) {
+          continue;
+        }
+        const vout = tx.outputs.length + 1 + i;
+        const cellpack = decipherCellpack(protostone.message);
+        this.traces.set(
+          `${txid}:${vout}`,
+          await this.runCellpack(tx, bytes, cellpack, vout)
+        );
+      }
+    }
+  }
+
+  private async runCellpack(
+    tx: Transaction,
+    bytes: Uint8Array,
+    cellpack: bigint[],
+    vout: number
+  ): Promise<TraceEvent[]> {
+    const target = AlkaneId.fromWords(cellpack);
+    const inputs = cellpack.slice(2);
+    const invoke = (myself: AlkaneId): TraceEvent => ({
+      event: "invoke",
+      data: {
+        type: "call",
+        context: {
+          myself: idData(myself),
+          caller: idData(new AlkaneId(0, 0)),
+          inputs: inputs.map(String),
+          vout,
+        },
+      },
+    });
+
+    try {
+      const block = Number(target.block);
+      if (block === AlkaneIdBlock.Create || block === AlkaneIdBlock.Reserved) {
+        const { alkaneId, result } = await this.vm.deploy(
+          revealedBytecode(tx),
+          inputs,
+          {
+            reservedNumber:
+              block === AlkaneIdBlock.Reserved ? target.tx : undefined,
+          }
+        );
+        return [
+          { event: "create", data: idData(alkaneId) },
+          invoke(alkaneId),
+          returned(result),
+        ];
+      }
+
+      const result = await this.vm.execute({
+        target,
+        inputs,
+        vout,
+        transaction: bytes,
+      });
+      return [invoke(target), returned(result)];
+    } catch (error) {
+      return [
+        invoke(target),
+        {
+          event: "return",
+          data: {
+            status: "revert",
+            error: error instanceof Error ? error.message : String(error),
+          },
+        },
+      ];
+    }
+  }
+}
+
+export interface RegtestNodeOptions extends NodeConfig {
+  indexer: string;
+  /** Number of funded dev accounts (default: 10) */
+  accounts?: number;
+  /** Mine a block after every broadcast transaction (default: true) */
+  autoMine?: boolean;
+  /** Receives the output of the child processes */
+  log?: (line: string) => void;
+}
+
+/**
+ * A regtest bitcoind indexed by metashrew running the alkanes indexer, both
+ * started from local binaries. `btc_*` methods are proxied to bitcoind and
+ * everything else to metashrew.
+ */
+export class RegtestNode implements DevNode {
+  readonly accounts: DevAccount[];
+  private options: RegtestNodeOptions;
+  private bitcoin: JsonRpcProvider;
+  private metashrew: JsonRpcProvider;
+  private processes: ChildProcess[] = [];
+
+  constructor(options: RegtestNodeOptions) {
+    this.options = options;
+    this.accounts = devAccounts(options.accounts ?? 10, "regtest");
+    this.bitcoin = new JsonRpcProvider({
+      url: `http://127.0.0.1:${this.bitcoinRpcPort}`,
+      headers: {
+        authorization: `Basic ${Buffer.from(RPC_CREDENTIALS).toString("base64")}`,
+      },
+    });
+    this.metashrew = new JsonRpcProvider(
+      `http://127.0.0.1:${options.metashrewPort ?? DEFAULT_METASHREW_PORT}`
+    );
+  }
+
+  private get bitcoinRpcPort(): number {
+    return this.options.bitcoinRpcPort ?? DEFAULT_BITCOIN_RPC_PORT;
+  }
+
+  /**
+   * Starts bitcoind, funds the dev accounts with mature coinbase outputs,
+   * then starts metashrew and waits for it to index the chain.
+   */
+  async start(): Promise<void> {
+    const dataDir = path.resolve(this.options.dataDir ?? ".alkali/node");
+    const [user, password] = RPC_CREDENTIALS.split(":");
+    await fs.mkdir(path.join(dataDir, "bitcoin"), { recursive: true });
+    await fs.mkdir(path.join(dataDir, "metashrew"), { recursive: true });
+    await fs.access(this.options.indexer).catch(() => {
+      throw new Error(`Alkanes indexer ${this.options.indexer} not found`);
+    });
+
+    this.launch(this.options.bitcoind ?? "bitcoind", [
+      "-regtest",
+      "-server",
+      "-txindex",
+      `-datadir=${path.join(dataDir, "bitcoin")}`,
+      `-rpcuser=${user}`,
+      `-rpcpassword=${password}`,
+      `-rpcport=${this.bitcoinRpcPort}`,
+      "-fallbackfee=0.0001",
+    ]);
+    await this.waitFor("bitcoind", () => this.bitcoin.request("getblockcount"));
+
+    // Each account gets a coinbase; the last 100 blocks make them spendable
+    if ((await this.bitcoin.request<number>("getblockcount")) === 0) {
+      for (const account of this.accounts) {
+        await this.bitcoin.request("generatetoaddress", [1, account.address]);
+      }
+      await this.bitcoin.request("generatetoaddress", [
+        COINBASE_MATURITY,
+        this.accounts[0].address,
+      ]);
+    }
+    for (const account of this.accounts) {
+      account.utxo = await this.findUtxo(account.address);
+    }
+
+    this.launch(this.options.metashrew ?? "rockshrew-mono", [
+      "--daemon-rpc-url",
+      `http://127.0.0.1:${this.bitcoinRpcPort}`,
+      "--auth",
+      RPC_CREDENTIALS,
+      "--indexer",
+      path.resolve(this.options.indexer),
+      "--db-path",
+      path.join(dataDir, "metashrew"),
+      "--host",
+      "127.0.0.1",
+      "--port",
+      String(this.options.metashrewPort ?? DEFAULT_METASHREW_PORT),
+    ]);
+    await this.waitFor("metashrew", () => this.syncIndexer());
+  }
+
+  async stop(): Promise<void> {
+    const exits = this.processes.map(
+      (child) =>
+        new Promise<void>((resolve) => {
+          if (child.exitCode !== null) {
+            resolve();
+            return;
+          }
+          child.once("exit", () => resolve());
+          child.kill("SIGTERM");
+        })
+    );
+    await Promise.all(exits);
+    this.processes = [];
+  }
+
+  async handle(method: string, params: any[]): Promise<any> {
+    if (!method.startsWith("btc_")) {
+      return this.metashrew.request(method, params);
+    }
+
+    const result = await this.bitcoin.request(method.slice(4), params);
+    if (
+      method === "btc_sendrawtransaction" &&
+      this.options.autoMine !== false
+    ) {
+      await this.bitcoin.request("generatetoaddress", [
+        1,
+        this.accounts[0].address,
+      ]);
+      await this.syncIndexer();
+    }
+    return result;
+  }
+
+  private launch(command: string, args: string[]): void {
+    const child = spawn(command, args, { stdio: ["ignore", "pipe", "pipe"] });
+    const log = this.options.log;
+    if (log) {
+      child.stdout?.on("data", (data) => log(data.toString().trimEnd()));
+      child.stderr?.on("data", (data) => log(data.toString().trimEnd()));
+    } else {
+      child.stdout?.resume();
+      child.stderr?.resume();
+    }
+    child.on("error", () => {}); // reported by waitFor
+    this.processes.push(child);
+  }
+
+  // Polls `ready` until it resolves, failing early if a child exited
+  private async waitFor(
+    name: string,
+    ready: () => Promise<unknown>,
+    { attempts = 120, intervalMs = 500 } = {}
+  ): Promise<void> {
+    let lastError: unknown;
+    for (let attempt = 0; attempt < attempts; attempt++) {
+      const exited = this.processes.find(
+        (child) => child.exitCode !== null || child.pid === undefined
+      );
+      if (exited) {
+        throw new Error(
+          `${exited.spawnfile} exited before ${name} was ready; is it installed?`
+        );
+      }
+      try {
+        await ready();
+        return;
+      } catch (error) {
+        lastError = error;
+      }
+      await new Promise((resolve) => setTimeout(resolve, intervalMs));
+    }
+    throw new Error(`${name} did not become ready: ${lastError}`);
+  }
+
+  // Resolves once metashrew has indexed bitcoind's tip
+  private async syncIndexer(): Promise<void> {
+    const tip = await this.bitcoin.request<number>("getblockcount");
+    for (;;) {
+      if ((await this.metashrew.getIndexerHeight()) >= tip) {
+        return;
+      }
+      await new Promise((resolve) => setTimeout(resolve, 200));
+    }
+  }
+
+  private async findUtxo(address: string): Promise<Utxo | undefined> {
+    const scan = await this.bitcoin.request("scantxoutset", [
+      "start",
+      [`addr(${address})`],
+    ]);
+    const tip = await this.bitcoin.request<number>("getblockcount");
+    const spendable = (scan.unspents ?? [])
+      .filter((entry: any) => tip - entry.height + 1 >= COINBASE_MATURITY)
+      .sort((a: any, b: any) => a.height - b.height);
+    if (spendable.length === 0) {
+      return undefined;
+    }
+    return {
+      txid: spendable[0].txid,
+      vout: spendable[0].vout,
+      value: BigInt(Math.round(spendable[0].amount * 1e8)),
+    };
+  }
+}
+
+// The reveal input spends the commit through the envelope leaf, so its
+// witness is [signature, envelope script, control block]
+function revealedBytecode(tx: Transaction): Uint8Array {
+  for (const input of tx.inputs) {
+    const witness = input.witness ?? [];
+    if (witness.length < 3) {
+      continue;
+    }
+    try {
+      return extractEnvelopeBytecode(witness[witness.length - 2]);
+    } catch {
+      // not an envelope spend
+    }
+  }
+  throw new Error("Deployment has no bytecode envelope");
+}
+
+function returned(result: ExecutionResult): TraceEvent {
+  return {
+    event: "return",
+    data: {
+      status: result.status === 0 ? "success" : "revert",
+      response: formatResponse(result),
+      error: result.error,
+    },
+  };
+}
+
+function formatResponse(result: ExecutionResult) {
+  return {
+    data: hex(result.response.data),
+    alkanes: result.response.alkanes.transfers.map((transfer) => ({
+      id: idData(transfer.id),
+      value: transfer.value.toString(),
+    })),
+  };
+}
+
+function idData(id: AlkaneId) {
+  return { block: id.block.toString(), tx: id.tx.toString() };
+}
+
+function hex(bytes: Uint8Array): string {
+  return "0x" + toHex(bytes);
+}
+
+function strip0x(value?: string): string {
+  return (value ?? "").replace(/^0x/, "");
+}
@@ -0,0 +1,422 @@
+// Runestone / Protostone encoding for embedding Alkanes calls in a
+// Bitcoin transaction's OP_RETURN output.
+
+import {
+  MAX_SCRIPT_ELEMENT_SIZE,
+  OP_PUSHDATA1,
+  OP_PUSHDATA2,
+  OP_RETURN,
+  pushData,
+} from "./bitcoin";
+
+const OP_13 = 0x5d; // Runestone magic number
+
+// Protostones are carried 15 bytes per u128 so every chunk stays below 2^120
+const PROTOSTONE_CHUNK_BYTES = 15;
+
+export const ALKANES_PROTOCOL_TAG = 1n;
+
+export enum RunestoneTag {
+  Body = 0,
+  Flags = 2,
+  Rune = 4,
+  Premine = 6,
+  Cap = 8,
+  Amount = 10,
+  Mint = 20,
+  Pointer = 22,
+  Cenotaph = 126,
+  Nop = 127,
+  Protocol = 16383,
+}
+
+export enum ProtostoneTag {
+  Body = 0,
+  Message = 81,
+  Burn = 83,
+  Pointer = 91,
+  Refund = 93,
+}
+
+export interface RuneId {
+  block: bigint;
+  tx: bigint;
+}
+
+export interface Edict {
+  id: RuneId;
+  amount: bigint;
+  output: number;
+}
+
+export interface Protostone {
+  protocolTag: bigint;
+  edicts?: Edict[];
+  pointer?: number;
+  refund?: number;
+  burn?: number;
+  message?: Uint8Array;
+}
+
+export interface Runestone {
+  edicts?: Edict[];
+  pointer?: number;
+  protostones: Protostone[];
+}
+
+export function encodeVarint(value: bigint): Uint8Array {
+  if (value < 0n || value >= 1n << 128n) {
+    throw new Error(`Varint ${value} is not a u128`);
+  }
+
+  const bytes: number[] = [];
+  let n = value;
+  while (n >> 7n > 0n) {
+    bytes.push(Number(n & 0x7fn) | 0x80);
+    n >>= 7n;
+  }
+  bytes.push(Number(n));
+  return new Uint8Array(bytes);
+}
+
+export function encodeVarints(values: bigint[]): Uint8Array {
+  return concat(values.map(encodeVarint));
+}
+
+export function decodeVarints(data: Uint8Array): bigint[] {
+  const values: bigint[] = [];
+  let offset = 0;
+
+  while (offset < data.length) {
+    let value = 0n;
+    let shift = 0n;
+    for (;;) {
+      if (offset >= data.length) {
+        throw new Error("Truncated varint");
+      }
+      const byte = data[offset++];
+      value |= BigInt(byte & 0x7f) << shift;
+      if ((byte & 0x80) === 0) {
+        break;
+      }
+      shift += 7n;
+      if (shift > 126n) {
+        throw new Error("Varint overflows u128");
+      }
+    }
+    if (value >= 1n << 128n) {
+      throw new Error("Varint overflows u128");
+    }
+    values.push(value);
+  }
+
+  return values;
+}
+
+/**
+ * Serializes cellpack words (target block, target tx, inputs...) into the
+ * protostone message bytes, as `Cellpack::encipher` does on the Rust side.
+ */
+export function encipherCellpack(words: bigint[]): Uint8Array {
+  return encodeVarints(words);
+}
+
+export function decipherCellpack(message: Uint8Array): bigint[] {
+  return decodeVarints(message);
+}
+
+/** Flattens protostones into the integer stream carried by the Protocol tag. */
+export function encodeProtostones(protostones: Protostone[]): bigint[] {
+  const result: bigint[] = [];
+
+  for (const protostone of protostones) {
+    const payload: bigint[] = [];
+
+    if (protostone.burn !== undefined) {
+      payload.push(BigInt(ProtostoneTag.Burn), BigInt(protostone.burn));
+    }
+    if (protostone.message !== undefined && protostone.message.length > 0) {
+      for (const chunk of splitBytes(protostone.message)) {
+        payload.push(BigInt(ProtostoneTag.Message), chunk);
+      }
+    }
+    if (protostone.pointer !== undefined) {
+      payload.push(BigInt(ProtostoneTag.Pointer), BigInt(protostone.pointer));
+    }
+    if (protostone.refund !== undefined) {
+      payload.push(BigInt(ProtostoneTag.Refund), BigInt(protostone.refund));
+    }
+    if (protostone.edicts !== undefined && protostone.edicts.length > 0) {
+      payload.push(
+        BigInt(ProtostoneTag.Body),
+        ...encodeEdicts(protostone.edicts)
+      );
+    }
+
+    result.push(protostone.protocolTag, BigInt(payload.length), ...payload);
+  }
+
+  return result;
+}
+
+export function decodeProtostones(values: bigint[]): Protostone[] {
+  const protostones: Protostone[] = [];
+  let offset = 0;
+
+  while (offset < values.length) {
+    // A zero protocol tag is chunk padding, not another protostone
+    if (values[offset] === 0n) {
+      break;
+    }
+    if (offset + 2 > values.length) {
+      throw new Error("Truncated protostone header");
+    }
+    const protocolTag = values[offset];
+    const length = Number(values[offset + 1]);
+    offset += 2;
+    if (offset + length > values.length) {
+      throw new Error(`Protostone payload of ${length} values is truncated`);
+    }
+
+    const payload = values.slice(offset, offset + length);
+    offset += length;
+
+    const protostone: Protostone = { protocolTag };
+    const messageChunks: bigint[] = [];
+    let i = 0;
+    while (i < payload.length) {
+      const tag = Number(payload[i]);
+      if (tag === ProtostoneTag.Body) {
+        protostone.edicts = decodeEdicts(payload.slice(i + 1));
+        break;
+      }
+      if (i + 1 >= payload.length) {
+        throw new Error(`Protostone tag ${tag} is missing its value`);
+      }
+      const value = payload[i + 1];
+      switch (tag) {
+        case ProtostoneTag.Message:
+          messageChunks.push(value);
+          break;
+        case ProtostoneTag.Burn:
+          protostone.burn = Number(value);
+          break;
+        case ProtostoneTag.Pointer:
+          protostone.pointer = Number(value);
+          break;
+        case ProtostoneTag.Refund:
+          protostone.refund = Number(value);
+          break;
+        default:
+          throw new Error(`Unknown protostone tag ${tag}`);
+      }
+      i += 2;
+    }
+
+    if (messageChunks.length > 0) {
+      protostone.message = joinBytes(messageChunks);
+    }
+    protostones.push(protostone);
+  }
+
+  return protostones;
+}
+
+/** Builds the complete `OP_RETURN OP_13 <payload>` runestone script. */
+export function encodeRunestone(runestone: Runestone): Uint8Array {
+  const fields: bigint[] = [];
+
+  const protocolBytes = encodeVarints(
+    encodeProtostones(runestone.protostones)
+  );
+  for (const chunk of splitBytes(protocolBytes)) {
+    fields.push(BigInt(RunestoneTag.Protocol), chunk);
+  }
+  if (runestone.pointer !== undefined) {
+    fields.push(BigInt(RunestoneTag.Pointer), BigInt(runestone.pointer));
+  }
+  if (runestone.edicts !== undefined && runestone.edicts.length > 0) {
+    fields.push(BigInt(RunestoneTag.Body), ...encodeEdicts(runestone.edicts));
+  }
+
+  const payload = encodeVarints(fields);
+  const pushes: Uint8Array[] = [];
+  for (let i = 0; i < payload.length; i += MAX_SCRIPT_ELEMENT_SIZE) {
+    pushes.push(pushData(payload.subarray(i, i + MAX_SCRIPT_ELEMENT_SIZE)));
+  }
+
+  return concat([new Uint8Array([OP_RETURN, OP_13]), ...pushes]);
+}
+
+export function decodeRunestone(script: Uint8Array): Runestone {
+  if (script[0] !== OP_RETURN || script[1] !== OP_13) {
+    throw new Error("Script is not a runestone (expected OP_RETURN OP_13)");
+  }
+
+  const payload = concat(readPushes(script.subarray(2)));
+  const fields = decodeVarints(payload);
+
+  const runestone: Runestone = { protostones: [] };
+  const protocolChunks: bigint[] = [];
+  let i = 0;
+  while (i < fields.length) {
+    const tag = Number(fields[i]);
+    if (tag === RunestoneTag.Body) {
+      runestone.edicts = decodeEdicts(fields.slice(i + 1));
+      break;
+    }
+    if (i + 1 >= fields.length) {
+      throw new Error(`Runestone tag ${tag} is missing its value`);
+    }
+    const value = fields[i + 1];
+    switch (tag) {
+      case RunestoneTag.Protocol:
+        protocolChunks.push(value);
+        break;
+      case RunestoneTag.Pointer:
+        runestone.pointer = Number(value);
+        break;
+      default:
+        throw new Error(`Unsupported runestone tag ${tag}`);
+    }
+    i += 2;
+  }
+
+  if (protocolChunks.length > 0) {
+    runestone.protostones = decodeProtostones(
+      decodeVarints(joinBytes(protocolChunks))
+    );
+  }
+
+  return runestone;
+}
+
+/**
+ * Convenience for the common case: a single Alkanes protostone whose
+ * message is the given cellpack.
+ */
+export function encodeCellpackRunestone(
+  cellpack: bigint[],
+  options: { pointer?: number; refund?: number; edicts?: Edict[] } = {}
+): Uint8Array {
+  return encodeRunestone({
+    protostones: [
+      {
+        protocolTag: ALKANES_PROTOCOL_TAG,
+        message: encipherCellpack(cellpack),
+        pointer: options.pointer ?? 0,
+        refund: options.refund ?? 0,
+        edicts: options.edicts,
+      },
+    ],
+  });
+}
+
+// Edicts are sorted by id and delta-encoded: block delta, tx (delta within
+// the same block), amount, output
+function encodeEdicts(edicts: Edict[]): bigint[] {
+  const sorted = [...edicts].sort((a, b) =>
+    a.id.block === b.id.block
+      ? Number(a.id.tx - b.id.tx)
+      : Number(a.id.block - b.id.block)
+  );
+
+  const result: bigint[] = [];
+  let previous: RuneId = { block: 0n, tx: 0n };
+  for (const edict of sorted) {
+    const blockDelta = edict.id.block - previous.block;
+    const tx = blockDelta === 0n ? edict.id.tx - previous.tx : edict.id.tx;
+    result.push(blockDelta, tx, edict.amount, BigInt(edict.output));
+    previous = edict.id;
+  }
+  return result;
+}
+
+function decodeEdicts(values: bigint[]): Edict[] {
+  if (values.length % 4 !== 0) {
+    throw new Error("Edict body must contain groups of four values");
+  }
+
+  const edicts: Edict[] = [];
+  let previous: RuneId = { block: 0n, tx: 0n };
+  for (let i = 0; i < values.length; i += 4) {
+    const [blockDelta, tx, amount, output] = values.slice(i, i + 4);
+    const id = {
+      block: previous.block + blockDelta,
+      tx: blockDelta === 0n ? previous.tx + tx : tx,
+    };
+    edicts.push({ id, amount, output: Number(output) });
+    previous = id;
+  }
+  return edicts;
+}
+
+// Little-endian 15-byte chunks
+function splitBytes(bytes: Uint8Array): bigint[] {
+  const chunks: bigint[] = [];
+  for (let start = 0; start < bytes.length; start += PROTOSTONE_CHUNK_BYTES) {
+    const end = Math.min(start + PROTOSTONE_CHUNK_BYTES, bytes.length);
+    let chunk = 0n;
+    for (let i = end - 1; i >= start; i--) {
+      chunk = (chunk << 8n) | BigInt(bytes[i]);
+    }
+    chunks.push(chunk);
+  }
+  return chunks;
+}
+
+// Inverse of splitBytes. The final chunk's zero padding is kept, just as the
+// indexer sees it, so deciphered messages may end in extra zero values
+function joinBytes(chunks: bigint[]): Uint8Array {
+  const bytes: number[] = [];
+  for (const chunk of chunks) {
+    if (chunk >= 1n << BigInt(PROTOSTONE_CHUNK_BYTES * 8)) {
+      throw new Error(`Chunk ${chunk} exceeds ${PROTOSTONE_CHUNK_BYTES} bytes`);
+    }
+    let n = chunk;
+    for (let i = 0; i < PROTOSTONE_CHUNK_BYTES; i++) {
+      bytes.push(Number(n & 0xffn));
+      n >>= 8n;
+    }
+  }
+  return new Uint8Array(bytes);
+}
+
+function readPushes(script: Uint8Array): Uint8Array[] {
+  const pushes: Uint8Array[] = [];
+  let offset = 0;
+
+  while (offset < script.length) {
+    const opcode = script[offset++];
+    let length: number;
+    if (opcode > 0 && opcode < OP_PUSHDATA1) {
+      length = opcode;
+    } else if (opcode === OP_PUSHDATA1) {
+      length = script[offset++];
+    } else if (opcode === OP_PUSHDATA2) {
+      length = script[offset] | (script[offset + 1] << 8);
+      offset += 2;
+    } else {
+      throw new Error(
+        `Unexpected opcode 0x${opcode.toString(16)} in runestone`
+      );
+    }
+    if (offset + length > script.length) {
+      throw new Error("Truncated data push in runestone");
+    }
+    pushes.push(script.subarray(offset, offset + length));
+    offset += length;
+  }
+
+  return pushes;
+}
+
+function concat(parts: Uint8Array[]): Uint8Array {
+  const result = new Uint8Array(parts.reduce((sum, p) => sum + p.length, 0));
+  let offset = 0;
+  for (const part of parts) {
+    result.set(part, offset);
+    offset += part.length;
+  }
+  return result;
+}
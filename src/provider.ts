@@ -0,0 +1,305 @@
+import { AlkaneId } from "./alkaneId";
+import { getNetworkConfig, loadConfig } from "./config";
+import { AlkaneTransfer, CallResponse } from "./types";
+
+// Request for executing a cellpack against the indexer's current state
+// without broadcasting anything
+export interface SimulateRequest {
+  target: AlkaneId;
+  inputs: bigint[];
+  alkanes?: AlkaneTransfer[];
+  height?: bigint;
+  pointer?: number;
+  refundPointer?: number;
+  vout?: number;
+  txindex?: number;
+  transaction?: Uint8Array;
+  block?: Uint8Array;
+}
+
+export interface SimulateResult {
+  status: number;
+  gasUsed: bigint;
+  response: CallResponse;
+  error?: string;
+}
+
+export interface TraceEvent {
+  event: string;
+  data: any;
+}
+
+export interface ProtoruneOutpoint {
+  txid: string;
+  vout: number;
+  value: bigint;
+  height: number;
+  balances: AlkaneTransfer[];
+}
+
+export interface Provider {
+  // alkanes / metashrew views
+  simulate(request: SimulateRequest): Promise<SimulateResult>;
+  trace(txid: string, vout: number): Promise<TraceEvent[]>;
+  getBytecode(id: AlkaneId): Promise<Uint8Array>;
+  /** Raw value stored under `key` by the alkane; empty when unset */
+  getStorageAt(id: AlkaneId, key: Uint8Array): Promise<Uint8Array>;
+  /**
+   * Keys the alkane has set under `prefix`, in byte order. Only local
+   * chains can list them; metashrew indexers keep no such index.
+   */
+  getStorageKeys?(id: AlkaneId, prefix: Uint8Array): Promise<Uint8Array[]>;
+  getProtorunesByAddress(
+    address: string,
+    protocolTag?: bigint
+  ): Promise<ProtoruneOutpoint[]>;
+  getIndexerHeight(): Promise<number>;
+
+  // bitcoind
+  sendRawTransaction(txHex: string): Promise<string>;
+  getBlockCount(): Promise<number>;
+}
+
+// JSON-RPC error code of methods the server does not provide
+const METHOD_NOT_FOUND = -32601;
+
+export interface JsonRpcProviderOptions {
+  url: string;
+  /** Separate bitcoind endpoint; defaults to `url` with `btc_` methods */
+  bitcoinUrl?: string;
+  headers?: Record<string, string>;
+}
+
+export class JsonRpcError extends Error {
+  code: number;
+  data?: any;
+
+  constructor(method: string, code: number, message: string, data?: any) {
+    super(`${method} failed (${code}): ${message}`);
+    this.name = "JsonRpcError";
+    this.code = code;
+    this.data = data;
+  }
+}
+
+/**
+ * Provider for a metashrew/alkanes JSON-RPC gateway (`alkanes_*`,
+ * `metashrew_*` and proxied `btc_*` methods), optionally with a plain
+ * bitcoind endpoint for the bitcoin calls.
+ */
+export class JsonRpcProvider implements Provider {
+  private options: JsonRpcProviderOptions;
+  private nextId = 1;
+
+  constructor(options: string | JsonRpcProviderOptions) {
+    this.options = typeof options === "string" ? { url: options } : options;
+  }
+
+  /**
+   * Reads `networks.<network>` (default: the config's `network`) from the
+   * project config, found in the working directory unless `configPath` is
+   * given.
+   */
+  static async fromConfig(
+    configPath?: string,
+    network?: string
+  ): Promise<JsonRpcProvider> {
+    const config = await loadConfig({ configPath });
+    const { url, bitcoinUrl, headers } = getNetworkConfig(config, network);
+    return new JsonRpcProvider({ url, bitcoinUrl, headers });
+  }
+
+  async request<T = any>(
+    method: string,
+    params: any[] = [],
+    url: string = this.options.url
+  ): Promise<T> {
+    const body = JSON.stringify(
+      { jsonrpc: "2.0", id: this.nextId++, method, params },
+      (_, value) => (typeof value === "bigint" ? value.toString() : value)
+    );
+
+    const res = await fetch(url, {
+      method: "POST",
+      headers: {
+        "content-type": "application/json",
+        ...this.options.headers,
+      },
+      body,
+    });
+    if (!res.ok) {
+      throw new Error(`${method} failed: HTTP ${res.status} from ${url}`);
+    }
+
+    const payload = await res.json();
+    if (payload.error) {
+      throw new JsonRpcError(
+        method,
+        payload.error.code,
+        payload.error.message,
+        payload.error.data
+      );
+    }
+    return payload.result as T;
+  }
+
+  async simulate(request: SimulateRequest): Promise<SimulateResult> {
+    const result = await this.request("alkanes_simulate", [
+      {
+        alkanes: (request.alkanes ?? []).map((transfer) => ({
+          id: idParam(transfer.id),
+          value: transfer.value.toString(),
+        })),
+        transaction: hexParam(request.transaction),
+        block: hexParam(request.block),
+        height: (request.height ?? 0n).toString(),
+        txindex: request.txindex ?? 0,
+        target: idParam(request.target),
+        inputs: request.inputs.map((input) => input.toString()),
+        pointer: request.pointer ?? 0,
+        refundPointer: request.refundPointer ?? 0,
+        vout: request.vout ?? 0,
+      },
+    ]);
+
+    const execution = result.execution ?? {};
+    return {
+      status: Number(result.status ?? 0),
+      gasUsed: BigInt(result.gasUsed ?? 0),
+      response: {
+        data: parseHex(execution.data),
+        alkanes: { transfers: (execution.alkanes ?? []).map(parseTransfer) },
+      },
+      error: execution.error || undefined,
+    };
+  }
+
+  async trace(txid: string, vout: number): Promise<TraceEvent[]> {
+    return (await this.request("alkanes_trace", [{ txid, vout }])) ?? [];
+  }
+
+  async getBytecode(id: AlkaneId): Promise<Uint8Array> {
+    const result = await this.request<string>("alkanes_getbytecode", [
+      idParam(id),
+    ]);
+    return parseHex(result);
+  }
+
+  async getStorageAt(id: AlkaneId, key: Uint8Array): Promise<Uint8Array> {
+    const result = await this.request<string>("alkanes_getstorageat", [
+      { id: idParam(id), path: hexParam(key) },
+    ]);
+    return parseHex(result);
+  }
+
+  /** Lists storage keys through `alkali node`'s `alkali_storagekeys`. */
+  async getStorageKeys(
+    id: AlkaneId,
+    prefix: Uint8Array
+  ): Promise<Uint8Array[]> {
+    try {
+      const result = await this.request<string[]>("alkali_storagekeys", [
+        { id: idParam(id), prefix: hexParam(prefix) },
+      ]);
+      return result.map(parseHex);
+    } catch (error) {
+      if (error instanceof JsonRpcError && error.code === METHOD_NOT_FOUND) {
+        throw new Error(
+          `${this.options.url} cannot list storage keys; only \`alkali node\` can`
+        );
+      }
+      throw error;
+    }
+  }
+
+  async getProtorunesByAddress(
+    address: string,
+    protocolTag = 1n
+  ): Promise<ProtoruneOutpoint[]> {
+    const result = await this.request("alkanes_protorunesbyaddress", [
+      { address, protocolTag: protocolTag.toString() },
+    ]);
+    return (result?.outpoints ?? []).map((entry: any) => ({
+      txid: entry.outpoint.txid,
+      vout: Number(entry.outpoint.vout),
+      value: BigInt(entry.output?.value ?? 0),
+      height: Number(entry.height ?? 0),
+      balances: (entry.runes ?? []).map((rune: any) =>
+        parseTransfer({ id: rune.rune.id, value: rune.balance })
+      ),
+    }));
+  }
+
+  async getIndexerHeight(): Promise<number> {
+    return Number(await this.request("metashrew_height"));
+  }
+
+  async sendRawTransaction(txHex: string): Promise<string> {
+    return this.bitcoinRequest("sendrawtransaction", [txHex]);
+  }
+
+  async getBlockCount(): Promise<number> {
+    return Number(await this.bitcoinRequest("getblockcount"));
+  }
+
+  private bitcoinRequest<T = any>(
+    method: string,
+    params: any[] = []
+  ): Promise<T> {
+    return this.options.bitcoinUrl
+      ? this.request<T>(method, params, this.options.bitcoinUrl)
+      : this.request<T>(`btc_${method}`, params);
+  }
+}
+
+/**
+ * Finds the id the indexer assigned to a contract deployed through
+ * `[1, 0]`, from the `create` event in the protostone's trace. Protostone
+ * traces live at the virtual vouts after the transaction's real outputs.
+ */
+export async function resolveDeployedId(
+  provider: Provider,
+  txid: string,
+  vout: number,
+  { attempts = 30, intervalMs = 2000 } = {}
+): Promise<AlkaneId> {
+  for (let attempt = 0; attempt < attempts; attempt++) {
+    const events = await provider.trace(txid, vout);
+    const created = events.find((event) => event.event === "create");
+    if (created) {
+      return AlkaneId.from(created.data);
+    }
+    const reverted = events.find(
+      (event) => event.event === "return" && event.data?.status === "revert"
+    );
+    if (reverted) {
+      throw new Error(`Deployment ${txid} reverted`);
+    }
+    if (attempt + 1 < attempts) {
+      await new Promise((resolve) => setTimeout(resolve, intervalMs));
+    }
+  }
+  throw new Error(`No create event traced for ${txid}:${vout}`);
+}
+
+function idParam(id: AlkaneId) {
+  return { block: id.block.toString(), tx: id.tx.toString() };
+}
+
+function hexParam(bytes?: Uint8Array): string {
+  return "0x" + (bytes ? Buffer.from(bytes).toString("hex") : "");
+}
+
+function parseHex(hex?: string | null): Uint8Array {
+  if (!hex) {
+    return new Uint8Array(0);
+  }
+  return new Uint8Array(Buffer.from(hex.replace(/^0x/, ""), "hex"));
+}
+
+function parseTransfer(transfer: any): AlkaneTransfer {
+  return {
+    id: new AlkaneId(BigInt(transfer.id.block), BigInt(transfer.id.tx)),
+    value: BigInt(transfer.value),
+  };
+}
@@ -0,0 +1,350 @@
+import { AlkaneId } from "./alkaneId";
+import { AlkanesEncoder } from "./encoder";
+import { Provider } from "./provider";
+import { AlkanesABI, AlkanesType, StorageKey } from "./types";
+
+// Layout of lists built with `append`/`select_index` on a StoragePointer:
+// the item count under `<key>/length` and items under `<key>/<index>`
+const LIST_LENGTH_KEYWORD = "/length";
+const LIST_LENGTH_TYPE = "u32";
+
+/**
+ * A deployed contract's storage, read through the indexer and decoded by
+ * the ABI's `storage` entries. Values of map entries are addressed by one
+ * sub-key per `select` segment of their path.
+ */
+export class ContractStorage {
+  private abi: AlkanesABI;
+  private address: () => AlkaneId;
+  private provider: () => Provider;
+  private encoder = new AlkanesEncoder();
+
+  constructor(
+    abi: AlkanesABI,
+    address: () => AlkaneId,
+    provider: () => Provider
+  ) {
+    this.abi = abi;
+    this.address = address;
+    this.provider = provider;
+  }
+
+  get keys(): StorageKey[] {
+    return this.abi.storage;
+  }
+
+  /** The raw bytes stored at `key`, empty when unset. */
+  async getRaw(key: string, ...subKeys: any[]): Promise<Uint8Array> {
+    const entry = this.entry(key);
+    return this.read(storageKeyBytes(entry, subKeys, this.encoder));
+  }
+
+  /**
+   * The value stored at `key`, decoded by its type. Unset integers read as
+   * zero, like `get_value` does on the contract side; lists are read item
+   * by item.
+   */
+  async get(key: string, ...subKeys: any[]): Promise<any> {
+    const entry = this.entry(key);
+    const slot = storageKeyBytes(entry, subKeys, this.encoder);
+
+    if (typeof entry.type === "object" && "vec" in entry.type) {
+      const itemType = entry.type.vec.type;
+      const length = decodeSlot(
+        this.encoder,
+        LIST_LENGTH_TYPE,
+        await this.read(concat(slot, utf8(LIST_LENGTH_KEYWORD)))
+      );
+      const items: any[] = [];
+      for (let index = 0; index < length; index++) {
+        const data = await this.read(concat(slot, utf8(`/${index}`)));
+        items.push(decodeSlot(this.encoder, itemType, data));
+      }
+      return items;
+    }
+
+    return decodeSlot(this.encoder, entry.type, await this.read(slot));
+  }
+
+  /**
+   * Reads a map entry for each of `subKeys`, or for each one the contract
+   * has set when they are omitted. Listing them needs a provider that can
+   * enumerate storage, like the local VM or `alkali node`; metashrew
+   * indexers cannot. Entries with several `select` segments take and yield
+   * an array of sub-keys each.
+   */
+  async entries(key: string, subKeys?: any[]): Promise<Array<[any, any]>> {
+    const entry = this.entry(key);
+    const selects = selectSegments(entry).length;
+    subKeys ??= await this.subKeys(entry);
+    const entries: Array<[any, any]> = [];
+    for (const subKey of subKeys) {
+      const path = selects > 1 ? subKey : [subKey];
+      entries.push([subKey, await this.get(key, ...path)]);
+    }
+    return entries;
+  }
+
+  // Sub-keys of the entry's slots the contract has set
+  private async subKeys(entry: StorageKey): Promise<any[]> {
+    const provider = this.provider();
+    if (!provider.getStorageKeys) {
+      throw new Error(
+        `The provider cannot list storage keys; pass the sub-keys of ${entry.key}`
+      );
+    }
+    const slots = await provider.getStorageKeys(
+      this.address(),
+      utf8(entry.key)
+    );
+    const single = selectSegments(entry).length === 1;
+    return slots
+      .map((slot) => parseStorageKey(entry, slot, this.encoder))
+      .filter((subKeys): subKeys is any[] => subKeys !== undefined)
+      .map((subKeys) => (single ? subKeys[0] : subKeys));
+  }
+
+  private entry(key: string): StorageKey {
+    const entry = this.abi.storage.find((storage) => storage.key === key);
+    if (!entry) {
+      throw new Error(`Storage key ${key} not found in ABI`);
+    }
+    return entry;
+  }
+
+  private read(slot: Uint8Array): Promise<Uint8Array> {
+    return this.provider().getStorageAt(this.address(), slot);
+  }
+}
+
+/**
+ * The full storage key of `entry`: its keyword, then each path segment,
+ * with `select` segments taken from `subKeys` in order.
+ */
+export function storageKeyBytes(
+  entry: StorageKey,
+  subKeys: any[],
+  encoder = new AlkanesEncoder()
+): Uint8Array {
+  const selects = selectSegments(entry);
+  if (subKeys.length !== selects.length) {
+    throw new Error(
+      `Storage key ${entry.key} takes ${selects.length} sub-key(s), got ${subKeys.length}`
+    );
+  }
+
+  let next = 0;
+  const parts = [utf8(entry.key)];
+  for (const segment of entry.path ?? []) {
+    if ("keyword" in segment) {
+      parts.push(utf8(segment.keyword));
+      continue;
+    }
+    try {
+      parts.push(encodeSubKey(encoder, segment.select, subKeys[next++]));
+    } catch (error) {
+      const message = error instanceof Error ? error.message : error;
+      throw new Error(`Invalid sub-key for ${entry.key}: ${message}`);
+    }
+  }
+  return concat(...parts);
+}
+
+/**
+ * The sub-keys of `entry` a full storage key was built from, or undefined
+ * when it is not one of the entry's slots. A `String` or `Vec<u8>` sub-key
+ * runs to the next keyword segment, or to the end when it is the last
+ * segment; list entries are found by their `/length` slot.
+ */
+export function parseStorageKey(
+  entry: StorageKey,
+  slot: Uint8Array,
+  encoder = new AlkanesEncoder()
+): any[] | undefined {
+  const keyword = utf8(entry.key);
+  const suffix = utf8(isList(entry.type) ? LIST_LENGTH_KEYWORD : "");
+  if (
+    slot.length < keyword.length + suffix.length ||
+    !startsWith(slot, keyword) ||
+    !startsWith(slot.subarray(slot.length - suffix.length), suffix)
+  ) {
+    return undefined;
+  }
+
+  let rest = slot.subarray(keyword.length, slot.length - suffix.length);
+  const segments = entry.path ?? [];
+  const subKeys: any[] = [];
+  for (const [index, segment] of segments.entries()) {
+    if ("keyword" in segment) {
+      const bytes = utf8(segment.keyword);
+      if (!startsWith(rest, bytes)) {
+        return undefined;
+      }
+      rest = rest.subarray(bytes.length);
+      continue;
+    }
+
+    const next = segments[index + 1];
+    let length = subKeySize(encoder, segment.select);
+    if (length === undefined && next === undefined) {
+      length = rest.length;
+    } else if (length === undefined && next && "keyword" in next) {
+      length = indexOf(rest, utf8(next.keyword));
+    }
+    if (length === undefined || length < 0 || length > rest.length) {
+      return undefined;
+    }
+    try {
+      subKeys.push(
+        decodeSubKey(encoder, segment.select, rest.subarray(0, length))
+      );
+    } catch {
+      return undefined;
+    }
+    rest = rest.subarray(length);
+  }
+  return rest.length === 0 ? subKeys : undefined;
+}
+
+/**
+ * Sub-keys typed in on the command line, parsed by the type of the
+ * `select` segment each one fills: integers as decimal or 0x-prefixed
+ * hex, `true`/`false` for bools, hex for `Vec<u8>` and JSON otherwise.
+ */
+export function parseSubKeys(entry: StorageKey, args: string[]): any[] {
+  const selects = selectSegments(entry);
+  if (args.length !== selects.length) {
+    throw new Error(
+      `Storage key ${entry.key} takes ${selects.length} sub-key(s), got ${args.length}`
+    );
+  }
+  return selects.map(({ select: type }, index) => {
+    const arg = args[index];
+    if (type === "String" || type === "Vec<u8>") {
+      return arg;
+    }
+    if (type === "bool") {
+      if (arg !== "true" && arg !== "false") {
+        throw new Error(
+          `Sub-key ${index} of ${entry.key} must be true or false, got ${arg}`
+        );
+      }
+      return arg === "true";
+    }
+    if (typeof type === "string") {
+      if (!/^(\d+|0x[0-9a-f]+)$/i.test(arg)) {
+        throw new Error(
+          `Sub-key ${index} of ${entry.key} must be a ${type}, got ${arg}`
+        );
+      }
+      return BigInt(arg);
+    }
+    return JSON.parse(arg);
+  });
+}
+
+function selectSegments(entry: StorageKey) {
+  return (entry.path ?? []).filter((segment) => "select" in segment);
+}
+
+// Sub-keys are appended as their raw bytes: `select(&bytes)`, the UTF-8 of
+// a `keyword(&name)`, or an integer's `to_le_bytes()`
+function encodeSubKey(
+  encoder: AlkanesEncoder,
+  type: AlkanesType,
+  value: any
+): Uint8Array {
+  if (type === "Vec<u8>") {
+    if (value instanceof Uint8Array) {
+      return value;
+    }
+    if (typeof value === "string" && /^(0x)?([0-9a-f]{2})*$/i.test(value)) {
+      return new Uint8Array(Buffer.from(value.replace(/^0x/, ""), "hex"));
+    }
+    throw new Error(`expected Uint8Array or hex string, got ${value}`);
+  }
+  if (type === "String") {
+    return utf8(String(value));
+  }
+  return encoder.encode(type, value);
+}
+
+// Byte length of fixed-size sub-keys; strings and byte vectors have none
+function subKeySize(
+  encoder: AlkanesEncoder,
+  type: AlkanesType
+): number | undefined {
+  if (typeof type !== "string" || type === "String" || type === "Vec<u8>") {
+    return undefined;
+  }
+  return encoder.encode(type, type === "bool" ? false : 0).length;
+}
+
+// The inverse of encodeSubKey; byte vectors come back as hex
+function decodeSubKey(
+  encoder: AlkanesEncoder,
+  type: AlkanesType,
+  bytes: Uint8Array
+): any {
+  if (type === "Vec<u8>") {
+    return Buffer.from(bytes).toString("hex");
+  }
+  if (type === "String") {
+    return new TextDecoder("utf-8", { fatal: true }).decode(bytes);
+  }
+  return encoder.decode(type, bytes);
+}
+
+function isList(type: AlkanesType): boolean {
+  return typeof type === "object" && "vec" in type;
+}
+
+function decodeSlot(
+  encoder: AlkanesEncoder,
+  type: AlkanesType,
+  data: Uint8Array
+): any {
+  if (type === "Vec<u8>") {
+    return data.slice();
+  }
+  if (type === "String") {
+    return new TextDecoder("utf-8", { fatal: true }).decode(data);
+  }
+  if (data.length === 0 && typeof type === "string") {
+    const zero = encoder.encode(type, type === "bool" ? false : 0);
+    return encoder.decode(type, zero);
+  }
+  return encoder.decode(type, data);
+}
+
+function startsWith(bytes: Uint8Array, prefix: Uint8Array): boolean {
+  return (
+    bytes.length >= prefix.length &&
+    prefix.every((byte, index) => bytes[index] === byte)
+  );
+}
+
+function indexOf(bytes: Uint8Array, needle: Uint8Array): number {
+  for (let start = 0; start + needle.length <= bytes.length; start++) {
+    if (startsWith(bytes.subarray(start), needle)) {
+      return start;
+    }
+  }
+  return -1;
+}
+
+function utf8(text: string): Uint8Array {
+  return new TextEncoder().encode(text);
+}
+
+function concat(...parts: Uint8Array[]): Uint8Array {
+  const result = new Uint8Array(
+    parts.reduce((sum, part) => sum + part.length, 0)
+  );
+  let offset = 0;
+  for (const part of parts) {
+    result.set(part, offset);
+    offset += part.length;
+  }
+  return result;
+}
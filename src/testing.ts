@@ -0,0 +1,240 @@
+// Contract testing harness used by `alkali test`: deploys compiled contracts
+// into an in-memory AlkanesVM and calls them with ABI-encoded arguments.
+
+import { AlkaneId, AlkaneIdLike } from "./alkaneId";
+import { loadArtifact } from "./artifacts";
+import { AlkanesContract } from "./contract";
+import { AlkanesEncoder } from "./encoder";
+import {
+  AlkanesABI,
+  AlkanesMethod,
+  AlkanesOpcode,
+  AlkaneTransfer,
+  DecodedCallResponse,
+} from "./types";
+import {
+  AlkanesVM,
+  AlkanesVMOptions,
+  ExecutionResult,
+  StorageDiff,
+} from "./vm";
+import { extractAbi } from "./wasm";
+
+export interface TestEnvironmentOptions extends AlkanesVMOptions {
+  /** Build directory with the compiled contracts' manifest (default:
+   * $ALKALI_ARTIFACTS, which `alkali test` sets, or build) */
+  artifactsDir?: string;
+}
+
+export interface TestCallOptions {
+  /** Alkanes sent along with the call */
+  alkanes?: AlkaneTransfer[];
+  /** Calling alkane; it must hold `alkanes` (default: the transaction) */
+  caller?: AlkaneIdLike;
+  fuel?: bigint;
+}
+
+export interface TestCallResult<T = any> extends DecodedCallResponse<T> {
+  storage: StorageDiff[];
+  logs: string[];
+}
+
+/** Thrown by calls that revert; `result` holds the failed execution. */
+export class RevertError extends Error {
+  result: ExecutionResult;
+
+  constructor(message: string, result: ExecutionResult) {
+    super(message);
+    this.name = "RevertError";
+    this.result = result;
+  }
+}
+
+/**
+ * A fresh chain per test file (or per test): contracts deployed into it
+ * only see each other, and blocks advance only through `mine`.
+ */
+export class AlkanesTestEnvironment {
+  readonly vm: AlkanesVM;
+  private artifactsDir: string;
+
+  constructor(options: TestEnvironmentOptions = {}) {
+    this.vm = new AlkanesVM({ height: 1n, ...options });
+    this.artifactsDir =
+      options.artifactsDir ?? process.env.ALKALI_ARTIFACTS ?? "build";
+  }
+
+  get height(): bigint {
+    return this.vm.height;
+  }
+
+  /** Advances the chain by `blocks`, returning the new height. */
+  mine(blocks = 1): bigint {
+    this.vm.height += BigInt(blocks);
+    return this.vm.height;
+  }
+
+  /**
+   * Deploys the compiled contract `name` (e.g. `"Example"` for
+   * contracts/Example.rs) with the given `initialize` arguments.
+   */
+  async deploy(
+    name: string,
+    args: any[] = [],
+    options: { reservedNumber?: bigint } = {}
+  ): Promise<TestContract> {
+    const { bytecode, abi } = await loadArtifact(this.artifactsDir, name);
+    return this.deployBytecode(bytecode, args, { ...options, abi });
+  }
+
+  /** Deploys WASM bytecode, described by `abi` or its embedded ABI. */
+  async deployBytecode(
+    bytecode: Uint8Array,
+    args: any[] = [],
+    options: { abi?: AlkanesABI; reservedNumber?: bigint } = {}
+  ): Promise<TestContract> {
+    const abi = options.abi ?? extractAbi(bytecode);
+    if (!abi) {
+      throw new Error("Bytecode has no embedded ABI; pass options.abi");
+    }
+    const initialize = abi.methods.find((m) => m.name === "initialize") ?? {
+      opcode: AlkanesOpcode.Initialize,
+      name: "initialize",
+      inputs: [],
+      outputs: [],
+    };
+    const { inputs } = new AlkanesEncoder().buildCellpack(
+      AlkaneId.create(),
+      initialize,
+      args
+    );
+
+    const { alkaneId } = await this.vm.deploy(bytecode, inputs, {
+      reservedNumber: options.reservedNumber,
+    });
+    return new TestContract(this.vm, abi, alkaneId);
+  }
+}
+
+/** A contract deployed into an AlkanesTestEnvironment. */
+export class TestContract {
+  readonly alkaneId: AlkaneId;
+  readonly abi: AlkanesABI;
+  /** The contract bound to the VM, for `read` and `storage` */
+  readonly contract: AlkanesContract;
+  private vm: AlkanesVM;
+  private encoder = new AlkanesEncoder();
+
+  constructor(vm: AlkanesVM, abi: AlkanesABI, alkaneId: AlkaneId) {
+    this.vm = vm;
+    this.abi = abi;
+    this.alkaneId = alkaneId;
+    this.contract = new AlkanesContract({
+      abi,
+      bytecode: "",
+      address: alkaneId,
+      provider: vm,
+    });
+  }
+
+  get storage() {
+    return this.contract.storage;
+  }
+
+  /** Balance of `token` held by this contract. */
+  balanceOf(token: AlkaneIdLike): bigint {
+    return this.vm.balanceOf(this.alkaneId, token);
+  }
+
+  /** Calls `method` and keeps its state changes; throws if it reverts. */
+  async call<T = any>(
+    method: string,
+    args: any[] = [],
+    options: TestCallOptions = {}
+  ): Promise<TestCallResult<T>> {
+    return this.run(method, args, options, true);
+  }
+
+  /** Calls `method` and discards its state changes; throws if it reverts. */
+  async simulate<T = any>(
+    method: string,
+    args: any[] = [],
+    options: TestCallOptions = {}
+  ): Promise<TestCallResult<T>> {
+    return this.run(method, args, options, false);
+  }
+
+  private async run(
+    methodName: string,
+    args: any[],
+    { alkanes, caller, fuel }: TestCallOptions,
+    commit: boolean
+  ): Promise<TestCallResult> {
+    const method = this.getMethod(methodName);
+    const request = {
+      ...this.encoder.buildCellpack(this.alkaneId, method, args),
+      alkanes,
+      caller,
+      fuel,
+    };
+    const result = commit
+      ? await this.vm.execute(request)
+      : await this.vm.simulate(request);
+    if (result.status !== 0) {
+      throw new RevertError(
+        `${methodName} reverted: ${result.error ?? `status ${result.status}`}`,
+        result
+      );
+    }
+
+    const values = this.encoder.decodeResponse(
+      method.outputs,
+      result.response.data
+    );
+    return {
+      value: values.length > 1 ? values : values[0],
+      data: result.response.data,
+      transfers: result.response.alkanes.transfers,
+      gasUsed: result.gasUsed,
+      storage: result.storage,
+      logs: result.logs,
+    };
+  }
+
+  private getMethod(name: string): AlkanesMethod {
+    const method = this.abi.methods.find((m) => m.name === name);
+    if (!method) {
+      throw new Error(`Method ${name} not found in ABI`);
+    }
+    return method;
+  }
+}
+
+/**
+ * Awaits `call`, which must revert, and returns its RevertError. A string
+ * or RegExp `expected` is matched against the revert message.
+ */
+export async function expectRevert(
+  call: Promise<unknown>,
+  expected?: string | RegExp
+): Promise<RevertError> {
+  try {
+    await call;
+  } catch (error) {
+    if (!(error instanceof RevertError)) {
+      throw error;
+    }
+    const matches =
+      expected === undefined ||
+      (typeof expected === "string"
+        ? error.message.includes(expected)
+        : expected.test(error.message));
+    if (!matches) {
+      throw new Error(
+        `Expected a revert matching ${expected}, got: ${error.message}`
+      );
+    }
+    return error;
+  }
+  throw new Error("Expected the call to revert, but it succeeded");
+}
@@ -0,0 +1,174 @@
+// Generates a typed contract class from an ABI, so scripts get compile-time
+// checking of method names, argument types and decoded return values.
+
+import { AlkanesABI, AlkanesMethod, AlkanesParam, AlkanesType } from "./types";
+
+export interface TypegenOptions {
+  /** Module the generated file imports from (default: @jonatns/alkali) */
+  importPath?: string;
+}
+
+// Fixed-size arrays up to this length become tuple types
+const MAX_TUPLE_ARRAY_LENGTH = 16;
+
+const RESERVED_WORDS = new Set(
+  (
+    "break case catch class const continue debugger default delete do else " +
+    "enum export extends false finally for function if import in instanceof " +
+    "new null return super switch this throw true try typeof var void while " +
+    "with implements interface let package private protected public static " +
+    "yield await"
+  ).split(" ")
+);
+
+/**
+ * TypeScript type of a value of `type`, as the encoder accepts and decodes
+ * it: integers up to 32 bits are numbers, wider ones bigints.
+ */
+export function tsType(type: AlkanesType): string {
+  if (typeof type === "string") {
+    switch (type) {
+      case "u8":
+      case "u16":
+      case "u32":
+      case "i8":
+      case "i16":
+      case "i32":
+        return "number";
+      case "u64":
+      case "u128":
+      case "i64":
+      case "i128":
+        return "bigint";
+      case "String":
+        return "string";
+      case "bool":
+        return "boolean";
+      case "Vec<u8>":
+        return "Uint8Array";
+      default:
+        throw new Error(`Unsupported type: ${type}`);
+    }
+  }
+
+  if ("array" in type) {
+    const { type: itemType, length } = type.array;
+    return length <= MAX_TUPLE_ARRAY_LENGTH
+      ? `[${Array(length).fill(tsType(itemType)).join(", ")}]`
+      : `${arrayItem(itemType)}[]`;
+  }
+
+  if ("vec" in type) {
+    return `${arrayItem(type.vec.type)}[]`;
+  }
+
+  if ("tuple" in type) {
+    return `[${type.tuple.map(tsType).join(", ")}]`;
+  }
+
+  throw new Error(`Unsupported type: ${JSON.stringify(type)}`);
+}
+
+/** Source of a `.ts` module exporting a typed contract class for `abi`. */
+export function generateTypes(
+  abi: AlkanesABI,
+  { importPath = "@jonatns/alkali" }: TypegenOptions = {}
+): string {
+  const name = identifier(abi.name, "Contract");
+
+  const signature = (
+    method: AlkanesMethod,
+    params: string,
+    returns: string
+  ) =>
+    docComment(method) +
+    `  ${propertyName(method.name)}(${params}): Promise<${returns}>;`;
+  // Sent calls take the signing options after the method's arguments
+  const methods = abi.methods.map((method) =>
+    signature(
+      method,
+      [parameters(method.inputs), "options: CallOptions"]
+        .filter(Boolean)
+        .join(", "),
+      "CallResult"
+    )
+  );
+  const reads = abi.methods.map((method) =>
+    signature(method, parameters(method.inputs), returnType(method.outputs))
+  );
+
+  return `// Generated by \`alkali typegen\` from the ${abi.name} ABI. Do not edit.
+
+import {
+  AlkanesABI,
+  AlkanesContract,
+  CallOptions,
+  CallResult,
+  ContractConfig,
+} from "${importPath}";
+
+export const ${name}ABI: AlkanesABI = ${JSON.stringify(abi, null, 2)};
+
+export type ${name}Methods = {
+${methods.join("\n")}
+};
+
+export type ${name}Read = {
+${reads.join("\n")}
+};
+
+export class ${name} extends AlkanesContract {
+  declare readonly methods: ${name}Methods;
+  declare readonly read: ${name}Read;
+
+  constructor(config: Omit<ContractConfig, "abi">) {
+    super({ ...config, abi: ${name}ABI });
+  }
+}
+`;
+}
+
+function arrayItem(type: AlkanesType): string {
+  const ts = tsType(type);
+  return /^\w+$/.test(ts) || ts.startsWith("[") ? ts : `(${ts})`;
+}
+
+function parameters(inputs: AlkanesParam[]): string {
+  return inputs
+    .map(
+      (param, i) =>
+        `${identifier(param.name, `arg${i}`)}: ${tsType(param.type)}`
+    )
+    .join(", ");
+}
+
+function returnType(outputs: AlkanesParam[]): string {
+  if (outputs.length === 0) {
+    return "undefined";
+  }
+  if (outputs.length === 1) {
+    return tsType(outputs[0].type);
+  }
+  return `[${outputs.map((output) => tsType(output.type)).join(", ")}]`;
+}
+
+function docComment(method: AlkanesMethod): string {
+  const lines = [
+    ...(method.doc?.replace(/\*\//g, "*\\/").split("\n") ?? []),
+    `Opcode ${method.opcode}`,
+  ];
+  const body = lines.map((line) => `   * ${line}`.trimEnd()).join("\n");
+  return `  /**\n${body}\n   */\n`;
+}
+
+function propertyName(name: string): string {
+  return /^[A-Za-z_$][A-Za-z0-9_$]*$/.test(name) ? name : JSON.stringify(name);
+}
+
+function identifier(name: string, fallback: string): string {
+  const cleaned = name.replace(/[^A-Za-z0-9_$]/g, "_");
+  if (!cleaned || /^[0-9]/.test(cleaned)) {
+    return fallback;
+  }
+  return RESERVED_WORDS.has(cleaned) ? `${cleaned}_` : cleaned;
+}
@@ -1,3 +1,6 @@
+import { AlkaneId, AlkaneIdLike } from "./alkaneId";
+import { Provider } from "./provider";
+
 export type AlkanesPrimitive =
   | "u8"
   | "u16"
@@ -46,10 +49,15 @@ export interface AlkanesMethod {
   outputs: AlkanesParam[];
 }
 
+// Sub-key appended to a storage key: a fixed keyword, or a runtime value
+// of the given type (raw bytes for `.select(&bytes)`)
+export type StorageSegment = { keyword: string } | { select: AlkanesType };
+
 // Storage key definition
 export interface StorageKey {
   key: string;
   type: AlkanesType;
+  path?: StorageSegment[]; // For maps keyed by runtime values
 }
 
 // Contract ABI
@@ -63,7 +71,7 @@ export interface AlkanesABI {
 
 // Response types
 export interface AlkaneTransfer {
-  id: string;
+  id: AlkaneId;
   value: bigint;
 }
 
@@ -74,15 +82,46 @@ export interface CallResponse {
   };
 }
 
+// CallResponse with `data` decoded by the method's outputs: `value` is the
+// single output, an array of several, or undefined when none are declared
+export interface DecodedCallResponse<T = any> {
+  value: T;
+  data: Uint8Array;
+  transfers: AlkaneTransfer[];
+  gasUsed: bigint;
+}
+
+// Cellpack: the target alkane followed by the u128 inputs its execute() shifts
+export interface Cellpack {
+  target: AlkaneId;
+  inputs: bigint[];
+}
+
+// Outcome of a commit/reveal deployment
+export interface DeployResult {
+  alkaneId: AlkaneId;
+  commitTxid: string;
+  revealTxid: string;
+}
+
+// Outcome of a broadcast method call
+export interface CallResult {
+  txid: string;
+}
+
 // Contract instance configuration
 export interface ContractConfig {
   abi: AlkanesABI;
   bytecode: string;
-  address?: string;
+  address?: AlkaneIdLike;
+  provider?: Provider;
 }
 
 // Input encoding helpers
 export interface Encoder {
   encode(type: AlkanesType, value: any): Uint8Array;
   decode(type: AlkanesType, data: Uint8Array): any;
+  encodeWords(type: AlkanesType, value: any): bigint[];
+  decodeWords(type: AlkanesType, words: bigint[]): any;
+  decodeResponse(outputs: AlkanesParam[], data: Uint8Array): any[];
 }
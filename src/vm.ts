@@ -0,0 +1,832 @@
+// In-process Alkanes VM: instantiates contract WASM with Node's WebAssembly
+// runtime and serves the alkanes host imports from an in-memory state, so
+// contracts run without bitcoind or metashrew.
+
+import { AlkaneId, AlkaneIdBlock, AlkaneIdLike } from "./alkaneId";
+import {
+  ProtoruneOutpoint,
+  Provider,
+  SimulateRequest,
+  SimulateResult,
+  TraceEvent,
+} from "./provider";
+import { AlkaneTransfer, CallResponse } from "./types";
+import {
+  FUEL_GLOBAL_EXPORT,
+  START_EXPORT,
+  meterFuel,
+  toWasm,
+} from "./wasm";
+
+// Fuel charged for host operations, and for each function entry and loop
+// iteration of the guest, which is instrumented to count it (see
+// meterFuel). Gas figures still differ from the indexer's per-instruction
+// metering.
+export const FUEL = {
+  perBlock: 1n,
+  perRequestByte: 1n,
+  perLoadByte: 2n,
+  perStoreByte: 40n,
+  extcall: 500n,
+  query: 10n,
+};
+
+const DEFAULT_FUEL = 100_000_000n;
+const U128_BYTES = 16;
+const U128_MASK = (1n << 128n) - 1n;
+const U64_MASK = (1n << 64n) - 1n;
+
+// Revert data starts with the Solidity Error(string) selector
+const REVERT_SELECTOR = [0x08, 0xc3, 0x79, 0xa0];
+
+// The transaction itself, as the caller of top-level executions
+const TRANSACTION_CALLER = new AlkaneId(0, 0);
+
+export interface ExecuteRequest extends Omit<SimulateRequest, "target"> {
+  target: AlkaneIdLike;
+  caller?: AlkaneIdLike;
+  /** Fuel available to the execution (default: 100M) */
+  fuel?: bigint;
+}
+
+export interface StorageDiff {
+  alkane: AlkaneId;
+  key: Uint8Array;
+  /** Empty when the key was unset */
+  before: Uint8Array;
+  after: Uint8Array;
+}
+
+export interface ExecutionResult extends SimulateResult {
+  /** Storage written by the execution, across every alkane it called */
+  storage: StorageDiff[];
+  /** Messages passed to `__log`, in order */
+  logs: string[];
+}
+
+export interface AlkanesVMOptions {
+  height?: bigint;
+  /** Default fuel of each execution */
+  fuel?: bigint;
+}
+
+export class AlkanesVM implements Provider {
+  height: bigint;
+  private fuel: bigint;
+  private state = new VmState();
+
+  constructor(options: AlkanesVMOptions = {}) {
+    this.height = options.height ?? 0n;
+    this.fuel = options.fuel ?? DEFAULT_FUEL;
+  }
+
+  /**
+   * Deploys `bytecode` (raw or gzipped WASM) as the next `[2, n]`, or as
+   * `[4, n]` for a reserved number, running it with `inputs` (by default
+   * the initialize opcode). Throws if that execution reverts.
+   */
+  async deploy(
+    bytecode: Uint8Array,
+    inputs: bigint[] = [0n],
+    { reservedNumber }: { reservedNumber?: bigint } = {}
+  ): Promise<{ alkaneId: AlkaneId; result: ExecutionResult }> {
+    const wasm = toWasm(bytecode);
+    const compiled = new WebAssembly.Module(meterFuel(wasm, FUEL.perBlock));
+    const state = this.state.clone();
+
+    const alkaneId =
+      reservedNumber === undefined
+        ? new AlkaneId(AlkaneIdBlock.Sequence, state.sequence++)
+        : new AlkaneId(AlkaneIdBlock.ReservedDeployed, reservedNumber);
+    if (state.contracts.has(alkaneId.toString())) {
+      throw new Error(`${alkaneId} is already deployed`);
+    }
+    state.contracts.set(alkaneId.toString(), { wasm, module: compiled });
+
+    const result = this.run(state, { target: alkaneId, inputs });
+    if (result.status !== 0) {
+      throw new Error(`Deploying ${alkaneId} reverted: ${result.error}`);
+    }
+    this.state = state;
+    return { alkaneId, result };
+  }
+
+  /** Executes a cellpack, committing its state changes if it succeeds. */
+  async execute(request: ExecuteRequest): Promise<ExecutionResult> {
+    const state = this.state.clone();
+    const result = this.run(state, request);
+    if (result.status === 0) {
+      this.state = state;
+    }
+    return result;
+  }
+
+  /** Executes a cellpack and discards its state changes. */
+  async simulate(request: ExecuteRequest): Promise<ExecutionResult> {
+    return this.run(this.state.clone(), request);
+  }
+
+  async getBytecode(id: AlkaneId): Promise<Uint8Array> {
+    return this.state.contracts.get(id.toString())?.wasm ?? new Uint8Array(0);
+  }
+
+  async getStorageAt(id: AlkaneId, key: Uint8Array): Promise<Uint8Array> {
+    return this.state.load(id, key);
+  }
+
+  async getStorageKeys(
+    id: AlkaneId,
+    prefix: Uint8Array
+  ): Promise<Uint8Array[]> {
+    return this.state.keys(id, prefix);
+  }
+
+  /** Overwrites a storage slot, e.g. to set up a test. */
+  setStorage(id: AlkaneIdLike, key: Uint8Array, value: Uint8Array): void {
+    this.state.store(AlkaneId.from(id), key, value);
+  }
+
+  /** Balance of `token` held by the alkane `holder`. */
+  balanceOf(holder: AlkaneIdLike, token: AlkaneIdLike): bigint {
+    return this.state.balance(AlkaneId.from(holder), AlkaneId.from(token));
+  }
+
+  async trace(): Promise<TraceEvent[]> {
+    return [];
+  }
+
+  async getProtorunesByAddress(): Promise<ProtoruneOutpoint[]> {
+    return [];
+  }
+
+  async getIndexerHeight(): Promise<number> {
+    return Number(this.height);
+  }
+
+  async sendRawTransaction(): Promise<string> {
+    throw new Error("The local VM does not accept transactions");
+  }
+
+  async getBlockCount(): Promise<number> {
+    return Number(this.height);
+  }
+
+  private run(state: VmState, request: ExecuteRequest): ExecutionResult {
+    const before = state.clone();
+    const execution = new Execution(
+      state,
+      request.fuel ?? this.fuel,
+      this.height,
+      request.transaction ?? new Uint8Array(0),
+      request.block ?? new Uint8Array(0)
+    );
+
+    try {
+      const response = execution.call(
+        AlkaneId.from(request.caller ?? TRANSACTION_CALLER),
+        AlkaneId.from(request.target),
+        request.inputs,
+        request.alkanes ?? [],
+        BigInt(request.vout ?? 0),
+        request.caller === undefined
+      );
+      return {
+        status: 0,
+        gasUsed: execution.fuelUsed,
+        response,
+        storage: state.diff(before),
+        logs: execution.logs,
+      };
+    } catch (error) {
+      return {
+        status: 1,
+        gasUsed: execution.fuelUsed,
+        response: { data: new Uint8Array(0), alkanes: { transfers: [] } },
+        storage: [],
+        logs: execution.logs,
+        error: revertMessage(error, execution.logs),
+      };
+    }
+  }
+}
+
+interface DeployedContract {
+  wasm: Uint8Array;
+  module: WebAssembly.Module;
+}
+
+// Failures that revert the current call rather than crash the VM
+class Revert extends Error {}
+
+// Storage, balances and code of every alkane; cloned per execution frame so
+// a revert can drop the frame's changes
+class VmState {
+  contracts = new Map<string, DeployedContract>();
+  storage = new Map<string, Map<string, Uint8Array>>();
+  balances = new Map<string, Map<string, bigint>>();
+  sequence = 0n;
+
+  clone(): VmState {
+    const copy = new VmState();
+    copy.contracts = new Map(this.contracts);
+    copy.storage = new Map(
+      [...this.storage].map(([id, slots]) => [id, new Map(slots)])
+    );
+    copy.balances = new Map(
+      [...this.balances].map(([id, tokens]) => [id, new Map(tokens)])
+    );
+    copy.sequence = this.sequence;
+    return copy;
+  }
+
+  load(id: AlkaneId, key: Uint8Array): Uint8Array {
+    return (
+      this.storage.get(id.toString())?.get(toHex(key)) ?? new Uint8Array(0)
+    );
+  }
+
+  // Set keys under `prefix`; hex order is byte order
+  keys(id: AlkaneId, prefix: Uint8Array): Uint8Array[] {
+    const start = toHex(prefix);
+    return [...(this.storage.get(id.toString()) ?? [])]
+      .filter(([key, value]) => key.startsWith(start) && value.length > 0)
+      .map(([key]) => key)
+      .sort()
+      .map(fromHex);
+  }
+
+  store(id: AlkaneId, key: Uint8Array, value: Uint8Array) {
+    const slots = this.storage.get(id.toString()) ?? new Map();
+    slots.set(toHex(key), value);
+    this.storage.set(id.toString(), slots);
+  }
+
+  balance(holder: AlkaneId, token: AlkaneId): bigint {
+    return this.balances.get(holder.toString())?.get(token.toString()) ?? 0n;
+  }
+
+  credit(holder: AlkaneId, token: AlkaneId, value: bigint) {
+    const tokens = this.balances.get(holder.toString()) ?? new Map();
+    tokens.set(token.toString(), this.balance(holder, token) + value);
+    this.balances.set(holder.toString(), tokens);
+  }
+
+  /** Moves `transfers` out of `from`; alkanes mint their own token. */
+  debit(from: AlkaneId, transfers: AlkaneTransfer[]) {
+    for (const { id, value } of transfers) {
+      if (id.equals(from)) {
+        continue;
+      }
+      const balance = this.balance(from, id);
+      if (balance < value) {
+        throw new Revert(
+          `${from} holds ${balance} of ${id}, cannot send ${value}`
+        );
+      }
+      this.credit(from, id, -value);
+    }
+  }
+
+  diff(before: VmState): StorageDiff[] {
+    const diffs: StorageDiff[] = [];
+    for (const [id, slots] of this.storage) {
+      for (const [key, after] of slots) {
+        const previous = before.storage.get(id)?.get(key) ?? new Uint8Array(0);
+        if (toHex(previous) !== toHex(after)) {
+          diffs.push({
+            alkane: AlkaneId.parse(id),
+            key: fromHex(key),
+            before: previous,
+            after,
+          });
+        }
+      }
+    }
+    return diffs;
+  }
+}
+
+// One top-level execution: the fuel, logs and state shared by its frames
+class Execution {
+  fuelUsed = 0n;
+  logs: string[] = [];
+  readonly transaction: Uint8Array;
+  readonly block: Uint8Array;
+  private state: VmState;
+  private fuelLimit: bigint;
+  private height: bigint;
+
+  constructor(
+    state: VmState,
+    fuelLimit: bigint,
+    height: bigint,
+    transaction: Uint8Array,
+    block: Uint8Array
+  ) {
+    this.state = state;
+    this.fuelLimit = fuelLimit;
+    this.height = height;
+    this.transaction = transaction;
+    this.block = block;
+  }
+
+  charge(fuel: bigint) {
+    this.fuelUsed += fuel;
+    if (this.fuelUsed > this.fuelLimit) {
+      throw new Revert(`out of fuel (limit ${this.fuelLimit})`);
+    }
+  }
+
+  fuelLeft(): bigint {
+    return this.fuelLimit - this.fuelUsed;
+  }
+
+  /** Charges what a guest counted its fuel global down to `left` by. */
+  meter(left: bigint) {
+    this.charge(this.fuelLeft() - left);
+  }
+
+  /**
+   * Runs `target` with `inputs` on behalf of `caller`, moving `alkanes` to
+   * it and its returned alkanes back. `mintIncoming` credits the incoming
+   * alkanes without debiting the caller, for transaction-level calls.
+   */
+  call(
+    caller: AlkaneId,
+    target: AlkaneId,
+    inputs: bigint[],
+    alkanes: AlkaneTransfer[],
+    vout: bigint,
+    mintIncoming = false,
+    { storageOf = target, commit = true } = {}
+  ): CallResponse {
+    const contract = this.state.contracts.get(target.toString());
+    if (!contract) {
+      throw new Revert(`No contract deployed at ${target}`);
+    }
+
+    if (!mintIncoming) {
+      this.state.debit(caller, alkanes);
+    }
+    for (const { id, value } of alkanes) {
+      this.state.credit(target, id, value);
+    }
+
+    const context = concat(
+      encodeId(target),
+      encodeId(caller),
+      u128(vout),
+      encodeParcel(alkanes),
+      ...inputs.map(u128)
+    );
+    const frame = new Frame(this, contract.module, storageOf, context);
+    const { response, storage } = frame.run();
+
+    if (commit) {
+      for (const [key, value] of storage) {
+        this.charge(FUEL.perStoreByte * BigInt(key.length + value.length));
+        this.state.store(storageOf, key, value);
+      }
+    }
+    this.state.debit(target, response.alkanes.transfers);
+    for (const { id, value } of response.alkanes.transfers) {
+      this.state.credit(caller, id, value);
+    }
+    return response;
+  }
+
+  /**
+   * `__call`-style call from a running contract: applies its storage
+   * checkpoint, resolves factory targets, and runs the callee against a
+   * copy of the state that only sticks if the callee succeeds.
+   */
+  extcall(
+    kind: "call" | "staticcall" | "delegatecall",
+    frame: Frame,
+    cellpack: bigint[],
+    alkanes: AlkaneTransfer[],
+    checkpoint: Array<[Uint8Array, Uint8Array]>
+  ): CallResponse {
+    this.charge(FUEL.extcall);
+    for (const [key, value] of checkpoint) {
+      this.state.store(frame.storageOf, key, value);
+    }
+
+    // Cloning a factory belongs to the call, so a revert undoes it too
+    const outer = this.state;
+    this.state = outer.clone();
+    try {
+      let target = AlkaneId.fromWords(cellpack);
+      if (target.isFactory()) {
+        target = this.clone(target);
+      }
+      const response = this.call(
+        frame.myself,
+        target,
+        cellpack.slice(2),
+        alkanes,
+        0n,
+        false,
+        {
+          storageOf: kind === "delegatecall" ? frame.storageOf : target,
+          commit: kind !== "staticcall",
+        }
+      );
+      if (kind !== "staticcall") {
+        Object.assign(outer, this.state);
+      }
+      return response;
+    } finally {
+      this.state = outer;
+    }
+  }
+
+  private clone(factory: AlkaneId): AlkaneId {
+    const template = new AlkaneId(
+      factory.block === BigInt(AlkaneIdBlock.FactoryFromSequence)
+        ? AlkaneIdBlock.Sequence
+        : AlkaneIdBlock.ReservedDeployed,
+      factory.tx
+    );
+    const contract = this.state.contracts.get(template.toString());
+    if (!contract) {
+      throw new Revert(`Cannot clone ${template}: nothing is deployed there`);
+    }
+    const id = new AlkaneId(AlkaneIdBlock.Sequence, this.state.sequence++);
+    this.state.contracts.set(id.toString(), contract);
+    return id;
+  }
+
+  query(what: "height" | "sequence" | "fuel"): bigint {
+    this.charge(FUEL.query);
+    switch (what) {
+      case "height":
+        return this.height;
+      case "sequence":
+        return this.state.sequence;
+      case "fuel":
+        return this.fuelLeft();
+    }
+  }
+
+  balance(holder: AlkaneId, token: AlkaneId): bigint {
+    this.charge(FUEL.query);
+    return this.state.balance(holder, token);
+  }
+
+  load(id: AlkaneId, key: Uint8Array): Uint8Array {
+    return this.state.load(id, key);
+  }
+}
+
+// A running contract instance and the host imports it sees
+class Frame {
+  /** Alkane whose storage the contract reads and writes */
+  readonly storageOf: AlkaneId;
+  private execution: Execution;
+  private module: WebAssembly.Module;
+  private context: Uint8Array;
+  private memory?: WebAssembly.Memory;
+  private fuel?: WebAssembly.Global;
+  private returnData = new Uint8Array(0);
+
+  constructor(
+    execution: Execution,
+    module: WebAssembly.Module,
+    storageOf: AlkaneId,
+    context: Uint8Array
+  ) {
+    this.execution = execution;
+    this.module = module;
+    this.storageOf = storageOf;
+    this.context = context;
+  }
+
+  get myself(): AlkaneId {
+    return decodeId(this.context, 0);
+  }
+
+  run(): {
+    response: CallResponse;
+    storage: Array<[Uint8Array, Uint8Array]>;
+  } {
+    const instance = new WebAssembly.Instance(this.module, {
+      env: this.imports(),
+    });
+    this.memory = instance.exports.memory as WebAssembly.Memory;
+    this.fuel = instance.exports[FUEL_GLOBAL_EXPORT] as WebAssembly.Global;
+    const start = instance.exports[START_EXPORT] as (() => void) | undefined;
+    const execute = instance.exports.__execute as () => number;
+    if (!this.memory || typeof execute !== "function") {
+      throw new Revert("Contract does not export memory and __execute");
+    }
+
+    const pointer = this.guest(() => {
+      start?.();
+      return execute();
+    });
+    return parseExtendedResponse(this.readBuffer(pointer));
+  }
+
+  // While the guest runs, its fuel global holds what the execution has
+  // left; running out traps, which is reported as running out of fuel
+  private guest<T>(run: () => T): T {
+    this.fuel!.value = this.execution.fuelLeft();
+    try {
+      return run();
+    } finally {
+      this.execution.meter(this.fuel!.value);
+    }
+  }
+
+  private host<T>(run: () => T): T {
+    this.execution.meter(this.fuel!.value);
+    try {
+      return run();
+    } finally {
+      this.fuel!.value = this.execution.fuelLeft();
+    }
+  }
+
+  private imports(): Record<string, Function> {
+    return Object.fromEntries(
+      Object.entries(this.hostFunctions()).map(([name, hostFunction]) => [
+        name,
+        (...args: number[]) => this.host(() => hostFunction(...args)),
+      ])
+    );
+  }
+
+  private hostFunctions(): Record<string, (...args: number[]) => unknown> {
+    const execution = this.execution;
+    const loaded = (bytes: Uint8Array) => {
+      execution.charge(FUEL.perLoadByte * BigInt(bytes.length));
+      return bytes;
+    };
+    const extcall =
+      (kind: "call" | "staticcall" | "delegatecall") =>
+      (cellpack: number, alkanes: number, checkpoint: number): number => {
+        try {
+          const response = execution.extcall(
+            kind,
+            this,
+            decodeWords(this.readBuffer(cellpack)),
+            parseParcel(new Reader(this.readBuffer(alkanes))),
+            parseStorageMap(new Reader(this.readBuffer(checkpoint)))
+          );
+          this.returnData = concat(
+            encodeParcel(response.alkanes.transfers),
+            response.data
+          );
+          return this.returnData.length;
+        } catch (error) {
+          if (!(error instanceof Revert) && !isTrap(error)) {
+            throw error;
+          }
+          this.returnData = concat(
+            new Uint8Array(REVERT_SELECTOR),
+            utf8(revertMessage(error, []))
+          );
+          return -1;
+        }
+      };
+
+    return {
+      abort: () => {
+        throw new Revert("abort");
+      },
+      __log: (pointer: number) => {
+        execution.logs.push(new TextDecoder().decode(this.readBuffer(pointer)));
+      },
+      __request_context: () => {
+        execution.charge(FUEL.perRequestByte * BigInt(this.context.length));
+        return this.context.length;
+      },
+      __load_context: (output: number) => {
+        this.write(output, loaded(this.context));
+        return 0;
+      },
+      __request_storage: (key: number) => {
+        const value = execution.load(this.storageOf, this.readBuffer(key));
+        execution.charge(FUEL.perRequestByte * BigInt(value.length));
+        return value.length;
+      },
+      __load_storage: (key: number, output: number) => {
+        const value = execution.load(this.storageOf, this.readBuffer(key));
+        this.write(output, loaded(value));
+        return value.length;
+      },
+      __height: (output: number) => {
+        this.write(output, u64(execution.query("height")));
+      },
+      __sequence: (output: number) => {
+        this.write(output, u128(execution.query("sequence")));
+      },
+      __fuel: (output: number) => {
+        this.write(output, u64(execution.query("fuel")));
+      },
+      __balance: (who: number, what: number, output: number) => {
+        const holder = decodeId(this.readBuffer(who), 0);
+        const token = decodeId(this.readBuffer(what), 0);
+        this.write(output, u128(execution.balance(holder, token)));
+      },
+      __returndatacopy: (output: number) => {
+        this.write(output, loaded(this.returnData));
+      },
+      __request_transaction: () => execution.transaction.length,
+      __load_transaction: (output: number) => {
+        this.write(output, loaded(execution.transaction));
+      },
+      __request_block: () => execution.block.length,
+      __load_block: (output: number) => {
+        this.write(output, loaded(execution.block));
+      },
+      __call: extcall("call"),
+      __staticcall: extcall("staticcall"),
+      __delegatecall: extcall("delegatecall"),
+    };
+  }
+
+  // Guest buffers use the ArrayBuffer layout: a u32 length before the data
+  private readBuffer(pointer: number): Uint8Array {
+    const buffer = this.memory!.buffer;
+    if (pointer < 4 || pointer > buffer.byteLength) {
+      throw new Revert(`Invalid buffer pointer ${pointer}`);
+    }
+    const length = new DataView(buffer).getUint32(pointer - 4, true);
+    if (pointer + length > buffer.byteLength) {
+      throw new Revert(`Buffer at ${pointer} overruns memory`);
+    }
+    return new Uint8Array(buffer, pointer, length).slice();
+  }
+
+  private write(pointer: number, bytes: Uint8Array) {
+    const buffer = this.memory!.buffer;
+    if (pointer + bytes.length > buffer.byteLength) {
+      throw new Revert(`Write at ${pointer} overruns memory`);
+    }
+    new Uint8Array(buffer, pointer, bytes.length).set(bytes);
+  }
+}
+
+// ExtendedCallResponse: transfer parcel, storage map, then the data
+function parseExtendedResponse(bytes: Uint8Array): {
+  response: CallResponse;
+  storage: Array<[Uint8Array, Uint8Array]>;
+} {
+  const reader = new Reader(bytes);
+  const transfers = parseParcel(reader);
+  const storage = parseStorageMap(reader);
+  return {
+    response: { data: reader.rest(), alkanes: { transfers } },
+    storage,
+  };
+}
+
+function parseParcel(reader: Reader): AlkaneTransfer[] {
+  if (reader.remaining === 0) {
+    return [];
+  }
+  const count = reader.u128();
+  const transfers: AlkaneTransfer[] = [];
+  for (let i = 0n; i < count; i++) {
+    const id = new AlkaneId(reader.u128(), reader.u128());
+    transfers.push({ id, value: reader.u128() });
+  }
+  return transfers;
+}
+
+function parseStorageMap(reader: Reader): Array<[Uint8Array, Uint8Array]> {
+  if (reader.remaining === 0) {
+    return [];
+  }
+  const count = reader.u32();
+  const entries: Array<[Uint8Array, Uint8Array]> = [];
+  for (let i = 0; i < count; i++) {
+    const key = reader.bytes(reader.u32());
+    entries.push([key, reader.bytes(reader.u32())]);
+  }
+  return entries;
+}
+
+function encodeParcel(transfers: AlkaneTransfer[]): Uint8Array {
+  return concat(
+    u128(BigInt(transfers.length)),
+    ...transfers.map(({ id, value }) => concat(encodeId(id), u128(value)))
+  );
+}
+
+function encodeId(id: AlkaneId): Uint8Array {
+  return concat(u128(id.block), u128(id.tx));
+}
+
+function decodeId(bytes: Uint8Array, offset: number): AlkaneId {
+  const reader = new Reader(bytes.subarray(offset));
+  return new AlkaneId(reader.u128(), reader.u128());
+}
+
+function decodeWords(bytes: Uint8Array): bigint[] {
+  const reader = new Reader(bytes);
+  const words: bigint[] = [];
+  while (reader.remaining > 0) {
+    words.push(reader.u128());
+  }
+  return words;
+}
+
+function u128(value: bigint): Uint8Array {
+  return littleEndian(value & U128_MASK, U128_BYTES);
+}
+
+function u64(value: bigint): Uint8Array {
+  return littleEndian(value & U64_MASK, 8);
+}
+
+function littleEndian(value: bigint, size: number): Uint8Array {
+  const bytes = new Uint8Array(size);
+  for (let i = 0; i < size; i++) {
+    bytes[i] = Number(value & 0xffn);
+    value >>= 8n;
+  }
+  return bytes;
+}
+
+function isTrap(error: unknown): boolean {
+  return error instanceof WebAssembly.RuntimeError;
+}
+
+// The last log usually carries the panic message of a trapping contract
+function revertMessage(error: unknown, logs: string[]): string {
+  const message = error instanceof Error ? error.message : String(error);
+  if (isTrap(error) && logs.length > 0) {
+    return `${logs[logs.length - 1]} (${message})`;
+  }
+  return message;
+}
+
+// Cursor over host-side byte buffers
+class Reader {
+  private data: Uint8Array;
+  private offset = 0;
+
+  constructor(data: Uint8Array) {
+    this.data = data;
+  }
+
+  get remaining(): number {
+    return this.data.length - this.offset;
+  }
+
+  bytes(length: number): Uint8Array {
+    if (length > this.remaining) {
+      throw new Revert(
+        `Malformed buffer: need ${length} byte(s) at offset ${this.offset}, have ${this.remaining}`
+      );
+    }
+    const bytes = this.data.slice(this.offset, this.offset + length);
+    this.offset += length;
+    return bytes;
+  }
+
+  u32(): number {
+    const bytes = this.bytes(4);
+    return new DataView(bytes.buffer).getUint32(0, true);
+  }
+
+  u128(): bigint {
+    const bytes = this.bytes(U128_BYTES);
+    let value = 0n;
+    for (let i = U128_BYTES - 1; i >= 0; i--) {
+      value = (value << 8n) | BigInt(bytes[i]);
+    }
+    return value;
+  }
+
+  rest(): Uint8Array {
+    return this.bytes(this.remaining);
+  }
+}
+
+function utf8(text: string): Uint8Array {
+  return new TextEncoder().encode(text);
+}
+
+function toHex(bytes: Uint8Array): string {
+  return Buffer.from(bytes).toString("hex");
+}
+
+function fromHex(hex: string): Uint8Array {
+  return new Uint8Array(Buffer.from(hex, "hex"));
+}
+
+function concat(...parts: Uint8Array[]): Uint8Array {
+  const result = new Uint8Array(
+    parts.reduce((sum, part) => sum + part.length, 0)
+  );
+  let offset = 0;
+  for (const part of parts) {
+    result.set(part, offset);
+    offset += part.length;
+  }
+  return result;
+}
@@ -0,0 +1,460 @@
+// WASM custom sections carrying the contract ABI, so deployed bytecode is
+// self-describing, and the fuel metering the VM runs contracts under.
+
+import { gunzipSync } from "zlib";
+import { AlkanesABI } from "./types";
+
+export const ABI_SECTION_NAME = "alkali.abi";
+
+const WASM_MAGIC = [0x00, 0x61, 0x73, 0x6d];
+const WASM_HEADER_BYTES = 8;
+const CUSTOM_SECTION_ID = 0;
+const IMPORT_SECTION_ID = 2;
+const GLOBAL_SECTION_ID = 6;
+const EXPORT_SECTION_ID = 7;
+const START_SECTION_ID = 8;
+const CODE_SECTION_ID = 10;
+// Standard sections must appear in this order (the tag section, 13, sits
+// before globals and the data count section, 12, before code)
+const SECTION_ORDER = [1, 2, 3, 4, 5, 13, 6, 7, 8, 9, 12, 10, 11];
+
+/** Mutable i64 global a metered module counts its remaining fuel down in */
+export const FUEL_GLOBAL_EXPORT = "__alkali_fuel";
+/** A metered module's start function, which instantiating no longer runs */
+export const START_EXPORT = "__alkali_start";
+
+export interface WasmSection {
+  id: number;
+  /** Custom section name; undefined for standard sections */
+  name?: string;
+  /** Section contents, after the name for custom sections */
+  payload: Uint8Array;
+  start: number;
+  end: number;
+}
+
+/** Accepts raw or gzip-compressed (as deployed in envelopes) bytecode. */
+export function toWasm(bytecode: Uint8Array): Uint8Array {
+  if (bytecode[0] === 0x1f && bytecode[1] === 0x8b) {
+    return new Uint8Array(gunzipSync(bytecode));
+  }
+  return bytecode;
+}
+
+export function readSections(wasm: Uint8Array): WasmSection[] {
+  if (
+    wasm.length < WASM_HEADER_BYTES ||
+    WASM_MAGIC.some((byte, i) => wasm[i] !== byte)
+  ) {
+    throw new Error("Not a WASM module");
+  }
+
+  const sections: WasmSection[] = [];
+  let offset = WASM_HEADER_BYTES;
+  while (offset < wasm.length) {
+    const start = offset;
+    const id = wasm[offset++];
+    const size = readU32(wasm, offset);
+    offset = size.next;
+    const end = offset + size.value;
+    if (end > wasm.length) {
+      throw new Error(`WASM section at offset ${start} overruns the module`);
+    }
+
+    if (id === CUSTOM_SECTION_ID) {
+      const nameLength = readU32(wasm, offset);
+      const nameEnd = nameLength.next + nameLength.value;
+      const name = new TextDecoder().decode(
+        wasm.subarray(nameLength.next, nameEnd)
+      );
+      sections.push({
+        id,
+        name,
+        payload: wasm.subarray(nameEnd, end),
+        start,
+        end,
+      });
+    } else {
+      sections.push({ id, payload: wasm.subarray(offset, end), start, end });
+    }
+    offset = end;
+  }
+  return sections;
+}
+
+/** Returns `wasm` with `abi` in its `alkali.abi` section, replacing any. */
+export function embedAbi(wasm: Uint8Array, abi: AlkanesABI): Uint8Array {
+  const kept = readSections(wasm)
+    .filter((section) => section.name !== ABI_SECTION_NAME)
+    .map((section) => wasm.subarray(section.start, section.end));
+
+  const name = new TextEncoder().encode(ABI_SECTION_NAME);
+  const payload = new TextEncoder().encode(JSON.stringify(abi));
+  const contents = [...encodeU32(name.length), ...name, ...payload];
+  const section = new Uint8Array([
+    CUSTOM_SECTION_ID,
+    ...encodeU32(contents.length),
+    ...contents,
+  ]);
+
+  return concatBytes([wasm.subarray(0, WASM_HEADER_BYTES), ...kept, section]);
+}
+
+/** The ABI embedded in `bytecode`, or undefined if it has none. */
+export function extractAbi(bytecode: Uint8Array): AlkanesABI | undefined {
+  const section = readSections(toWasm(bytecode)).find(
+    (section) => section.name === ABI_SECTION_NAME
+  );
+  if (!section) {
+    return undefined;
+  }
+  return JSON.parse(new TextDecoder().decode(section.payload));
+}
+
+/**
+ * Instruments `wasm` to count fuel down by `cost` at each function entry
+ * and loop iteration, in a global exported as `__alkali_fuel`, trapping
+ * once it drops below zero. The start function is exported as
+ * `__alkali_start` instead, so that it is metered too.
+ */
+export function meterFuel(wasm: Uint8Array, cost: bigint): Uint8Array {
+  const sections = readSections(wasm);
+  const payload = (id: number) =>
+    sections.find((section) => section.id === id)?.payload;
+
+  // The new global comes after the imported and the defined ones
+  const globals = payload(GLOBAL_SECTION_ID);
+  const fuelGlobal = encodeU32(
+    importedGlobals(payload(IMPORT_SECTION_ID)) +
+      (globals ? readU32(globals, 0).value : 0)
+  );
+  // fuel -= cost; if (fuel < 0) unreachable
+  const charge = [
+    ...[0x23, ...fuelGlobal, 0x42, ...encodeS64(cost), 0x7d],
+    ...[0x24, ...fuelGlobal, 0x23, ...fuelGlobal, 0x42, 0x00, 0x53],
+    ...[0x04, 0x40, 0x00, 0x0b],
+  ];
+
+  const exports = [...encodeName(FUEL_GLOBAL_EXPORT), 0x03, ...fuelGlobal];
+  const start = payload(START_SECTION_ID);
+  if (start) {
+    exports.push(
+      ...encodeName(START_EXPORT),
+      0x00,
+      ...encodeU32(readU32(start, 0).value)
+    );
+  }
+
+  // (mut i64) initialized with i64.const 0
+  const global = [0x7e, 0x01, 0x42, 0x00, 0x0b];
+  const replaced = new Map<number, number[]>([
+    [GLOBAL_SECTION_ID, appendEntries(globals, 1, global)],
+    [
+      EXPORT_SECTION_ID,
+      appendEntries(payload(EXPORT_SECTION_ID), start ? 2 : 1, exports),
+    ],
+  ]);
+  const code = payload(CODE_SECTION_ID);
+  if (code) {
+    replaced.set(CODE_SECTION_ID, meterCode(code, charge));
+  }
+
+  const parts: Uint8Array[] = [wasm.subarray(0, WASM_HEADER_BYTES)];
+  const emit = (id: number, contents: number[]) =>
+    parts.push(
+      new Uint8Array([id, ...encodeU32(contents.length), ...contents])
+    );
+  const order = (id: number) => SECTION_ORDER.indexOf(id);
+  for (const section of sections) {
+    // Sections this adds go before the first one that follows them
+    for (const [id, contents] of replaced) {
+      if (
+        section.id !== CUSTOM_SECTION_ID &&
+        order(section.id) > order(id)
+      ) {
+        emit(id, contents);
+        replaced.delete(id);
+      }
+    }
+    if (replaced.has(section.id)) {
+      emit(section.id, replaced.get(section.id)!);
+      replaced.delete(section.id);
+    } else if (section.id !== START_SECTION_ID) {
+      parts.push(wasm.subarray(section.start, section.end));
+    }
+  }
+  for (const [id, contents] of replaced) {
+    emit(id, contents);
+  }
+  return concatBytes(parts);
+}
+
+// Number of globals among the entries of an import section
+function importedGlobals(imports?: Uint8Array): number {
+  if (!imports) {
+    return 0;
+  }
+  const count = readU32(imports, 0);
+  let offset = count.next;
+  let globals = 0;
+  const skipName = () => {
+    const length = readU32(imports, offset);
+    offset = length.next + length.value;
+  };
+  for (let i = 0; i < count.value; i++) {
+    skipName();
+    skipName();
+    const kind = imports[offset++];
+    switch (kind) {
+      case 0x00: // function: type index
+        offset = skipLeb(imports, offset);
+        break;
+      case 0x01: // table: reference type, limits
+        offset = skipLimits(imports, offset + 1);
+        break;
+      case 0x02: // memory: limits
+        offset = skipLimits(imports, offset);
+        break;
+      case 0x03: // global: value type, mutability
+        offset += 2;
+        globals++;
+        break;
+      case 0x04: // tag: attribute, type index
+        offset = skipLeb(imports, offset + 1);
+        break;
+      default:
+        throw new Error(`Unknown import kind ${kind}`);
+    }
+  }
+  return globals;
+}
+
+// A vector section's payload with `added` more entries encoded in `entries`
+function appendEntries(
+  payload: Uint8Array | undefined,
+  added: number,
+  entries: number[]
+): number[] {
+  const count = payload ? readU32(payload, 0) : { value: 0, next: 0 };
+  return [
+    ...encodeU32(count.value + added),
+    ...(payload?.subarray(count.next) ?? []),
+    ...entries,
+  ];
+}
+
+// The code section with `charge` at the start of each body and loop
+function meterCode(code: Uint8Array, charge: number[]): number[] {
+  const count = readU32(code, 0);
+  const result = encodeU32(count.value);
+  let offset = count.next;
+  for (let i = 0; i < count.value; i++) {
+    const size = readU32(code, offset);
+    const end = size.next + size.value;
+    const body = code.subarray(size.next, end);
+
+    // Local declarations: a count of (count, value type) runs
+    const runs = readU32(body, 0);
+    let position = runs.next;
+    for (let run = 0; run < runs.value; run++) {
+      position = skipLeb(body, position) + 1;
+    }
+    const metered: number[] = [...body.subarray(0, position), ...charge];
+    while (position < body.length) {
+      const next = instructionEnd(body, position);
+      metered.push(...body.subarray(position, next));
+      if (body[position] === 0x03) {
+        metered.push(...charge);
+      }
+      position = next;
+    }
+
+    result.push(...encodeU32(metered.length), ...metered);
+    offset = end;
+  }
+  return result;
+}
+
+// Offset after the instruction at `offset` and its immediates
+function instructionEnd(code: Uint8Array, offset: number): number {
+  const opcode = code[offset++];
+  const leb = (count = 1) => {
+    for (let i = 0; i < count; i++) {
+      offset = skipLeb(code, offset);
+    }
+    return offset;
+  };
+  const memarg = () => {
+    const align = readU32(code, offset);
+    offset = align.next;
+    // Bit 6 of the alignment flags a memory index
+    return leb(align.value & 0x40 ? 2 : 1);
+  };
+
+  if (
+    opcode <= 0x01 ||
+    opcode === 0x05 ||
+    opcode === 0x0b ||
+    opcode === 0x0f ||
+    opcode === 0x1a ||
+    opcode === 0x1b ||
+    (opcode >= 0x45 && opcode <= 0xc4) ||
+    opcode === 0xd1
+  ) {
+    return offset;
+  }
+  if (opcode >= 0x02 && opcode <= 0x04) {
+    // Block type: empty, a value type, or a type index (a signed LEB128)
+    const type = code[offset];
+    return type === 0x40 || (type >= 0x6f && type <= 0x7f)
+      ? offset + 1
+      : leb();
+  }
+  if (opcode >= 0x28 && opcode <= 0x3e) {
+    return memarg();
+  }
+  switch (opcode) {
+    case 0x0c: // br
+    case 0x0d: // br_if
+    case 0x10: // call
+    case 0x12: // return_call
+    case 0x20: // local.get
+    case 0x21: // local.set
+    case 0x22: // local.tee
+    case 0x23: // global.get
+    case 0x24: // global.set
+    case 0x25: // table.get
+    case 0x26: // table.set
+    case 0x3f: // memory.size
+    case 0x40: // memory.grow
+    case 0x41: // i32.const
+    case 0x42: // i64.const
+    case 0xd2: // ref.func
+      return leb();
+    case 0x0e: // br_table: the targets, then the default
+      return leb(readU32(code, offset).value + 2);
+    case 0x11: // call_indirect
+    case 0x13: // return_call_indirect
+      return leb(2);
+    case 0x1c: {
+      // select with a vector of value types
+      const types = readU32(code, offset);
+      return types.next + types.value;
+    }
+    case 0x43: // f32.const
+      return offset + 4;
+    case 0x44: // f64.const
+      return offset + 8;
+    case 0xd0: // ref.null
+      return offset + 1;
+    case 0xfc: {
+      const sub = readU32(code, offset);
+      offset = sub.next;
+      if (sub.value <= 7) {
+        return offset; // saturating truncations
+      }
+      // memory.init, memory.copy, table.init, table.copy take two
+      return leb([8, 10, 12, 14].includes(sub.value) ? 2 : 1);
+    }
+    case 0xfd: {
+      const sub = readU32(code, offset);
+      offset = sub.next;
+      if (sub.value <= 11 || sub.value === 92 || sub.value === 93) {
+        return memarg();
+      }
+      if (sub.value === 12 || sub.value === 13) {
+        return offset + 16; // v128.const, i8x16.shuffle
+      }
+      if (sub.value >= 21 && sub.value <= 34) {
+        return offset + 1; // lane index
+      }
+      if (sub.value >= 84 && sub.value <= 91) {
+        return memarg() + 1;
+      }
+      return offset;
+    }
+    default:
+      throw new Error(
+        `Cannot meter opcode 0x${opcode.toString(16)} at offset ${offset - 1}`
+      );
+  }
+}
+
+function skipLimits(bytes: Uint8Array, offset: number): number {
+  const flags = bytes[offset++];
+  offset = skipLeb(bytes, offset);
+  return flags & 1 ? skipLeb(bytes, offset) : offset;
+}
+
+function skipLeb(bytes: Uint8Array, offset: number): number {
+  while (bytes[offset++] & 0x80) {
+    if (offset >= bytes.length) {
+      throw new Error(`Invalid LEB128 integer at offset ${offset}`);
+    }
+  }
+  return offset;
+}
+
+function encodeName(name: string): number[] {
+  const bytes = new TextEncoder().encode(name);
+  return [...encodeU32(bytes.length), ...bytes];
+}
+
+// Signed LEB128, as i64.const takes
+function encodeS64(value: bigint): number[] {
+  const bytes: number[] = [];
+  for (;;) {
+    const byte = Number(value & 0x7fn);
+    value >>= 7n;
+    if ((value === 0n && !(byte & 0x40)) || (value === -1n && byte & 0x40)) {
+      bytes.push(byte);
+      return bytes;
+    }
+    bytes.push(byte | 0x80);
+  }
+}
+
+function concatBytes(parts: Uint8Array[]): Uint8Array {
+  const result = new Uint8Array(
+    parts.reduce((sum, part) => sum + part.length, 0)
+  );
+  let offset = 0;
+  for (const part of parts) {
+    result.set(part, offset);
+    offset += part.length;
+  }
+  return result;
+}
+
+// Unsigned LEB128, as used for WASM sizes
+function readU32(
+  bytes: Uint8Array,
+  offset: number
+): { value: number; next: number } {
+  let value = 0;
+  let shift = 0;
+  for (;;) {
+    if (offset >= bytes.length || shift > 28) {
+      throw new Error(`Invalid LEB128 integer at offset ${offset}`);
+    }
+    const byte = bytes[offset++];
+    value += (byte & 0x7f) * 2 ** shift;
+    if ((byte & 0x80) === 0) {
+      return { value, next: offset };
+    }
+    shift += 7;
+  }
+}
+
+function encodeU32(value: number): number[] {
+  const bytes: number[] = [];
+  do {
+    let byte = value & 0x7f;
+    value = Math.floor(value / 128);
+    if (value > 0) {
+      byte |= 0x80;
+    }
+    bytes.push(byte);
+  } while (value > 0);
+  return bytes;
+}
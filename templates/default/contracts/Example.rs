@@ -6,17 +6,19 @@ use anyhow::Result;
 #[derive(Default)]
 pub struct ExampleContract(());
 
-impl AlkaneResponder for ExampleContract {
-    fn execute(&self) -> Result<CallResponse> {
+#[alkali::contract]
+impl ExampleContract {
+    /// Runs once when the contract is deployed
+    #[opcode(0)]
+    fn initialize(&self) -> Result<CallResponse> {
         let context = self.context()?;
-        let mut inputs = context.inputs.clone();
-        let mut response = CallResponse::forward(&context.incoming_alkanes);
+        Ok(CallResponse::forward(&context.incoming_alkanes))
+    }
 
-        match shift_or_err(&mut inputs)? {
-            /* initialize() */
-            0 => Ok(response),
-            _ => Err(anyhow!("unrecognized opcode")),
-        }
+    /// The contract's display name, returned in the response data
+    #[opcode(99)]
+    fn name(&self) -> Result<String> {
+        Ok("Example".to_string())
     }
 }
 
@@ -1,9 +1,47 @@
-import { AlkanesContract } from "alkali";
+import {
+  AlkanesContract,
+  JsonRpcProvider,
+  fromHex,
+  getNetworkConfig,
+  loadArtifact,
+  loadConfig,
+} from "@jonatns/alkali";
 
+// Deploys the compiled Example contract to the config's network, funded by
+// FUNDING_UTXO (txid:vout:value) and signed with ALKALI_PRIVATE_KEY or the
+// network's first account. Run `alkali compile` first.
 async function main() {
-  const contract = await AlkanesContract.fromFile("contracts/Example.rs");
-  const deployed = await contract.deploy();
-  console.log(`Contract deployed to: ${deployed.address}`);
+  const config = await loadConfig();
+  const network = getNetworkConfig(config);
+  const { bytecode, abi } = await loadArtifact(config.paths.build, "Example");
+
+  const [txid, vout, value] = (process.env.FUNDING_UTXO ?? "").split(":");
+  const key = process.env.ALKALI_PRIVATE_KEY ?? network.accounts[0];
+  if (!txid || !vout || !value || !key) {
+    throw new Error(
+      "Set FUNDING_UTXO=txid:vout:value and ALKALI_PRIVATE_KEY (or the network's accounts)"
+    );
+  }
+
+  const contract = new AlkanesContract({
+    abi,
+    bytecode: Buffer.from(bytecode).toString("base64"),
+    provider: new JsonRpcProvider({
+      url: network.url,
+      bitcoinUrl: network.bitcoinUrl,
+      headers: network.headers,
+    }),
+  });
+  const { alkaneId, revealTxid } = await contract.deploy({
+    privateKey: fromHex(key),
+    utxo: { txid, vout: Number(vout), value: BigInt(value) },
+    feeRate: 10,
+    network: network.network,
+  });
+  console.log(`Contract deployed to ${alkaneId} in ${revealTxid}`);
 }
 
-main().catch(console.error);
+main().catch((error) => {
+  console.error(error);
+  process.exit(1);
+});
@@ -0,0 +1,17 @@
+import { AlkanesTestEnvironment, TestContract } from "@jonatns/alkali";
+
+describe("Example", () => {
+  let env: AlkanesTestEnvironment;
+  let example: TestContract;
+
+  beforeEach(async () => {
+    env = new AlkanesTestEnvironment();
+    example = await env.deploy("Example");
+  });
+
+  it("returns its name", async () => {
+    const { value, transfers } = await example.call("name");
+    expect(value).toBe("Example");
+    expect(transfers).toEqual([]);
+  });
+});
@@ -0,0 +1,32 @@
+use alkali_test::{AlkaneId, MockContext, MockHost};
+use alkanes_runtime::runtime::AlkaneResponder;
+use example::ExampleContract;
+
+const EXAMPLE: AlkaneId = AlkaneId::new(2, 1);
+const TOKEN: AlkaneId = AlkaneId::new(2, 7);
+
+#[test]
+fn returns_its_name() {
+    let mut host = MockHost::new(MockContext::new(EXAMPLE).inputs([99]));
+
+    let response = host.run(|| ExampleContract::default().execute()).unwrap();
+    assert_eq!(response.data, b"Example");
+}
+
+#[test]
+fn forwards_incoming_alkanes_on_initialize() {
+    let context = MockContext::new(EXAMPLE).incoming(TOKEN, 100).inputs([0]);
+    let mut host = MockHost::new(context);
+
+    let response = host.run(|| ExampleContract::default().execute()).unwrap();
+    assert_eq!(response.alkanes.0.len(), 1);
+    assert_eq!(response.alkanes.0[0].value, 100);
+}
+
+#[test]
+fn rejects_unknown_opcodes() {
+    let mut host = MockHost::new(MockContext::new(EXAMPLE).inputs([12]));
+
+    let error = host.run(|| ExampleContract::default().execute()).unwrap_err();
+    assert_eq!(error.to_string(), "unrecognized opcode 12");
+}